@@ -3,7 +3,7 @@ use near_primitives::challenge::{ChunkProofs, ChunkState};
 use near_primitives::errors::{EpochError, StorageError};
 use near_primitives::shard_layout::ShardLayoutError;
 use near_primitives::sharding::{ChunkHash, ShardChunkHeader};
-use near_primitives::types::{BlockHeight, EpochId, ShardId};
+use near_primitives::types::{AccountId, BlockHeight, EpochId, ShardId};
 use near_time::Utc;
 use std::io;
 
@@ -98,6 +98,9 @@ pub enum Error {
     /// Invalid chunk tx root hash.
     #[error("Invalid Chunk Tx Root Hash")]
     InvalidChunkTxRoot,
+    /// Block body hash in the header doesn't match the block body.
+    #[error("Invalid Block Body Hash")]
+    InvalidBlockBodyHash,
     /// Invalid receipts proof.
     #[error("Invalid Receipts Proof")]
     InvalidReceiptsProof,
@@ -167,6 +170,12 @@ pub enum Error {
     /// Invalid Approvals
     #[error("Invalid Approvals")]
     InvalidApprovals,
+    /// The approval at the given position doesn't come from an approver of the block
+    #[error("Invalid approver at position {0}")]
+    InvalidApprover(usize),
+    /// The approval of the given approver has an invalid signature
+    #[error("Invalid approval signature of {0}")]
+    InvalidApprovalSignature(AccountId),
     /// Invalid Gas Limit
     #[error("Invalid Gas Limit")]
     InvalidGasLimit,
@@ -284,6 +293,7 @@ impl Error {
             | Error::InvalidOutcomesProof
             | Error::InvalidChunkHeadersRoot
             | Error::InvalidChunkTxRoot
+            | Error::InvalidBlockBodyHash
             | Error::InvalidReceiptsProof
             | Error::InvalidStatePayload
             | Error::InvalidTransactions
@@ -298,6 +308,8 @@ impl Error {
             | Error::InvalidValidatorProposals
             | Error::InvalidSignature
             | Error::InvalidApprovals
+            | Error::InvalidApprover(_)
+            | Error::InvalidApprovalSignature(_)
             | Error::InvalidGasLimit
             | Error::InvalidGasPrice
             | Error::InvalidGasUsed
@@ -360,6 +372,7 @@ impl Error {
             Error::InvalidOutcomesProof => "invalid_outcomes_proof",
             Error::InvalidChunkHeadersRoot => "invalid_chunk_headers_root",
             Error::InvalidChunkTxRoot => "invalid_chunk_tx_root",
+            Error::InvalidBlockBodyHash => "invalid_block_body_hash",
             Error::InvalidReceiptsProof => "invalid_receipts_proof",
             Error::InvalidStatePayload => "invalid_state_payload",
             Error::InvalidTransactions => "invalid_transactions",
@@ -374,6 +387,8 @@ impl Error {
             Error::InvalidValidatorProposals => "invalid_validator_proposals",
             Error::InvalidSignature => "invalid_signature",
             Error::InvalidApprovals => "invalid_approvals",
+            Error::InvalidApprover(_) => "invalid_approver",
+            Error::InvalidApprovalSignature(_) => "invalid_approval_signature",
             Error::InvalidGasLimit => "invalid_gas_limit",
             Error::InvalidGasPrice => "invalid_gas_price",
             Error::InvalidGasUsed => "invalid_gas_used",
@@ -50,7 +50,7 @@ use near_epoch_manager::types::BlockHeaderInfo;
 use near_epoch_manager::EpochManagerAdapter;
 use near_o11y::log_assert;
 use near_primitives::block::{genesis_chunks, Block, BlockValidityError, Tip};
-use near_primitives::block_header::BlockHeader;
+use near_primitives::block_header::{Approval, ApprovalInner, BlockHeader};
 use near_primitives::challenge::{
     BlockDoubleSign, Challenge, ChallengeBody, ChallengesResult, ChunkProofs, ChunkState,
     MaybeEncodedShardChunk, PartialState, SlashedValidator,
@@ -61,7 +61,7 @@ use near_primitives::congestion_info::CongestionInfo;
 use near_primitives::epoch_manager::epoch_sync::EpochSyncInfo;
 #[cfg(feature = "new_epoch_sync")]
 use near_primitives::errors::epoch_sync::EpochSyncHashType;
-use near_primitives::errors::EpochError;
+use near_primitives::errors::{EpochError, InvalidTxError};
 use near_primitives::hash::{hash, CryptoHash};
 use near_primitives::merkle::{
     combine_hash, merklize, verify_path, Direction, MerklePath, MerklePathItem, PartialMerkleTree,
@@ -79,11 +79,13 @@ use near_primitives::state_sync::{
     ShardStateSyncResponseHeader, ShardStateSyncResponseHeaderV2, StateHeaderKey, StatePartKey,
 };
 use near_primitives::stateless_validation::{ChunkStateWitness, ChunkStateWitnessSize};
-use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, SignedTransaction};
+use near_primitives::transaction::{
+    ExecutionOutcome, ExecutionOutcomeWithIdAndProof, SignedTransaction,
+};
 use near_primitives::types::chunk_extra::ChunkExtra;
 use near_primitives::types::{
-    AccountId, Balance, BlockExtra, BlockHeight, BlockHeightDelta, EpochId, Gas, MerkleHash,
-    NumBlocks, ShardId, StateRoot,
+    AccountId, Balance, BlockExtra, BlockHeight, BlockHeightDelta, EpochHeight, EpochId, Gas,
+    MerkleHash, Nonce, NumBlocks, ShardId, StateRoot,
 };
 use near_primitives::unwrap_or_return;
 #[cfg(feature = "new_epoch_sync")]
@@ -98,6 +100,7 @@ use near_primitives::views::{
 use near_store::config::StateSnapshotType;
 use near_store::flat::{store_helper, FlatStorageReadyStatus, FlatStorageStatus};
 use near_store::get_genesis_state_roots;
+use near_store::trie::mem::loading::compute_trie_root_from_flat_state;
 use near_store::DBCol;
 use node_runtime::bootstrap_congestion_info;
 use rayon::iter::{IntoParallelIterator, ParallelIterator};
@@ -160,6 +163,85 @@ impl Debug for BlockMissingChunks {
     }
 }
 
+/// Blocks and state roots involved in state syncing to a given sync hash.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StateSyncTargetInfo {
+    /// The first block of the epoch being synced to.
+    pub sync_hash: CryptoHash,
+    /// The last block of the previous epoch.
+    pub sync_prev_hash: CryptoHash,
+    /// The block whose post-state snapshot state parts are served from.
+    pub sync_prev_prev_hash: CryptoHash,
+    /// Epoch of the sync block.
+    pub epoch_id: EpochId,
+    /// For every shard, the state root that the downloaded state parts add up
+    /// to, i.e. the prev state root of the last chunk of the previous epoch.
+    pub prev_state_roots: Vec<(ShardId, StateRoot)>,
+}
+
+/// Accounts expected to produce the block and the chunks at a given height.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProducerAssignment {
+    pub height: BlockHeight,
+    pub block_producer: AccountId,
+    /// Chunk producer of every shard of the epoch, in shard id order.
+    pub chunk_producers: Vec<(ShardId, AccountId)>,
+}
+
+/// What an account is scheduled to produce, see `Chain::next_production_slot`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProductionSlot {
+    Block,
+    Chunk(ShardId),
+}
+
+/// Heights that garbage collection has progressed to, see `Chain::gc_bounds`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GcBounds {
+    /// Height of the oldest block on the canonical chain that is kept.
+    pub tail: BlockHeight,
+    /// Height below which the forks have been garbage collected.
+    pub fork_tail: BlockHeight,
+    /// Height below which the redundant chunk data has been cleared.
+    pub chunk_tail: BlockHeight,
+    /// Height starting from which no data is garbage collected.
+    pub gc_stop_height: BlockHeight,
+}
+
+/// Why a transaction would be rejected at submission, see
+/// `Chain::describe_tx_rejection`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TxRejectionReason {
+    /// The transaction refers to a block which is too far behind the head.
+    Expired {
+        base_block_height: BlockHeight,
+        /// Number of blocks by which the validity period is exceeded.
+        expired_by: BlockHeightDelta,
+    },
+    /// The transaction refers to a block which this node doesn't know.
+    UnknownBlockHash,
+    /// The nonce of the transaction isn't greater than the nonce of the access key.
+    InvalidNonce { tx_nonce: Nonce, ak_nonce: Nonce },
+    /// The signer can't cover the cost of the transaction.
+    NotEnoughBalance { balance: Balance, cost: Balance, shortfall: Balance },
+    /// Any other reason, as reported by the transaction validation.
+    Invalid(InvalidTxError),
+}
+
+impl From<InvalidTxError> for TxRejectionReason {
+    fn from(err: InvalidTxError) -> Self {
+        match err {
+            InvalidTxError::InvalidNonce { tx_nonce, ak_nonce } => {
+                Self::InvalidNonce { tx_nonce, ak_nonce }
+            }
+            InvalidTxError::NotEnoughBalance { balance, cost, .. } => {
+                Self::NotEnoughBalance { balance, cost, shortfall: cost.saturating_sub(balance) }
+            }
+            err => Self::Invalid(err),
+        }
+    }
+}
+
 /// Check if block header is known
 /// Returns Err(Error) if any error occurs when checking store
 ///         Ok(Err(BlockKnownError)) if the block header is known
@@ -288,6 +370,10 @@ pub struct Chain {
     // A handle that allows the main process to interrupt resharding if needed.
     // This typically happens when the main process is interrupted.
     pub resharding_handle: ReshardingHandle,
+
+    /// Whether to check that the state restored by state sync reconstructs
+    /// a trie with the expected state root before finalizing it.
+    pub verify_memtrie_after_state_sync: bool,
 }
 
 impl Drop for Chain {
@@ -392,6 +478,7 @@ impl Chain {
                 "resharding_config",
             ),
             resharding_handle: ReshardingHandle::new(),
+            verify_memtrie_after_state_sync: false,
         })
     }
 
@@ -602,6 +689,7 @@ impl Chain {
             snapshot_callbacks,
             resharding_config: chain_config.resharding_config,
             resharding_handle: ReshardingHandle::new(),
+            verify_memtrie_after_state_sync: chain_config.verify_memtrie_after_state_sync,
         })
     }
 
@@ -813,6 +901,248 @@ impl Chain {
             .map(|_| ())
     }
 
+    /// Checks that the chunk mask of the block header matches the chunks which
+    /// are new in the block, i.e. were included at its height. A chunk missing
+    /// at this height must have its mask bit unset.
+    pub fn validate_chunk_mask(&self, block: &Block) -> Result<(), Error> {
+        let header = block.header();
+        let chunk_mask = header.chunk_mask();
+        let num_shards = self.epoch_manager.shard_ids(header.epoch_id())?.len();
+        if chunk_mask.len() != num_shards || block.chunks().len() != num_shards {
+            debug!(
+                target: "chain",
+                block_hash = ?header.hash(),
+                chunk_mask_len = chunk_mask.len(),
+                num_chunks = block.chunks().len(),
+                num_shards,
+                "Chunk mask length doesn't match the number of shards");
+            return Err(Error::InvalidChunkMask);
+        }
+        for (shard_index, (chunk_header, &mask_bit)) in
+            block.chunks().iter().zip(chunk_mask.iter()).enumerate()
+        {
+            let is_new_chunk = chunk_header.height_included() == header.height();
+            if is_new_chunk != mask_bit {
+                debug!(
+                    target: "chain",
+                    block_hash = ?header.hash(),
+                    shard_index,
+                    mask_bit,
+                    height_included = chunk_header.height_included(),
+                    "Chunk mask doesn't match the chunk inclusion");
+                return Err(Error::InvalidChunkMask);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the block producer and the chunk producers of all shards for
+    /// the given height of the epoch.
+    pub fn producers_at_height(
+        &self,
+        epoch_id: &EpochId,
+        height: BlockHeight,
+    ) -> Result<ProducerAssignment, Error> {
+        let block_producer = self.epoch_manager.get_block_producer(epoch_id, height)?;
+        let chunk_producers = self
+            .epoch_manager
+            .shard_ids(epoch_id)?
+            .into_iter()
+            .map(|shard_id| {
+                let chunk_producer =
+                    self.epoch_manager.get_chunk_producer(epoch_id, height, shard_id)?;
+                Ok((shard_id, chunk_producer))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        Ok(ProducerAssignment { height, block_producer, chunk_producers })
+    }
+
+    /// Checks the approvals of the block against the block approvers of its
+    /// epoch: every approval must come from an approver which isn't slashed,
+    /// have a valid signature of the endorsement or skip message, and the
+    /// approvals together must reach the doomslug threshold.
+    pub fn verify_block_approvals(&self, block: &Block) -> Result<(), Error> {
+        let header = block.header();
+        let prev_header = self.get_block_header(header.prev_hash())?;
+        let approvers = self.epoch_manager.get_epoch_block_approvers_ordered(header.prev_hash())?;
+        let approvals = header.approvals();
+        if approvals.len() > approvers.len() {
+            return Err(Error::InvalidApprover(approvers.len()));
+        }
+        let message_to_sign = Approval::get_data_for_sig(
+            &ApprovalInner::new(prev_header.hash(), prev_header.height(), header.height()),
+            header.height(),
+        );
+        for (position, ((approver, is_slashed), approval)) in
+            approvers.iter().zip(approvals).enumerate()
+        {
+            let Some(signature) = approval else { continue };
+            if *is_slashed {
+                return Err(Error::InvalidApprover(position));
+            }
+            if !signature.verify(message_to_sign.as_ref(), &approver.public_key) {
+                return Err(Error::InvalidApprovalSignature(approver.account_id.clone()));
+            }
+        }
+        let stakes = approvers
+            .iter()
+            .map(|(approver, is_slashed)| {
+                (approver.stake_this_epoch, approver.stake_next_epoch, *is_slashed)
+            })
+            .collect::<Vec<_>>();
+        if !Doomslug::can_approved_block_be_produced(
+            self.doomslug_threshold_mode,
+            approvals,
+            &stakes,
+        ) {
+            return Err(Error::NotEnoughApprovals);
+        }
+        Ok(())
+    }
+
+    /// Returns the next height after the head at which `account_id` is
+    /// scheduled to produce a block or a chunk, looking only at the current
+    /// and the next epoch.  The block takes precedence over the chunks if the
+    /// account produces both at that height.  Returns `None` if the account
+    /// doesn't produce anything in these epochs.
+    ///
+    /// The epoch boundary is estimated from the epoch length, so the result
+    /// may be off if the current epoch ends up being longer than that.
+    pub fn next_production_slot(
+        &self,
+        account_id: &AccountId,
+    ) -> Result<Option<(BlockHeight, ProductionSlot)>, Error> {
+        let head = self.head()?;
+        let epoch_start_height =
+            self.epoch_manager.get_epoch_start_height(&head.last_block_hash)?;
+        let epoch_length = self.epoch_manager.get_epoch_config(&head.epoch_id)?.epoch_length;
+        let next_epoch_start_height = epoch_start_height + epoch_length;
+        let epochs = [
+            (head.epoch_id, head.height + 1, next_epoch_start_height),
+            (head.next_epoch_id, next_epoch_start_height, next_epoch_start_height + epoch_length),
+        ];
+        for (epoch_id, start_height, end_height) in epochs {
+            if !self.is_producer_in_epoch(&epoch_id, &head.last_block_hash, account_id)? {
+                continue;
+            }
+            for height in start_height.max(head.height + 1)..end_height {
+                let assignment = self.producers_at_height(&epoch_id, height)?;
+                if assignment.block_producer == *account_id {
+                    return Ok(Some((height, ProductionSlot::Block)));
+                }
+                if let Some((shard_id, _)) = assignment
+                    .chunk_producers
+                    .iter()
+                    .find(|(_, chunk_producer)| chunk_producer == account_id)
+                {
+                    return Ok(Some((height, ProductionSlot::Chunk(*shard_id))));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    /// Whether the epoch is at most `max_age` epochs older than the epoch of
+    /// the head. Epochs ahead of the head are considered recent as well.
+    pub fn is_recent_epoch(&self, epoch_id: &EpochId, max_age: EpochHeight) -> Result<bool, Error> {
+        let head = self.head()?;
+        let head_epoch_height = self.epoch_manager.get_epoch_info(&head.epoch_id)?.epoch_height();
+        let epoch_height = self.epoch_manager.get_epoch_info(epoch_id)?.epoch_height();
+        Ok(epoch_height + max_age >= head_epoch_height)
+    }
+
+    /// Describes why the transaction would be rejected if it was submitted
+    /// now, or returns `None` if it would be accepted. The checks are the same
+    /// as the ones done by the client when processing a transaction, but the
+    /// result carries the details needed to explain the rejection.
+    ///
+    /// The state of the signer is only checked if this node has the state of
+    /// its shard.
+    pub fn describe_tx_rejection(
+        &self,
+        tx: &SignedTransaction,
+    ) -> Result<Option<TxRejectionReason>, Error> {
+        let head = self.head()?;
+        let head_block = self.get_block(&head.last_block_hash)?;
+        let base_block_hash = tx.transaction.block_hash();
+        if let Err(err) = self.chain_store.check_transaction_validity_period(
+            head_block.header(),
+            base_block_hash,
+            self.transaction_validity_period,
+        ) {
+            if err != InvalidTxError::Expired {
+                return Ok(Some(err.into()));
+            }
+            let Ok(base_block_header) = self.get_block_header(base_block_hash) else {
+                return Ok(Some(TxRejectionReason::UnknownBlockHash));
+            };
+            let base_block_height = base_block_header.height();
+            let expired_by =
+                head.height.saturating_sub(base_block_height + self.transaction_validity_period);
+            return Ok(Some(TxRejectionReason::Expired { base_block_height, expired_by }));
+        }
+
+        let gas_price = head_block.header().next_gas_price();
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&head.last_block_hash)?;
+        let receiver_shard =
+            self.epoch_manager.account_id_to_shard_id(tx.transaction.receiver_id(), &epoch_id)?;
+        let receiver_congestion_info =
+            head_block.block_congestion_info().get(&receiver_shard).copied();
+        let protocol_version = self.epoch_manager.get_epoch_protocol_version(&epoch_id)?;
+        if let Some(err) = self.runtime_adapter.validate_tx(
+            gas_price,
+            None,
+            tx,
+            true,
+            &epoch_id,
+            protocol_version,
+            receiver_congestion_info,
+        )? {
+            return Ok(Some(err.into()));
+        }
+
+        let shard_id =
+            self.epoch_manager.account_id_to_shard_id(tx.transaction.signer_id(), &epoch_id)?;
+        let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
+        let Ok(chunk_extra) = self.get_chunk_extra(&head.last_block_hash, &shard_uid) else {
+            return Ok(None);
+        };
+        Ok(self
+            .runtime_adapter
+            .validate_tx(
+                gas_price,
+                Some(*chunk_extra.state_root()),
+                tx,
+                false,
+                &epoch_id,
+                protocol_version,
+                receiver_congestion_info,
+            )?
+            .map(Into::into))
+    }
+
+    /// Whether `account_id` is a block or a chunk producer in the epoch.
+    fn is_producer_in_epoch(
+        &self,
+        epoch_id: &EpochId,
+        last_known_block_hash: &CryptoHash,
+        account_id: &AccountId,
+    ) -> Result<bool, Error> {
+        let is_block_producer = self
+            .epoch_manager
+            .get_epoch_block_producers_ordered(epoch_id, last_known_block_hash)?
+            .iter()
+            .any(|(validator, _)| validator.account_id() == account_id);
+        if is_block_producer {
+            return Ok(true);
+        }
+        Ok(self
+            .epoch_manager
+            .get_epoch_chunk_producers(epoch_id)?
+            .iter()
+            .any(|validator| validator.account_id() == account_id))
+    }
+
     fn validate_block_impl(
         epoch_manager: &dyn EpochManagerAdapter,
         genesis_block: &Block,
@@ -1157,6 +1487,23 @@ impl Chain {
         Ok(VerifyBlockHashAndSignatureResult::Correct)
     }
 
+    /// Checks that the block body matches the commitments in its header.
+    /// Returns `InvalidChunkHeadersRoot` if the chunk headers don't match the
+    /// chunk headers root, and `InvalidBlockBodyHash` if the body doesn't match
+    /// the block body hash (for block versions that have one).
+    pub fn verify_block_body_integrity(&self, block: &Block) -> Result<(), Error> {
+        let chunk_headers_root = Block::compute_chunk_headers_root(block.chunks().iter()).0;
+        if block.header().chunk_headers_root() != &chunk_headers_root {
+            return Err(Error::InvalidChunkHeadersRoot);
+        }
+        if let Some(block_body_hash) = block.compute_block_body_hash() {
+            if block.header().block_body_hash() != Some(block_body_hash) {
+                return Err(Error::InvalidBlockBodyHash);
+            }
+        }
+        Ok(())
+    }
+
     /// Verify that `challenges` are valid
     /// If all challenges are valid, returns ChallengesResult, which comprises of the list of
     /// validators that need to be slashed and the list of blocks that are challenged.
@@ -2602,6 +2949,32 @@ impl Chain {
         Ok(shard_state_header)
     }
 
+    /// Resolves `sync_hash` into the blocks and the per-shard state roots that
+    /// state sync to it will use.
+    pub fn state_sync_target_info(
+        &self,
+        sync_hash: CryptoHash,
+    ) -> Result<StateSyncTargetInfo, Error> {
+        let sync_block_header = self.get_block_header(&sync_hash)?;
+        let epoch_id = *sync_block_header.epoch_id();
+        let sync_prev_block = self.get_block(sync_block_header.prev_hash())?;
+        if &epoch_id == sync_prev_block.header().epoch_id() {
+            return Err(sync_hash_not_first_hash(sync_hash));
+        }
+        let prev_state_roots = sync_prev_block
+            .chunks()
+            .iter()
+            .map(|chunk_header| (chunk_header.shard_id(), chunk_header.prev_state_root()))
+            .collect();
+        Ok(StateSyncTargetInfo {
+            sync_hash,
+            sync_prev_hash: *sync_prev_block.hash(),
+            sync_prev_prev_hash: *sync_prev_block.header().prev_hash(),
+            epoch_id,
+            prev_state_roots,
+        })
+    }
+
     pub fn get_state_response_part(
         &mut self,
         shard_id: ShardId,
@@ -2959,6 +3332,13 @@ impl Chain {
     ) -> Result<(), Error> {
         let _span = tracing::debug_span!(target: "sync", "set_state_finalize").entered();
         let shard_state_header = self.get_state_header(shard_id, sync_hash)?;
+        if self.verify_memtrie_after_state_sync {
+            self.verify_synced_state(
+                shard_id,
+                sync_hash,
+                shard_state_header.chunk_prev_state_root(),
+            )?;
+        }
         let mut height = shard_state_header.chunk_height_included();
         let mut chain_update = self.chain_update();
         let shard_uid = chain_update.set_state_finalize(shard_id, sync_hash, shard_state_header)?;
@@ -2987,6 +3367,35 @@ impl Chain {
         Ok(())
     }
 
+    /// Builds a memtrie from the flat state written by the applied state parts
+    /// of the shard and checks that its root is the expected state root.
+    /// This reads the whole state of the shard, so it is only done if
+    /// `verify_memtrie_after_state_sync` is enabled.
+    fn verify_synced_state(
+        &self,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+        expected_state_root: StateRoot,
+    ) -> Result<(), Error> {
+        let _span = tracing::debug_span!(target: "sync", "verify_synced_state").entered();
+        let epoch_id = *self.get_block_header(&sync_hash)?.epoch_id();
+        let shard_uid = self.epoch_manager.shard_id_to_uid(shard_id, &epoch_id)?;
+        let state_root =
+            compute_trie_root_from_flat_state(self.runtime_adapter.store(), shard_uid)?;
+        if state_root != expected_state_root {
+            tracing::error!(
+                target: "sync",
+                ?shard_uid,
+                ?sync_hash,
+                ?state_root,
+                ?expected_state_root,
+                "State restored by state sync doesn't match the expected state root");
+            return Err(Error::InvalidStateRoot);
+        }
+        tracing::debug!(target: "sync", ?shard_uid, ?sync_hash, ?state_root, "Verified the state restored by state sync");
+        Ok(())
+    }
+
     pub fn clear_downloaded_parts(
         &mut self,
         shard_id: ShardId,
@@ -3359,6 +3768,36 @@ impl Chain {
         Ok(FinalExecutionOutcomeView { status, transaction, transaction_outcome, receipts_outcome })
     }
 
+    /// Returns the transactions included in the new chunks of the given block,
+    /// together with their execution status as currently known. Execution of
+    /// a transaction may span several blocks, in which case its status stays
+    /// `Started` until all of its receipts are executed.
+    pub fn block_transactions_with_outcomes(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<Vec<(SignedTransaction, FinalExecutionStatus)>, Error> {
+        let block = self.get_block(block_hash)?;
+        let mut transactions = vec![];
+        for chunk_header in block.chunks().iter() {
+            if chunk_header.height_included() != block.header().height() {
+                continue;
+            }
+            let chunk = self.get_chunk(&chunk_header.chunk_hash())?;
+            for transaction in chunk.transactions() {
+                let transaction_hash = transaction.get_hash();
+                let mut outcomes = vec![];
+                self.get_recursive_transaction_results(&mut outcomes, &transaction_hash, false)?;
+                let status = if outcomes.is_empty() {
+                    FinalExecutionStatus::NotStarted
+                } else {
+                    self.get_execution_status(&outcomes, &transaction_hash)
+                };
+                transactions.push((transaction.clone(), status));
+            }
+        }
+        Ok(transactions)
+    }
+
     /// Returns corresponding receipts for provided outcome
     /// The incoming list in receipts_outcome may be partial
     pub fn get_transaction_result_with_receipt(
@@ -4209,6 +4648,18 @@ impl Chain {
         self.chain_store.tail()
     }
 
+    /// Gets the tail, fork tail and chunk tail together with the height that
+    /// garbage collection currently stops at.
+    pub fn gc_bounds(&self) -> Result<GcBounds, Error> {
+        let head = self.chain_store.head()?;
+        Ok(GcBounds {
+            tail: self.chain_store.tail()?,
+            fork_tail: self.chain_store.fork_tail()?,
+            chunk_tail: self.chain_store.chunk_tail()?,
+            gc_stop_height: self.runtime_adapter.get_gc_stop_height(&head.last_block_hash),
+        })
+    }
+
     /// Gets chain header head.
     #[inline]
     pub fn header_head(&self) -> Result<Tip, Error> {
@@ -4303,12 +4754,51 @@ impl Chain {
         self.chain_store.get_earliest_block_hash()
     }
 
+    /// Returns the gas price set by each canonical block with height in
+    /// `[from_height, to_height]`, i.e. the price charged in its successor.
+    /// The range is clamped to the blocks still stored, between the tail and
+    /// the head. Skipped heights have no block and are left out.
+    pub fn gas_price_history(
+        &self,
+        from_height: BlockHeight,
+        to_height: BlockHeight,
+    ) -> Result<Vec<(BlockHeight, Balance)>, Error> {
+        let from_height = from_height.max(self.tail()?);
+        let to_height = to_height.min(self.head()?.height);
+        let mut history = vec![];
+        for height in from_height..=to_height {
+            match self.get_block_header_by_height(height) {
+                Ok(header) => history.push((height, header.next_gas_price())),
+                Err(Error::DBNotFoundErr(_)) => continue,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(history)
+    }
+
     /// Check if block exists.
     #[inline]
     pub fn block_exists(&self, hash: &CryptoHash) -> Result<bool, Error> {
         self.chain_store.block_exists(hash)
     }
 
+    /// Returns the shards this node tracked at the given, possibly historical,
+    /// block according to its tracked-shards config. Shards tracked only because
+    /// of validator duties are not included.
+    /// Returns an error if the epoch data for the block is no longer available,
+    /// e.g. because it was garbage collected.
+    pub fn tracked_shards_at(&self, block_hash: &CryptoHash) -> Result<Vec<ShardId>, Error> {
+        let block_info = self.epoch_manager.get_block_info(block_hash)?;
+        let epoch_id = block_info.epoch_id();
+        let mut tracked_shards = vec![];
+        for shard_id in self.epoch_manager.shard_ids(epoch_id)? {
+            if self.shard_tracker.tracks_shard_at_epoch(shard_id, epoch_id)? {
+                tracked_shards.push(shard_id);
+            }
+        }
+        Ok(tracked_shards)
+    }
+
     /// Get block extra that was computer after applying previous block.
     #[inline]
     pub fn get_block_extra(&self, block_hash: &CryptoHash) -> Result<Arc<BlockExtra>, Error> {
@@ -4325,6 +4815,37 @@ impl Chain {
         self.chain_store.get_chunk_extra(block_hash, shard_uid)
     }
 
+    /// Lists the epochs which still have blocks that are not garbage collected,
+    /// together with their start heights and protocol versions, ordered by the
+    /// start height.
+    pub fn epoch_protocol_history(
+        &self,
+    ) -> Result<Vec<(EpochId, BlockHeight, ProtocolVersion)>, Error> {
+        let mut epoch_starts = self
+            .chain_store
+            .store()
+            .iter(DBCol::EpochStart)
+            .map(|item| {
+                let (key, value) = item?;
+                Ok((EpochId::try_from_slice(&key)?, BlockHeight::try_from_slice(&value)?))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        epoch_starts.sort_by_key(|(_, start_height)| *start_height);
+
+        let tail = self.chain_store.tail()?;
+        let mut history = vec![];
+        for (index, (epoch_id, start_height)) in epoch_starts.iter().enumerate() {
+            // The epoch is fully garbage collected if the next one starts at or
+            // below the tail.
+            if epoch_starts.get(index + 1).is_some_and(|(_, next_start)| *next_start <= tail) {
+                continue;
+            }
+            let protocol_version = self.epoch_manager.get_epoch_protocol_version(epoch_id)?;
+            history.push((*epoch_id, *start_height, protocol_version));
+        }
+        Ok(history)
+    }
+
     /// Get destination shard id for a given receipt id.
     #[inline]
     pub fn get_shard_id_for_receipt_id(&self, receipt_id: &CryptoHash) -> Result<ShardId, Error> {
@@ -4474,6 +4995,31 @@ impl Chain {
             .ok_or_else(|| Error::DBNotFoundErr(format!("EXECUTION OUTCOME: {}", id)))
     }
 
+    /// Get all recorded results of the transaction or receipt with the given id,
+    /// which can be more than one if it was executed in multiple forks.
+    /// Every outcome comes with the hash of its block and whether that block is
+    /// on the canonical chain.
+    pub fn all_outcomes_for_receipt(
+        &self,
+        id: &CryptoHash,
+    ) -> Result<Vec<(CryptoHash, ExecutionOutcome, bool)>, Error> {
+        self.chain_store
+            .get_outcomes_by_id(id)?
+            .into_iter()
+            .map(|outcome| {
+                let header = self.get_block_header(&outcome.block_hash)?;
+                let is_canonical = self.is_on_current_chain(&header)?;
+                Ok((outcome.block_hash, outcome.outcome_with_id.outcome, is_canonical))
+            })
+            .collect()
+    }
+
+    /// Get the receipt with the given id. Returns `None` if the receipt isn't
+    /// stored, e.g. if it was never included in a chunk or was garbage collected.
+    pub fn get_receipt(&self, receipt_id: &CryptoHash) -> Result<Option<Arc<Receipt>>, Error> {
+        self.chain_store.get_receipt(receipt_id)
+    }
+
     /// Retrieve the up to `max_headers_returned` headers on the main chain
     /// `hashes`: a list of block "locators". `hashes` should be ordered from older blocks to
     ///           more recent blocks. This function will find the first block in `hashes`
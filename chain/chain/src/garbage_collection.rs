@@ -2,6 +2,7 @@ use std::collections::HashMap;
 use std::sync::Arc;
 use std::{fmt, io};
 
+use borsh::BorshDeserialize;
 use near_chain_configs::GCConfig;
 use near_chain_primitives::Error;
 use near_epoch_manager::EpochManagerAdapter;
@@ -10,7 +11,10 @@ use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::get_block_shard_uid;
 use near_primitives::state_sync::{StateHeaderKey, StatePartKey};
 use near_primitives::types::{BlockHeight, BlockHeightDelta, EpochId, NumBlocks, ShardId};
-use near_primitives::utils::{get_block_shard_id, get_outcome_id_block_hash, index_to_bytes};
+use near_primitives::utils::{
+    get_block_shard_id, get_height_block_hash, get_height_block_hash_rev,
+    get_outcome_id_block_hash, index_to_bytes,
+};
 use near_store::flat::store_helper;
 use near_store::{DBCol, KeyForStateChanges, ShardTries, ShardUId};
 
@@ -163,6 +167,17 @@ impl ChainStore {
             fork_tail = gc_stop_height;
         }
         let mut gc_blocks_remaining = gc_config.gc_blocks_limit;
+        let extra_outcome_retention_epochs = gc_config.extra_outcome_retention_epochs();
+        if extra_outcome_retention_epochs > 0 {
+            let epoch_length = epoch_manager.get_epoch_config(&head.epoch_id)?.epoch_length;
+            let retention_delta = extra_outcome_retention_epochs.saturating_mul(epoch_length);
+            let mut chain_store_update = self.store_update();
+            chain_store_update.expire_retained_outcomes(
+                tail.saturating_sub(retention_delta),
+                gc_config.gc_blocks_limit,
+            )?;
+            chain_store_update.commit()?;
+        }
 
         // Forks Cleaning
         let gc_fork_clean_step = gc_config.gc_fork_clean_step;
@@ -205,6 +220,9 @@ impl ChainStore {
                     break;
                 } else if prev_block_refcount == 1 {
                     debug_assert_eq!(blocks_current_height.len(), 1);
+                    if extra_outcome_retention_epochs > 0 {
+                        chain_store_update.retain_outcomes(block_hash)?;
+                    }
                     chain_store_update.clear_block_data(
                         epoch_manager.as_ref(),
                         *block_hash,
@@ -926,6 +944,73 @@ impl<'a> ChainStoreUpdate<'a> {
         Ok(())
     }
 
+    /// Copies the execution outcomes of a canonical block that is about to be
+    /// garbage collected into DBCol::RetainedOutcomes, so that they outlive
+    /// the block by `extra_outcome_retention_epochs`.
+    fn retain_outcomes(&mut self, block_hash: &CryptoHash) -> Result<(), Error> {
+        let block = self.get_block(block_hash)?;
+        let mut store_update = self.store().store_update();
+        let mut retained_ids = vec![];
+        for chunk_header in
+            block.chunks().iter().filter(|h| h.height_included() == block.header().height())
+        {
+            let outcome_ids = self
+                .chain_store()
+                .get_outcomes_by_block_hash_and_shard_id(block_hash, chunk_header.shard_id())?;
+            for outcome_id in outcome_ids {
+                let Some(outcome) =
+                    self.chain_store().get_outcome_by_id_and_block_hash(&outcome_id, block_hash)?
+                else {
+                    continue;
+                };
+                store_update.set_ser(
+                    DBCol::RetainedOutcomes,
+                    &get_outcome_id_block_hash(&outcome_id, block_hash),
+                    &outcome,
+                )?;
+                retained_ids.push(outcome_id);
+            }
+        }
+        if !retained_ids.is_empty() {
+            store_update.set_ser(
+                DBCol::RetainedOutcomeIdsByHeight,
+                &get_height_block_hash(block.header().height(), block_hash),
+                &retained_ids,
+            )?;
+        }
+        self.merge(store_update);
+        Ok(())
+    }
+
+    /// Removes up to `limit` blocks worth of retained outcomes belonging to
+    /// blocks below `expire_height`.
+    fn expire_retained_outcomes(
+        &mut self,
+        expire_height: BlockHeight,
+        limit: NumBlocks,
+    ) -> Result<(), Error> {
+        let mut expired = vec![];
+        for item in self.store().iter(DBCol::RetainedOutcomeIdsByHeight).take(limit as usize) {
+            let (key, value) = item?;
+            let (height, block_hash) = get_height_block_hash_rev(&key)?;
+            if height >= expire_height {
+                break;
+            }
+            let outcome_ids = Vec::<CryptoHash>::try_from_slice(&value)?;
+            expired.push((key, block_hash, outcome_ids));
+        }
+        for (key, block_hash, outcome_ids) in expired {
+            for outcome_id in outcome_ids {
+                self.gc_col(
+                    DBCol::RetainedOutcomes,
+                    &get_outcome_id_block_hash(&outcome_id, &block_hash),
+                );
+            }
+            self.gc_col(DBCol::RetainedOutcomeIdsByHeight, &key);
+        }
+        Ok(())
+    }
+
     fn gc_col(&mut self, col: DBCol, key: &[u8]) {
         let mut store_update = self.store().store_update();
         match col {
@@ -1043,12 +1128,19 @@ impl<'a> ChainStoreUpdate<'a> {
             DBCol::LatestWitnessesByIndex => {
                 store_update.delete(col, key);
             }
+            DBCol::RetainedOutcomes => {
+                store_update.delete(col, key);
+            }
+            DBCol::RetainedOutcomeIdsByHeight => {
+                store_update.delete(col, key);
+            }
             DBCol::DbVersion
             | DBCol::BlockMisc
             | DBCol::_GCCount
             | DBCol::BlockHeight  // block sync needs it + genesis should be accessible
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
+            | DBCol::RecentOutboundConnectionsByPeer
             | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
@@ -1072,6 +1164,7 @@ impl<'a> ChainStoreUpdate<'a> {
             | DBCol::FlatStateDeltaMetadata
             | DBCol::FlatStorageStatus
             | DBCol::Misc
+            | DBCol::StateSnapshotAvailability
             => unreachable!(),
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => unreachable!(),
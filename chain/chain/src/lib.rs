@@ -1,5 +1,8 @@
 pub use block_processing_utils::BlockProcessingArtifact;
-pub use chain::{check_known, collect_receipts, Chain};
+pub use chain::{
+    check_known, collect_receipts, Chain, GcBounds, ProducerAssignment, ProductionSlot,
+    StateSyncTargetInfo, TxRejectionReason,
+};
 pub use chain_update::ChainUpdate;
 pub use doomslug::{Doomslug, DoomslugBlockProductionReadiness, DoomslugThresholdMode};
 pub use lightclient::{create_light_client_block_view, get_epoch_block_producers_view};
@@ -88,6 +88,9 @@ impl NightshadeRuntime {
         epoch_manager: Arc<EpochManagerHandle>,
         trie_viewer_state_size_limit: Option<u64>,
         max_gas_burnt_view: Option<Gas>,
+        max_gas_burnt_view_cap: Option<Gas>,
+        max_concurrent_view_calls: Option<usize>,
+        view_account_cache_size: Option<usize>,
         runtime_config_store: Option<RuntimeConfigStore>,
         gc_num_epochs_to_keep: u64,
         trie_config: TrieConfig,
@@ -99,7 +102,13 @@ impl NightshadeRuntime {
         };
 
         let runtime = Runtime::new();
-        let trie_viewer = TrieViewer::new(trie_viewer_state_size_limit, max_gas_burnt_view);
+        let trie_viewer = TrieViewer::new(
+            trie_viewer_state_size_limit,
+            max_gas_burnt_view,
+            max_gas_burnt_view_cap,
+            max_concurrent_view_calls,
+            view_account_cache_size,
+        );
         let flat_storage_manager = FlatStorageManager::new(store.clone());
         let shard_uids: Vec<_> = genesis_config.shard_layout.shard_uids().collect();
         let tries = ShardTries::new(
@@ -149,6 +158,9 @@ impl NightshadeRuntime {
             epoch_manager,
             None,
             None,
+            None,
+            None,
+            None,
             Some(runtime_config_store),
             DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             Default::default(),
@@ -178,6 +190,9 @@ impl NightshadeRuntime {
             epoch_manager,
             None,
             None,
+            None,
+            None,
+            None,
             runtime_config_store,
             DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             trie_config,
@@ -1460,7 +1475,6 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
         epoch_info_provider: &dyn EpochInfoProvider,
         current_protocol_version: ProtocolVersion,
     ) -> Result<Vec<u8>, node_runtime::state_viewer::errors::CallFunctionError> {
-        let state_update = self.tries.new_trie_update_view(*shard_uid, state_root);
         let view_state = ViewApplyState {
             shard_id: shard_uid.shard_id(),
             block_height: height,
@@ -1471,9 +1485,15 @@ impl node_runtime::adapter::ViewRuntimeAdapter for NightshadeRuntime {
             block_timestamp,
             current_protocol_version,
             cache: Some(self.compiled_contract_cache.handle()),
+            storage_get_mode: None,
+            originator_id: None,
+            public_key: None,
+            max_gas_burnt: None,
         };
-        self.trie_viewer.call_function(
-            state_update,
+        self.trie_viewer.call_function_at_root(
+            &self.tries,
+            *shard_uid,
+            state_root,
             view_state,
             contract_id,
             method_name,
@@ -2,7 +2,9 @@ use std::collections::HashSet;
 
 use near_chain_primitives::Error;
 use near_primitives::block::Block;
+use near_primitives::hash::CryptoHash;
 use near_primitives::stateless_validation::ChunkEndorsement;
+use near_primitives::types::{AccountId, ShardId};
 
 use crate::Chain;
 
@@ -104,4 +106,46 @@ impl Chain {
         }
         Ok(())
     }
+
+    /// Returns the chunk validators which endorsed the chunk of every shard in
+    /// the block with the given hash, resolved from the endorsement signatures
+    /// in the block body and the order of the chunk validators assigned to the
+    /// chunk. The list is empty for the shards whose chunk wasn't included at
+    /// the height of the block, as the block doesn't carry endorsements for them.
+    /// The signatures aren't verified, this is done by
+    /// `validate_chunk_endorsements_in_block` when the block is processed.
+    pub fn block_chunk_endorsements(
+        &self,
+        block_hash: &CryptoHash,
+    ) -> Result<Vec<(ShardId, Vec<AccountId>)>, Error> {
+        let block = self.get_block(block_hash)?;
+        if block.chunks().len() != block.chunk_endorsements().len() {
+            return Err(Error::InvalidChunkEndorsement);
+        }
+        let epoch_id =
+            self.epoch_manager.get_epoch_id_from_prev_block(block.header().prev_hash())?;
+        let mut endorsements = vec![];
+        for (chunk_header, signatures) in block.chunks().iter().zip(block.chunk_endorsements()) {
+            if chunk_header.height_included() != block.header().height() {
+                endorsements.push((chunk_header.shard_id(), vec![]));
+                continue;
+            }
+            let chunk_validator_assignments = self.epoch_manager.get_chunk_validator_assignments(
+                &epoch_id,
+                chunk_header.shard_id(),
+                chunk_header.height_created(),
+            )?;
+            let ordered_chunk_validators = chunk_validator_assignments.ordered_chunk_validators();
+            if ordered_chunk_validators.len() != signatures.len() {
+                return Err(Error::InvalidChunkEndorsement);
+            }
+            let endorsers = ordered_chunk_validators
+                .into_iter()
+                .zip(signatures)
+                .filter_map(|(account_id, signature)| signature.is_some().then_some(account_id))
+                .collect();
+            endorsements.push((chunk_header.shard_id(), endorsers));
+        }
+        Ok(endorsements)
+    }
 }
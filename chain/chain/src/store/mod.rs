@@ -758,7 +758,8 @@ impl ChainStore {
 
 impl ChainStore {
     /// Returns outcomes on all forks generated by applying transaction or
-    /// receipt with the given id.
+    /// receipt with the given id. Includes outcomes of garbage collected
+    /// blocks which are still kept in DBCol::RetainedOutcomes.
     pub fn get_outcomes_by_id(
         &self,
         id: &CryptoHash,
@@ -768,6 +769,12 @@ impl ChainStore {
                 DBCol::TransactionResultForBlock,
                 id.as_ref(),
             )
+            .chain(
+                self.store.iter_prefix_ser::<ExecutionOutcomeWithProof>(
+                    DBCol::RetainedOutcomes,
+                    id.as_ref(),
+                ),
+            )
             .map(|item| {
                 let (key, outcome_with_proof) = item?;
                 let (_, block_hash) = get_outcome_id_block_hash_rev(key.as_ref())?;
@@ -73,6 +73,9 @@ pub struct StoreValidator {
     store: Store,
     inner: StoreValidatorCache,
     timeout: Option<i64>,
+    /// If set, the per-block checks only cover the blocks with heights in this
+    /// inclusive range.
+    height_range: Option<(BlockHeight, BlockHeight)>,
     start_time: Instant,
     pub is_archival: bool,
 
@@ -99,6 +102,7 @@ impl StoreValidator {
             store: store,
             inner: StoreValidatorCache::new(),
             timeout: None,
+            height_range: None,
             start_time: Clock::real().now(),
             is_archival,
             errors: vec![],
@@ -108,6 +112,16 @@ impl StoreValidator {
     pub fn set_timeout(&mut self, timeout: i64) {
         self.timeout = Some(timeout)
     }
+    /// Limits the per-block checks to the blocks with heights in the inclusive
+    /// range. The final checks, which need a sweep over all the data, are
+    /// skipped then.
+    pub fn set_height_range(&mut self, from_height: BlockHeight, to_height: BlockHeight) {
+        self.height_range = Some((from_height, to_height))
+    }
+    fn is_height_in_range(&self, height: BlockHeight) -> bool {
+        self.height_range
+            .map_or(true, |(from_height, to_height)| from_height <= height && height <= to_height)
+    }
     pub fn is_failed(&self) -> bool {
         self.tests == 0 || !self.errors.is_empty()
     }
@@ -129,16 +143,24 @@ impl StoreValidator {
                 DBCol::BlockHeader => {
                     let block_hash = CryptoHash::try_from(key_ref)?;
                     let header = BlockHeader::try_from_slice(value_ref)?;
+                    if !self.is_height_in_range(header.height()) {
+                        continue;
+                    }
                     // Block Header Hash is valid
                     self.check(&validate::block_header_hash_validity, &block_hash, &header, col);
                     // Block Header Height is valid
                     self.check(&validate::block_header_height_validity, &block_hash, &header, col);
                     // Block Header can be indexed by Height
                     self.check(&validate::header_hash_indexed_by_height, &block_hash, &header, col);
+                    // Block for a Block Header on the Canonical Chain exists
+                    self.check(&validate::header_block_exists, &block_hash, &header, col);
                 }
                 DBCol::Block => {
                     let block_hash = CryptoHash::try_from(key_ref)?;
                     let block = Block::try_from_slice(value_ref)?;
+                    if !self.is_height_in_range(block.header().height()) {
+                        continue;
+                    }
                     // Block Hash is valid
                     self.check(&validate::block_hash_validity, &block_hash, &block, col);
                     // Block Height is valid
@@ -149,6 +171,8 @@ impl StoreValidator {
                     self.check(&validate::block_header_exists, &block_hash, &block, col);
                     // Chunks for current Block exist
                     self.check(&validate::block_chunks_exist, &block_hash, &block, col);
+                    // Chunk Extras exist for all tracked Shards of current Block
+                    self.check(&validate::block_chunk_extras_exist, &block_hash, &block, col);
                     // Chunks for current Block have Height Created not higher than Block Height
                     self.check(&validate::block_chunks_height_validity, &block_hash, &block, col);
                     // BlockInfo for current Block exists
@@ -160,6 +184,9 @@ impl StoreValidator {
                 }
                 DBCol::BlockHeight => {
                     let height = BlockHeight::try_from_slice(key_ref)?;
+                    if !self.is_height_in_range(height) {
+                        continue;
+                    }
                     let hash = CryptoHash::try_from(value_ref)?;
                     // Block on the Canonical Chain is stored properly
                     self.check(&validate::canonical_header_validity, &height, &hash, col);
@@ -170,6 +197,9 @@ impl StoreValidator {
                 DBCol::Chunks => {
                     let chunk_hash = ChunkHash::try_from_slice(key_ref)?;
                     let shard_chunk = ShardChunk::try_from_slice(value_ref)?;
+                    if !self.is_height_in_range(shard_chunk.height_created()) {
+                        continue;
+                    }
                     // Chunk Hash is valid
                     self.check(&validate::chunk_hash_validity, &chunk_hash, &shard_chunk, col);
                     // Chunk Height Created is not lower than Chunk Tail
@@ -207,12 +237,18 @@ impl StoreValidator {
                 }
                 DBCol::ChunkHashesByHeight => {
                     let height = BlockHeight::try_from_slice(key_ref)?;
+                    if !self.is_height_in_range(height) {
+                        continue;
+                    }
                     let chunk_hashes = HashSet::<ChunkHash>::try_from_slice(value_ref)?;
                     // ShardChunk which can be indexed by Height exists
                     self.check(&validate::chunk_of_height_exists, &height, &chunk_hashes, col);
                 }
                 DBCol::HeaderHashesByHeight => {
                     let height = BlockHeight::try_from_slice(key_ref)?;
+                    if !self.is_height_in_range(height) {
+                        continue;
+                    }
                     let header_hashes = HashSet::<CryptoHash>::try_from_slice(value_ref)?;
                     // Headers which can be indexed by Height exists
                     self.check(
@@ -346,6 +382,10 @@ impl StoreValidator {
                 return;
             }
         }
+        if self.height_range.is_some() {
+            // Not all the blocks have been counted, so the final checks would fail
+            return;
+        }
 
         // Final checks
         // There is no more than one Block which Height is lower than Tail and not equal to Genesis
@@ -383,7 +423,9 @@ impl StoreValidator {
 mod tests {
     use near_async::time::Clock;
     use near_chain_configs::{Genesis, MutableConfigValue};
+    use near_epoch_manager::shard_tracker::TrackedConfig;
     use near_epoch_manager::EpochManager;
+    use near_primitives::shard_layout::get_block_shard_uid;
     use near_store::genesis::initialize_genesis_state;
     use near_store::test_utils::create_test_store;
 
@@ -454,6 +496,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_height_range() {
+        let (_chain, mut sv) = init();
+        sv.set_height_range(1, 10);
+        assert!(sv.validate_col(DBCol::Block).is_ok());
+        assert!(sv.validate_col(DBCol::BlockHeader).is_ok());
+        // Genesis is the only block and it's out of the range.
+        assert_eq!(sv.tests_done(), 0);
+
+        sv.set_height_range(0, 10);
+        assert!(sv.validate_col(DBCol::Block).is_ok());
+        assert!(sv.tests_done() > 0);
+    }
+
     #[test]
     fn test_db_corruption() {
         let (chain, mut sv) = init();
@@ -478,6 +534,24 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_header_without_block() {
+        let (chain, mut sv) = init();
+        validate::head_tail_validity(&mut sv).unwrap();
+        let header = chain.get_block_header_by_height(0).unwrap();
+        assert!(validate::header_block_exists(&mut sv, header.hash(), &header).is_ok());
+
+        let mut store_update = chain.chain_store().store().store_update();
+        store_update.delete(DBCol::Block, header.hash().as_ref());
+        store_update.commit().unwrap();
+        match validate::header_block_exists(&mut sv, header.hash(), &header) {
+            Err(StoreValidatorError::DBNotFound { reason, .. }) => {
+                assert!(reason.contains(&format!("{:?}", header.hash())));
+            }
+            _ => assert!(false),
+        }
+    }
+
     #[test]
     fn test_discrepancy() {
         let (chain, mut sv) = init();
@@ -490,6 +564,31 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_chunk_extra_missing() {
+        let (chain, mut sv) = init();
+        sv.shard_tracker = ShardTracker::new(TrackedConfig::AllShards, sv.epoch_manager.clone());
+        validate::head_tail_validity(&mut sv).unwrap();
+        let block = chain.get_block_by_height(0).unwrap();
+        assert!(validate::block_chunk_extras_exist(&mut sv, block.hash(), &block).is_ok());
+
+        let shard_uid = sv.epoch_manager.shard_id_to_uid(0, block.header().epoch_id()).unwrap();
+        let mut store_update = chain.chain_store().store().store_update();
+        store_update.delete(DBCol::ChunkExtra, &get_block_shard_uid(block.hash(), &shard_uid));
+        store_update.commit().unwrap();
+        match validate::block_chunk_extras_exist(&mut sv, block.hash(), &block) {
+            Err(StoreValidatorError::DBNotFound { reason, .. }) => {
+                assert!(reason.contains(&format!("{:?}", block.hash())));
+                assert!(reason.contains(&format!("{:?}", shard_uid)));
+            }
+            _ => assert!(false),
+        }
+
+        // Shards which are not tracked are skipped.
+        sv.shard_tracker = ShardTracker::new_empty(sv.epoch_manager.clone());
+        assert!(validate::block_chunk_extras_exist(&mut sv, block.hash(), &block).is_ok());
+    }
+
     #[test]
     fn test_validation_failed() {
         let (_chain, mut sv) = init();
@@ -183,6 +183,34 @@ pub(crate) fn block_header_height_validity(
     Ok(())
 }
 
+pub(crate) fn header_block_exists(
+    sv: &mut StoreValidator,
+    block_hash: &CryptoHash,
+    header: &BlockHeader,
+) -> Result<(), StoreValidatorError> {
+    let height = header.height();
+    if (height <= sv.inner.tail && height != sv.config.genesis_height) || height > sv.inner.head {
+        // The Block is either GC-ed or not processed yet
+        return Ok(());
+    }
+    let canonical_hash = unwrap_or_err!(
+        sv.store.get_ser::<CryptoHash>(DBCol::BlockHeight, &index_to_bytes(height)),
+        "Can't get Block Hash from BlockHeight from storage"
+    );
+    if canonical_hash.as_ref() != Some(block_hash) {
+        // Headers on forks may legitimately have no Block, e.g. if the Block was never received
+        return Ok(());
+    }
+    let block = unwrap_or_err_db!(
+        sv.store.get_ser::<Block>(DBCol::Block, block_hash.as_ref()),
+        "Can't get Block {:?} for Block Header at height {} from storage",
+        block_hash,
+        height
+    );
+    check_discrepancy!(block.hash(), block_hash, "Block doesn't match its Block Header");
+    Ok(())
+}
+
 pub(crate) fn block_hash_validity(
     _sv: &mut StoreValidator,
     block_hash: &CryptoHash,
@@ -405,6 +433,44 @@ pub(crate) fn block_chunks_exist(
     Ok(())
 }
 
+pub(crate) fn block_chunk_extras_exist(
+    sv: &mut StoreValidator,
+    block_hash: &CryptoHash,
+    block: &Block,
+) -> Result<(), StoreValidatorError> {
+    let height = block.header().height();
+    if height < sv.inner.tail || height > sv.inner.head {
+        // Chunk extras are only expected for the blocks between Tail and Head
+        return Ok(());
+    }
+    for chunk_header in block.chunks().iter() {
+        let shard_id = chunk_header.shard_id();
+        if !sv.shard_tracker.care_about_shard(
+            sv.me.as_ref(),
+            block.header().prev_hash(),
+            shard_id,
+            true,
+        ) {
+            continue;
+        }
+        let shard_uid = sv
+            .epoch_manager
+            .shard_id_to_uid(shard_id, block.header().epoch_id())
+            .map_err(|err| StoreValidatorError::DBNotFound {
+                func_name: "get_shard_layout",
+                reason: err.to_string(),
+            })?;
+        let block_shard_uid = get_block_shard_uid(block_hash, &shard_uid);
+        unwrap_or_err_db!(
+            sv.store.get_ser::<ChunkExtra>(DBCol::ChunkExtra, block_shard_uid.as_ref()),
+            "Can't get chunk extra for block {:?} and shard {:?} from storage",
+            block_hash,
+            shard_uid
+        );
+    }
+    Ok(())
+}
+
 pub(crate) fn block_chunks_height_validity(
     _sv: &mut StoreValidator,
     _block_hash: &CryptoHash,
@@ -1,8 +1,9 @@
 use crate::near_chain_primitives::error::BlockKnownError;
 use crate::test_utils::{setup, wait_for_all_blocks_in_processing};
-use crate::{Block, BlockProcessingArtifact, ChainStoreAccess, Error};
+use crate::{Block, BlockProcessingArtifact, ChainStoreAccess, Error, ProductionSlot};
 use assert_matches::assert_matches;
 use near_async::time::{Clock, Duration, FakeClock, Utc};
+use near_epoch_manager::EpochManagerAdapter;
 use near_o11y::testonly::init_test_logger;
 use near_primitives::hash::CryptoHash;
 use near_primitives::test_utils::TestBlockBuilder;
@@ -139,6 +140,29 @@ fn build_chain_with_skips_and_forks() {
     assert_matches!(chain.process_block_test(&None, c4), Err(Error::CannotBeFinalized));
 }
 
+#[test]
+fn gas_price_history() {
+    init_test_logger();
+    let (mut chain, _, _, signer) = setup(Clock::real());
+    let genesis = chain.get_block(&chain.genesis().hash().clone()).unwrap();
+    let b1 = TestBlockBuilder::new(Clock::real(), &genesis, signer.clone()).build();
+    let b3 = TestBlockBuilder::new(Clock::real(), &b1, signer.clone()).height(3).build();
+    let b4 = TestBlockBuilder::new(Clock::real(), &b3, signer).build();
+    chain.process_block_test(&None, b1).unwrap();
+    chain.process_block_test(&None, b3).unwrap();
+    chain.process_block_test(&None, b4).unwrap();
+
+    // Height 2 was skipped and the range is clamped to the head.
+    let history = chain.gas_price_history(0, 10).unwrap();
+    let expected: Vec<_> = [0, 1, 3, 4]
+        .into_iter()
+        .map(|height| (height, chain.get_block_header_by_height(height).unwrap().next_gas_price()))
+        .collect();
+    assert_eq!(history, expected);
+    assert_eq!(chain.gas_price_history(2, 3).unwrap(), expected[2..3]);
+    assert!(chain.gas_price_history(4, 3).unwrap().is_empty());
+}
+
 /// Verifies that getting block by its height are updated correctly when blocks from different forks are
 /// processed, especially when certain heights are skipped.
 /// Chain looks as follows (variable name + height):
@@ -247,3 +271,41 @@ fn next_blocks() {
     assert_eq!(chain.mut_chain_store().get_next_block_hash(&b1_hash).unwrap(), b3_hash);
     assert_eq!(chain.mut_chain_store().get_next_block_hash(&b3_hash).unwrap(), b4_hash);
 }
+
+#[test]
+fn producers_at_height() {
+    init_test_logger();
+    let (chain, epoch_manager, _, _) = setup(Clock::real());
+    let epoch_id = chain.head().unwrap().epoch_id;
+    let height = 5;
+    let assignment = chain.producers_at_height(&epoch_id, height).unwrap();
+    assert_eq!(assignment.height, height);
+    assert_eq!(
+        assignment.block_producer,
+        epoch_manager.get_block_producer(&epoch_id, height).unwrap()
+    );
+    let shard_ids = epoch_manager.shard_ids(&epoch_id).unwrap();
+    assert_eq!(assignment.chunk_producers.len(), shard_ids.len());
+    for ((shard_id, chunk_producer), expected_shard_id) in
+        assignment.chunk_producers.into_iter().zip(shard_ids)
+    {
+        assert_eq!(shard_id, expected_shard_id);
+        assert_eq!(
+            chunk_producer,
+            epoch_manager.get_chunk_producer(&epoch_id, height, shard_id).unwrap()
+        );
+    }
+}
+
+#[test]
+fn next_production_slot() {
+    init_test_logger();
+    let (chain, _, _, _) = setup(Clock::real());
+    // "test" is the only validator, so it produces the very next block.
+    let head = chain.head().unwrap();
+    assert_eq!(
+        chain.next_production_slot(&"test".parse().unwrap()).unwrap(),
+        Some((head.height + 1, ProductionSlot::Block))
+    );
+    assert_eq!(chain.next_production_slot(&"other".parse().unwrap()).unwrap(), None);
+}
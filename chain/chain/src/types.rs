@@ -225,6 +225,9 @@ pub struct ChainConfig {
     pub background_migration_threads: usize,
     /// The resharding configuration.
     pub resharding_config: MutableConfigValue<ReshardingConfig>,
+    /// Whether to check the state restored by state sync against the
+    /// expected state root before finalizing it.
+    pub verify_memtrie_after_state_sync: bool,
 }
 
 impl ChainConfig {
@@ -236,6 +239,7 @@ impl ChainConfig {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            verify_memtrie_after_state_sync: false,
         }
     }
 }
@@ -4,8 +4,8 @@ use near_primitives::{
     hash::CryptoHash,
     merkle::MerklePath,
     receipt::Receipt,
-    sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunkHeader},
-    types::EpochId,
+    sharding::{ChunkHash, EncodedShardChunk, PartialEncodedChunk, ShardChunkHeader},
+    types::{AccountId, EpochId},
 };
 
 #[derive(Message, Debug, strum::IntoStaticStr, PartialEq)]
@@ -66,4 +66,19 @@ pub enum ShardsManagerRequestFromClient {
         epoch_id: EpochId,
         ancestor_hash: CryptoHash,
     },
+    /// Requests all parts of the given chunks, even for shards this node doesn't track, so that
+    /// they can be reconstructed and persisted in full. Completion is reported as for
+    /// RequestChunks. Best-effort; the requests are not retried.
+    RequestFullChunks { chunks_to_request: Vec<ShardChunkHeader> },
+    /// Requests exactly the given parts of a chunk whose header is already known, from the
+    /// given account or, if none is given, from a random block producer tracking the shard.
+    /// Meant for targeted repair of specific missing parts.
+    RequestChunkParts { chunk_hash: ChunkHash, part_ords: Vec<u64>, from: Option<AccountId> },
+    /// Checks whether the given chunk has been fully reconstructed and persisted in the chunk
+    /// store. The answer is sent back as ShardsManagerResponse::ChunkCompleteStatus.
+    CheckChunkComplete(ChunkHash),
+    /// Asks for the per (peer, shard) counts of answered part requests, to find peers which
+    /// fail to serve the parts. The answer is sent back as
+    /// ShardsManagerResponse::PartRequestStats.
+    GetPartRequestStats,
 }
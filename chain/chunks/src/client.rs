@@ -8,7 +8,7 @@ use near_pool::{InsertTransactionResult, PoolIteratorWrapper, TransactionPool};
 use near_primitives::shard_layout::{account_id_to_shard_uid, ShardLayout, ShardUId};
 use near_primitives::{
     epoch_manager::RngSeed,
-    sharding::{EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
+    sharding::{ChunkHash, EncodedShardChunk, PartialEncodedChunk, ShardChunk, ShardChunkHeader},
     transaction::SignedTransaction,
     types::{AccountId, ShardId},
 };
@@ -31,6 +31,32 @@ pub enum ShardsManagerResponse {
     /// block, so that if we are a block producer, we may create a block that contains
     /// this chunk now. The producer of this chunk is also provided.
     ChunkHeaderReadyForInclusion { chunk_header: ShardChunkHeader, chunk_producer: AccountId },
+    /// Answers ShardsManagerRequestFromClient::CheckChunkComplete, telling whether the chunk
+    /// has been fully reconstructed and persisted in the chunk store.
+    ChunkCompleteStatus { chunk_hash: ChunkHash, complete: bool },
+    /// Answers ShardsManagerRequestFromClient::GetPartRequestStats.
+    PartRequestStats(Vec<PartRequestStats>),
+}
+
+/// How well a peer serves the chunk part requests for a shard. Peers are
+/// identified by the account they were asked by.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartRequestStats {
+    pub account_id: AccountId,
+    pub shard_id: ShardId,
+    /// Number of part requests sent to the peer.
+    pub requested: u64,
+    /// Number of part requests to which the peer responded with some data.
+    pub answered: u64,
+}
+
+impl PartRequestStats {
+    pub fn success_rate(&self) -> f64 {
+        if self.requested == 0 {
+            return 0.;
+        }
+        self.answered as f64 / self.requested as f64
+    }
 }
 
 pub struct ShardedTransactionPool {
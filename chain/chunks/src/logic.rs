@@ -120,6 +120,20 @@ pub fn make_outgoing_receipts_proofs(
     Ok(it)
 }
 
+/// Checks that every receipt proof proves the inclusion of its receipts into
+/// the outgoing receipts root of the chunk header.
+pub fn validate_receipts_against_header(
+    receipt_proofs: &[ReceiptProof],
+    header: &ShardChunkHeader,
+) -> Result<(), Error> {
+    for proof in receipt_proofs {
+        if !proof.verify_against_receipt_root(header.prev_outgoing_receipts_root()) {
+            return Err(Error::ChainError(near_chain::Error::InvalidReceiptsProof));
+        }
+    }
+    Ok(())
+}
+
 pub fn make_partial_encoded_chunk_from_owned_parts_and_needed_receipts<'a>(
     header: &'a ShardChunkHeader,
     parts: impl Iterator<Item = &'a PartialEncodedChunkPart>,
@@ -63,3 +63,28 @@ pub static PARTIAL_ENCODED_CHUNK_FORWARD_CACHED_WITHOUT_PREV_BLOCK: Lazy<Counter
     .unwrap()
     },
 );
+
+pub static CHUNK_REQUEST_TO_COMPLETION_TIME: Lazy<near_o11y::metrics::HistogramVec> =
+    Lazy::new(|| {
+        near_o11y::metrics::try_create_histogram_vec(
+            "near_chunk_request_to_completion_time",
+            concat!(
+                "Time from adding a chunk to the request pool until all of its needed parts ",
+                "and receipts are available, in seconds",
+            ),
+            &["shard_id"],
+            Some(exponential_buckets(0.001, 2.0, 16).unwrap()),
+        )
+        .unwrap()
+    });
+
+pub static STALE_CHAIN_HEAD: Lazy<Counter> = Lazy::new(|| {
+    near_o11y::metrics::try_create_counter(
+        "near_shards_manager_stale_chain_head",
+        concat!(
+            "Number of times the ShardsManager found that its cached chain head wasn't ",
+            "updated by the client for longer than the configured interval"
+        ),
+    )
+    .unwrap()
+});
@@ -80,15 +80,17 @@
 
 use crate::adapter::ShardsManagerRequestFromClient;
 use crate::chunk_cache::{EncodedChunksCache, EncodedChunksCacheEntry};
-use crate::client::ShardsManagerResponse;
+use crate::client::{PartRequestStats, ShardsManagerResponse};
 use crate::logic::{
     cares_about_shard_this_or_next_epoch, chunk_needs_to_be_fetched_from_archival,
     decode_encoded_chunk, make_outgoing_receipts_proofs,
     make_partial_encoded_chunk_from_owned_parts_and_needed_receipts, need_part, need_receipt,
+    validate_receipts_against_header,
 };
 use crate::metrics;
 use ::time::ext::InstantExt as _;
 use actix::Actor;
+use borsh::{BorshDeserialize, BorshSerialize};
 use near_async::actix_wrapper::ActixWrapper;
 use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt};
 use near_async::messaging::{self, Handler, Sender};
@@ -98,7 +100,11 @@ use near_chain::byzantine_assert;
 use near_chain::chunks_store::ReadOnlyChunksStore;
 use near_chain::near_chain_primitives::error::Error::DBNotFoundErr;
 use near_chain::types::EpochManagerAdapter;
-use near_chain_configs::MutableValidatorSigner;
+use near_chain_configs::{
+    default_chunk_request_retry_jitter, default_max_chain_head_staleness,
+    default_max_chunk_response_bytes, default_max_chunk_response_parts, ClientConfig,
+    MutableValidatorSigner,
+};
 pub use near_chunks_primitives::Error;
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_network::shards_manager::ShardsManagerRequestFromNetwork;
@@ -110,6 +116,7 @@ use near_network::types::{NetworkRequests, PeerManagerMessageRequest};
 use near_performance_metrics_macros::perf;
 use near_primitives::block::Tip;
 use near_primitives::congestion_info::CongestionInfo;
+use near_primitives::epoch_manager::block_info::BlockInfo;
 use near_primitives::errors::EpochError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::merkle::{verify_path, MerklePath};
@@ -129,10 +136,12 @@ use near_primitives::utils::MaybeValidated;
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::version::ProtocolVersion;
 use near_primitives::{checked_feature, unwrap_or_return};
-use near_store::{DBCol, Store, HEADER_HEAD_KEY, HEAD_KEY};
+use near_store::{DBCol, Store, HEADER_HEAD_KEY, HEAD_KEY, TAIL_KEY};
+use rand::rngs::StdRng;
 use rand::seq::IteratorRandom;
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 use reed_solomon_erasure::galois_8::ReedSolomon;
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::num::NonZeroUsize;
 use std::sync::Arc;
@@ -145,6 +154,40 @@ const CHUNK_REQUEST_RETRY_MAX: time::Duration = time::Duration::seconds(1000);
 const CHUNK_FORWARD_CACHE_SIZE: usize = 1000;
 // Only request chunks from peers whose latest height >= chunk_height - CHUNK_REQUEST_PEER_HORIZON
 const CHUNK_REQUEST_PEER_HORIZON: BlockHeightDelta = 5;
+/// Key in `DBCol::Misc` under which the pending chunk requests are persisted.
+const CHUNK_REQUEST_POOL_KEY: &[u8] = b"CHUNK_REQUEST_POOL";
+/// Maximum number of chunks being fetched in full on request of the client at once.
+const MAX_FULL_CHUNK_REQUESTS: usize = 128;
+/// Maximum number of peers for which the part request stats are kept.
+const MAX_PART_REQUEST_STATS_PEERS: usize = 1000;
+/// Maximum number of chunks for which the target of the latest part request is remembered.
+const MAX_OUTSTANDING_PART_REQUESTS: usize = 10_000;
+
+/// Counters of the part requests sent to a peer for a shard.
+#[derive(Clone, Copy, Default)]
+struct PartRequestCounters {
+    requested: u64,
+    answered: u64,
+}
+
+/// A part request sent to a peer which hasn't been answered yet.
+struct OutstandingPartRequest {
+    shard_id: ShardId,
+    part_ords: HashSet<u64>,
+    /// Shards whose receipt proofs were requested.
+    receipt_shards: HashSet<ShardId>,
+}
+
+impl OutstandingPartRequest {
+    /// Whether the response contains any of the requested parts or receipt proofs.
+    fn answered_by(&self, response: &PartialEncodedChunkResponseMsg) -> bool {
+        response.parts.iter().any(|part| self.part_ords.contains(&part.part_ord))
+            || response
+                .receipts
+                .iter()
+                .any(|receipt| self.receipt_shards.contains(&receipt.1.to_shard_id))
+    }
+}
 
 #[derive(PartialEq, Eq)]
 pub enum ChunkStatus {
@@ -179,6 +222,17 @@ pub(crate) struct ChunkRequestInfo {
     last_requested: time::Instant,
 }
 
+/// Persisted form of a `ChunkRequestInfo`. Timestamps are not stored; a
+/// reloaded request is treated as if it was just added.
+#[derive(BorshSerialize, BorshDeserialize)]
+struct PersistedChunkRequest {
+    chunk_hash: ChunkHash,
+    height: BlockHeight,
+    ancestor_hash: CryptoHash,
+    prev_block_hash: CryptoHash,
+    shard_id: ShardId,
+}
+
 struct RequestPool {
     retry_duration: time::Duration,
     switch_to_others_duration: time::Duration,
@@ -254,11 +308,24 @@ pub struct ShardsManagerActor {
     shard_tracker: ShardTracker,
     peer_manager_adapter: Sender<PeerManagerMessageRequest>,
     client_adapter: Sender<ShardsManagerResponse>,
+    /// The numbers of parts come from the genesis number of block producer
+    /// seats and don't change across epochs, so the encoder built on
+    /// construction is never stale.
     rs: ReedSolomon,
 
     encoded_chunks: EncodedChunksCache,
     requested_partial_encoded_chunks: RequestPool,
     chunk_forwards_cache: lru::LruCache<ChunkHash, HashMap<u64, PartialEncodedChunkPart>>,
+    /// Maximum number of forwarded parts cached per chunk. Part ordinals are
+    /// always validated against the total number of parts, so this only
+    /// matters if it is lower than that.
+    max_forwarded_parts_per_chunk: usize,
+    /// Maximum number of parts and receipt proofs in a single response we
+    /// serve. Larger responses are split into several.
+    max_response_parts: usize,
+    /// Maximum size in bytes of a single response we serve, unless it
+    /// contains only one part or receipt proof.
+    max_response_bytes: usize,
 
     // This is a best-effort cache of the chain's head, not the source of truth. The source
     // of truth is in the chain store and written to by the Client.
@@ -269,7 +336,66 @@ pub struct ShardsManagerActor {
     // header_head is new, but we would only know that the older chunks are old because
     // header_head is much newer.
     chain_header_head: Tip,
+    /// When the cached chain heads were last updated by the client.
+    chain_heads_updated: time::Instant,
+    /// How long the cached chain heads may go without an update before we
+    /// warn that they are stale.
+    max_chain_head_staleness: time::Duration,
+    /// Whether the cached chain heads were found stale by the last check.
+    chain_heads_stale: bool,
     chunk_request_retry_period: Duration,
+    /// Fraction of `chunk_request_retry_period` by which the resends are jittered.
+    chunk_request_retry_jitter: f64,
+    /// Source of randomness for the jitter of the resends.
+    rng: StdRng,
+    /// If set, the pending chunk requests are persisted into this store
+    /// whenever they change, so that they can be resumed after a restart.
+    request_pool_store: Option<Store>,
+    /// The chunks of the requests persisted last, see `persist_request_pool`.
+    persisted_chunk_requests: HashSet<ChunkHash>,
+    /// Chunks which should be reconstructed and persisted in full even if we
+    /// don't track their shard, see `request_full_chunks`.
+    full_chunk_requests: HashSet<ChunkHash>,
+    /// Per (peer, shard) counters of the part requests, see `get_part_request_stats`.
+    part_request_stats: lru::LruCache<AccountId, HashMap<ShardId, PartRequestCounters>>,
+    /// The unanswered part requests sent for a chunk, by the peer they were
+    /// sent to. Only the latest request to each peer is kept.
+    outstanding_part_requests: lru::LruCache<ChunkHash, HashMap<AccountId, OutstandingPartRequest>>,
+}
+
+/// Splits `response` into responses with at most `max_parts` parts and receipt
+/// proofs, and at most `max_bytes` bytes, each. A part or receipt proof larger
+/// than `max_bytes` is sent in a response of its own.
+fn split_partial_encoded_chunk_response(
+    response: PartialEncodedChunkResponseMsg,
+    max_parts: usize,
+    max_bytes: usize,
+) -> Vec<PartialEncodedChunkResponseMsg> {
+    let PartialEncodedChunkResponseMsg { chunk_hash, parts, receipts } = response;
+    let new_response = || PartialEncodedChunkResponseMsg {
+        chunk_hash: chunk_hash.clone(),
+        parts: vec![],
+        receipts: vec![],
+    };
+    let mut responses = vec![new_response()];
+    let (mut num_items, mut num_bytes) = (0, 0usize);
+    let mut make_room = |responses: &mut Vec<PartialEncodedChunkResponseMsg>, size: usize| {
+        if num_items > 0 && (num_items >= max_parts || num_bytes.saturating_add(size) > max_bytes) {
+            responses.push(new_response());
+            (num_items, num_bytes) = (0, 0);
+        }
+        num_items += 1;
+        num_bytes = num_bytes.saturating_add(size);
+    };
+    for part in parts {
+        make_room(&mut responses, borsh::object_length(&part).unwrap_or(usize::MAX));
+        responses.last_mut().unwrap().parts.push(part);
+    }
+    for receipt in receipts {
+        make_room(&mut responses, borsh::object_length(&receipt).unwrap_or(usize::MAX));
+        responses.last_mut().unwrap().receipts.push(receipt);
+    }
+    responses
 }
 
 impl messaging::Actor for ShardsManagerActor {
@@ -299,7 +425,7 @@ pub fn start_shards_manager(
     client_adapter_for_shards_manager: Sender<ShardsManagerResponse>,
     validator_signer: MutableValidatorSigner,
     store: Store,
-    chunk_request_retry_period: Duration,
+    config: &ClientConfig,
 ) -> (actix::Addr<ActixWrapper<ShardsManagerActor>>, actix::ArbiterHandle) {
     let shards_manager_arbiter = actix::Arbiter::new().handle();
     // TODO: make some better API for accessing chain properties like head.
@@ -311,8 +437,8 @@ pub fn start_shards_manager(
         .get_ser::<Tip>(DBCol::BlockMisc, HEADER_HEAD_KEY)
         .unwrap()
         .expect("ShardsManager must be initialized after the chain is initialized");
-    let chunks_store = ReadOnlyChunksStore::new(store);
-    let shards_manager = ShardsManagerActor::new(
+    let chunks_store = ReadOnlyChunksStore::new(store.clone());
+    let mut shards_manager = ShardsManagerActor::new(
         Clock::real(),
         validator_signer,
         epoch_manager,
@@ -322,8 +448,18 @@ pub fn start_shards_manager(
         chunks_store,
         chain_head,
         chain_header_head,
-        chunk_request_retry_period,
+        config.chunk_request_retry_period,
     );
+    shards_manager
+        .set_max_response_size(config.max_chunk_response_parts, config.max_chunk_response_bytes);
+    shards_manager.set_max_chain_head_staleness(config.max_chain_head_staleness);
+    shards_manager.set_chunk_request_retry_jitter(config.chunk_request_retry_jitter);
+    if let Some(max_parts) = config.max_forwarded_parts_per_chunk {
+        shards_manager.set_max_forwarded_parts_per_chunk(max_parts);
+    }
+    if config.persist_chunk_request_pool {
+        shards_manager.enable_request_pool_persistence(store);
+    }
 
     let shards_manager_addr =
         ActixWrapper::<ShardsManagerActor>::start_in_arbiter(&shards_manager_arbiter, move |_| {
@@ -345,6 +481,7 @@ impl ShardsManagerActor {
         initial_chain_header_head: Tip,
         chunk_request_retry_period: Duration,
     ) -> Self {
+        let chain_heads_updated = clock.now().into();
         Self {
             clock,
             validator_signer,
@@ -368,33 +505,181 @@ impl ShardsManagerActor {
             chunk_forwards_cache: lru::LruCache::new(
                 NonZeroUsize::new(CHUNK_FORWARD_CACHE_SIZE).unwrap(),
             ),
+            max_forwarded_parts_per_chunk: usize::MAX,
+            max_response_parts: default_max_chunk_response_parts(),
+            max_response_bytes: default_max_chunk_response_bytes(),
             chain_head: initial_chain_head,
             chain_header_head: initial_chain_header_head,
+            chain_heads_updated,
+            max_chain_head_staleness: default_max_chain_head_staleness(),
+            chain_heads_stale: false,
             chunk_request_retry_period,
+            chunk_request_retry_jitter: default_chunk_request_retry_jitter(),
+            rng: StdRng::from_entropy(),
+            request_pool_store: None,
+            persisted_chunk_requests: HashSet::new(),
+            full_chunk_requests: HashSet::new(),
+            part_request_stats: lru::LruCache::new(
+                NonZeroUsize::new(MAX_PART_REQUEST_STATS_PEERS).unwrap(),
+            ),
+            outstanding_part_requests: lru::LruCache::new(
+                NonZeroUsize::new(MAX_OUTSTANDING_PART_REQUESTS).unwrap(),
+            ),
+        }
+    }
+
+    /// Persists the pending chunk requests into `store` whenever they change
+    /// and reloads whatever was persisted there before, so that fetching of
+    /// the missing chunks resumes right away after a restart.
+    pub fn enable_request_pool_persistence(&mut self, store: Store) {
+        self.load_request_pool(&store);
+        self.request_pool_store = Some(store);
+    }
+
+    /// Adds the requests persisted in `store` to the request pool, skipping the
+    /// ones for chunks below the chain tail which can no longer be applied.
+    fn load_request_pool(&mut self, store: &Store) {
+        let persisted = match store
+            .get_ser::<Vec<PersistedChunkRequest>>(DBCol::Misc, CHUNK_REQUEST_POOL_KEY)
+        {
+            Ok(persisted) => persisted.unwrap_or_default(),
+            Err(err) => {
+                warn!(target: "chunks", ?err, "Failed to load the persisted chunk requests");
+                return;
+            }
+        };
+        let tail = store.get_ser::<BlockHeight>(DBCol::BlockMisc, TAIL_KEY).ok().flatten();
+        let now: time::Instant = self.clock.now().into();
+        // Back-date the last request, so that the chunks are requested on the next resend.
+        let last_requested = now
+            .checked_sub_signed(self.requested_partial_encoded_chunks.retry_duration)
+            .unwrap_or(now);
+        let mut loaded = 0;
+        for request in persisted {
+            if tail.is_some_and(|tail| request.height < tail)
+                || self.requested_partial_encoded_chunks.contains_key(&request.chunk_hash)
+            {
+                continue;
+            }
+            self.requested_partial_encoded_chunks.insert(
+                request.chunk_hash,
+                ChunkRequestInfo {
+                    height: request.height,
+                    ancestor_hash: request.ancestor_hash,
+                    prev_block_hash: request.prev_block_hash,
+                    shard_id: request.shard_id,
+                    added: now,
+                    last_requested,
+                },
+            );
+            loaded += 1;
+        }
+        debug!(target: "chunks", loaded, "Loaded persisted chunk requests");
+    }
+
+    /// Writes a snapshot of the pending chunk requests to the store, if
+    /// persistence is enabled and the set of requests changed since the last
+    /// snapshot. The persisted fields of a request never change, so it is
+    /// enough to compare the chunk hashes.
+    fn persist_request_pool(&mut self) {
+        let Some(store) = &self.request_pool_store else {
+            return;
+        };
+        let requests = &self.requested_partial_encoded_chunks.requests;
+        if requests.len() == self.persisted_chunk_requests.len()
+            && requests.keys().all(|chunk_hash| self.persisted_chunk_requests.contains(chunk_hash))
+        {
+            return;
+        }
+        let persisted: Vec<PersistedChunkRequest> = self
+            .requested_partial_encoded_chunks
+            .requests
+            .iter()
+            .map(|(chunk_hash, request)| PersistedChunkRequest {
+                chunk_hash: chunk_hash.clone(),
+                height: request.height,
+                ancestor_hash: request.ancestor_hash,
+                prev_block_hash: request.prev_block_hash,
+                shard_id: request.shard_id,
+            })
+            .collect();
+        let mut store_update = store.store_update();
+        let result = store_update
+            .set_ser(DBCol::Misc, CHUNK_REQUEST_POOL_KEY, &persisted)
+            .and_then(|()| store_update.commit());
+        if let Err(err) = result {
+            warn!(target: "chunks", ?err, "Failed to persist the chunk requests");
+            return;
         }
+        self.persisted_chunk_requests = requests.keys().cloned().collect();
     }
 
     pub fn periodically_resend_chunk_requests(
         &mut self,
         delayed_action_runner: &mut dyn DelayedActionRunner<Self>,
     ) {
+        let delay = self.next_resend_delay();
         delayed_action_runner.run_later(
             "resend_chunk_requests",
-            self.chunk_request_retry_period,
+            delay,
             move |this, delayed_action_runner| {
                 this.resend_chunk_requests();
+                this.check_chain_head_staleness();
+                this.persist_request_pool();
                 this.periodically_resend_chunk_requests(delayed_action_runner);
             },
         )
     }
 
+    /// Time until the next resend of the chunk requests, which is the retry
+    /// period randomly shifted by up to the jitter fraction of it.
+    fn next_resend_delay(&mut self) -> Duration {
+        if self.chunk_request_retry_jitter <= 0.0 {
+            return self.chunk_request_retry_period;
+        }
+        let jitter = self.chunk_request_retry_jitter.min(1.0);
+        self.chunk_request_retry_period * (1.0 + self.rng.gen_range(-jitter..=jitter))
+    }
+
     fn update_chain_heads(&mut self, head: Tip, header_head: Tip) {
         self.encoded_chunks.update_largest_seen_height(
             head.height,
             &self.requested_partial_encoded_chunks.requests,
         );
+        // Full chunk requests aren't retried, so the ones whose chunk has left
+        // the cache without being completed will never be completed.
+        let encoded_chunks = &self.encoded_chunks;
+        self.full_chunk_requests.retain(|chunk_hash| encoded_chunks.get(chunk_hash).is_some());
         self.chain_head = head;
         self.chain_header_head = header_head;
+        self.chain_heads_updated = self.clock.now().into();
+        if std::mem::take(&mut self.chain_heads_stale) {
+            debug!(target: "chunks", "Cached chain heads are updated again");
+        }
+    }
+
+    /// Warns if the client hasn't updated the cached chain heads for longer
+    /// than `max_chain_head_staleness`, in which case decisions based on them
+    /// may be wrong. Only the first check that finds the heads stale warns,
+    /// until they are updated again. Returns whether the heads are stale.
+    fn check_chain_head_staleness(&mut self) -> bool {
+        let now: time::Instant = self.clock.now().into();
+        let staleness = now - self.chain_heads_updated;
+        if staleness <= self.max_chain_head_staleness {
+            return false;
+        }
+        if std::mem::replace(&mut self.chain_heads_stale, true) {
+            return true;
+        }
+        warn!(
+            target: "chunks",
+            ?staleness,
+            head_height = self.chain_head.height,
+            header_head_height = self.chain_header_head.height,
+            "Cached chain heads were not updated recently"
+        );
+        metrics::STALE_CHAIN_HEAD.inc();
+        true
     }
 
     fn request_partial_encoded_chunk(
@@ -510,40 +795,20 @@ impl ShardsManagerActor {
         for (target_account, part_ords) in bp_to_parts {
             // extra check that we are not sending request to ourselves.
             if no_account_id || me != target_account.as_ref() {
-                let prefer_peer = request_from_archival || rand::thread_rng().gen::<bool>();
-                debug!(
-                    target: "chunks",
-                    ?part_ords,
-                    shard_id,
-                    ?target_account,
-                    prefer_peer,
-                    "Requesting parts",
-                );
-
-                let request = PartialEncodedChunkRequestMsg {
-                    chunk_hash: chunk_hash.clone(),
-                    part_ords,
-                    tracking_shards: if target_account == shard_representative_target {
-                        shards_to_fetch_receipts.clone()
-                    } else {
-                        HashSet::new()
-                    },
+                let tracking_shards = if target_account == shard_representative_target {
+                    shards_to_fetch_receipts.clone()
+                } else {
+                    HashSet::new()
                 };
-                let target = AccountIdOrPeerTrackingShard {
-                    account_id: target_account,
-                    prefer_peer,
+                self.send_partial_encoded_chunk_request(
+                    chunk_hash,
+                    part_ords,
+                    tracking_shards,
+                    target_account,
                     shard_id,
-                    only_archival: request_from_archival,
-                    min_height: height.saturating_sub(CHUNK_REQUEST_PEER_HORIZON),
-                };
-
-                self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-                    NetworkRequests::PartialEncodedChunkRequest {
-                        target,
-                        request,
-                        create_time: self.clock.now(),
-                    },
-                ));
+                    height,
+                    request_from_archival,
+                );
             } else {
                 warn!(target: "client", "{:?} requests parts {:?} for chunk {:?} from self",
                     me, part_ords, chunk_hash
@@ -554,6 +819,166 @@ impl ShardsManagerActor {
         Ok(())
     }
 
+    /// Sends a PartialEncodedChunkRequestMsg for the given parts and receipt
+    /// proofs to the given target account, or to a peer tracking the shard.
+    fn send_partial_encoded_chunk_request(
+        &mut self,
+        chunk_hash: &ChunkHash,
+        part_ords: Vec<u64>,
+        tracking_shards: HashSet<ShardId>,
+        target_account: Option<AccountId>,
+        shard_id: ShardId,
+        height: BlockHeight,
+        request_from_archival: bool,
+    ) {
+        let prefer_peer = request_from_archival || rand::thread_rng().gen::<bool>();
+        debug!(
+            target: "chunks",
+            ?part_ords,
+            shard_id,
+            ?target_account,
+            prefer_peer,
+            "Requesting parts",
+        );
+
+        if let Some(account_id) = &target_account {
+            self.record_part_request(
+                chunk_hash,
+                account_id,
+                shard_id,
+                &part_ords,
+                &tracking_shards,
+            );
+        }
+        let request = PartialEncodedChunkRequestMsg {
+            chunk_hash: chunk_hash.clone(),
+            part_ords,
+            tracking_shards,
+        };
+        let target = AccountIdOrPeerTrackingShard {
+            account_id: target_account,
+            prefer_peer,
+            shard_id,
+            only_archival: request_from_archival,
+            min_height: height.saturating_sub(CHUNK_REQUEST_PEER_HORIZON),
+        };
+
+        self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+            NetworkRequests::PartialEncodedChunkRequest {
+                target,
+                request,
+                create_time: self.clock.now(),
+            },
+        ));
+    }
+
+    /// Counts a part request sent to the given peer. Only the latest request
+    /// for a chunk to each peer can be answered, so if an earlier request to
+    /// the same peer is still outstanding, it stays unanswered.
+    fn record_part_request(
+        &mut self,
+        chunk_hash: &ChunkHash,
+        account_id: &AccountId,
+        shard_id: ShardId,
+        part_ords: &[u64],
+        receipt_shards: &HashSet<ShardId>,
+    ) {
+        let stats = self.part_request_stats.get_or_insert_mut(account_id.clone(), HashMap::new);
+        stats.entry(shard_id).or_default().requested += 1;
+        self.outstanding_part_requests.get_or_insert_mut(chunk_hash.clone(), HashMap::new).insert(
+            account_id.clone(),
+            OutstandingPartRequest {
+                shard_id,
+                part_ords: part_ords.iter().copied().collect(),
+                receipt_shards: receipt_shards.clone(),
+            },
+        );
+    }
+
+    /// Counts the outstanding part requests for the chunk which asked for any
+    /// of the parts or receipt proofs in the response as answered. Responses
+    /// don't tell which peer sent them, so they are matched by content.
+    fn record_part_request_answered(&mut self, response: &PartialEncodedChunkResponseMsg) {
+        let Some(requests) = self.outstanding_part_requests.get_mut(&response.chunk_hash) else {
+            return;
+        };
+        let mut answered = vec![];
+        requests.retain(|account_id, request| {
+            if request.answered_by(response) {
+                answered.push((account_id.clone(), request.shard_id));
+                return false;
+            }
+            true
+        });
+        if requests.is_empty() {
+            self.outstanding_part_requests.pop(&response.chunk_hash);
+        }
+        for (account_id, shard_id) in answered {
+            if let Some(stats) = self.part_request_stats.get_mut(&account_id) {
+                stats.entry(shard_id).or_default().answered += 1;
+            }
+        }
+    }
+
+    /// Snapshot of the per (peer, shard) part request counters.
+    pub fn get_part_request_stats(&self) -> Vec<PartRequestStats> {
+        let mut result = vec![];
+        for (account_id, stats) in self.part_request_stats.iter() {
+            for (shard_id, counters) in stats {
+                result.push(PartRequestStats {
+                    account_id: account_id.clone(),
+                    shard_id: *shard_id,
+                    requested: counters.requested,
+                    answered: counters.answered,
+                });
+            }
+        }
+        result
+    }
+
+    /// Requests exactly the given parts of a chunk whose header is already
+    /// known, either from `from` or from a random block producer tracking the
+    /// shard. Unlike `request_partial_encoded_chunk`, this does not consult
+    /// part ownership or the parts we already have, which makes it suitable
+    /// for targeted repair of specific parts.
+    fn request_chunk_parts(
+        &mut self,
+        chunk_hash: &ChunkHash,
+        part_ords: Vec<u64>,
+        from: Option<AccountId>,
+        me: Option<&AccountId>,
+    ) -> Result<(), near_chain::Error> {
+        let Some(entry) = self.encoded_chunks.get(chunk_hash) else {
+            return Err(near_chain::Error::ChunkMissing(chunk_hash.clone()));
+        };
+        let header = entry.header.clone();
+        let num_total_parts = self.epoch_manager.num_total_parts() as u64;
+        if let Some(part_ord) = part_ords.iter().find(|part_ord| **part_ord >= num_total_parts) {
+            return Err(near_chain::Error::Other(format!(
+                "part ordinal {} out of range, chunk has {} parts",
+                part_ord, num_total_parts
+            )));
+        }
+        let target_account = match from {
+            Some(account_id) => Some(account_id),
+            None => self.get_random_target_tracking_shard(
+                header.prev_block_hash(),
+                header.shard_id(),
+                me,
+            )?,
+        };
+        self.send_partial_encoded_chunk_request(
+            chunk_hash,
+            part_ords,
+            HashSet::new(),
+            target_account,
+            header.shard_id(),
+            header.height_created(),
+            false,
+        );
+        Ok(())
+    }
+
     /// Get a random shard block producer that is not me.
     fn get_random_target_tracking_shard(
         &self,
@@ -779,6 +1204,60 @@ impl ShardsManagerActor {
         }
     }
 
+    /// Requests all parts of the given chunks, even for shards we don't track,
+    /// so that they can be reconstructed and persisted in full. The chunks
+    /// may be complete already, e.g. if we only stored our own parts of them.
+    /// This is best-effort: the requests are sent once and not retried, it is
+    /// up to the caller to request the chunks again if they are still missing.
+    fn request_full_chunks(
+        &mut self,
+        chunks_to_request: Vec<ShardChunkHeader>,
+        me: Option<&AccountId>,
+    ) {
+        let _span = debug_span!(
+            target: "chunks",
+            "request_full_chunks",
+            num_chunks_to_request = chunks_to_request.len())
+        .entered();
+        for chunk_header in chunks_to_request {
+            let chunk_hash = chunk_header.chunk_hash();
+            if !self.full_chunk_requests.contains(&chunk_hash)
+                && self.full_chunk_requests.len() >= MAX_FULL_CHUNK_REQUESTS
+            {
+                warn!(target: "chunks", ?chunk_hash, "Too many full chunk requests, dropping");
+                continue;
+            }
+            self.full_chunk_requests.insert(chunk_hash.clone());
+            let num_parts =
+                self.encoded_chunks.get_or_insert_from_header(&chunk_header).parts.len();
+            if num_parts >= self.epoch_manager.num_data_parts() {
+                if let Err(err) = self.try_process_chunk_parts_and_receipts(&chunk_header, me) {
+                    warn!(target: "chunks", ?err, ?chunk_hash, "Error completing full chunk");
+                }
+                continue;
+            }
+            let prev_block_hash = *chunk_header.prev_block_hash();
+            let request_from_archival = chunk_needs_to_be_fetched_from_archival(
+                &prev_block_hash,
+                &self.chain_header_head.last_block_hash,
+                self.epoch_manager.as_ref(),
+            )
+            .unwrap_or(false);
+            if let Err(err) = self.request_partial_encoded_chunk(
+                chunk_header.height_created(),
+                &prev_block_hash,
+                chunk_header.shard_id(),
+                &chunk_hash,
+                true,
+                false,
+                request_from_archival,
+                me,
+            ) {
+                warn!(target: "chunks", ?err, ?chunk_hash, "Error requesting full chunk");
+            }
+        }
+    }
+
     /// Request chunks for an orphan block.
     /// `epoch_id`: epoch_id of the orphan block
     /// `ancestor_hash`: because BlockInfo for the immediate parent of an orphan block is not ready,
@@ -878,15 +1357,21 @@ impl ShardsManagerActor {
         let elapsed = (self.clock.now().signed_duration_since(started)).as_seconds_f64();
         let labels = [
             source.name_for_metrics(),
-            if response.parts.is_empty() && response.receipts.is_empty() { "failed" } else { "ok" },
+            if Self::served_from(source, &response).is_some() { "ok" } else { "failed" },
         ];
         metrics::PARTIAL_ENCODED_CHUNK_REQUEST_PROCESSING_TIME
             .with_label_values(&labels)
             .observe(elapsed);
 
-        self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
-            NetworkRequests::PartialEncodedChunkResponse { route_back, response },
-        ));
+        for response in split_partial_encoded_chunk_response(
+            response,
+            self.max_response_parts,
+            self.max_response_bytes,
+        ) {
+            self.peer_manager_adapter.send(PeerManagerMessageRequest::NetworkRequests(
+                NetworkRequests::PartialEncodedChunkResponse { route_back, response },
+            ));
+        }
     }
 
     /// Finds the parts and receipt proofs asked for in the request, and returns a response
@@ -965,6 +1450,39 @@ impl ShardsManagerActor {
         (PartialEncodedChunkResponseSource::ShardChunkOnDisk, response)
     }
 
+    /// Reports where a request for the given parts of the chunk would be served
+    /// from, for debugging how a node serves old chunks: from the cache, from the
+    /// stored partial chunk, or, on archival nodes after the partial chunk was
+    /// garbage collected, by encoding the stored full chunk again. Runs the same
+    /// lookup as an actual request. Returns `None` if none of the parts would be
+    /// served.
+    pub fn chunk_response_source(
+        &self,
+        chunk_hash: &ChunkHash,
+        part_ords: &[u64],
+    ) -> Option<PartialEncodedChunkResponseSource> {
+        let request = PartialEncodedChunkRequestMsg {
+            chunk_hash: chunk_hash.clone(),
+            part_ords: part_ords.to_vec(),
+            tracking_shards: HashSet::new(),
+        };
+        let (source, response) = self.prepare_partial_encoded_chunk_response_unsorted(request);
+        Self::served_from(source, &response)
+    }
+
+    /// Returns the source a response was served from, or `None` if it contains
+    /// nothing that was asked for.
+    fn served_from(
+        source: PartialEncodedChunkResponseSource,
+        response: &PartialEncodedChunkResponseMsg,
+    ) -> Option<PartialEncodedChunkResponseSource> {
+        if response.parts.is_empty() && response.receipts.is_empty() {
+            None
+        } else {
+            Some(source)
+        }
+    }
+
     /// Looks up the given part_ords and tracking_shards from the cache, appending
     /// any we have found into the response, and deleting those we have found from
     /// part_ords and tracking_shards.
@@ -1177,6 +1695,17 @@ impl ShardsManagerActor {
         }
     }
 
+    /// Drops everything known about a chunk whose header turned out to be
+    /// invalid: its cache entry, the request for it and the forwarded parts,
+    /// so that no data received for it is retained. Parts of the chunk received
+    /// later have to go through the header validation again.
+    fn invalidate_chunk(&mut self, chunk_hash: &ChunkHash, reason: &Error) {
+        debug!(target: "chunks", ?chunk_hash, ?reason, "Invalidating chunk");
+        self.encoded_chunks.remove(chunk_hash);
+        self.requested_partial_encoded_chunks.remove(chunk_hash);
+        self.chunk_forwards_cache.pop(chunk_hash);
+    }
+
     fn validate_partial_encoded_chunk_forward(
         &mut self,
         forward: &PartialEncodedChunkForwardMsg,
@@ -1233,34 +1762,55 @@ impl ShardsManagerActor {
         Ok(header)
     }
 
+    /// Returns whether the chunk has been fully reconstructed and persisted in the chunk store.
+    /// Note that only nodes tracking the shard of the chunk persist the full chunk.
+    pub fn chunk_complete(&self, chunk_hash: &ChunkHash) -> bool {
+        if self.encoded_chunks.get(chunk_hash).is_some_and(|entry| !entry.complete) {
+            return false;
+        }
+        self.store.get_chunk(chunk_hash).is_ok()
+    }
+
+    /// Sets how long the cached chain heads may go without an update from the
+    /// client before they are reported as stale.
+    pub fn set_max_chain_head_staleness(&mut self, max_staleness: time::Duration) {
+        self.max_chain_head_staleness = max_staleness;
+    }
+
+    /// Caps the number of parts and receipt proofs, and the size in bytes, of a
+    /// single PartialEncodedChunkResponse served to peers.
+    pub fn set_max_response_size(&mut self, max_parts: usize, max_bytes: usize) {
+        self.max_response_parts = max_parts.max(1);
+        self.max_response_bytes = max_bytes;
+    }
+
+    /// Sets the fraction of the retry period by which the resends of the chunk
+    /// requests are jittered. A jitter of 0 makes the resends strictly periodic.
+    pub fn set_chunk_request_retry_jitter(&mut self, jitter: f64) {
+        self.chunk_request_retry_jitter = jitter;
+    }
+
+    /// Sets the maximum number of forwarded parts cached per chunk.
+    pub fn set_max_forwarded_parts_per_chunk(&mut self, max_parts: usize) {
+        self.max_forwarded_parts_per_chunk = max_parts;
+    }
+
     fn insert_forwarded_chunk(&mut self, forward: PartialEncodedChunkForwardMsg) {
-        let chunk_hash = forward.chunk_hash.clone();
-        let num_total_parts = self.epoch_manager.num_total_parts() as u64;
-        match self.chunk_forwards_cache.get_mut(&chunk_hash) {
-            None => {
-                // Never seen this chunk hash before, collect the parts and cache them
-                let parts = forward.parts.into_iter().filter_map(|part| {
-                    let part_ord = part.part_ord;
-                    if part_ord > num_total_parts {
-                        warn!(target: "chunks", "Received chunk part with part_ord greater than the total number of chunks");
-                        None
-                    } else {
-                        Some((part_ord, part))
-                    }
-                }).collect();
-                self.chunk_forwards_cache.put(chunk_hash, parts);
+        let num_total_parts = self.epoch_manager.num_total_parts();
+        let max_parts = self.max_forwarded_parts_per_chunk.min(num_total_parts);
+        let existing_parts =
+            self.chunk_forwards_cache.get_or_insert_mut(forward.chunk_hash.clone(), HashMap::new);
+        for part in forward.parts {
+            let part_ord = part.part_ord;
+            if part_ord >= num_total_parts as u64 {
+                warn!(target: "chunks", part_ord, num_total_parts, "Received chunk part with part_ord greater than the total number of parts");
+                continue;
             }
-
-            Some(existing_parts) => {
-                for part in forward.parts {
-                    let part_ord = part.part_ord;
-                    if part_ord > num_total_parts {
-                        warn!(target: "chunks", "Received chunk part with part_ord greater than the total number of chunks");
-                        continue;
-                    }
-                    existing_parts.insert(part_ord, part);
-                }
+            if existing_parts.len() >= max_parts && !existing_parts.contains_key(&part_ord) {
+                warn!(target: "chunks", chunk_hash = ?forward.chunk_hash, max_parts, "Too many forwarded parts for the chunk, ignoring the rest");
+                break;
             }
+            existing_parts.insert(part_ord, part);
         }
     }
 
@@ -1324,6 +1874,16 @@ impl ShardsManagerActor {
     // first when the chunk entry is inserted in `encoded_chunks`
     // then in `process_partial_encoded_chunk` after checking the previous block is ready
     fn validate_chunk_header(&self, header: &ShardChunkHeader) -> Result<(), Error> {
+        self.validate_chunk_header_with_signature(header, None)
+    }
+
+    /// Same as `validate_chunk_header`, but takes the result of verifying the signature
+    /// against the previous block of the chunk, if it has already been verified.
+    fn validate_chunk_header_with_signature(
+        &self,
+        header: &ShardChunkHeader,
+        signature_verified: Option<bool>,
+    ) -> Result<(), Error> {
         let chunk_hash = header.chunk_hash();
         let _span = debug_span!(target: "chunks", "validate_chunk_header", ?chunk_hash).entered();
         // 1.  check signature
@@ -1361,7 +1921,16 @@ impl ShardsManagerActor {
             }
         };
 
-        if !self.epoch_manager.verify_chunk_header_signature(header, &epoch_id, &ancestor_hash)? {
+        let signature_valid = match signature_verified {
+            // The verified signature only applies if the previous block is known.
+            Some(valid) if ancestor_hash == *header.prev_block_hash() => valid,
+            _ => self.epoch_manager.verify_chunk_header_signature(
+                header,
+                &epoch_id,
+                &ancestor_hash,
+            )?,
+        };
+        if !signature_valid {
             return if epoch_id_confirmed {
                 byzantine_assert!(false);
                 Err(Error::InvalidChunkSignature)
@@ -1396,6 +1965,72 @@ impl ShardsManagerActor {
         }
     }
 
+    /// Verifies chunk producer signatures of a batch of chunk headers, e.g. when the
+    /// chunks waiting for a block are processed at once after the block is accepted.
+    /// Each entry is `(header, epoch_id, last_known_hash)`, with the same meaning as the
+    /// arguments of `EpochManagerAdapter::verify_chunk_header_signature`.
+    /// Headers are grouped by epoch so that the epoch info, chunk producer keys and
+    /// slashing info are looked up once per group instead of once per chunk.
+    /// Returns one result per header, in the same order as the input.
+    fn verify_chunk_header_signatures_batch(
+        &self,
+        headers: &[(&ShardChunkHeader, EpochId, CryptoHash)],
+    ) -> Vec<Result<bool, Error>> {
+        let mut headers_by_epoch: HashMap<EpochId, Vec<usize>> = HashMap::new();
+        for (index, (_, epoch_id, _)) in headers.iter().enumerate() {
+            headers_by_epoch.entry(*epoch_id).or_default().push(index);
+        }
+
+        let mut results: Vec<Option<Result<bool, Error>>> =
+            std::iter::repeat_with(|| None).take(headers.len()).collect();
+        for (epoch_id, indices) in headers_by_epoch {
+            let epoch_info = match self.epoch_manager.get_epoch_info(&epoch_id) {
+                Ok(epoch_info) => epoch_info,
+                Err(err) => {
+                    for index in indices {
+                        results[index] = Some(Err(err.clone().into()));
+                    }
+                    continue;
+                }
+            };
+            let mut chunk_producers: HashMap<(BlockHeight, ShardId), ValidatorStake> =
+                HashMap::new();
+            let mut block_infos: HashMap<CryptoHash, Arc<BlockInfo>> = HashMap::new();
+            for index in indices {
+                let (header, _, last_known_hash) = headers[index];
+                let mut verify = || -> Result<bool, Error> {
+                    let key = (header.height_created(), header.shard_id());
+                    let chunk_producer = match chunk_producers.entry(key) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            let account_id =
+                                self.epoch_manager.get_chunk_producer(&epoch_id, key.0, key.1)?;
+                            let chunk_producer =
+                                epoch_info
+                                    .get_validator_by_account(&account_id)
+                                    .ok_or(EpochError::NotAValidator(account_id, epoch_id))?;
+                            entry.insert(chunk_producer)
+                        }
+                    };
+                    let block_info = match block_infos.entry(last_known_hash) {
+                        Entry::Occupied(entry) => entry.into_mut(),
+                        Entry::Vacant(entry) => {
+                            entry.insert(self.epoch_manager.get_block_info(&last_known_hash)?)
+                        }
+                    };
+                    if block_info.slashed().contains_key(chunk_producer.account_id()) {
+                        return Ok(false);
+                    }
+                    Ok(header
+                        .signature()
+                        .verify(header.chunk_hash().as_ref(), chunk_producer.public_key()))
+                };
+                results[index] = Some(verify());
+            }
+        }
+        results.into_iter().map(|result| result.unwrap()).collect()
+    }
+
     /// Inserts the header if it is not already known, and process the forwarded chunk parts cached
     /// for this chunk, if any. Returns true if the header was newly inserted or forwarded parts
     /// were newly processed.
@@ -1472,7 +2107,7 @@ impl ShardsManagerActor {
         // Verify the partial encoded chunk is valid and worth processing
         // 1.a Leave if we received known chunk
         if let Some(entry) = self.encoded_chunks.get(&chunk_hash) {
-            if entry.complete {
+            if entry.complete && !self.full_chunk_requests.contains(&chunk_hash) {
                 return Ok(ProcessPartialEncodedChunkResult::Known);
             }
             debug!(target: "chunks", num_parts_in_cache = entry.parts.len(), total_needed = self.epoch_manager.num_data_parts());
@@ -1481,7 +2116,8 @@ impl ShardsManagerActor {
         }
 
         // 1.b Checking chunk height
-        let chunk_requested = self.requested_partial_encoded_chunks.contains_key(&chunk_hash);
+        let chunk_requested = self.requested_partial_encoded_chunks.contains_key(&chunk_hash)
+            || self.full_chunk_requests.contains(&chunk_hash);
         if !chunk_requested {
             if !self.encoded_chunks.height_within_horizon(header.height_created()) {
                 return Err(Error::ChainError(near_chain::Error::InvalidChunkHeight));
@@ -1512,7 +2148,10 @@ impl ShardsManagerActor {
                 }
                 _ => return Err(chain_error.into()),
             },
-            Err(err) => return Err(err),
+            Err(err) => {
+                self.invalidate_chunk(&chunk_hash, &err);
+                return Err(err);
+            }
             Ok(_) => (),
         }
         let partial_encoded_chunk = partial_encoded_chunk.as_ref().into_inner();
@@ -1526,15 +2165,15 @@ impl ShardsManagerActor {
         }
 
         // 1.e Checking receipts validity
-        for proof in partial_encoded_chunk.prev_outgoing_receipts.iter() {
-            // TODO: only validate receipts we care about
-            // https://github.com/near/nearcore/issues/5885
-            // we can't simply use prev_block_hash to check if the node tracks this shard or not
-            // because prev_block_hash may not be ready
-            if !proof.verify_against_receipt_root(header.prev_outgoing_receipts_root()) {
-                byzantine_assert!(false);
-                return Err(Error::ChainError(near_chain::Error::InvalidReceiptsProof));
-            }
+        // TODO: only validate receipts we care about
+        // https://github.com/near/nearcore/issues/5885
+        // we can't simply use prev_block_hash to check if the node tracks this shard or not
+        // because prev_block_hash may not be ready
+        if let Err(err) =
+            validate_receipts_against_header(&partial_encoded_chunk.prev_outgoing_receipts, header)
+        {
+            byzantine_assert!(false);
+            return Err(err);
         }
 
         // 2. Consider it valid; merge parts and receipts included in the partial encoded chunk
@@ -1595,6 +2234,10 @@ impl ShardsManagerActor {
         response: PartialEncodedChunkResponseMsg,
         me: Option<&AccountId>,
     ) -> Result<(), Error> {
+        // An empty response means that the peer doesn't have the data we asked for.
+        if !response.parts.is_empty() || !response.receipts.is_empty() {
+            self.record_part_request_answered(&response);
+        }
         let header = self.get_partial_encoded_chunk_header(&response.chunk_hash)?;
         let partial_chunk = PartialEncodedChunk::new(header, response.parts, response.receipts);
         // We already know the header signature is valid because we read it from the
@@ -1660,10 +2303,7 @@ impl ShardsManagerActor {
                             }
                             err => {
                                 // the chunk header is invalid
-                                // remove this entry from the cache and remove the request from the request pool
-                                debug!(target: "chunks", ?err, "Chunk header is invalid");
-                                self.encoded_chunks.remove(&chunk_hash);
-                                self.requested_partial_encoded_chunks.remove(&chunk_hash);
+                                self.invalidate_chunk(&chunk_hash, &err);
                                 Err(err)
                             }
                         };
@@ -1702,13 +2342,15 @@ impl ShardsManagerActor {
         // we can safely unwrap here because we already checked that chunk_hash exist in encoded_chunks
         let entry = self.encoded_chunks.get(&chunk_hash).unwrap();
 
+        // A chunk requested in full is treated as one of a shard we track, so
+        // that it is only completed once it can be reconstructed.
         let cares_about_shard = cares_about_shard_this_or_next_epoch(
             me,
             &prev_block_hash,
             header.shard_id(),
             true,
             &self.shard_tracker,
-        );
+        ) || self.full_chunk_requests.contains(&chunk_hash);
 
         debug!(target: "chunks", cares_about_shard, can_reconstruct, have_all_parts, have_all_receipts);
         if !cares_about_shard && have_all_parts && have_all_receipts {
@@ -1767,8 +2409,16 @@ impl ShardsManagerActor {
     ) {
         let _span = debug_span!(target: "chunks", "complete_chunk").entered();
         let chunk_hash = partial_chunk.chunk_hash();
+        self.full_chunk_requests.remove(&chunk_hash);
+        self.outstanding_part_requests.pop(&chunk_hash);
         self.encoded_chunks.mark_entry_complete(&chunk_hash);
         self.encoded_chunks.remove_from_cache_if_outside_horizon(&chunk_hash);
+        if let Some(request) = self.requested_partial_encoded_chunks.get_request_info(&chunk_hash) {
+            let now: time::Instant = self.clock.now().into();
+            metrics::CHUNK_REQUEST_TO_COMPLETION_TIME
+                .with_label_values(&[&request.shard_id.to_string()])
+                .observe((now - request.added).as_secs_f64());
+        }
         self.requested_partial_encoded_chunks.remove(&chunk_hash);
         debug!(target: "chunks", "Completed chunk {:?}", chunk_hash);
         self.client_adapter
@@ -1792,6 +2442,7 @@ impl ShardsManagerActor {
                 }
             }
         }
+        self.validate_chunk_headers_batch(prev_block_hash, &chunks_to_process);
         for header in chunks_to_process {
             debug!(target: "chunks",
                 chunk_hash = ?header.chunk_hash(),
@@ -1803,6 +2454,39 @@ impl ShardsManagerActor {
         }
     }
 
+    /// Fully validates the headers of chunks built on the accepted block `prev_block_hash`,
+    /// verifying the signatures of all of them in one batch. Headers that fail validation
+    /// are left as they are, so that `try_process_chunk_parts_and_receipts` validates them
+    /// again and handles the error.
+    fn validate_chunk_headers_batch(
+        &mut self,
+        prev_block_hash: &CryptoHash,
+        headers: &[ShardChunkHeader],
+    ) {
+        let Ok(epoch_id) = self.epoch_manager.get_epoch_id_from_prev_block(prev_block_hash) else {
+            return;
+        };
+        let headers: Vec<_> = headers
+            .iter()
+            .filter(|header| {
+                self.encoded_chunks
+                    .get(&header.chunk_hash())
+                    .is_some_and(|entry| !entry.header_fully_validated)
+            })
+            .collect();
+        let batch: Vec<_> =
+            headers.iter().map(|header| (*header, epoch_id, *prev_block_hash)).collect();
+        let results = self.verify_chunk_header_signatures_batch(&batch);
+        for (header, result) in headers.into_iter().zip(results) {
+            let Ok(signature_valid) = result else {
+                continue;
+            };
+            if self.validate_chunk_header_with_signature(header, Some(signature_valid)).is_ok() {
+                self.encoded_chunks.mark_entry_validated(&header.chunk_hash());
+            }
+        }
+    }
+
     /// Send the parts of the partial_encoded_chunk that are owned by `self.me()` to the
     /// other validators that are tracking the shard.
     fn send_partial_encoded_chunk_to_chunk_trackers(
@@ -2159,9 +2843,26 @@ impl ShardsManagerActor {
                     );
                 }
             }
-        }
-    }
-
+            ShardsManagerRequestFromClient::RequestFullChunks { chunks_to_request } => {
+                self.request_full_chunks(chunks_to_request, me)
+            }
+            ShardsManagerRequestFromClient::RequestChunkParts { chunk_hash, part_ords, from } => {
+                if let Err(err) = self.request_chunk_parts(&chunk_hash, part_ords, from, me) {
+                    warn!(target: "chunks", ?err, ?chunk_hash, "Error requesting chunk parts");
+                }
+            }
+            ShardsManagerRequestFromClient::CheckChunkComplete(chunk_hash) => {
+                let complete = self.chunk_complete(&chunk_hash);
+                self.client_adapter
+                    .send(ShardsManagerResponse::ChunkCompleteStatus { chunk_hash, complete });
+            }
+            ShardsManagerRequestFromClient::GetPartRequestStats => {
+                self.client_adapter
+                    .send(ShardsManagerResponse::PartRequestStats(self.get_part_request_stats()));
+            }
+        }
+    }
+
     pub fn handle_network_request(&mut self, request: ShardsManagerRequestFromNetwork) {
         let _span = tracing::debug_span!(
             target: "chunks",
@@ -2245,6 +2946,7 @@ mod test {
     use assert_matches::assert_matches;
     use near_async::messaging::IntoSender;
     use near_async::time::FakeClock;
+    use near_chain::ChainStoreAccess;
     use near_chain_configs::MutableConfigValue;
     use near_epoch_manager::shard_tracker::TrackedConfig;
     use near_epoch_manager::test_utils::setup_epoch_manager_with_block_and_chunk_producers;
@@ -2252,6 +2954,7 @@ mod test {
     use near_network::types::NetworkRequests;
     use near_primitives::block::Tip;
     use near_primitives::hash::{hash, CryptoHash};
+    use near_primitives::merkle::{Direction, MerklePathItem};
     use near_primitives::types::EpochId;
     use near_primitives::validator_signer::EmptyValidatorSigner;
     use near_store::test_utils::create_test_store;
@@ -2330,6 +3033,394 @@ mod test {
         };
     }
 
+    #[test]
+    fn test_request_full_chunks() {
+        // A non-validator that doesn't track the shard only needs the header to complete a chunk.
+        let mut fixture = ChunkTestFixture::new(false, 3, 6, 1, false);
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            MutableConfigValue::new(None, "validator_signer"),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let result = shards_manager
+            .process_partial_encoded_chunk(
+                MaybeValidated::from(fixture.make_partial_encoded_chunk(&[0])),
+                None,
+            )
+            .unwrap();
+        assert_matches!(result, ProcessPartialEncodedChunkResult::HaveAllPartsAndReceipts);
+        let completed_in_full = |fixture: &ChunkTestFixture| {
+            let mut completed = vec![];
+            while let Some(message) = fixture.mock_client_adapter.pop() {
+                if let ShardsManagerResponse::ChunkCompleted { shard_chunk, .. } = message {
+                    completed.push(shard_chunk.is_some());
+                }
+            }
+            completed
+        };
+        assert_eq!(completed_in_full(&fixture), vec![false]);
+        while fixture.mock_network.pop().is_some() {}
+
+        // Requesting the chunk in full asks for all the parts we don't have yet.
+        shards_manager.handle_client_request(ShardsManagerRequestFromClient::RequestFullChunks {
+            chunks_to_request: vec![fixture.mock_chunk_header.clone()],
+        });
+        let mut requested_parts = HashSet::new();
+        while let Some(r) = fixture.mock_network.pop() {
+            if let NetworkRequests::PartialEncodedChunkRequest { request, .. } =
+                r.as_network_requests_ref()
+            {
+                requested_parts.extend(request.part_ords.iter().copied());
+            }
+        }
+        let num_parts = fixture.mock_chunk_parts.len() as u64;
+        assert_eq!(requested_parts, (1..num_parts).collect::<HashSet<_>>());
+
+        // Once the parts arrive, the chunk is completed again, this time in full.
+        let result = shards_manager
+            .process_partial_encoded_chunk(
+                MaybeValidated::from(
+                    fixture.make_partial_encoded_chunk(&(1..num_parts).collect::<Vec<_>>()),
+                ),
+                None,
+            )
+            .unwrap();
+        assert_matches!(result, ProcessPartialEncodedChunkResult::HaveAllPartsAndReceipts);
+        assert_eq!(completed_in_full(&fixture), vec![true]);
+        assert!(shards_manager.full_chunk_requests.is_empty());
+    }
+
+    #[test]
+    fn test_full_chunk_requests_evicted_with_cache() {
+        let fixture = ChunkTestFixture::new(false, 3, 6, 1, false);
+        let mut shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            MutableConfigValue::new(None, "validator_signer"),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+
+        // None of the requested parts ever arrive, so the chunk is stuck.
+        shards_manager.handle_client_request(ShardsManagerRequestFromClient::RequestFullChunks {
+            chunks_to_request: vec![fixture.mock_chunk_header.clone()],
+        });
+        assert!(shards_manager.full_chunk_requests.contains(&chunk_hash));
+
+        // The request survives while the chunk is kept in the cache.
+        let height = fixture.mock_chunk_header.height_created();
+        let head = Tip { height: height + 10, ..fixture.mock_chain_head.clone() };
+        shards_manager.update_chain_heads(head.clone(), head);
+        assert!(shards_manager.full_chunk_requests.contains(&chunk_hash));
+
+        // It is dropped together with the chunk once the head moves far enough.
+        let head = Tip { height: height + 10_000, ..fixture.mock_chain_head.clone() };
+        shards_manager.update_chain_heads(head.clone(), head);
+        assert!(shards_manager.encoded_chunks.get(&chunk_hash).is_none());
+        assert!(shards_manager.full_chunk_requests.is_empty());
+    }
+
+    #[test]
+    fn test_request_chunk_parts() {
+        let mut fixture = ChunkTestFixture::new(true, 3, 6, 1, true);
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        let num_parts = fixture.mock_chunk_parts.len() as u64;
+
+        // The chunk is not known yet, so nothing is requested.
+        shards_manager.handle_client_request(ShardsManagerRequestFromClient::RequestChunkParts {
+            chunk_hash: chunk_hash.clone(),
+            part_ords: vec![1],
+            from: None,
+        });
+        assert!(fixture.mock_network.pop().is_none());
+
+        let partial_encoded_chunk = fixture.make_partial_encoded_chunk(&[0]);
+        let result = shards_manager
+            .process_partial_encoded_chunk(
+                MaybeValidated::from(partial_encoded_chunk),
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        assert_matches!(result, ProcessPartialEncodedChunkResult::NeedBlock);
+        while fixture.mock_network.pop().is_some() {}
+
+        // Only the requested parts are requested, including ones we already have.
+        shards_manager.handle_client_request(ShardsManagerRequestFromClient::RequestChunkParts {
+            chunk_hash: chunk_hash.clone(),
+            part_ords: vec![0, num_parts - 1],
+            from: Some(fixture.mock_chunk_part_owner.clone()),
+        });
+        let msg = fixture.mock_network.pop().unwrap();
+        match msg.as_network_requests_ref() {
+            NetworkRequests::PartialEncodedChunkRequest { target, request, .. } => {
+                assert_eq!(target.account_id.as_ref(), Some(&fixture.mock_chunk_part_owner));
+                assert_eq!(request.chunk_hash, chunk_hash);
+                assert_eq!(request.part_ords, vec![0, num_parts - 1]);
+                assert!(request.tracking_shards.is_empty());
+            }
+            _ => panic!("unexpected message {:?}", msg),
+        }
+        assert!(fixture.mock_network.pop().is_none());
+
+        // Out of range part ordinals are rejected.
+        shards_manager.handle_client_request(ShardsManagerRequestFromClient::RequestChunkParts {
+            chunk_hash,
+            part_ords: vec![1, num_parts],
+            from: None,
+        });
+        assert!(fixture.mock_network.pop().is_none());
+    }
+
+    #[test]
+    fn test_invalidate_chunk() {
+        let fixture = ChunkTestFixture::default();
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        shards_manager
+            .process_partial_encoded_chunk(
+                MaybeValidated::from(fixture.make_partial_encoded_chunk(&[0])),
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        let added = clock.now().into();
+        shards_manager.requested_partial_encoded_chunks.insert(
+            chunk_hash.clone(),
+            ChunkRequestInfo {
+                height: fixture.mock_chunk_header.height_created(),
+                ancestor_hash: Default::default(),
+                prev_block_hash: fixture.mock_chunk_header.prev_block_hash(),
+                shard_id: fixture.mock_chunk_header.shard_id(),
+                added,
+                last_requested: added,
+            },
+        );
+        shards_manager.insert_forwarded_chunk(
+            PartialEncodedChunkForwardMsg::from_header_and_parts(
+                &fixture.mock_chunk_header,
+                fixture.mock_chunk_parts[1..2].to_vec(),
+            ),
+        );
+        assert!(shards_manager.encoded_chunks.get(&chunk_hash).is_some());
+
+        shards_manager.invalidate_chunk(&chunk_hash, &Error::InvalidChunkHeader);
+        assert!(shards_manager.encoded_chunks.get(&chunk_hash).is_none());
+        assert!(!shards_manager.requested_partial_encoded_chunks.contains_key(&chunk_hash));
+        assert!(shards_manager.chunk_forwards_cache.get(&chunk_hash).is_none());
+
+        // Parts received afterwards start from scratch instead of adding up to
+        // the parts received before the invalidation.
+        shards_manager
+            .process_partial_encoded_chunk(
+                MaybeValidated::from(fixture.make_partial_encoded_chunk(&[2])),
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        let entry = shards_manager.encoded_chunks.get(&chunk_hash).unwrap();
+        assert!(entry.header_fully_validated);
+        assert_eq!(entry.parts.keys().copied().collect::<Vec<_>>(), vec![2]);
+    }
+
+    #[test]
+    fn test_chunk_request_resend_jitter() {
+        let fixture = ChunkTestFixture::new(false, 3, 6, 1, false);
+        let clock = FakeClock::default();
+        let retry_period = Duration::milliseconds(100);
+        let make_shards_manager = || {
+            ShardsManagerActor::new(
+                clock.clock(),
+                mutable_validator_signer(&fixture.mock_shard_tracker),
+                Arc::new(fixture.epoch_manager.clone()),
+                fixture.shard_tracker.clone(),
+                fixture.mock_network.as_sender(),
+                fixture.mock_client_adapter.as_sender(),
+                fixture.chain_store.new_read_only_chunks_store(),
+                fixture.mock_chain_head.clone(),
+                fixture.mock_chain_head.clone(),
+                retry_period,
+            )
+        };
+
+        // Without jitter the requests are resent exactly every retry period.
+        let mut shards_manager = make_shards_manager();
+        shards_manager.set_chunk_request_retry_jitter(0.0);
+        assert_eq!(shards_manager.next_resend_delay(), retry_period);
+
+        // With jitter, actors with the same retry period resend at different times,
+        // but never further than the jitter away from the period.
+        let mut first = make_shards_manager();
+        first.set_chunk_request_retry_jitter(0.1);
+        first.rng = StdRng::seed_from_u64(1);
+        let mut second = make_shards_manager();
+        second.set_chunk_request_retry_jitter(0.1);
+        second.rng = StdRng::seed_from_u64(2);
+        let first_delays: Vec<_> = (0..10).map(|_| first.next_resend_delay()).collect();
+        let second_delays: Vec<_> = (0..10).map(|_| second.next_resend_delay()).collect();
+        assert_ne!(first_delays, second_delays);
+        for delay in first_delays.into_iter().chain(second_delays) {
+            assert!(delay >= Duration::milliseconds(90) && delay <= Duration::milliseconds(110));
+        }
+    }
+
+    #[test]
+    fn test_validate_receipts_against_header() {
+        let fixture = ChunkTestFixture::new(false, 3, 6, 1, false);
+        let mut receipt_proofs = make_outgoing_receipts_proofs(
+            &fixture.mock_chunk_header,
+            &fixture.mock_outgoing_receipts,
+            &fixture.epoch_manager,
+        )
+        .unwrap()
+        .collect::<Vec<_>>();
+        assert!(!receipt_proofs.is_empty());
+        validate_receipts_against_header(&receipt_proofs, &fixture.mock_chunk_header).unwrap();
+
+        receipt_proofs[0]
+            .1
+            .proof
+            .push(MerklePathItem { hash: CryptoHash::default(), direction: Direction::Left });
+        assert_matches!(
+            validate_receipts_against_header(&receipt_proofs, &fixture.mock_chunk_header),
+            Err(Error::ChainError(near_chain::Error::InvalidReceiptsProof))
+        );
+    }
+
+    #[test]
+    fn test_part_request_stats() {
+        let mut fixture = ChunkTestFixture::new(true, 3, 6, 1, true);
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        let shard_id = fixture.mock_chunk_header.shard_id();
+        let peer: AccountId = "peer".parse().unwrap();
+
+        let partial_encoded_chunk = fixture.make_partial_encoded_chunk(&[0]);
+        shards_manager
+            .process_partial_encoded_chunk(
+                MaybeValidated::from(partial_encoded_chunk),
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        while fixture.mock_network.pop().is_some() {}
+
+        let mut request_and_respond = |parts: Vec<PartialEncodedChunkPart>| {
+            shards_manager.handle_client_request(
+                ShardsManagerRequestFromClient::RequestChunkParts {
+                    chunk_hash: chunk_hash.clone(),
+                    part_ords: vec![1],
+                    from: Some(peer.clone()),
+                },
+            );
+            shards_manager.handle_network_request(
+                ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkResponse {
+                    partial_encoded_chunk_response: PartialEncodedChunkResponseMsg {
+                        chunk_hash: chunk_hash.clone(),
+                        parts,
+                        receipts: vec![],
+                    },
+                    received_time: clock.now(),
+                },
+            );
+            shards_manager
+                .handle_client_request(ShardsManagerRequestFromClient::GetPartRequestStats);
+            let mut stats = None;
+            while let Some(message) = fixture.mock_client_adapter.pop() {
+                if let ShardsManagerResponse::PartRequestStats(s) = message {
+                    stats = s.into_iter().find(|s| s.account_id == peer && s.shard_id == shard_id);
+                }
+            }
+            let stats = stats.unwrap();
+            (stats.requested, stats.answered)
+        };
+
+        // An empty response doesn't count as an answer.
+        assert_eq!(request_and_respond(vec![]), (1, 0));
+        assert_eq!(request_and_respond(vec![fixture.mock_chunk_parts[1].clone()]), (2, 1));
+        assert_eq!(request_and_respond(vec![]), (3, 1));
+
+        // Requests to different peers for the same chunk are tracked separately,
+        // and a response is credited to the peer whose parts it carries.
+        let other_peer: AccountId = "other_peer".parse().unwrap();
+        for (part_ord, from) in [(1, &peer), (2, &other_peer)] {
+            shards_manager.handle_client_request(
+                ShardsManagerRequestFromClient::RequestChunkParts {
+                    chunk_hash: chunk_hash.clone(),
+                    part_ords: vec![part_ord],
+                    from: Some(from.clone()),
+                },
+            );
+        }
+        shards_manager.handle_network_request(
+            ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkResponse {
+                partial_encoded_chunk_response: PartialEncodedChunkResponseMsg {
+                    chunk_hash: chunk_hash.clone(),
+                    parts: vec![fixture.mock_chunk_parts[2].clone()],
+                    receipts: vec![],
+                },
+                received_time: clock.now(),
+            },
+        );
+        let stats = shards_manager.get_part_request_stats();
+        let counters = |account_id: &AccountId| {
+            let stats = stats
+                .iter()
+                .find(|s| &s.account_id == account_id && s.shard_id == shard_id)
+                .unwrap();
+            (stats.requested, stats.answered)
+        };
+        assert_eq!(counters(&peer), (4, 1));
+        assert_eq!(counters(&other_peer), (1, 1));
+    }
+
     #[test]
     fn test_resend_chunk_requests() {
         // Test that resending chunk requests won't request for parts the node already received
@@ -2411,6 +3502,130 @@ mod test {
         assert_eq!(requested_parts, HashSet::new());
     }
 
+    #[test]
+    fn test_request_pool_persisted_across_restarts() {
+        let fixture = ChunkTestFixture::new(true, 3, 6, 1, true);
+        let store = fixture.chain_store.store().clone();
+        let make_shards_manager = |clock: &FakeClock| {
+            ShardsManagerActor::new(
+                clock.clock(),
+                mutable_validator_signer(&fixture.mock_shard_tracker),
+                Arc::new(fixture.epoch_manager.clone()),
+                fixture.shard_tracker.clone(),
+                fixture.mock_network.as_sender(),
+                fixture.mock_client_adapter.as_sender(),
+                fixture.chain_store.new_read_only_chunks_store(),
+                fixture.mock_chain_head.clone(),
+                fixture.mock_chain_head.clone(),
+                Duration::hours(1),
+            )
+        };
+        let clock = FakeClock::default();
+        let mut shards_manager = make_shards_manager(&clock);
+        shards_manager.enable_request_pool_persistence(store.clone());
+        shards_manager.request_chunk_single(
+            &fixture.mock_chunk_header,
+            CryptoHash::default(),
+            false,
+            Some(&fixture.mock_shard_tracker),
+        );
+        // A request for a chunk below the tail shouldn't be resumed.
+        let stale_chunk_hash = ChunkHash(hash(b"stale"));
+        shards_manager.requested_partial_encoded_chunks.insert(
+            stale_chunk_hash.clone(),
+            ChunkRequestInfo {
+                height: fixture.mock_chunk_header.height_created() - 1,
+                ancestor_hash: CryptoHash::default(),
+                prev_block_hash: CryptoHash::default(),
+                shard_id: fixture.mock_chunk_header.shard_id(),
+                added: clock.now().into(),
+                last_requested: clock.now().into(),
+            },
+        );
+        shards_manager.persist_request_pool();
+        drop(shards_manager);
+        while fixture.mock_network.pop().is_some() {}
+
+        let mut store_update = store.store_update();
+        store_update
+            .set_ser(DBCol::BlockMisc, TAIL_KEY, &fixture.mock_chunk_header.height_created())
+            .unwrap();
+        store_update.commit().unwrap();
+
+        let mut shards_manager = make_shards_manager(&clock);
+        assert_eq!(shards_manager.requested_partial_encoded_chunks.len(), 0);
+        shards_manager.enable_request_pool_persistence(store);
+        assert!(shards_manager
+            .requested_partial_encoded_chunks
+            .contains_key(&fixture.mock_chunk_header.chunk_hash()));
+        assert!(!shards_manager.requested_partial_encoded_chunks.contains_key(&stale_chunk_hash));
+
+        // The resumed request is sent out on the first resend.
+        shards_manager.resend_chunk_requests();
+        assert!(fixture.mock_network.pop().is_some());
+    }
+
+    #[test]
+    fn test_request_pool_persisted_on_change() {
+        let fixture = ChunkTestFixture::new(true, 3, 6, 1, true);
+        let store = fixture.chain_store.store().clone();
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        shards_manager.enable_request_pool_persistence(store.clone());
+        let persisted = || {
+            store
+                .get_ser::<Vec<PersistedChunkRequest>>(DBCol::Misc, CHUNK_REQUEST_POOL_KEY)
+                .unwrap()
+                .map(|requests| requests.len())
+        };
+        let clear_persisted = || {
+            let mut store_update = store.store_update();
+            store_update.delete(DBCol::Misc, CHUNK_REQUEST_POOL_KEY);
+            store_update.commit().unwrap();
+        };
+
+        shards_manager.request_chunk_single(
+            &fixture.mock_chunk_header,
+            CryptoHash::default(),
+            false,
+            Some(&fixture.mock_shard_tracker),
+        );
+        shards_manager.persist_request_pool();
+        assert_eq!(persisted(), Some(1));
+
+        // Nothing is written while the requests stay the same.
+        clear_persisted();
+        shards_manager.resend_chunk_requests();
+        shards_manager.persist_request_pool();
+        assert_eq!(persisted(), None);
+
+        // A new request is persisted again.
+        shards_manager.requested_partial_encoded_chunks.insert(
+            ChunkHash(hash(b"other")),
+            ChunkRequestInfo {
+                height: fixture.mock_chunk_header.height_created(),
+                ancestor_hash: CryptoHash::default(),
+                prev_block_hash: CryptoHash::default(),
+                shard_id: fixture.mock_chunk_header.shard_id(),
+                added: clock.now().into(),
+                last_requested: clock.now().into(),
+            },
+        );
+        shards_manager.persist_request_pool();
+        assert_eq!(persisted(), Some(2));
+    }
+
     #[test]
     fn test_invalid_chunk() {
         // Test that process_partial_encoded_chunk will reject invalid chunk
@@ -2861,6 +4076,54 @@ mod test {
         assert_eq!(response.parts.len(), fixture.all_part_ords.len());
     }
 
+    #[test]
+    fn test_chunk_response_split() {
+        let mut fixture = ChunkTestFixture::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        shards_manager.set_max_response_size(2, usize::MAX);
+
+        persist_chunk(
+            fixture.make_partial_encoded_chunk(&fixture.all_part_ords),
+            None,
+            &mut fixture.chain_store,
+        )
+        .unwrap();
+
+        shards_manager.process_partial_encoded_chunk_request(
+            PartialEncodedChunkRequestMsg {
+                chunk_hash: fixture.mock_chunk_header.chunk_hash(),
+                part_ords: fixture.all_part_ords.clone(),
+                tracking_shards: HashSet::new(),
+            },
+            CryptoHash::default(),
+            None,
+        );
+        let mut num_responses = 0;
+        let mut part_ords = HashSet::new();
+        while let Some(request) = fixture.mock_network.pop() {
+            if let NetworkRequests::PartialEncodedChunkResponse { response, .. } =
+                request.as_network_requests_ref()
+            {
+                assert!(response.parts.len() <= 2);
+                num_responses += 1;
+                part_ords.extend(response.parts.iter().map(|part| part.part_ord));
+            }
+        }
+        assert_eq!(num_responses, fixture.all_part_ords.len().div_ceil(2));
+        assert_eq!(part_ords, fixture.all_part_ords.iter().copied().collect::<HashSet<_>>());
+    }
+
     #[test]
     fn test_chunk_response_for_uncached_shard_chunk() {
         let mut fixture = ChunkTestFixture::default();
@@ -2895,6 +4158,69 @@ mod test {
         assert_eq!(response.parts.len(), fixture.all_part_ords.len());
     }
 
+    #[test]
+    fn test_chunk_response_source() {
+        let make_shards_manager = |fixture: &ChunkTestFixture| {
+            ShardsManagerActor::new(
+                FakeClock::default().clock(),
+                mutable_validator_signer(&fixture.mock_shard_tracker),
+                Arc::new(fixture.epoch_manager.clone()),
+                fixture.shard_tracker.clone(),
+                fixture.mock_network.as_sender(),
+                fixture.mock_client_adapter.as_sender(),
+                fixture.chain_store.new_read_only_chunks_store(),
+                fixture.mock_chain_head.clone(),
+                fixture.mock_chain_head.clone(),
+                Duration::hours(1),
+            )
+        };
+
+        // A recent chunk is served from the cache or the stored partial chunk.
+        let mut fixture = ChunkTestFixture::default();
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        let mut shards_manager = make_shards_manager(&fixture);
+        assert_eq!(shards_manager.chunk_response_source(&chunk_hash, &fixture.all_part_ords), None);
+        shards_manager
+            .process_partial_encoded_chunk(
+                fixture.make_partial_encoded_chunk(&fixture.all_part_ords).into(),
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        assert_eq!(
+            shards_manager.chunk_response_source(&chunk_hash, &fixture.all_part_ords),
+            Some(PartialEncodedChunkResponseSource::InMemoryCache)
+        );
+        persist_chunk(
+            fixture.make_partial_encoded_chunk(&fixture.all_part_ords),
+            None,
+            &mut fixture.chain_store,
+        )
+        .unwrap();
+        let shards_manager = make_shards_manager(&fixture);
+        assert_eq!(
+            shards_manager.chunk_response_source(&chunk_hash, &fixture.all_part_ords),
+            Some(PartialEncodedChunkResponseSource::PartialChunkOnDisk)
+        );
+
+        // A chunk whose partial chunk was garbage collected is encoded again
+        // from the full chunk kept by archival nodes.
+        let fixture = ChunkTestFixture::default();
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        let mut update = fixture.chain_store.store_update();
+        update.save_chunk(
+            fixture
+                .mock_encoded_chunk
+                .decode_chunk(fixture.epoch_manager.num_data_parts())
+                .unwrap(),
+        );
+        update.commit().unwrap();
+        let shards_manager = make_shards_manager(&fixture);
+        assert_eq!(
+            shards_manager.chunk_response_source(&chunk_hash, &fixture.all_part_ords),
+            Some(PartialEncodedChunkResponseSource::ShardChunkOnDisk)
+        );
+    }
+
     #[test]
     fn test_chunk_response_combining_cache_and_partial_chunks() {
         let mut fixture = ChunkTestFixture::default();
@@ -3148,6 +4474,287 @@ mod test {
         assert_eq!(response.parts.len(), 2);
     }
 
+    #[test]
+    fn test_chunk_request_to_completion_time() {
+        let mut fixture = ChunkTestFixture::default();
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let histogram = metrics::CHUNK_REQUEST_TO_COMPLETION_TIME
+            .with_label_values(&[&fixture.mock_chunk_header.shard_id().to_string()]);
+        let count_before = histogram.get_sample_count();
+        let sum_before = histogram.get_sample_sum();
+
+        shards_manager.request_chunk_single(
+            &fixture.mock_chunk_header,
+            CryptoHash::default(),
+            false,
+            Some(&fixture.mock_shard_tracker),
+        );
+        clock.advance(Duration::seconds(2));
+        shards_manager.insert_header_if_not_exists_and_process_cached_chunk_forwards(
+            &fixture.mock_chunk_header,
+        );
+        let forward = PartialEncodedChunkForwardMsg::from_header_and_parts(
+            &fixture.mock_chunk_header,
+            fixture.mock_chunk_parts.clone(),
+        );
+        shards_manager
+            .process_partial_encoded_chunk_forward(forward, Some(&fixture.mock_shard_tracker))
+            .unwrap();
+        let process_result = shards_manager
+            .try_process_chunk_parts_and_receipts(
+                &fixture.mock_chunk_header,
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        assert_matches!(process_result, ProcessPartialEncodedChunkResult::HaveAllPartsAndReceipts);
+        while fixture.mock_client_adapter.pop().is_some() {}
+
+        // Other tests may observe the same metric concurrently, hence the lower bounds.
+        assert!(histogram.get_sample_count() > count_before);
+        assert!(histogram.get_sample_sum() - sum_before >= 2.0);
+    }
+
+    #[test]
+    fn test_stale_chain_head() {
+        let fixture = ChunkTestFixture::default();
+        let clock = FakeClock::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            clock.clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        shards_manager.set_max_chain_head_staleness(Duration::seconds(10));
+        let stale_before = metrics::STALE_CHAIN_HEAD.get();
+
+        clock.advance(Duration::seconds(5));
+        assert!(!shards_manager.check_chain_head_staleness());
+        clock.advance(Duration::seconds(10));
+        assert!(shards_manager.check_chain_head_staleness());
+        // The heads stay stale, but that is only reported once. No other test
+        // lets the heads go stale, so the metric can be checked exactly.
+        clock.advance(Duration::seconds(10));
+        assert!(shards_manager.check_chain_head_staleness());
+        assert_eq!(metrics::STALE_CHAIN_HEAD.get(), stale_before + 1.0);
+
+        // Updating the heads resets the staleness.
+        let head = fixture.mock_chain_head.clone();
+        shards_manager.update_chain_heads(head.clone(), head);
+        assert!(!shards_manager.check_chain_head_staleness());
+
+        // Going stale again is reported again.
+        clock.advance(Duration::seconds(15));
+        assert!(shards_manager.check_chain_head_staleness());
+        assert_eq!(metrics::STALE_CHAIN_HEAD.get(), stale_before + 2.0);
+    }
+
+    #[test]
+    fn test_chunk_complete() {
+        let mut fixture = ChunkTestFixture::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        shards_manager.insert_header_if_not_exists_and_process_cached_chunk_forwards(
+            &fixture.mock_chunk_header,
+        );
+        assert!(!shards_manager.chunk_complete(&chunk_hash));
+
+        let forward = PartialEncodedChunkForwardMsg::from_header_and_parts(
+            &fixture.mock_chunk_header,
+            fixture.mock_chunk_parts.clone(),
+        );
+        shards_manager
+            .process_partial_encoded_chunk_forward(forward, Some(&fixture.mock_shard_tracker))
+            .unwrap();
+        let process_result = shards_manager
+            .try_process_chunk_parts_and_receipts(
+                &fixture.mock_chunk_header,
+                Some(&fixture.mock_shard_tracker),
+            )
+            .unwrap();
+        assert_matches!(process_result, ProcessPartialEncodedChunkResult::HaveAllPartsAndReceipts);
+        // The chunk is reconstructed, but not persisted by the client yet.
+        assert!(!shards_manager.chunk_complete(&chunk_hash));
+
+        let shard_chunk = loop {
+            match fixture.mock_client_adapter.pop() {
+                Some(ShardsManagerResponse::ChunkCompleted { shard_chunk, .. }) => {
+                    break shard_chunk.unwrap()
+                }
+                Some(_) => {}
+                None => panic!("Expected ChunkCompleted"),
+            }
+        };
+        while fixture.mock_client_adapter.pop().is_some() {}
+        let mut store_update = fixture.chain_store.store_update();
+        store_update.save_chunk(shard_chunk);
+        store_update.commit().unwrap();
+        assert!(shards_manager.chunk_complete(&chunk_hash));
+
+        shards_manager
+            .handle_client_request(ShardsManagerRequestFromClient::CheckChunkComplete(chunk_hash));
+        assert_matches!(
+            fixture.mock_client_adapter.pop(),
+            Some(ShardsManagerResponse::ChunkCompleteStatus { complete: true, .. })
+        );
+    }
+
+    #[test]
+    // Forwarded parts with invalid ordinals or beyond the configured limit are
+    // not cached.
+    fn test_chunk_forwards_cache_limit() {
+        let fixture = ChunkTestFixture::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let num_total_parts = fixture.epoch_manager.num_total_parts();
+        let chunk_hash = fixture.mock_chunk_header.chunk_hash();
+        let mut parts = fixture.mock_chunk_parts.clone();
+        for part_ord in num_total_parts as u64..2 * num_total_parts as u64 {
+            let mut part = parts[0].clone();
+            part.part_ord = part_ord;
+            parts.push(part);
+        }
+        let forward =
+            PartialEncodedChunkForwardMsg::from_header_and_parts(&fixture.mock_chunk_header, parts);
+
+        shards_manager.insert_forwarded_chunk(forward.clone());
+        let cached_parts = shards_manager.chunk_forwards_cache.get(&chunk_hash).unwrap();
+        assert!(cached_parts.len() <= num_total_parts);
+        assert!(cached_parts.keys().all(|part_ord| *part_ord < num_total_parts as u64));
+
+        shards_manager.chunk_forwards_cache.clear();
+        shards_manager.set_max_forwarded_parts_per_chunk(2);
+        shards_manager.insert_forwarded_chunk(forward.clone());
+        shards_manager.insert_forwarded_chunk(forward);
+        assert_eq!(shards_manager.chunk_forwards_cache.get(&chunk_hash).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_verify_chunk_header_signatures_batch() {
+        let fixture = ChunkTestFixture::default();
+        let shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let valid_header = fixture.mock_chunk_header.clone();
+        let mut invalid_header = fixture.mock_chunk_header.clone();
+        match &mut invalid_header {
+            ShardChunkHeader::V1(header) => header.signature = Default::default(),
+            ShardChunkHeader::V2(header) => header.signature = Default::default(),
+            ShardChunkHeader::V3(header) => header.signature = Default::default(),
+        }
+        let ancestor_hash = CryptoHash::default();
+        let epoch_id = fixture.epoch_manager.get_epoch_id_from_prev_block(&ancestor_hash).unwrap();
+        let unknown_epoch_id = EpochId(hash(b"unknown"));
+        let headers = vec![
+            (&valid_header, epoch_id, ancestor_hash),
+            (&invalid_header, epoch_id, ancestor_hash),
+            (&valid_header, unknown_epoch_id, ancestor_hash),
+            (&valid_header, epoch_id, ancestor_hash),
+        ];
+
+        let batch_results = shards_manager.verify_chunk_header_signatures_batch(&headers);
+        let single_results: Vec<_> = headers
+            .iter()
+            .map(|(header, epoch_id, last_known_hash)| {
+                fixture.epoch_manager.verify_chunk_header_signature(
+                    header,
+                    epoch_id,
+                    last_known_hash,
+                )
+            })
+            .collect();
+
+        assert_eq!(batch_results.len(), single_results.len());
+        for (batch, single) in batch_results.iter().zip(single_results.iter()) {
+            assert_eq!(batch.as_ref().ok(), single.as_ref().ok());
+        }
+        assert_matches!(batch_results[0], Ok(true));
+        assert_matches!(batch_results[1], Ok(false));
+        assert_matches!(batch_results[2], Err(_));
+        assert_matches!(batch_results[3], Ok(true));
+    }
+
+    #[test]
+    fn test_validate_chunk_headers_batch() {
+        let fixture = ChunkTestFixture::default();
+        let mut shards_manager = ShardsManagerActor::new(
+            FakeClock::default().clock(),
+            mutable_validator_signer(&fixture.mock_shard_tracker),
+            Arc::new(fixture.epoch_manager.clone()),
+            fixture.shard_tracker.clone(),
+            fixture.mock_network.as_sender(),
+            fixture.mock_client_adapter.as_sender(),
+            fixture.chain_store.new_read_only_chunks_store(),
+            fixture.mock_chain_head.clone(),
+            fixture.mock_chain_head.clone(),
+            Duration::hours(1),
+        );
+        let valid_header = fixture.mock_chunk_header.clone();
+        let mut invalid_header = fixture.mock_chunk_header.clone();
+        match &mut invalid_header {
+            ShardChunkHeader::V1(header) => header.signature = Default::default(),
+            ShardChunkHeader::V2(header) => header.signature = Default::default(),
+            ShardChunkHeader::V3(header) => header.signature = Default::default(),
+        }
+        let chunk_hash = valid_header.chunk_hash();
+        let prev_block_hash = *valid_header.prev_block_hash();
+        shards_manager.encoded_chunks.get_or_insert_from_header(&invalid_header);
+
+        // A header with a bad signature is left for the per-chunk validation.
+        shards_manager.validate_chunk_headers_batch(&prev_block_hash, &[invalid_header]);
+        assert!(!shards_manager.encoded_chunks.get(&chunk_hash).unwrap().header_fully_validated);
+
+        shards_manager.validate_chunk_headers_batch(&prev_block_hash, &[valid_header]);
+        assert!(shards_manager.encoded_chunks.get(&chunk_hash).unwrap().header_fully_validated);
+    }
+
     #[test]
     fn test_report_chunk_for_inclusion_to_client() {
         let fixture = ChunkTestFixture::default();
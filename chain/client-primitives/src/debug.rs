@@ -5,7 +5,7 @@ use near_primitives::congestion_info::CongestionInfo;
 use near_primitives::types::EpochId;
 use near_primitives::views::{
     CatchupStatusView, ChainProcessingInfo, EpochValidatorInfo, RequestedStatePartsView,
-    SyncStatusView,
+    StateSyncTaskView, SyncStatusView,
 };
 use near_primitives::{
     block_header::ApprovalInner,
@@ -193,6 +193,8 @@ pub enum DebugStatus {
     ChainProcessingStatus,
     // The state parts already requested.
     RequestedStateParts,
+    // Structured view of the unfinished state sync downloads.
+    StateSyncTasks,
 }
 
 impl actix::Message for DebugStatus {
@@ -214,4 +216,6 @@ pub enum DebugStatusResponse {
     ChainProcessingStatus(ChainProcessingInfo),
     // The state parts already requested.
     RequestedStateParts(Vec<RequestedStatePartsView>),
+    // The unfinished state sync downloads.
+    StateSyncTasks(Vec<StateSyncTaskView>),
 }
@@ -5,18 +5,19 @@ use near_primitives::merkle::{MerklePath, PartialMerkleTree};
 use near_primitives::network::PeerId;
 use near_primitives::sharding::ChunkHash;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockReference, EpochId, EpochReference, MaybeBlockId, ShardId,
-    TransactionOrReceiptId,
+    AccountId, Balance, BlockHeight, BlockReference, EpochId, EpochReference, Gas, MaybeBlockId,
+    ShardId, StateChangeValueKind, TransactionOrReceiptId,
 };
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
 use near_primitives::views::{
     BlockView, ChunkView, DownloadStatusView, EpochValidatorInfo, ExecutionOutcomeWithIdView,
     GasPriceView, LightClientBlockLiteView, LightClientBlockView, MaintenanceWindowsView,
-    QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView, SplitStorageInfoView,
-    StateChangesKindsView, StateChangesRequestView, StateChangesView, SyncStatusView, TxStatusView,
+    QueryRequest, QueryResponse, ReceiptView, ShardSyncDownloadView, ShardSyncProgressView,
+    SplitStorageInfoView, StateChangesKindsView, StateChangesRequestView, StateChangesView,
+    StateSyncTaskView, SyncStatusView, TxStatusView,
 };
 pub use near_primitives::views::{StatusResponse, StatusSyncInfo};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
 use time::{Duration, OffsetDateTime as Utc};
@@ -150,6 +151,9 @@ impl From<ShardSyncDownload> for ShardSyncDownloadView {
         ShardSyncDownloadView {
             downloads: download.downloads.iter().map(|x| x.into()).collect(),
             status: download.status.to_string(),
+            progress: download.parts_progress().map(|(num_parts_done, num_parts)| {
+                ShardSyncProgressView { num_parts_done, num_parts }
+            }),
         }
     }
 }
@@ -194,6 +198,16 @@ impl ShardSyncDownload {
         }
         self.downloads.get_mut(0)
     }
+
+    /// Returns the number of state parts downloaded so far and the total
+    /// number of parts, or None if the number of parts isn't known yet.
+    pub fn parts_progress(&self) -> Option<(u64, u64)> {
+        if self.status == ShardSyncStatus::StateDownloadHeader || self.downloads.is_empty() {
+            return None;
+        }
+        let num_parts_done = self.downloads.iter().filter(|download| download.done).count();
+        Some((num_parts_done as u64, self.downloads.len() as u64))
+    }
 }
 
 pub fn format_shard_sync_phase_per_shard(
@@ -251,6 +265,45 @@ pub struct StateSyncStatus {
     pub sync_status: HashMap<ShardId, ShardSyncDownload>,
 }
 
+impl StateSyncStatus {
+    /// Returns a structured snapshot of every download that hasn't finished
+    /// yet, ordered by shard.  Unlike `format_shard_sync_phase` this is meant
+    /// to be consumed by tools rather than read by humans.
+    pub fn task_statuses(&self, now: Utc) -> Vec<StateSyncTaskView> {
+        let mut shard_ids: Vec<_> = self.sync_status.keys().copied().collect();
+        shard_ids.sort();
+        let mut tasks = vec![];
+        for shard_id in shard_ids {
+            let shard_sync = &self.sync_status[&shard_id];
+            let phase = shard_sync.status.to_string();
+            for (idx, download) in shard_sync.downloads.iter().enumerate() {
+                if download.done {
+                    continue;
+                }
+                let task_id = match shard_sync.status {
+                    ShardSyncStatus::StateDownloadHeader => "header".to_string(),
+                    _ => format!("part {idx}"),
+                };
+                let state = if download.error {
+                    "failed"
+                } else if download.run_me.load(Ordering::SeqCst) {
+                    "pending"
+                } else {
+                    "requested"
+                };
+                tasks.push(StateSyncTaskView {
+                    task_id,
+                    shard_id,
+                    phase: phase.clone(),
+                    elapsed_ms: (now - download.start_time).whole_milliseconds().max(0) as u128,
+                    state: state.to_string(),
+                });
+            }
+        }
+        tasks
+    }
+}
+
 /// If alternate flag was specified, write formatted sync_status per shard.
 impl std::fmt::Debug for StateSyncStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
@@ -858,6 +911,8 @@ impl Message for GetStateChangesWithCauseInBlock {
 pub struct GetStateChangesWithCauseInBlockForTrackedShards {
     pub block_hash: CryptoHash,
     pub epoch_id: EpochId,
+    /// If set, only the state changes of these kinds are returned.
+    pub kinds: Option<HashSet<StateChangeValueKind>>,
 }
 
 impl Message for GetStateChangesWithCauseInBlockForTrackedShards {
@@ -940,6 +995,61 @@ impl Message for GetExecutionOutcomesForBlock {
     type Result = Result<HashMap<ShardId, Vec<ExecutionOutcomeWithIdView>>, String>;
 }
 
+/// Same as `GetExecutionOutcomesForBlock`, but also returns for every shard the
+/// proof that its outcome root is included in the outcome root of a block header.
+#[derive(Debug)]
+pub struct GetExecutionOutcomesWithProofsForBlock {
+    pub block_hash: CryptoHash,
+}
+
+/// Proof of a shard outcome root against `outcome_root` of the header of block `block_hash`.
+#[derive(Debug, Clone)]
+pub struct OutcomeRootProof {
+    pub block_hash: CryptoHash,
+    pub proof: MerklePath,
+}
+
+pub struct ShardExecutionOutcomesWithProof {
+    pub outcomes: Vec<ExecutionOutcomeWithIdView>,
+    /// `None` if the block committing to the outcome root of the shard is not known yet.
+    pub outcome_root_proof: Option<OutcomeRootProof>,
+}
+
+impl Message for GetExecutionOutcomesWithProofsForBlock {
+    type Result = Result<HashMap<ShardId, ShardExecutionOutcomesWithProof>, String>;
+}
+
+/// Gets the gas usage of applying the chunks of a block for the shards tracked
+/// by the node.
+#[derive(Debug)]
+pub struct GetChunkGasSummariesForBlock {
+    pub block_hash: CryptoHash,
+}
+
+/// Gas usage of applying the chunk of a shard, as recorded in its chunk extra.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkGasSummary {
+    pub gas_used: Gas,
+    pub gas_limit: Gas,
+    pub balance_burnt: Balance,
+}
+
+impl Message for GetChunkGasSummariesForBlock {
+    type Result = Result<HashMap<ShardId, ChunkGasSummary>, String>;
+}
+
+/// Gets the shards the node cares about at a block, i.e. the shards whose
+/// chunks it applies and whose state it has, including the ones it tracks
+/// because of its validator duties.
+#[derive(Debug)]
+pub struct GetTrackedShardsForBlock {
+    pub block_hash: CryptoHash,
+}
+
+impl Message for GetTrackedShardsForBlock {
+    type Result = Result<Vec<ShardId>, String>;
+}
+
 #[derive(Debug)]
 pub struct GetBlockProof {
     pub block_hash: CryptoHash,
@@ -1072,6 +1182,20 @@ impl From<near_chain_primitives::Error> for GetMaintenanceWindowsError {
     }
 }
 
+/// Asks the node to fetch from its peers the chunks of the given shards
+/// included in the given block, and to store them in full even if the node
+/// doesn't track those shards. Best-effort: the chunks are requested once,
+/// and whether they arrived can be checked with `GetChunk`.
+#[derive(Debug)]
+pub struct FetchFullChunks {
+    pub block_hash: CryptoHash,
+    pub shard_ids: Vec<ShardId>,
+}
+
+impl Message for FetchFullChunks {
+    type Result = ();
+}
+
 #[derive(Debug)]
 pub struct GetClientConfig {}
 
@@ -219,6 +219,10 @@ impl Client {
             .config
             .produce_chunk_add_transactions_time_limit
             .update(update_client_config.produce_chunk_add_transactions_time_limit);
+        is_updated |= self
+            .config
+            .state_sync_paused_shards
+            .update(update_client_config.state_sync_paused_shards);
         is_updated
     }
 
@@ -236,6 +240,32 @@ pub struct ProduceChunkResult {
     pub transactions_storage_proof: Option<PartialState>,
 }
 
+/// Explains whether the node would produce a block at a given height on top
+/// of the current head. See `Client::explain_block_production`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductionDiagnosis {
+    pub height: BlockHeight,
+    /// The block producer scheduled for `height`.
+    pub block_producer: AccountId,
+    /// Account of the local validator key, if the node has one.
+    pub validator_id: Option<AccountId>,
+    /// Whether the local validator is the scheduled block producer.
+    pub is_block_producer: bool,
+    /// Whether the local validator key matches the key the scheduled block
+    /// producer has staked with. Always false if not the block producer.
+    pub has_validator_key: bool,
+    /// Whether the head, on top of which the block would be produced, is final.
+    pub prev_block_is_final: bool,
+    /// Whether the head is caught up, which is required to start a new epoch.
+    pub prev_block_caught_up: bool,
+    /// Whether a block at `height` or above is already known.
+    pub height_already_known: bool,
+    /// For each shard, whether a new chunk is ready to be included.
+    pub chunks_ready: Vec<(ShardId, bool)>,
+    /// The first reason why block production would be skipped, if any.
+    pub skip_reason: Option<&'static str>,
+}
+
 impl Client {
     pub fn new(
         clock: Clock,
@@ -263,6 +293,7 @@ impl Client {
             save_trie_changes: config.save_trie_changes,
             background_migration_threads: config.client_background_migration_threads,
             resharding_config: config.resharding_config.clone(),
+            verify_memtrie_after_state_sync: config.state_sync.verify_memtrie,
         };
         let chain = Chain::new(
             clock.clone(),
@@ -340,6 +371,10 @@ impl Client {
             config.state_sync_timeout,
             &config.chain_id,
             &config.state_sync.sync,
+            config.state_sync.per_shard_total_timeout,
+            config.state_sync.header_timeout,
+            config.state_sync.part_timeout,
+            config.state_sync.shard_priority.clone(),
             false,
         );
         let num_block_producer_seats = config.num_block_producer_seats as usize;
@@ -559,6 +594,81 @@ impl Client {
         Ok(true)
     }
 
+    /// Reports whether the node would produce a block at `height` on top of the
+    /// chain head and, if not, why. Mirrors the checks of `produce_block_on`
+    /// without modifying any state.
+    pub fn explain_block_production(
+        &self,
+        height: BlockHeight,
+    ) -> Result<ProductionDiagnosis, Error> {
+        let head = self.chain.head()?;
+        let prev_hash = head.last_block_hash;
+        let prev = self.chain.get_block_header(&prev_hash)?;
+        let epoch_id = self.epoch_manager.get_epoch_id_from_prev_block(&prev_hash)?;
+        let block_producer = self.epoch_manager.get_block_producer(&epoch_id, height)?;
+
+        let validator_signer = self.validator_signer.get();
+        let validator_id = validator_signer.as_ref().map(|signer| signer.validator_id().clone());
+        let is_block_producer = validator_id.as_ref() == Some(&block_producer);
+        let has_validator_key = match &validator_signer {
+            Some(signer) if is_block_producer => {
+                let (validator_stake, _) = self.epoch_manager.get_validator_by_account_id(
+                    &epoch_id,
+                    &prev_hash,
+                    &block_producer,
+                )?;
+                validator_stake.take_public_key() == signer.public_key()
+            }
+            _ => false,
+        };
+
+        let prev_block_is_final = self.chain.final_head()?.height >= prev.height();
+        let prev_block_caught_up = if self.epoch_manager.is_next_block_epoch_start(&prev_hash)? {
+            self.chain.prev_block_is_caught_up(prev.prev_hash(), &prev_hash)?
+        } else {
+            true
+        };
+        let height_already_known = height <= self.chain.chain_store().get_latest_known()?.height;
+        let ready_chunks = self
+            .chunk_inclusion_tracker
+            .get_chunk_headers_ready_for_inclusion(&epoch_id, &prev_hash);
+        let chunks_ready: Vec<(ShardId, bool)> = self
+            .epoch_manager
+            .shard_ids(&epoch_id)?
+            .into_iter()
+            .map(|shard_id| (shard_id, ready_chunks.contains_key(&shard_id)))
+            .collect();
+
+        let skip_reason = if validator_id.is_none() {
+            Some("no validator key")
+        } else if !is_block_producer {
+            Some("not the assigned producer")
+        } else if height_already_known {
+            Some("height already known")
+        } else if !prev_block_caught_up {
+            Some("previous block not caught up")
+        } else if !has_validator_key {
+            Some("validator key mismatch")
+        } else if !self.config.produce_empty_blocks && ready_chunks.is_empty() {
+            Some("no chunks ready")
+        } else {
+            None
+        };
+
+        Ok(ProductionDiagnosis {
+            height,
+            block_producer,
+            validator_id,
+            is_block_producer,
+            has_validator_key,
+            prev_block_is_final,
+            prev_block_caught_up,
+            height_already_known,
+            chunks_ready,
+            skip_reason,
+        })
+    }
+
     /// Produce block if we are block producer for given block `height`.
     /// Either returns produced block (not applied) or error.
     pub fn produce_block(&mut self, height: BlockHeight) -> Result<Option<Block>, Error> {
@@ -2495,6 +2605,10 @@ impl Client {
                             state_sync_timeout,
                             &self.config.chain_id,
                             &self.config.state_sync.sync,
+                            self.config.state_sync.per_shard_total_timeout,
+                            self.config.state_sync.header_timeout,
+                            self.config.state_sync.part_timeout,
+                            self.config.state_sync.shard_priority.clone(),
                             true,
                         ),
                         shards_to_split,
@@ -2533,6 +2647,7 @@ impl Client {
             // first. It will get updated with the shard sync download status
             // for other shards later.
             let new_shard_sync = shards_to_split;
+            state_sync.set_paused_shards(&self.config.state_sync_paused_shards.get());
             match state_sync.run(
                 &me,
                 sync_hash,
@@ -2549,6 +2664,11 @@ impl Client {
                 self.runtime_adapter.clone(),
             )? {
                 StateSyncResult::InProgress => {}
+                StateSyncResult::ShardTimedOut(shard_id) => {
+                    // The sync hash of a catchup is fixed by the epoch, so the
+                    // shard is simply downloaded again on the next run.
+                    debug!(target: "catchup", shard_id, "state sync timed out, restarting");
+                }
                 StateSyncResult::Completed => {
                     debug!(target: "catchup", "state sync completed now catch up blocks");
                     self.chain.catchup_blocks_step(
@@ -47,8 +47,8 @@ use near_chunks::adapter::ShardsManagerRequestFromClient;
 use near_chunks::client::ShardsManagerResponse;
 use near_chunks::logic::get_shards_cares_about_this_or_next_epoch;
 use near_client_primitives::types::{
-    Error, GetClientConfig, GetClientConfigError, GetNetworkInfo, NetworkInfoResponse,
-    StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
+    Error, FetchFullChunks, GetClientConfig, GetClientConfigError, GetNetworkInfo,
+    NetworkInfoResponse, StateSyncStatus, Status, StatusError, StatusSyncInfo, SyncStatus,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
 use near_epoch_manager::{EpochManagerAdapter, RngSeed};
@@ -169,7 +169,11 @@ pub fn start_client(
     let resharding_handle = client.chain.resharding_handle.clone();
 
     let client_sender_for_sync_jobs = LateBoundSender::<ClientSenderForSyncJobs>::new();
-    let sync_jobs_actor = SyncJobsActor::new(client_sender_for_sync_jobs.as_multi_sender());
+    let mut sync_jobs_actor = SyncJobsActor::new(client_sender_for_sync_jobs.as_multi_sender());
+    if client_config.state_sync.dedicated_computation_pool {
+        sync_jobs_actor =
+            sync_jobs_actor.with_computation_spawner(Arc::new(RayonAsyncComputationSpawner));
+    }
     let (sync_jobs_actor_addr, sync_jobs_arbiter) = sync_jobs_actor.spawn_actix_actor();
 
     let client_sender_for_client = LateBoundSender::<ClientSenderForClient>::new();
@@ -1729,6 +1733,9 @@ impl ClientActorInner {
             return;
         }
 
+        self.client
+            .state_sync
+            .set_paused_shards(&self.client.config.state_sync_paused_shards.get());
         let state_sync_result = self.client.state_sync.run(
             &me,
             sync_hash,
@@ -1747,6 +1754,12 @@ impl ClientActorInner {
         let state_sync_result = unwrap_and_report_state_sync_result!(state_sync_result);
         match state_sync_result {
             StateSyncResult::InProgress => (),
+            StateSyncResult::ShardTimedOut(shard_id) => {
+                // Drop the state sync status so that the next sync iteration
+                // picks a fresh sync hash and starts over.
+                warn!(target: "sync", shard_id, "State sync timed out, restarting");
+                self.client.sync_status.update(SyncStatus::NoSync);
+            }
             StateSyncResult::Completed => {
                 info!(target: "sync", "State sync: all shards are done");
 
@@ -2136,10 +2149,49 @@ impl Handler<ShardsManagerResponse> for ClientActorInner {
             } => {
                 self.client.mark_chunk_header_ready_for_inclusion(chunk_header, chunk_producer);
             }
+            ShardsManagerResponse::ChunkCompleteStatus { chunk_hash, complete } => {
+                debug!(target: "client", ?chunk_hash, complete, "Chunk complete status");
+            }
+            ShardsManagerResponse::PartRequestStats(stats) => {
+                for stat in stats {
+                    debug!(
+                        target: "client",
+                        account_id = %stat.account_id,
+                        shard_id = stat.shard_id,
+                        requested = stat.requested,
+                        answered = stat.answered,
+                        "Chunk part request stats");
+                }
+            }
         }
     }
 }
 
+impl Handler<FetchFullChunks> for ClientActorInner {
+    fn handle(&mut self, msg: FetchFullChunks) {
+        let FetchFullChunks { block_hash, shard_ids } = msg;
+        let block = match self.client.chain.get_block(&block_hash) {
+            Ok(block) => block,
+            Err(err) => {
+                debug!(target: "client", ?block_hash, ?err, "Cannot fetch chunks of unknown block");
+                return;
+            }
+        };
+        let chunks_to_request = block
+            .chunks()
+            .iter()
+            .filter(|chunk| {
+                chunk.height_included() == block.header().height()
+                    && shard_ids.contains(&chunk.shard_id())
+            })
+            .cloned()
+            .collect();
+        self.client
+            .shards_manager_adapter
+            .send(ShardsManagerRequestFromClient::RequestFullChunks { chunks_to_request });
+    }
+}
+
 impl Handler<GetClientConfig> for ClientActorInner {
     fn handle(&mut self, msg: GetClientConfig) -> Result<ClientConfig, GetClientConfigError> {
         tracing::debug!(target: "client", ?msg);
@@ -13,7 +13,7 @@ use near_client_primitives::debug::{
 use near_client_primitives::types::Error;
 use near_client_primitives::{
     debug::{EpochInfoView, TrackedShardsView},
-    types::StatusError,
+    types::{StatusError, SyncStatus},
 };
 use near_epoch_manager::EpochManagerAdapter;
 use near_o11y::log_assert;
@@ -178,6 +178,15 @@ impl Handler<DebugStatus> for ClientActorInner {
             DebugStatus::ChainProcessingStatus => Ok(DebugStatusResponse::ChainProcessingStatus(
                 self.client.chain.get_chain_processing_info(),
             )),
+            DebugStatus::StateSyncTasks => {
+                let tasks = match &self.client.sync_status {
+                    SyncStatus::StateSync(state_sync_status) => {
+                        state_sync_status.task_statuses(self.client.clock.now_utc())
+                    }
+                    _ => vec![],
+                };
+                Ok(DebugStatusResponse::StateSyncTasks(tasks))
+            }
         }
     }
 }
@@ -1,14 +1,16 @@
 pub use near_client_primitives::types::{
-    Error, GetBlock, GetBlockProof, GetBlockProofResponse, GetBlockWithMerkleTree, GetChunk,
-    GetClientConfig, GetExecutionOutcome, GetExecutionOutcomeResponse,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
+    ChunkGasSummary, Error, FetchFullChunks, GetBlock, GetBlockProof, GetBlockProofResponse,
+    GetBlockWithMerkleTree, GetChunk, GetChunkGasSummariesForBlock, GetClientConfig,
+    GetExecutionOutcome, GetExecutionOutcomeResponse, GetExecutionOutcomesForBlock,
+    GetExecutionOutcomesWithProofsForBlock, GetGasPrice, GetMaintenanceWindows, GetNetworkInfo,
     GetNextLightClientBlock, GetProtocolConfig, GetReceipt, GetSplitStorageInfo, GetStateChanges,
     GetStateChangesInBlock, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfo, GetValidatorOrdered, Query,
-    QueryError, Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
+    GetStateChangesWithCauseInBlockForTrackedShards, GetTrackedShardsForBlock, GetValidatorInfo,
+    GetValidatorOrdered, OutcomeRootProof, Query, QueryError, ShardExecutionOutcomesWithProof,
+    Status, StatusResponse, SyncStatus, TxStatus, TxStatusError,
 };
 
-pub use crate::client::{Client, ProduceChunkResult};
+pub use crate::client::{Client, ProduceChunkResult, ProductionDiagnosis};
 #[cfg(feature = "test_features")]
 pub use crate::client_actor::NetworkAdversarialMessage;
 pub use crate::client_actor::{start_client, ClientActor, StartClientResult};
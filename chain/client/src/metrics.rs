@@ -502,6 +502,15 @@ pub(crate) static STATE_SYNC_EXTERNAL_PARTS_FAILED: Lazy<IntCounterVec> = Lazy::
     .unwrap()
 });
 
+pub(crate) static STATE_SYNC_EXTERNAL_LOCATION_REQUESTS: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_state_sync_external_location_requests_total",
+        "Requests to each of the configured external storage locations, by result",
+        &["location", "result"],
+    )
+    .unwrap()
+});
+
 pub(crate) static STATE_SYNC_EXTERNAL_PARTS_REQUEST_DELAY: Lazy<HistogramVec> = Lazy::new(|| {
     try_create_histogram_vec(
         "near_state_sync_external_parts_request_delay_sec",
@@ -1,9 +1,18 @@
 use crate::metrics;
-use futures::TryStreamExt;
+use futures::{ready, TryStreamExt};
+use near_async::time::Clock;
+use near_chain_configs::Compression;
+use near_primitives::hash::{hash, CryptoHash};
+use near_primitives::state_sync::STATE_PART_MEMORY_LIMIT;
 use near_primitives::types::{EpochId, ShardId};
+use std::collections::{BTreeSet, HashSet};
+use std::future::Future;
 use std::io::{Read, Write};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
 use std::time::Duration;
 use std::time::Instant;
 
@@ -58,35 +67,82 @@ pub enum ExternalConnection {
         reqwest_client: Arc<reqwest::Client>,
         bucket: String,
     },
+    Azure {
+        reqwest_client: Arc<reqwest::Client>,
+        // Base URL of the blob service of the storage account.
+        endpoint: String,
+        container: String,
+        // Shared access signature granting write and list access to the container.
+        // Downloads don't need it if the container allows anonymous reads.
+        sas_token: Option<String>,
+    },
 }
 
 const GCS_ENCODE_SET: &percent_encoding::AsciiSet =
     &percent_encoding::NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_');
 
+/// Same as `GCS_ENCODE_SET`, but keeps the slashes, as Azure uses them to
+/// emulate directories in the blob names.
+const AZURE_ENCODE_SET: &percent_encoding::AsciiSet = &GCS_ENCODE_SET.remove(b'/');
+
+/// Suffix marking the state parts compressed with zstd.
+const ZSTD_SUFFIX: &str = ".zst";
+
+/// Suffix of the files holding the checksums of the state parts.
+const CHECKSUM_SUFFIX: &str = ".checksum";
+
+/// Size of the chunks in which files are read from the filesystem storage.
+const DOWNLOAD_CHUNK_SIZE: usize = 64 * 1024;
+
 impl ExternalConnection {
     pub async fn get_file(
         &self,
         shard_id: ShardId,
         location: &str,
         file_type: &StateFileType,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        self.download(shard_id, location, file_type, None).await
+    }
+
+    /// Downloads the file at the given location. Every chunk of the file is
+    /// charged to `limiter`, if given, as soon as it arrives, so that the
+    /// download is throttled while it is in progress.
+    async fn download(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+        limiter: Option<&BandwidthLimiter>,
     ) -> Result<Vec<u8>, anyhow::Error> {
         let _timer = metrics::STATE_SYNC_EXTERNAL_PARTS_REQUEST_DELAY
             .with_label_values(&[&shard_id.to_string(), &file_type.to_string()])
             .start_timer();
         match self {
             ExternalConnection::S3 { bucket } => {
-                let response = bucket.get_object(location).await?;
-                tracing::debug!(target: "sync", %shard_id, location, response_code = response.status_code(), num_bytes = response.bytes().len(), "S3 request finished");
-                if response.status_code() == 200 {
-                    Ok(response.bytes().to_vec())
+                let mut writer = ThrottledWriter::new(limiter);
+                let status_code = bucket.get_object_stream(location, &mut writer).await?;
+                let data = writer.data;
+                tracing::debug!(target: "sync", %shard_id, location, response_code = status_code, num_bytes = data.len(), "S3 request finished");
+                if status_code == 200 {
+                    Ok(data)
                 } else {
-                    Err(anyhow::anyhow!("Bad response status code: {}", response.status_code()))
+                    Err(anyhow::anyhow!("Bad response status code: {}", status_code))
                 }
             }
             ExternalConnection::Filesystem { root_dir } => {
                 let path = root_dir.join(location);
                 tracing::debug!(target: "sync", %shard_id, ?path, "Reading a file");
-                let data = std::fs::read(&path)?;
+                let mut file = std::fs::File::open(&path)?;
+                let mut data = vec![];
+                let mut chunk = vec![0; DOWNLOAD_CHUNK_SIZE];
+                loop {
+                    let num_bytes = file.read(&mut chunk)?;
+                    if num_bytes == 0 {
+                        break;
+                    }
+                    charge_download(limiter, num_bytes).await;
+                    data.extend_from_slice(&chunk[..num_bytes]);
+                }
                 Ok(data)
             }
             ExternalConnection::GCS { reqwest_client, bucket, .. } => {
@@ -104,12 +160,28 @@ impl ExternalConnection {
                         Err(e.into())
                     }
                     Ok(r) => {
-                        let bytes = r.bytes().await?.to_vec();
+                        let bytes = read_response(r, limiter).await?;
                         tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "GCS state_part request finished");
                         Ok(bytes)
                     }
                 }
             }
+            ExternalConnection::Azure { reqwest_client, endpoint, container, sas_token } => {
+                let url = azure_blob_url(endpoint, container, location, sas_token.as_deref());
+                let response = reqwest_client.get(&url).send().await?.error_for_status();
+
+                match response {
+                    Err(e) => {
+                        tracing::debug!(target: "sync", %shard_id, location, error = ?e, "Azure state_part request failed");
+                        Err(e.into())
+                    }
+                    Ok(r) => {
+                        let bytes = read_response(r, limiter).await?;
+                        tracing::debug!(target: "sync", %shard_id, location, num_bytes = bytes.len(), "Azure state_part request finished");
+                        Ok(bytes)
+                    }
+                }
+            }
         }
     }
 
@@ -168,7 +240,119 @@ impl ExternalConnection {
                 tracing::debug!(target: "state_sync_dump", shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to GCS");
                 Ok(())
             }
+            ExternalConnection::Azure { reqwest_client, endpoint, container, sas_token } => {
+                let url = azure_blob_url(endpoint, container, location, sas_token.as_deref());
+                reqwest_client
+                    .put(&url)
+                    .header("x-ms-blob-type", "BlockBlob")
+                    .header(reqwest::header::CONTENT_TYPE, "application/octet-stream")
+                    .body(data.to_vec())
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                tracing::debug!(target: "state_sync_dump", shard_id, part_length = data.len(), ?location, ?file_type, "Wrote a state part to Azure");
+                Ok(())
+            }
+        }
+    }
+
+    /// Downloads a state part and decompresses it if needed.
+    /// The part is first looked up in the given compression format, and then
+    /// in the other one, so that buckets with a mix of formats are supported.
+    pub async fn get_state_part(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+        compression: Compression,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        self.download_state_part(shard_id, location, file_type, compression, None).await
+    }
+
+    async fn download_state_part(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+        compression: Compression,
+        limiter: Option<&BandwidthLimiter>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let fallback = match compression {
+            Compression::None => Compression::Zstd,
+            Compression::Zstd => Compression::None,
+        };
+        let preferred_location = compressed_location(location, compression);
+        match self.download(shard_id, &preferred_location, file_type, limiter).await {
+            Ok(data) => decompress_state_part(&data, compression),
+            Err(err) => {
+                tracing::debug!(target: "sync", %shard_id, location, ?compression, ?err, "State part not found, trying a different compression");
+                let data = self
+                    .download(
+                        shard_id,
+                        &compressed_location(location, fallback),
+                        file_type,
+                        limiter,
+                    )
+                    .await?;
+                decompress_state_part(&data, fallback)
+            }
+        }
+    }
+
+    /// Same as `get_state_part`, but also checks the part against the
+    /// checksum stored next to it. A mismatch is reported as an error, so
+    /// that the part is downloaded again.
+    pub async fn get_verified_state_part(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+        compression: Compression,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        self.download_verified_state_part(shard_id, location, file_type, compression, None).await
+    }
+
+    async fn download_verified_state_part(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+        compression: Compression,
+        limiter: Option<&BandwidthLimiter>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let data =
+            self.download_state_part(shard_id, location, file_type, compression, limiter).await?;
+        let checksum =
+            self.download(shard_id, &checksum_location(location), file_type, limiter).await?;
+        let expected = std::str::from_utf8(&checksum)?.trim().parse::<CryptoHash>()?;
+        let actual = hash(&data);
+        if actual != expected {
+            anyhow::bail!("Checksum mismatch of the state part at {location}: expected {expected}, got {actual}");
         }
+        Ok(data)
+    }
+
+    /// Compresses the given state part if requested and uploads it to external storage,
+    /// together with the checksum of the uncompressed part. The checksum goes
+    /// first, so that every part found in the storage has one.
+    pub async fn put_state_part(
+        &self,
+        file_type: StateFileType,
+        data: &[u8],
+        shard_id: ShardId,
+        location: &str,
+        compression: Compression,
+    ) -> Result<(), anyhow::Error> {
+        let checksum = hash(data).to_string();
+        self.put_file(
+            file_type.clone(),
+            checksum.as_bytes(),
+            shard_id,
+            &checksum_location(location),
+        )
+        .await?;
+        let data = compress_state_part(data, compression)?;
+        self.put_file(file_type, &data, shard_id, &compressed_location(location, compression)).await
     }
 
     fn extract_file_name_from_full_path(full_path: String) -> String {
@@ -238,9 +422,201 @@ impl ExternalConnection {
                     .flatten()
                     .collect())
             }
+            ExternalConnection::Azure { .. } => {
+                let prefix = format!("{}/", directory_path);
+                tracing::debug!(target: "state_sync_dump", shard_id, ?directory_path, "List state parts in Azure");
+                let (blobs, _) = self.list_azure_blobs(&prefix).await?;
+                Ok(blobs.into_iter().map(Self::extract_file_name_from_full_path).collect())
+            }
+        }
+    }
+
+    /// Lists the names of the immediate subdirectories of the given directory.
+    /// Like `list_objects`, this requires credentials when using GCS.
+    pub async fn list_directories(
+        &self,
+        directory_path: &str,
+    ) -> Result<Vec<String>, anyhow::Error> {
+        let prefix = format!("{}/", directory_path);
+        match self {
+            ExternalConnection::S3 { bucket } => {
+                let list_results = bucket.list(prefix, Some("/".to_string())).await?;
+                Ok(list_results
+                    .into_iter()
+                    .flat_map(|res| res.common_prefixes.unwrap_or_default())
+                    .map(|common_prefix| {
+                        Self::extract_file_name_from_full_path(
+                            common_prefix.prefix.trim_end_matches('/').to_string(),
+                        )
+                    })
+                    .collect())
+            }
+            ExternalConnection::Filesystem { root_dir } => {
+                let path = root_dir.join(directory_path);
+                if !path.is_dir() {
+                    return Ok(vec![]);
+                }
+                let mut dir_names = vec![];
+                for entry in std::fs::read_dir(&path)? {
+                    let entry = entry?;
+                    if entry.file_type()?.is_dir() {
+                        dir_names.push(Self::extract_file_name_from_path_buf(entry.path()));
+                    }
+                }
+                Ok(dir_names)
+            }
+            ExternalConnection::GCS { gcs_client, bucket, .. } => Ok(gcs_client
+                .object()
+                .list(
+                    bucket,
+                    cloud_storage::ListRequest {
+                        prefix: Some(prefix),
+                        delimiter: Some("/".to_string()),
+                        ..Default::default()
+                    },
+                )
+                .await?
+                .try_collect::<Vec<cloud_storage::object::ObjectList>>()
+                .await?
+                .into_iter()
+                .flat_map(|object_list| object_list.prefixes)
+                .map(|prefix| {
+                    Self::extract_file_name_from_full_path(prefix.trim_end_matches('/').to_string())
+                })
+                .collect()),
+            ExternalConnection::Azure { .. } => {
+                let (_, prefixes) = self.list_azure_blobs(&prefix).await?;
+                Ok(prefixes
+                    .into_iter()
+                    .map(|prefix| {
+                        Self::extract_file_name_from_full_path(
+                            prefix.trim_end_matches('/').to_string(),
+                        )
+                    })
+                    .collect())
+            }
+        }
+    }
+
+    /// Lists the blobs and the virtual directories directly under the given
+    /// prefix of an Azure container, following the continuation markers.
+    async fn list_azure_blobs(
+        &self,
+        prefix: &str,
+    ) -> Result<(Vec<String>, Vec<String>), anyhow::Error> {
+        let ExternalConnection::Azure { reqwest_client, endpoint, container, sas_token } = self
+        else {
+            anyhow::bail!("Not an Azure connection");
+        };
+        let mut url = format!("{}/{}", endpoint, container);
+        if let Some(sas_token) = sas_token {
+            url.push('?');
+            url.push_str(sas_token);
+        }
+        let (mut blobs, mut prefixes) = (vec![], vec![]);
+        let mut marker = String::new();
+        loop {
+            let body = reqwest_client
+                .get(&url)
+                .query(&[
+                    ("restype", "container"),
+                    ("comp", "list"),
+                    ("prefix", prefix),
+                    ("delimiter", "/"),
+                    ("marker", marker.as_str()),
+                ])
+                .send()
+                .await?
+                .error_for_status()?
+                .text()
+                .await?;
+            let page = parse_azure_blob_list(&body);
+            blobs.extend(page.blobs);
+            prefixes.extend(page.prefixes);
+            match page.next_marker {
+                Some(next_marker) => marker = next_marker,
+                None => return Ok((blobs, prefixes)),
+            }
         }
     }
 
+    /// Scans the external storage for the epochs of the given chain that have
+    /// state dumped, and reports for every shard of every such epoch whether
+    /// the header and all the state parts are present.
+    /// Like `list_objects`, this requires credentials when using GCS.
+    pub async fn list_available_state(
+        &self,
+        chain_id: &str,
+    ) -> Result<Vec<AvailableShardState>, anyhow::Error> {
+        let chain_dir = format!("chain_id={}", chain_id);
+        let mut result = vec![];
+        for epoch_height_dir in self.list_directories(&chain_dir).await? {
+            let Some(epoch_height) = parse_dir_value::<u64>(&epoch_height_dir, "epoch_height")
+            else {
+                continue;
+            };
+            let epoch_height_dir = format!("{}/{}", chain_dir, epoch_height_dir);
+            for epoch_id_dir in self.list_directories(&epoch_height_dir).await? {
+                let Some(epoch_hash) = parse_dir_value::<CryptoHash>(&epoch_id_dir, "epoch_id")
+                else {
+                    continue;
+                };
+                let epoch_id = EpochId(epoch_hash);
+                let epoch_dir = format!("{}/{}", epoch_height_dir, epoch_id_dir);
+                let mut shard_ids = BTreeSet::new();
+                for dir in self.list_directories(&epoch_dir).await? {
+                    shard_ids.extend(parse_dir_value::<ShardId>(&dir, "shard_id"));
+                }
+                for dir in self.list_directories(&format!("{}/headers", epoch_dir)).await? {
+                    shard_ids.extend(parse_dir_value::<ShardId>(&dir, "shard_id"));
+                }
+                for shard_id in shard_ids {
+                    result.push(
+                        self.available_shard_state(chain_id, epoch_id, epoch_height, shard_id)
+                            .await?,
+                    );
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    async fn available_shard_state(
+        &self,
+        chain_id: &str,
+        epoch_id: EpochId,
+        epoch_height: u64,
+        shard_id: ShardId,
+    ) -> Result<AvailableShardState, anyhow::Error> {
+        let has_header = self
+            .is_state_sync_header_stored_for_epoch(
+                shard_id,
+                &chain_id.to_string(),
+                &epoch_id,
+                epoch_height,
+            )
+            .await?;
+        let parts_dir = external_storage_location_directory(
+            chain_id,
+            &epoch_id,
+            epoch_height,
+            shard_id,
+            &StateFileType::StatePart { part_id: 0, num_parts: 0 },
+        );
+        let file_names = self.list_objects(shard_id, &parts_dir).await?;
+        let num_parts = file_names.iter().find_map(|name| get_num_parts_from_filename(name));
+        let part_ids: HashSet<u64> =
+            file_names.iter().filter_map(|name| get_part_id_from_filename(name)).collect();
+        Ok(AvailableShardState {
+            epoch_id,
+            epoch_height,
+            shard_id,
+            has_header,
+            num_parts,
+            num_parts_present: part_ids.len() as u64,
+        })
+    }
+
     /// Check if the state sync header exists in the external storage.
     pub async fn is_state_sync_header_stored_for_epoch(
         &self,
@@ -272,6 +648,254 @@ impl ExternalConnection {
     }
 }
 
+/// Connections to several external storages holding the same state dumps.
+/// Requests go to the storage which served the last successful one, and fail
+/// over to the other storages in order.
+#[derive(Clone)]
+pub struct FailoverExternalConnection {
+    /// Connections in the order of preference, with their names used in the metrics.
+    connections: Arc<Vec<(String, ExternalConnection)>>,
+    /// Index of the connection to try first.
+    current: Arc<AtomicUsize>,
+    /// Limits the download rate across all the clones of this connection.
+    bandwidth_limiter: Option<BandwidthLimiter>,
+}
+
+impl FailoverExternalConnection {
+    pub fn new(connections: Vec<(String, ExternalConnection)>) -> Self {
+        assert!(!connections.is_empty(), "At least one external storage is required");
+        Self {
+            connections: Arc::new(connections),
+            current: Default::default(),
+            bandwidth_limiter: None,
+        }
+    }
+
+    pub fn with_bandwidth_limit(mut self, clock: Clock, max_bytes_per_sec: Option<u64>) -> Self {
+        self.bandwidth_limiter =
+            max_bytes_per_sec.map(|bytes_per_sec| BandwidthLimiter::new(clock, bytes_per_sec));
+        self
+    }
+
+    /// Indices of the connections in the order in which to try them.
+    fn connection_order(&self) -> impl Iterator<Item = usize> {
+        let num_connections = self.connections.len();
+        let current = self.current.load(Ordering::Relaxed);
+        (0..num_connections).map(move |i| (current + i) % num_connections)
+    }
+
+    fn record_result(
+        &self,
+        index: usize,
+        result: Result<Vec<u8>, anyhow::Error>,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let name = &self.connections[index].0;
+        let label = if result.is_ok() { "ok" } else { "error" };
+        metrics::STATE_SYNC_EXTERNAL_LOCATION_REQUESTS.with_label_values(&[name, label]).inc();
+        match &result {
+            Ok(_) => self.current.store(index, Ordering::Relaxed),
+            Err(err) if self.connections.len() > 1 => {
+                tracing::debug!(target: "sync", location = name, ?err, "Request to external storage failed, failing over");
+            }
+            Err(_) => {}
+        }
+        result
+    }
+
+    pub async fn get_file(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let limiter = self.bandwidth_limiter.as_ref();
+        let mut last_err = None;
+        for index in self.connection_order() {
+            let result =
+                self.connections[index].1.download(shard_id, location, file_type, limiter).await;
+            match self.record_result(index, result) {
+                Ok(data) => return Ok(data),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap())
+    }
+
+    /// Downloads a state part like `ExternalConnection::get_state_part`, and
+    /// also checks its checksum if `verify_checksum` is set.
+    pub async fn get_state_part(
+        &self,
+        shard_id: ShardId,
+        location: &str,
+        file_type: &StateFileType,
+        compression: Compression,
+        verify_checksum: bool,
+    ) -> Result<Vec<u8>, anyhow::Error> {
+        let limiter = self.bandwidth_limiter.as_ref();
+        let mut last_err = None;
+        for index in self.connection_order() {
+            let connection = &self.connections[index].1;
+            let result = if verify_checksum {
+                connection
+                    .download_verified_state_part(
+                        shard_id,
+                        location,
+                        file_type,
+                        compression,
+                        limiter,
+                    )
+                    .await
+            } else {
+                connection
+                    .download_state_part(shard_id, location, file_type, compression, limiter)
+                    .await
+            };
+            match self.record_result(index, result) {
+                Ok(data) => return Ok(data),
+                Err(err) => last_err = Some(err),
+            }
+        }
+        Err(last_err.unwrap())
+    }
+}
+
+/// Token bucket limiting the rate of downloads. Downloads charge every chunk
+/// they receive and then wait until the resulting debt, if any, is repaid, so
+/// that a large file is throttled while it is being downloaded.
+#[derive(Clone)]
+pub struct BandwidthLimiter {
+    clock: Clock,
+    bytes_per_sec: u64,
+    /// Bytes available for download, negative if more than allowed has been
+    /// downloaded, and the time when it was last refilled.
+    bucket: Arc<Mutex<(f64, Instant)>>,
+}
+
+impl BandwidthLimiter {
+    /// The bucket holds up to a second worth of bytes, so that an idle period
+    /// doesn't allow for an arbitrarily large burst.
+    pub fn new(clock: Clock, bytes_per_sec: u64) -> Self {
+        assert!(bytes_per_sec > 0, "Bandwidth limit must be positive");
+        let bucket = Arc::new(Mutex::new((bytes_per_sec as f64, clock.now())));
+        Self { clock, bytes_per_sec, bucket }
+    }
+
+    /// Adds the bytes accumulated since the last refill to the bucket,
+    /// charges the given number of bytes, and returns the bytes available.
+    fn refill_and_consume(&self, num_bytes: u64) -> f64 {
+        let mut bucket = self.bucket.lock().unwrap();
+        let (available, last_refill) = &mut *bucket;
+        let now = self.clock.now();
+        let refilled =
+            now.saturating_duration_since(*last_refill).as_secs_f64() * self.bytes_per_sec as f64;
+        *available = (*available + refilled).min(self.bytes_per_sec as f64) - num_bytes as f64;
+        *last_refill = now;
+        *available
+    }
+
+    /// Charges the given number of downloaded bytes and waits until the bytes
+    /// downloaded so far fit within the limit.
+    pub async fn consume(&self, num_bytes: u64) {
+        let mut available = self.refill_and_consume(num_bytes);
+        while available < 0.0 {
+            let debt = -available / self.bytes_per_sec as f64;
+            self.clock.sleep(near_async::time::Duration::seconds_f64(debt)).await;
+            available = self.refill_and_consume(0);
+        }
+    }
+}
+
+/// Charges a downloaded chunk to the limiter, if any.
+async fn charge_download(limiter: Option<&BandwidthLimiter>, num_bytes: usize) {
+    if let Some(limiter) = limiter {
+        limiter.consume(num_bytes as u64).await;
+    }
+}
+
+/// Collects a file streamed from S3, charging every chunk to the limiter
+/// before accepting it, so that the stream is throttled while it is read.
+struct ThrottledWriter {
+    limiter: Option<BandwidthLimiter>,
+    /// Wait for the chunk currently being written to fit within the limit.
+    charge: Option<Pin<Box<dyn Future<Output = ()> + Send>>>,
+    data: Vec<u8>,
+}
+
+impl ThrottledWriter {
+    fn new(limiter: Option<&BandwidthLimiter>) -> Self {
+        Self { limiter: limiter.cloned(), charge: None, data: vec![] }
+    }
+}
+
+impl tokio::io::AsyncWrite for ThrottledWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let this = self.get_mut();
+        if let Some(limiter) = &this.limiter {
+            let charge = this.charge.get_or_insert_with(|| {
+                let limiter = limiter.clone();
+                let num_bytes = buf.len() as u64;
+                Box::pin(async move { limiter.consume(num_bytes).await })
+            });
+            ready!(charge.as_mut().poll(cx));
+            this.charge = None;
+        }
+        this.data.extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+/// Reads the body of the response chunk by chunk, charging every chunk to the
+/// limiter as it arrives.
+async fn read_response(
+    mut response: reqwest::Response,
+    limiter: Option<&BandwidthLimiter>,
+) -> Result<Vec<u8>, reqwest::Error> {
+    let mut data = vec![];
+    while let Some(chunk) = response.chunk().await? {
+        charge_download(limiter, chunk.len()).await;
+        data.extend_from_slice(&chunk);
+    }
+    Ok(data)
+}
+
+/// State of a shard at the beginning of an epoch, as found in the external storage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AvailableShardState {
+    pub epoch_id: EpochId,
+    pub epoch_height: u64,
+    pub shard_id: ShardId,
+    pub has_header: bool,
+    /// Total number of state parts, as recorded in the names of the parts.
+    /// `None` if no parts are present.
+    pub num_parts: Option<u64>,
+    /// Number of distinct state parts present.
+    pub num_parts_present: u64,
+}
+
+impl AvailableShardState {
+    /// Whether the shard state can be fully downloaded from the external storage.
+    pub fn is_complete(&self) -> bool {
+        self.has_header && self.num_parts == Some(self.num_parts_present)
+    }
+}
+
+/// Parses the value of a `key=value` directory name of the external storage layout.
+fn parse_dir_value<T: std::str::FromStr>(dir_name: &str, key: &str) -> Option<T> {
+    dir_name.strip_prefix(key)?.strip_prefix('=')?.parse().ok()
+}
+
 /// Construct the state file location on the external storage.
 pub fn external_storage_location(
     chain_id: &str,
@@ -316,12 +940,68 @@ pub fn location_prefix(
     }
 }
 
+/// Location of a state part stored with the given compression.
+fn compressed_location(location: &str, compression: Compression) -> String {
+    match compression {
+        Compression::None => location.to_string(),
+        Compression::Zstd => format!("{}{}", location, ZSTD_SUFFIX),
+    }
+}
+
+/// The checksum doesn't depend on the compression, as it covers the
+/// uncompressed state part.
+fn checksum_location(location: &str) -> String {
+    format!("{}{}", location, CHECKSUM_SUFFIX)
+}
+
+pub fn compress_state_part(
+    data: &[u8],
+    compression: Compression,
+) -> Result<Vec<u8>, anyhow::Error> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Zstd => Ok(zstd::encode_all(data, 0)?),
+    }
+}
+
+/// Upper bound on the size of a decompressed state part. The state is split
+/// into parts by the memory usage of the trie nodes, which is larger than their
+/// serialized size, so a valid part never gets close to this.
+const MAX_DECOMPRESSED_STATE_PART_SIZE: u64 = 4 * STATE_PART_MEMORY_LIMIT.0;
+
+pub fn decompress_state_part(
+    data: &[u8],
+    compression: Compression,
+) -> Result<Vec<u8>, anyhow::Error> {
+    decompress_state_part_with_limit(data, compression, MAX_DECOMPRESSED_STATE_PART_SIZE)
+}
+
+/// Decompresses at most `limit` bytes, so that a malicious part from an
+/// external bucket can't exhaust the memory of the node.
+fn decompress_state_part_with_limit(
+    data: &[u8],
+    compression: Compression,
+    limit: u64,
+) -> Result<Vec<u8>, anyhow::Error> {
+    match compression {
+        Compression::None => Ok(data.to_vec()),
+        Compression::Zstd => {
+            let mut decompressed = vec![];
+            zstd::Decoder::new(data)?.take(limit + 1).read_to_end(&mut decompressed)?;
+            if decompressed.len() as u64 > limit {
+                anyhow::bail!("Decompressed state part is larger than {limit} bytes");
+            }
+            Ok(decompressed)
+        }
+    }
+}
+
 pub fn part_filename(part_id: u64, num_parts: u64) -> String {
     format!("state_part_{:06}_of_{:06}", part_id, num_parts)
 }
 
 pub fn match_filename(s: &str) -> Option<regex::Captures> {
-    let re = regex::Regex::new(r"^state_part_(\d{6})_of_(\d{6})$").unwrap();
+    let re = regex::Regex::new(r"^state_part_(\d{6})_of_(\d{6})(\.zst)?$").unwrap();
     re.captures(s)
 }
 
@@ -391,6 +1071,83 @@ pub fn create_bucket_readwrite(
     create_bucket(bucket, region, timeout, creds)
 }
 
+/// Connects to an Azure Blob Storage container. Without a SAS token the
+/// container can only be read, and only if it allows anonymous access.
+pub fn create_azure_connection(
+    account: &str,
+    container: &str,
+    endpoint: Option<&str>,
+    sas_token: Option<String>,
+) -> ExternalConnection {
+    let endpoint = match endpoint {
+        Some(endpoint) => endpoint.trim_end_matches('/').to_string(),
+        None => format!("https://{}.blob.core.windows.net", account),
+    };
+    ExternalConnection::Azure {
+        reqwest_client: Arc::new(reqwest::Client::default()),
+        endpoint,
+        container: container.to_string(),
+        sas_token: sas_token.map(|token| token.trim_start_matches('?').to_string()),
+    }
+}
+
+/// Reads the SAS token of an Azure container from the given file, or from the
+/// `AZURE_STORAGE_SAS_TOKEN` environment variable if no file is given.
+pub fn read_azure_sas_token(
+    credentials_file: Option<PathBuf>,
+) -> Result<Option<String>, anyhow::Error> {
+    match credentials_file {
+        Some(credentials_file) => {
+            Ok(Some(std::fs::read_to_string(credentials_file)?.trim().to_string()))
+        }
+        None => Ok(std::env::var("AZURE_STORAGE_SAS_TOKEN").ok()),
+    }
+}
+
+fn azure_blob_url(endpoint: &str, container: &str, blob: &str, sas_token: Option<&str>) -> String {
+    let mut url = format!(
+        "{}/{}/{}",
+        endpoint,
+        container,
+        percent_encoding::utf8_percent_encode(blob, AZURE_ENCODE_SET)
+    );
+    if let Some(sas_token) = sas_token {
+        url.push('?');
+        url.push_str(sas_token);
+    }
+    url
+}
+
+/// One page of the response of the Azure List Blobs operation.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct AzureBlobListPage {
+    blobs: Vec<String>,
+    prefixes: Vec<String>,
+    next_marker: Option<String>,
+}
+
+/// Extracts the blob names, the virtual directories and the continuation
+/// marker from the XML response of the Azure List Blobs operation.
+fn parse_azure_blob_list(xml: &str) -> AzureBlobListPage {
+    let names = |pattern: &str| -> Vec<String> {
+        let re = regex::Regex::new(pattern).unwrap();
+        re.captures_iter(xml).map(|captures| unescape_xml(&captures[1])).collect()
+    };
+    AzureBlobListPage {
+        blobs: names(r"<Blob>\s*<Name>([^<]*)</Name>"),
+        prefixes: names(r"<BlobPrefix>\s*<Name>([^<]*)</Name>"),
+        next_marker: names(r"<NextMarker>([^<]+)</NextMarker>").pop(),
+    }
+}
+
+fn unescape_xml(s: &str) -> String {
+    s.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
 fn create_bucket(
     bucket: &str,
     region: &str,
@@ -405,12 +1162,21 @@ fn create_bucket(
 
 #[cfg(test)]
 mod test {
+    use crate::metrics;
     use crate::sync::external::{
-        get_num_parts_from_filename, get_part_id_from_filename, is_part_filename,
-        ExternalConnection, StateFileType,
+        compress_state_part, create_azure_connection, decompress_state_part_with_limit,
+        external_storage_location, get_num_parts_from_filename, get_part_id_from_filename,
+        is_part_filename, parse_azure_blob_list, AvailableShardState, AzureBlobListPage,
+        ExternalConnection, FailoverExternalConnection, StateFileType, DOWNLOAD_CHUNK_SIZE,
     };
+    use near_async::time::{Duration, FakeClock};
+    use near_chain_configs::{Compression, ExternalStorageLocation};
     use near_o11y::testonly::init_test_logger;
+    use near_primitives::hash::hash;
+    use near_primitives::types::EpochId;
     use rand::distributions::{Alphanumeric, DistString};
+    use std::future::Future;
+    use std::task::Poll;
 
     fn random_string(rand_len: usize) -> String {
         Alphanumeric.sample_string(&mut rand::thread_rng(), rand_len)
@@ -427,6 +1193,427 @@ mod test {
 
         assert_eq!(get_part_id_from_filename(&filename), Some(5));
         assert_eq!(get_part_id_from_filename("123123"), None);
+
+        let compressed_filename = format!("{}.zst", filename);
+        assert!(is_part_filename(&compressed_filename));
+        assert_eq!(get_num_parts_from_filename(&compressed_filename), Some(15));
+        assert_eq!(get_part_id_from_filename(&compressed_filename), Some(5));
+        assert!(!is_part_filename(&format!("{}.gz", filename)));
+    }
+
+    #[test]
+    fn test_compressed_state_part_round_trip() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let connection = ExternalConnection::Filesystem { root_dir: root_dir.path().to_path_buf() };
+
+        let data: Vec<u8> = random_string(1000).repeat(10).into();
+        let location =
+            "chain_id=test/epoch_height=1/epoch_id=test/shard_id=0/state_part_000000_of_000001";
+        let file_type = StateFileType::StatePart { part_id: 0, num_parts: 1 };
+
+        rt.block_on(async {
+            connection
+                .put_state_part(file_type.clone(), &data, 0, location, Compression::Zstd)
+                .await
+                .unwrap()
+        });
+        // The compressed part is stored with a suffix and is smaller than the original.
+        assert!(!root_dir.path().join(location).exists());
+        let stored = std::fs::read(root_dir.path().join(format!("{}.zst", location))).unwrap();
+        assert!(stored.len() < data.len());
+
+        // The part can be downloaded regardless of the preferred compression.
+        for compression in [Compression::Zstd, Compression::None] {
+            let downloaded = rt.block_on(async {
+                connection.get_state_part(0, location, &file_type, compression).await.unwrap()
+            });
+            assert_eq!(downloaded, data);
+        }
+
+        // Uncompressed parts are still found when compression is preferred.
+        let location =
+            "chain_id=test/epoch_height=1/epoch_id=test/shard_id=1/state_part_000000_of_000001";
+        rt.block_on(async {
+            connection
+                .put_state_part(file_type.clone(), &data, 1, location, Compression::None)
+                .await
+                .unwrap()
+        });
+        let downloaded = rt.block_on(async {
+            connection.get_state_part(1, location, &file_type, Compression::Zstd).await.unwrap()
+        });
+        assert_eq!(downloaded, data);
+    }
+
+    #[test]
+    fn test_decompressed_state_part_size_limit() {
+        let data = vec![0u8; 1 << 20];
+        let compressed = compress_state_part(&data, Compression::Zstd).unwrap();
+        assert!(compressed.len() < 1000);
+
+        let limit = data.len() as u64;
+        assert_eq!(
+            decompress_state_part_with_limit(&compressed, Compression::Zstd, limit).unwrap(),
+            data
+        );
+        assert!(
+            decompress_state_part_with_limit(&compressed, Compression::Zstd, limit - 1).is_err()
+        );
+    }
+
+    #[test]
+    fn test_verified_state_part() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let connection = ExternalConnection::Filesystem { root_dir: root_dir.path().to_path_buf() };
+
+        let data: Vec<u8> = random_string(1000).into();
+        let location =
+            "chain_id=test/epoch_height=1/epoch_id=test/shard_id=0/state_part_000000_of_000001";
+        let file_type = StateFileType::StatePart { part_id: 0, num_parts: 1 };
+        rt.block_on(async {
+            connection
+                .put_state_part(file_type.clone(), &data, 0, location, Compression::Zstd)
+                .await
+                .unwrap()
+        });
+
+        // The checksum matches.
+        let downloaded = rt.block_on(async {
+            connection
+                .get_verified_state_part(0, location, &file_type, Compression::Zstd)
+                .await
+                .unwrap()
+        });
+        assert_eq!(downloaded, data);
+
+        // The part got corrupted in the storage.
+        let mut corrupted = data.clone();
+        corrupted[0] ^= 1;
+        rt.block_on(async {
+            connection
+                .put_file(
+                    file_type.clone(),
+                    &compress_state_part(&corrupted, Compression::Zstd).unwrap(),
+                    0,
+                    &format!("{}.zst", location),
+                )
+                .await
+                .unwrap()
+        });
+        let result = rt.block_on(async {
+            connection.get_verified_state_part(0, location, &file_type, Compression::Zstd).await
+        });
+        assert!(result.unwrap_err().to_string().contains("Checksum mismatch"));
+        // Without the verification the corrupted part is accepted.
+        let downloaded = rt.block_on(async {
+            connection.get_state_part(0, location, &file_type, Compression::Zstd).await.unwrap()
+        });
+        assert_eq!(downloaded, corrupted);
+    }
+
+    #[test]
+    fn test_list_available_state() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let connection = ExternalConnection::Filesystem { root_dir: root_dir.path().to_path_buf() };
+        let chain_id = "test";
+        let epoch_id = EpochId(hash(b"epoch"));
+        let epoch_height = 5;
+
+        let put = |shard_id, file_type: StateFileType| {
+            let location =
+                external_storage_location(chain_id, &epoch_id, epoch_height, shard_id, &file_type);
+            rt.block_on(connection.put_file(file_type, b"data", shard_id, &location)).unwrap();
+        };
+        // Shard 0 is complete.
+        put(0, StateFileType::StateHeader);
+        put(0, StateFileType::StatePart { part_id: 0, num_parts: 2 });
+        put(0, StateFileType::StatePart { part_id: 1, num_parts: 2 });
+        // Shard 1 is missing some parts.
+        put(1, StateFileType::StateHeader);
+        put(1, StateFileType::StatePart { part_id: 2, num_parts: 3 });
+        // Shard 2 is missing the header.
+        put(2, StateFileType::StatePart { part_id: 0, num_parts: 1 });
+        // Unrelated directories are ignored.
+        std::fs::create_dir_all(root_dir.path().join("chain_id=test/unrelated")).unwrap();
+        std::fs::create_dir_all(root_dir.path().join("chain_id=other/epoch_height=1")).unwrap();
+
+        let available = rt.block_on(connection.list_available_state(chain_id)).unwrap();
+        let shard_state =
+            |shard_id, has_header, num_parts, num_parts_present| AvailableShardState {
+                epoch_id,
+                epoch_height,
+                shard_id,
+                has_header,
+                num_parts,
+                num_parts_present,
+            };
+        assert_eq!(
+            available,
+            vec![
+                shard_state(0, true, Some(2), 2),
+                shard_state(1, true, Some(3), 1),
+                shard_state(2, false, Some(1), 1),
+            ]
+        );
+        assert_eq!(
+            available.iter().map(AvailableShardState::is_complete).collect::<Vec<_>>(),
+            vec![true, false, false]
+        );
+        assert!(rt.block_on(connection.list_available_state("missing")).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_failover_to_working_location() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        // The first location never has the requested files.
+        let broken_dir = tempfile::tempdir().unwrap();
+        let mirror_dir = tempfile::tempdir().unwrap();
+        let connection = FailoverExternalConnection::new(vec![
+            (
+                "test_failover_broken".to_string(),
+                ExternalConnection::Filesystem { root_dir: broken_dir.path().to_path_buf() },
+            ),
+            (
+                "test_failover_mirror".to_string(),
+                ExternalConnection::Filesystem { root_dir: mirror_dir.path().to_path_buf() },
+            ),
+        ]);
+        let requests = |location: &str, result: &str| {
+            metrics::STATE_SYNC_EXTERNAL_LOCATION_REQUESTS
+                .with_label_values(&[location, result])
+                .get()
+        };
+
+        let data: Vec<u8> = random_string(1000).into();
+        let location =
+            "chain_id=test/epoch_height=1/epoch_id=test/shard_id=0/state_part_000000_of_000001";
+        let file_type = StateFileType::StatePart { part_id: 0, num_parts: 1 };
+        let mirror = ExternalConnection::Filesystem { root_dir: mirror_dir.path().to_path_buf() };
+        rt.block_on(mirror.put_state_part(
+            file_type.clone(),
+            &data,
+            0,
+            location,
+            Compression::None,
+        ))
+        .unwrap();
+
+        for verify_checksum in [false, true] {
+            let downloaded = rt
+                .block_on(connection.get_state_part(
+                    0,
+                    location,
+                    &file_type,
+                    Compression::None,
+                    verify_checksum,
+                ))
+                .unwrap();
+            assert_eq!(downloaded, data);
+        }
+        // The broken location is only tried once, then the mirror is preferred.
+        assert_eq!(requests("test_failover_broken", "error"), 1);
+        assert_eq!(requests("test_failover_mirror", "ok"), 2);
+
+        // A file missing everywhere is an error.
+        let missing = rt.block_on(connection.get_file(0, "missing", &StateFileType::StateHeader));
+        assert!(missing.is_err());
+        assert_eq!(requests("test_failover_broken", "error"), 2);
+        assert_eq!(requests("test_failover_mirror", "error"), 1);
+    }
+
+    /// Runs the future to completion, advancing the fake clock in small steps
+    /// whenever the future is blocked, and returns its output together with
+    /// the time that passed on the fake clock.
+    async fn run_with_fake_clock<F: Future>(clock: &FakeClock, future: F) -> (F::Output, f64) {
+        let start = clock.now();
+        let mut future = std::pin::pin!(future);
+        loop {
+            if let Poll::Ready(output) = futures::poll!(future.as_mut()) {
+                return (output, (clock.now() - start).as_secs_f64());
+            }
+            clock.advance(Duration::milliseconds(10));
+        }
+    }
+
+    #[test]
+    fn test_bandwidth_limit() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let storage = ExternalConnection::Filesystem { root_dir: root_dir.path().to_path_buf() };
+        let location = "chain_id=test/epoch_height=1/epoch_id=test/shard_id=0/header";
+        let file_type = StateFileType::StateHeader;
+        let file_size = 1000;
+        rt.block_on(storage.put_file(file_type.clone(), &vec![0; file_size], 0, location)).unwrap();
+
+        let clock = FakeClock::default();
+        let bytes_per_sec = 10_000;
+        let connection =
+            FailoverExternalConnection::new(vec![("test_bandwidth_limit".to_string(), storage)])
+                .with_bandwidth_limit(clock.clock(), Some(bytes_per_sec));
+        let num_tasks = 4;
+        let num_downloads_per_task = 5;
+        let (connection, file_type) = (&connection, &file_type);
+        let tasks = (0..num_tasks).map(|_| async move {
+            let mut downloaded = 0;
+            for _ in 0..num_downloads_per_task {
+                downloaded += connection.get_file(0, location, file_type).await.unwrap().len();
+            }
+            downloaded
+        });
+        let (downloaded, elapsed) =
+            rt.block_on(run_with_fake_clock(&clock, futures::future::join_all(tasks)));
+        let downloaded: usize = downloaded.into_iter().sum();
+
+        assert_eq!(downloaded, num_tasks * num_downloads_per_task * file_size);
+        // The initial burst of a second worth of bytes is free, the rest is
+        // downloaded at the limited rate.
+        let expected = (downloaded - bytes_per_sec as usize) as f64 / bytes_per_sec as f64;
+        assert!(
+            expected <= elapsed && elapsed <= expected + 0.1,
+            "downloaded {downloaded} bytes in {elapsed}s, expected {expected}s"
+        );
+    }
+
+    #[test]
+    fn test_bandwidth_limit_within_file() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let root_dir = tempfile::tempdir().unwrap();
+        let storage = ExternalConnection::Filesystem { root_dir: root_dir.path().to_path_buf() };
+        let location = "chain_id=test/epoch_height=1/epoch_id=test/shard_id=0/header";
+        let file_type = StateFileType::StateHeader;
+        let bytes_per_sec = 4 * DOWNLOAD_CHUNK_SIZE;
+        let file_size = 2 * bytes_per_sec;
+        rt.block_on(storage.put_file(file_type.clone(), &vec![0; file_size], 0, location)).unwrap();
+
+        let clock = FakeClock::default();
+        let connection = FailoverExternalConnection::new(vec![(
+            "test_bandwidth_limit_within_file".to_string(),
+            storage,
+        )])
+        .with_bandwidth_limit(clock.clock(), Some(bytes_per_sec as u64));
+        let (data, elapsed) =
+            rt.block_on(run_with_fake_clock(&clock, connection.get_file(0, location, &file_type)));
+
+        assert_eq!(data.unwrap().len(), file_size);
+        // A single file is throttled while it is downloaded: besides the
+        // initial burst of a second worth of bytes, the rest takes a second.
+        assert!(1.0 <= elapsed && elapsed <= 1.1, "downloaded {file_size} bytes in {elapsed}s");
+    }
+
+    #[test]
+    fn test_parse_azure_blob_list() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <EnumerationResults ServiceEndpoint="https://account.blob.core.windows.net/" ContainerName="state-parts">
+              <Prefix>dir/</Prefix>
+              <Blobs>
+                <Blob><Name>dir/header</Name><Properties /></Blob>
+                <Blob><Name>dir/a&amp;b</Name><Properties /></Blob>
+                <BlobPrefix><Name>dir/shard_id=0/</Name></BlobPrefix>
+              </Blobs>
+              <NextMarker>marker</NextMarker>
+            </EnumerationResults>"#;
+        assert_eq!(
+            parse_azure_blob_list(xml),
+            AzureBlobListPage {
+                blobs: vec!["dir/header".to_string(), "dir/a&b".to_string()],
+                prefixes: vec!["dir/shard_id=0/".to_string()],
+                next_marker: Some("marker".to_string()),
+            }
+        );
+        let last_page = "<EnumerationResults><Blobs /><NextMarker /></EnumerationResults>";
+        assert_eq!(parse_azure_blob_list(last_page), AzureBlobListPage::default());
+    }
+
+    /// Serves the given blobs over HTTP like the Azure blob service does, with
+    /// the container listing served at the path of the container.
+    fn start_mock_blob_service(blobs: std::collections::HashMap<String, Vec<u8>>) -> String {
+        use std::io::{BufRead, Write};
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let endpoint = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = std::io::BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                // Skip the headers, the mock only serves GET requests.
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let target = request_line.split(' ').nth(1).unwrap_or_default();
+                let path = target.split('?').next().unwrap();
+                let (status, body) = match blobs.get(path) {
+                    Some(body) => ("200 OK", body.clone()),
+                    None => ("404 Not Found", vec![]),
+                };
+                write!(
+                    stream,
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                )
+                .unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+        endpoint
+    }
+
+    #[test]
+    fn test_azure_download_and_list() {
+        init_test_logger();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let dir = "chain_id=test/epoch_height=1/epoch_id=test/shards/shard_id=0";
+        let location = format!("{}/header", dir);
+        let listing = format!(
+            "<EnumerationResults><Blobs><Blob><Name>{}</Name></Blob></Blobs><NextMarker /></EnumerationResults>",
+            location
+        );
+        let endpoint = start_mock_blob_service(
+            [
+                (format!("/state-parts/{}", location.replace('=', "%3D")), b"header".to_vec()),
+                ("/state-parts".to_string(), listing.into_bytes()),
+            ]
+            .into_iter()
+            .collect(),
+        );
+
+        let location_config = ExternalStorageLocation::Azure {
+            account: "account".to_string(),
+            container: "state-parts".to_string(),
+            endpoint: Some(endpoint.clone()),
+        };
+        let ExternalStorageLocation::Azure { account, container, endpoint } = &location_config
+        else {
+            unreachable!();
+        };
+        let connection = create_azure_connection(account, container, endpoint.as_deref(), None);
+
+        let file_type = StateFileType::StateHeader;
+        let data = rt.block_on(connection.get_file(0, &location, &file_type)).unwrap();
+        assert_eq!(data, b"header");
+        let missing = rt.block_on(connection.get_file(0, &format!("{}/missing", dir), &file_type));
+        assert!(missing.is_err(), "{:?}", missing);
+        assert_eq!(
+            rt.block_on(connection.list_objects(0, dir)).unwrap(),
+            vec!["header".to_string()]
+        );
+
+        let ExternalConnection::Azure { endpoint, .. } =
+            create_azure_connection("account", "state-parts", None, None)
+        else {
+            unreachable!();
+        };
+        assert_eq!(endpoint, "https://account.blob.core.windows.net");
     }
 
     /// This test should be ignored by default, as it requires gcloud credentials to run.
@@ -22,19 +22,20 @@
 
 use crate::metrics;
 use crate::sync::external::{
-    create_bucket_readonly, external_storage_location, ExternalConnection,
+    create_azure_connection, create_bucket_readonly, external_storage_location, ExternalConnection,
+    FailoverExternalConnection,
 };
 use borsh::BorshDeserialize;
 use futures::{future, FutureExt};
 use near_async::futures::{FutureSpawner, FutureSpawnerExt};
 use near_async::messaging::SendAsync;
-use near_async::time::{Clock, Duration, Utc};
+use near_async::time::{Clock, Duration, Instant, Utc};
 use near_chain::chain::{ApplyStatePartsRequest, LoadMemtrieRequest};
 use near_chain::near_chain_primitives;
 use near_chain::resharding::ReshardingRequest;
 use near_chain::types::RuntimeAdapter;
 use near_chain::Chain;
-use near_chain_configs::{ExternalStorageConfig, ExternalStorageLocation, SyncConfig};
+use near_chain_configs::{Compression, ExternalStorageConfig, ExternalStorageLocation, SyncConfig};
 use near_client_primitives::types::{
     format_shard_sync_phase, DownloadStatus, ShardSyncDownload, ShardSyncStatus,
 };
@@ -61,6 +62,7 @@ use std::sync::atomic::Ordering;
 use std::sync::mpsc::{channel, Receiver, Sender};
 use std::sync::Arc;
 use tokio::sync::{Semaphore, TryAcquireError};
+use tokio_util::sync::CancellationToken;
 use tracing::info;
 
 use super::external::StateFileType;
@@ -73,12 +75,19 @@ pub const MAX_PENDING_PART: u64 = MAX_STATE_PART_REQUEST * 10000;
 /// Time limit per state dump iteration.
 /// A node must check external storage for parts to dump again once time is up.
 pub const STATE_DUMP_ITERATION_TIME_LIMIT_SECS: u64 = 300;
+/// How long to avoid the external storage once the circuit breaker trips, unless configured.
+const DEFAULT_CIRCUIT_BREAKER_COOLDOWN: Duration = Duration::minutes(10);
+/// Upper bound of the growing cooldown of the circuit breaker, unless configured.
+const DEFAULT_CIRCUIT_BREAKER_MAX_COOLDOWN: Duration = Duration::hours(1);
 
 pub enum StateSyncResult {
     /// State sync still in progress. No action needed by the caller.
     InProgress,
     /// The state for all shards was downloaded.
     Completed,
+    /// State sync of the given shard took longer than the configured
+    /// `per_shard_total_timeout` and was abandoned. The caller should retry.
+    ShardTimedOut(ShardId),
 }
 
 struct PendingRequestStatus {
@@ -127,11 +136,46 @@ enum StateSyncInner {
         chain_id: String,
         /// This semaphore imposes a restriction on the maximum number of simultaneous downloads
         semaphore: Arc<tokio::sync::Semaphore>,
-        /// Connection to the external storage.
-        external: ExternalConnection,
+        /// Connections to the external storage and its mirrors.
+        external: FailoverExternalConnection,
+        /// Compression format of the state parts to try first.
+        compression: Compression,
+        /// Whether to check the downloaded state parts against their checksums.
+        verify_checksums: bool,
+        /// Stops the use of the external storage if it keeps failing.
+        circuit_breaker: ExternalCircuitBreaker,
+        /// Same as in `Peers`, used to request the state from the peers while
+        /// `circuit_breaker` is open.
+        last_part_id_requested: HashMap<(PeerId, ShardId), PendingRequestStatus>,
+        requested_target: lru::LruCache<(u64, CryptoHash), PeerId>,
     },
 }
 
+impl StateSyncInner {
+    /// Whether the state should be requested from the peers at the moment.
+    fn use_peers(&self) -> bool {
+        match self {
+            StateSyncInner::Peers { .. } => true,
+            StateSyncInner::External { circuit_breaker, .. } => circuit_breaker.is_open(),
+        }
+    }
+
+    /// Bookkeeping of the state part requests sent to the peers.
+    fn peer_requests(
+        &mut self,
+    ) -> (
+        &mut HashMap<(PeerId, ShardId), PendingRequestStatus>,
+        &mut lru::LruCache<(u64, CryptoHash), PeerId>,
+    ) {
+        match self {
+            StateSyncInner::Peers { last_part_id_requested, requested_target }
+            | StateSyncInner::External { last_part_id_requested, requested_target, .. } => {
+                (last_part_id_requested, requested_target)
+            }
+        }
+    }
+}
+
 /// Helper to track state sync.
 pub struct StateSync {
     clock: Clock,
@@ -145,6 +189,40 @@ pub struct StateSync {
     /// for the answer from the other node before giving up.
     timeout: Duration,
 
+    /// How long to wait for a state header before requesting it again.
+    /// Headers are small, so a hung request can be detected much sooner.
+    header_timeout: Duration,
+
+    /// How long to wait for a state part before requesting it again.
+    part_timeout: Duration,
+
+    /// Maximum time the state sync of a single shard may take, if set.
+    per_shard_total_timeout: Option<Duration>,
+
+    /// Shards to sync ahead of all others, most urgent first.
+    shard_priority: Vec<ShardId>,
+
+    /// If set, limits the number of shards whose state is applied at the same
+    /// time according to a ramp-up schedule.
+    apply_concurrency_ramp: Option<ConcurrencyRamp>,
+
+    /// Maps shard_id to the time when its state sync started.
+    shard_sync_started: HashMap<ShardId, Instant>,
+
+    /// Maps shard_id to the token passed to its part download tasks. The
+    /// token is cancelled when the shard times out, so that downloads still
+    /// in flight don't store their parts.
+    shard_cancellation: HashMap<ShardId, CancellationToken>,
+
+    /// Shards for which no new download or computation tasks are scheduled,
+    /// see `pause_shard`, mapped to the time they were paused at.
+    paused_shards: HashMap<ShardId, Instant>,
+
+    /// Shards resumed since the last `run`, mapped to for how long they were
+    /// paused. Their download timers are pushed back by that much on the next
+    /// `run`, so that the pause doesn't count as a timed out request.
+    resumed_shards: HashMap<ShardId, Duration>,
+
     /// Maps shard_id to result of applying downloaded state.
     state_parts_apply_results: HashMap<ShardId, Result<(), near_chain_primitives::error::Error>>,
 
@@ -167,6 +245,10 @@ impl StateSync {
         timeout: Duration,
         chain_id: &str,
         sync_config: &SyncConfig,
+        per_shard_total_timeout: Option<Duration>,
+        header_timeout: Option<Duration>,
+        part_timeout: Option<Duration>,
+        shard_priority: Vec<ShardId>,
         catchup: bool,
     ) -> Self {
         let inner = match sync_config {
@@ -178,30 +260,29 @@ impl StateSync {
             },
             SyncConfig::ExternalStorage(ExternalStorageConfig {
                 location,
+                additional_locations,
                 num_concurrent_requests,
                 num_concurrent_requests_during_catchup,
+                concurrency_ramp_up_period: _,
+                compression,
+                circuit_breaker_failure_threshold,
+                circuit_breaker_cooldown,
+                circuit_breaker_max_cooldown,
+                verify_checksums,
+                max_bytes_per_sec,
             }) => {
-                let external = match location {
-                    ExternalStorageLocation::S3 { bucket, region, .. } => {
-                        let bucket = create_bucket_readonly(
-                            &bucket,
-                            &region,
-                            timeout.max(Duration::ZERO).unsigned_abs(),
-                        );
-                        if let Err(err) = bucket {
-                            panic!("Failed to create an S3 bucket: {}", err);
-                        }
-                        ExternalConnection::S3 { bucket: Arc::new(bucket.unwrap()) }
-                    }
-                    ExternalStorageLocation::Filesystem { root_dir } => {
-                        ExternalConnection::Filesystem { root_dir: root_dir.clone() }
-                    }
-                    ExternalStorageLocation::GCS { bucket, .. } => ExternalConnection::GCS {
-                        gcs_client: Arc::new(cloud_storage::Client::default()),
-                        reqwest_client: Arc::new(reqwest::Client::default()),
-                        bucket: bucket.clone(),
-                    },
-                };
+                let external = FailoverExternalConnection::new(
+                    std::iter::once(location)
+                        .chain(additional_locations)
+                        .map(|location| {
+                            (
+                                external_location_name(location),
+                                create_external_connection(location, timeout),
+                            )
+                        })
+                        .collect(),
+                )
+                .with_bandwidth_limit(clock.clone(), *max_bytes_per_sec);
                 let num_permits = if catchup {
                     *num_concurrent_requests_during_catchup
                 } else {
@@ -211,15 +292,43 @@ impl StateSync {
                     chain_id: chain_id.to_string(),
                     semaphore: Arc::new(tokio::sync::Semaphore::new(num_permits)),
                     external,
+                    compression: *compression,
+                    verify_checksums: *verify_checksums,
+                    circuit_breaker: ExternalCircuitBreaker::new(
+                        clock.clone(),
+                        *circuit_breaker_failure_threshold,
+                        circuit_breaker_cooldown.unwrap_or(DEFAULT_CIRCUIT_BREAKER_COOLDOWN),
+                        circuit_breaker_max_cooldown
+                            .unwrap_or(DEFAULT_CIRCUIT_BREAKER_MAX_COOLDOWN),
+                    ),
+                    last_part_id_requested: Default::default(),
+                    requested_target: lru::LruCache::new(
+                        NonZeroUsize::new(MAX_PENDING_PART as usize).unwrap(),
+                    ),
                 }
             }
         };
+        let apply_concurrency_ramp = match sync_config {
+            SyncConfig::Peers => None,
+            SyncConfig::ExternalStorage(config) => config
+                .concurrency_ramp_up_period
+                .map(|period| ConcurrencyRamp::new(clock.clone(), period)),
+        };
         let (tx, rx) = channel::<StateSyncGetFileResult>();
         StateSync {
             clock,
             inner,
             network_adapter,
             timeout,
+            header_timeout: header_timeout.unwrap_or(timeout),
+            part_timeout: part_timeout.unwrap_or(timeout),
+            per_shard_total_timeout,
+            shard_priority,
+            apply_concurrency_ramp,
+            shard_sync_started: HashMap::new(),
+            shard_cancellation: HashMap::new(),
+            paused_shards: HashMap::new(),
+            resumed_shards: HashMap::new(),
             state_parts_apply_results: HashMap::new(),
             load_memtrie_results: HashMap::new(),
             resharding_state_roots: HashMap::new(),
@@ -260,8 +369,31 @@ impl StateSync {
             panic!("cannot sync to the first epoch after sharding upgrade. Please wait for the next epoch or find peers that are more up to date");
         }
         let need_to_reshard = epoch_manager.will_shard_layout_change(&prev_hash)?;
+        let apply_limit = self.apply_concurrency_limit(tracking_shards.len());
+        let mut num_applying = sync_status
+            .values()
+            .filter(|download| {
+                matches!(
+                    download.status,
+                    ShardSyncStatus::StateApplyInProgress | ShardSyncStatus::StateApplyFinalizing
+                )
+            })
+            .count();
 
         for shard_id in tracking_shards {
+            if self.paused_shards.contains_key(&shard_id) {
+                all_done &= sync_status
+                    .get(&shard_id)
+                    .is_some_and(|download| download.status == ShardSyncStatus::StateSyncDone);
+                continue;
+            }
+            if let Some(paused_for) = self.resumed_shards.remove(&shard_id) {
+                if let Some(shard_sync_download) = sync_status.get_mut(&shard_id) {
+                    for download in &mut shard_sync_download.downloads {
+                        download.prev_update_time += paused_for;
+                    }
+                }
+            }
             let version = prev_shard_layout.version();
             let shard_uid = ShardUId { version, shard_id: shard_id as u32 };
             let mut download_timeout = false;
@@ -270,6 +402,9 @@ impl StateSync {
                 run_shard_state_download = true;
                 ShardSyncDownload::new_download_state_header(now)
             });
+            if run_shard_state_download {
+                self.shard_sync_started.insert(shard_id, self.clock.now());
+            }
 
             let mut shard_sync_done = false;
             match &shard_sync_download.status {
@@ -290,14 +425,19 @@ impl StateSync {
                     run_shard_state_download = res.1;
                 }
                 ShardSyncStatus::StateApplyScheduling => {
-                    self.sync_shards_apply_scheduling_status(
-                        shard_id,
-                        shard_sync_download,
-                        sync_hash,
-                        chain,
-                        now,
-                        state_parts_task_scheduler,
-                    )?;
+                    if num_applying < apply_limit {
+                        self.sync_shards_apply_scheduling_status(
+                            shard_id,
+                            shard_sync_download,
+                            sync_hash,
+                            chain,
+                            now,
+                            state_parts_task_scheduler,
+                        )?;
+                        if shard_sync_download.status == ShardSyncStatus::StateApplyInProgress {
+                            num_applying += 1;
+                        }
+                    }
                 }
                 ShardSyncStatus::StateApplyInProgress => {
                     self.sync_shards_apply_status(
@@ -403,6 +543,12 @@ impl StateSync {
                 );
                 continue;
             }
+            if let StateSyncInner::External { circuit_breaker, .. } = &mut self.inner {
+                match &result {
+                    Ok(_) => circuit_breaker.record_success(),
+                    Err(_) => circuit_breaker.record_failure(),
+                }
+            }
             if let Some(shard_sync_download) = shard_sync.get_mut(&shard_id) {
                 let file_type = shard_sync_download.status.to_string();
                 let (download_result, download) = match result {
@@ -455,6 +601,11 @@ impl StateSync {
         shard_id: ShardId,
         apply_result: Result<(), near_chain::Error>,
     ) {
+        if apply_result.is_err() {
+            if let Some(ramp) = &mut self.apply_concurrency_ramp {
+                ramp.restart();
+            }
+        }
         self.state_parts_apply_results.insert(shard_id, apply_result);
     }
 
@@ -506,24 +657,18 @@ impl StateSync {
         shard_id: ShardId,
         sync_hash: CryptoHash,
     ) {
-        match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, requested_target } => {
-                let key = (part_id, sync_hash);
-                // Check that it came from the target that we requested it from.
-                if let Some(target) = requested_target.get(&key) {
-                    if last_part_id_requested.get_mut(&(target.clone(), shard_id)).map_or(
-                        false,
-                        |request| {
-                            request.missing_parts = request.missing_parts.saturating_sub(1);
-                            request.missing_parts == 0
-                        },
-                    ) {
-                        last_part_id_requested.remove(&(target.clone(), shard_id));
-                    }
-                }
-            }
-            StateSyncInner::External { .. } => {
-                // Do nothing.
+        let (last_part_id_requested, requested_target) = self.inner.peer_requests();
+        let key = (part_id, sync_hash);
+        // Check that it came from the target that we requested it from.
+        if let Some(target) = requested_target.get(&key) {
+            if last_part_id_requested.get_mut(&(target.clone(), shard_id)).map_or(
+                false,
+                |request| {
+                    request.missing_parts = request.missing_parts.saturating_sub(1);
+                    request.missing_parts == 0
+                },
+            ) {
+                last_part_id_requested.remove(&(target.clone(), shard_id));
             }
         }
     }
@@ -536,19 +681,15 @@ impl StateSync {
     ) -> Result<Vec<PeerId>, near_chain::Error> {
         let peers: Vec<PeerId> =
             highest_height_peers.iter().map(|peer| peer.peer_info.id.clone()).collect();
-        let res = match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, .. } => {
-                last_part_id_requested.retain(|_, request| !request.expired());
-                peers
-                    .into_iter()
-                    .filter(|peer| {
-                        // If we still have a pending request from this node - don't add another one.
-                        !last_part_id_requested.contains_key(&(peer.clone(), shard_id))
-                    })
-                    .collect::<Vec<_>>()
-            }
-            StateSyncInner::External { .. } => peers,
-        };
+        let (last_part_id_requested, _) = self.inner.peer_requests();
+        last_part_id_requested.retain(|_, request| !request.expired());
+        let res = peers
+            .into_iter()
+            .filter(|peer| {
+                // If we still have a pending request from this node - don't add another one.
+                !last_part_id_requested.contains_key(&(peer.clone(), shard_id))
+            })
+            .collect::<Vec<_>>();
         Ok(res)
     }
 
@@ -564,17 +705,14 @@ impl StateSync {
         state_parts_future_spawner: &dyn FutureSpawner,
     ) -> Result<(), near_chain::Error> {
         let mut possible_targets = vec![];
-        match self.inner {
-            StateSyncInner::Peers { .. } => {
-                possible_targets = self.select_peers(highest_height_peers, shard_id)?;
-                if possible_targets.is_empty() {
-                    tracing::debug!(target: "sync", "Can't request a state header: No possible targets");
-                    // In most cases it means that all the targets are currently busy (that we have a pending request with them).
-                    return Ok(());
-                }
+        // We do not need to select a target for external storage.
+        if self.inner.use_peers() {
+            possible_targets = self.select_peers(highest_height_peers, shard_id)?;
+            if possible_targets.is_empty() {
+                tracing::debug!(target: "sync", "Can't request a state header: No possible targets");
+                // In most cases it means that all the targets are currently busy (that we have a pending request with them).
+                return Ok(());
             }
-            // We do not need to select a target for external storage.
-            StateSyncInner::External { .. } => {}
         }
 
         // Downloading strategy starts here
@@ -617,33 +755,10 @@ impl StateSync {
         state_parts_future_spawner: &dyn FutureSpawner,
     ) {
         let header_download = new_shard_sync_download.get_header_download_mut().unwrap();
-        match &mut self.inner {
-            StateSyncInner::Peers { .. } => {
-                let peer_id = possible_targets.choose(&mut thread_rng()).cloned().unwrap();
-                tracing::debug!(target: "sync", ?peer_id, shard_id, ?sync_hash, ?possible_targets, "request_shard_header");
-                assert!(header_download.run_me.load(Ordering::SeqCst));
-                header_download.run_me.store(false, Ordering::SeqCst);
-                header_download.state_requests_count += 1;
-                header_download.last_target = Some(peer_id.clone());
-                let run_me = header_download.run_me.clone();
-                near_performance_metrics::actix::spawn(
-                    std::any::type_name::<Self>(),
-                    self.network_adapter
-                        .send_async(PeerManagerMessageRequest::NetworkRequests(
-                            NetworkRequests::StateRequestHeader { shard_id, sync_hash, peer_id },
-                        ))
-                        .then(move |result| {
-                            if let Ok(NetworkResponses::RouteNotFound) =
-                                result.map(|f| f.as_network_response())
-                            {
-                                // Send a StateRequestHeader on the next iteration
-                                run_me.store(true, Ordering::SeqCst);
-                            }
-                            future::ready(())
-                        }),
-                );
-            }
-            StateSyncInner::External { chain_id, external, .. } => {
+        if let StateSyncInner::External { chain_id, external, circuit_breaker, .. } =
+            &mut self.inner
+        {
+            if !circuit_breaker.is_open() {
                 let sync_block_header = chain.get_block_header(&sync_hash).unwrap();
                 let epoch_id = sync_block_header.epoch_id();
                 let epoch_info = chain.epoch_manager.get_epoch_info(epoch_id).unwrap();
@@ -659,8 +774,33 @@ impl StateSync {
                     state_parts_future_spawner,
                     self.state_parts_mpsc_tx.clone(),
                 );
+                return;
             }
         }
+
+        let peer_id = possible_targets.choose(&mut thread_rng()).cloned().unwrap();
+        tracing::debug!(target: "sync", ?peer_id, shard_id, ?sync_hash, ?possible_targets, "request_shard_header");
+        assert!(header_download.run_me.load(Ordering::SeqCst));
+        header_download.run_me.store(false, Ordering::SeqCst);
+        header_download.state_requests_count += 1;
+        header_download.last_target = Some(peer_id.clone());
+        let run_me = header_download.run_me.clone();
+        near_performance_metrics::actix::spawn(
+            std::any::type_name::<Self>(),
+            self.network_adapter
+                .send_async(PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::StateRequestHeader { shard_id, sync_hash, peer_id },
+                ))
+                .then(move |result| {
+                    if let Ok(NetworkResponses::RouteNotFound) =
+                        result.map(|f| f.as_network_response())
+                    {
+                        // Send a StateRequestHeader on the next iteration
+                        run_me.store(true, Ordering::SeqCst);
+                    }
+                    future::ready(())
+                }),
+        );
     }
 
     /// Makes requests to download state parts for the given epoch of the given shard.
@@ -676,42 +816,17 @@ impl StateSync {
     ) {
         // Iterate over all parts that needs to be requested (i.e. download.run_me is true).
         // Parts are ordered such that its index match its part_id.
-        match &mut self.inner {
-            StateSyncInner::Peers { last_part_id_requested, requested_target } => {
-                // We'll select all the 'highest' peers + validators as candidates (excluding those that gave us timeout in the past).
-                // And for each one of them, we'll ask for up to 16 (MAX_STATE_PART_REQUEST) parts.
-                let possible_targets_sampler =
-                    SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
-
-                // For every part that needs to be requested it is selected one
-                // peer (target) randomly to request the part from.
-                // IMPORTANT: here we use 'zip' with possible_target_sampler -
-                // which is limited. So at any moment we'll not request more
-                // than possible_targets.len() * MAX_STATE_PART_REQUEST parts.
-                for ((part_id, download), target) in
-                    parts_to_fetch(new_shard_sync_download).zip(possible_targets_sampler)
-                {
-                    sent_request_part(
-                        self.clock.clone(),
-                        target.clone(),
-                        part_id,
-                        shard_id,
-                        sync_hash,
-                        last_part_id_requested,
-                        requested_target,
-                        self.timeout,
-                    );
-                    request_part_from_peers(
-                        part_id,
-                        target,
-                        download,
-                        shard_id,
-                        sync_hash,
-                        &self.network_adapter,
-                    );
-                }
-            }
-            StateSyncInner::External { chain_id, semaphore, external } => {
+        if let StateSyncInner::External {
+            chain_id,
+            semaphore,
+            external,
+            compression,
+            verify_checksums,
+            circuit_breaker,
+            ..
+        } = &mut self.inner
+        {
+            if !circuit_breaker.is_open() {
                 let sync_block_header = chain.get_block_header(&sync_hash).unwrap();
                 let epoch_id = sync_block_header.epoch_id();
                 let epoch_info = chain.epoch_manager.get_epoch_info(epoch_id).unwrap();
@@ -720,6 +835,7 @@ impl StateSync {
                 let shard_state_header = chain.get_state_header(shard_id, sync_hash).unwrap();
                 let state_root = shard_state_header.chunk_prev_state_root();
                 let state_num_parts = shard_state_header.num_state_parts();
+                let cancellation = self.shard_cancellation.entry(shard_id).or_default();
 
                 for (part_id, download) in parts_to_fetch(new_shard_sync_download) {
                     request_part_from_external_storage(
@@ -734,7 +850,10 @@ impl StateSync {
                         state_root,
                         semaphore.clone(),
                         external.clone(),
+                        *compression,
+                        *verify_checksums,
                         runtime_adapter.clone(),
+                        cancellation.clone(),
                         state_parts_future_spawner,
                         self.state_parts_mpsc_tx.clone(),
                     );
@@ -742,8 +861,43 @@ impl StateSync {
                         break;
                     }
                 }
+                return;
             }
         }
+
+        let (last_part_id_requested, requested_target) = self.inner.peer_requests();
+        // We'll select all the 'highest' peers + validators as candidates (excluding those that gave us timeout in the past).
+        // And for each one of them, we'll ask for up to 16 (MAX_STATE_PART_REQUEST) parts.
+        let possible_targets_sampler =
+            SamplerLimited::new(possible_targets, MAX_STATE_PART_REQUEST);
+
+        // For every part that needs to be requested it is selected one
+        // peer (target) randomly to request the part from.
+        // IMPORTANT: here we use 'zip' with possible_target_sampler -
+        // which is limited. So at any moment we'll not request more
+        // than possible_targets.len() * MAX_STATE_PART_REQUEST parts.
+        for ((part_id, download), target) in
+            parts_to_fetch(new_shard_sync_download).zip(possible_targets_sampler)
+        {
+            sent_request_part(
+                self.clock.clone(),
+                target.clone(),
+                part_id,
+                shard_id,
+                sync_hash,
+                last_part_id_requested,
+                requested_target,
+                self.part_timeout,
+            );
+            request_part_from_peers(
+                part_id,
+                target,
+                download,
+                shard_id,
+                sync_hash,
+                &self.network_adapter,
+            );
+        }
     }
 
     /// The main 'step' function that should be called periodically to check and update the sync process.
@@ -778,10 +932,17 @@ impl StateSync {
 
             return Ok(StateSyncResult::Completed);
         }
+        if let Some(shard_id) = self.check_per_shard_total_timeout(chain, sync_hash, sync_status)? {
+            return Ok(StateSyncResult::ShardTimedOut(shard_id));
+        }
+        if let Some(ramp) = &mut self.apply_concurrency_ramp {
+            ramp.start_sync(sync_hash);
+        }
         // The downloaded parts are from all shards. This function takes all downloaded parts and
         // saves them to the DB.
         // TODO: Ideally, we want to process the downloads on a different thread than the one that runs the Client.
         self.process_downloaded_parts(chain, sync_hash, sync_status);
+        let tracking_shards = self.order_by_priority(tracking_shards);
         let all_done = self.sync_shards_status(
             me,
             sync_hash,
@@ -806,6 +967,109 @@ impl StateSync {
         }
     }
 
+    /// Finds a shard whose state sync has been running for longer than
+    /// `per_shard_total_timeout`. The shard's progress is dropped so that the
+    /// next attempt starts from scratch: its part downloads in flight are
+    /// cancelled and the parts stored so far are removed.
+    fn check_per_shard_total_timeout(
+        &mut self,
+        chain: &mut Chain,
+        sync_hash: CryptoHash,
+        sync_status: &mut HashMap<u64, ShardSyncDownload>,
+    ) -> Result<Option<ShardId>, near_chain::Error> {
+        let Some(timeout) = self.per_shard_total_timeout else {
+            return Ok(None);
+        };
+        let now = self.clock.now();
+        let Some((&shard_id, _)) = sync_status.iter().find(|(shard_id, shard_sync_download)| {
+            shard_sync_download.status != ShardSyncStatus::StateSyncDone
+                && !self.paused_shards.contains_key(shard_id)
+                && self
+                    .shard_sync_started
+                    .get(shard_id)
+                    .is_some_and(|started| now.signed_duration_since(*started) > timeout)
+        }) else {
+            return Ok(None);
+        };
+        tracing::warn!(
+            target: "sync",
+            %shard_id,
+            timeout_sec = timeout.whole_seconds(),
+            "State sync of the shard timed out");
+        sync_status.remove(&shard_id);
+        self.shard_sync_started.remove(&shard_id);
+        if let Some(cancellation) = self.shard_cancellation.remove(&shard_id) {
+            cancellation.cancel();
+        }
+        // Without a state header no parts were requested, so there is nothing to clear.
+        if let Ok(shard_state_header) = chain.get_state_header(shard_id, sync_hash) {
+            let state_num_parts = shard_state_header.num_state_parts();
+            chain.clear_downloaded_parts(shard_id, sync_hash, state_num_parts)?;
+        }
+        Ok(Some(shard_id))
+    }
+
+    /// Stops scheduling new download and computation tasks for the shard,
+    /// e.g. to throttle the sync of a heavy shard. The parts downloaded so far
+    /// are kept, and tasks already in flight are allowed to finish. The time
+    /// spent paused doesn't count towards any of the timeouts.
+    pub fn pause_shard(&mut self, shard_id: ShardId) {
+        if !self.paused_shards.contains_key(&shard_id) {
+            tracing::info!(target: "sync", %shard_id, "Pausing state sync of the shard");
+            self.paused_shards.insert(shard_id, self.clock.now());
+        }
+    }
+
+    /// Resumes the state sync of a shard paused with `pause_shard` from where
+    /// it left off.
+    pub fn resume_shard(&mut self, shard_id: ShardId) {
+        let Some(paused_at) = self.paused_shards.remove(&shard_id) else {
+            return;
+        };
+        tracing::info!(target: "sync", %shard_id, "Resuming state sync of the shard");
+        let paused_for = self.clock.now().signed_duration_since(paused_at);
+        if let Some(started) = self.shard_sync_started.get_mut(&shard_id) {
+            *started += paused_for;
+        }
+        *self.resumed_shards.entry(shard_id).or_insert(Duration::ZERO) += paused_for;
+    }
+
+    /// Pauses the given shards and resumes all other paused shards.
+    pub fn set_paused_shards(&mut self, shard_ids: &[ShardId]) {
+        let resumed: Vec<ShardId> = self
+            .paused_shards
+            .keys()
+            .filter(|shard_id| !shard_ids.contains(shard_id))
+            .copied()
+            .collect();
+        for shard_id in resumed {
+            self.resume_shard(shard_id);
+        }
+        for &shard_id in shard_ids {
+            self.pause_shard(shard_id);
+        }
+    }
+
+    pub fn is_shard_paused(&self, shard_id: ShardId) -> bool {
+        self.paused_shards.contains_key(&shard_id)
+    }
+
+    /// Moves the shards listed in `shard_priority` to the front, in the order
+    /// they are listed there. Shards are advanced in this order, so the first
+    /// ones get to request their parts before the concurrency limit is hit.
+    fn order_by_priority(&self, mut shards: Vec<ShardId>) -> Vec<ShardId> {
+        shards.sort_by_key(|shard_id| {
+            self.shard_priority.iter().position(|p| p == shard_id).unwrap_or(usize::MAX)
+        });
+        shards
+    }
+
+    /// Number of shards, out of `num_shards` tracked ones, whose state may be
+    /// applied at the same time according to the ramp-up schedule.
+    fn apply_concurrency_limit(&self, num_shards: usize) -> usize {
+        self.apply_concurrency_ramp.as_ref().map_or(num_shards, |ramp| ramp.limit(num_shards))
+    }
+
     pub fn update_download_on_state_response_message(
         &mut self,
         shard_sync_download: &mut ShardSyncDownload,
@@ -897,7 +1161,7 @@ impl StateSync {
                 ShardSyncDownload::new_download_state_parts(now, state_num_parts);
             Ok((false, true))
         } else {
-            let download_timeout = now - download.prev_update_time > self.timeout;
+            let download_timeout = now - download.prev_update_time > self.header_timeout;
             if download_timeout {
                 tracing::debug!(target: "sync", last_target = ?download.last_target, start_time = ?download.start_time, prev_update_time = ?download.prev_update_time, state_requests_count = download.state_requests_count, "header request timed out");
                 metrics::STATE_SYNC_HEADER_TIMEOUT
@@ -941,7 +1205,7 @@ impl StateSync {
             if !part_download.done {
                 parts_done = false;
                 let prev = part_download.prev_update_time;
-                let part_timeout = now - prev > self.timeout; // Retry parts that failed.
+                let part_timeout = now - prev > self.part_timeout; // Retry parts that failed.
                 if part_timeout || part_download.error {
                     download_timeout |= part_timeout;
                     if part_timeout || part_download.last_target.is_some() {
@@ -1012,6 +1276,9 @@ impl StateSync {
                 // The reasonable behavior here is to start from the very beginning.
                 metrics::STATE_SYNC_DISCARD_PARTS.with_label_values(&[&shard_id.to_string()]).inc();
                 tracing::error!(target: "sync", %shard_id, %sync_hash, ?err, "State sync finalizing error");
+                if let Some(ramp) = &mut self.apply_concurrency_ramp {
+                    ramp.restart();
+                }
                 *shard_sync_download = ShardSyncDownload::new_download_state_header(now);
                 chain.clear_downloaded_parts(shard_id, sync_hash, state_num_parts)?;
             }
@@ -1173,6 +1440,118 @@ impl StateSync {
     }
 }
 
+/// Stops the use of the external storage for a cooldown period after a number
+/// of consecutive failed downloads, so that a persistently failing storage,
+/// e.g. a deleted bucket, isn't retried forever. Once the cooldown elapses the
+/// external storage is tried again, and the first success closes the breaker.
+/// A failure before that trips the breaker again with twice the cooldown, up to
+/// `max_cooldown`.
+struct ExternalCircuitBreaker {
+    clock: Clock,
+    /// Number of consecutive failures after which the breaker trips. The
+    /// breaker never trips if unset.
+    failure_threshold: Option<u32>,
+    base_cooldown: Duration,
+    max_cooldown: Duration,
+    /// Cooldown of the next trip of the breaker.
+    cooldown: Duration,
+    consecutive_failures: u32,
+    /// The external storage is not used until this time.
+    open_until: Option<Utc>,
+}
+
+impl ExternalCircuitBreaker {
+    fn new(
+        clock: Clock,
+        failure_threshold: Option<u32>,
+        cooldown: Duration,
+        max_cooldown: Duration,
+    ) -> Self {
+        Self {
+            clock,
+            failure_threshold,
+            base_cooldown: cooldown,
+            max_cooldown,
+            cooldown: std::cmp::min(cooldown, max_cooldown),
+            consecutive_failures: 0,
+            open_until: None,
+        }
+    }
+
+    fn is_open(&self) -> bool {
+        self.open_until.is_some_and(|open_until| self.clock.now_utc() < open_until)
+    }
+
+    fn record_success(&mut self) {
+        if self.open_until.take().is_some() {
+            tracing::info!(target: "sync", "External storage recovered, using it for state sync again");
+        }
+        self.consecutive_failures = 0;
+        self.cooldown = std::cmp::min(self.base_cooldown, self.max_cooldown);
+    }
+
+    fn record_failure(&mut self) {
+        let Some(failure_threshold) = self.failure_threshold else {
+            return;
+        };
+        self.consecutive_failures += 1;
+        if self.consecutive_failures >= failure_threshold && !self.is_open() {
+            if self.open_until.is_some() {
+                // Still failing after the previous cooldown.
+                self.cooldown = std::cmp::min(self.cooldown * 2, self.max_cooldown);
+            }
+            tracing::error!(
+                target: "sync",
+                consecutive_failures = self.consecutive_failures,
+                cooldown_sec = self.cooldown.whole_seconds(),
+                "External storage keeps failing, requesting the state from peers until the cooldown elapses");
+            self.open_until = Some(self.clock.now_utc() + self.cooldown);
+        }
+    }
+}
+
+/// Linear ramp-up schedule for the number of shards whose state is applied at
+/// the same time. Starts with a single shard and allows all of them after
+/// `period`. The schedule starts over for every new sync and whenever applying
+/// the state fails.
+struct ConcurrencyRamp {
+    clock: Clock,
+    start: Utc,
+    period: Duration,
+    /// The sync the schedule was started for.
+    sync_hash: Option<CryptoHash>,
+}
+
+impl ConcurrencyRamp {
+    fn new(clock: Clock, period: Duration) -> Self {
+        let start = clock.now_utc();
+        Self { clock, start, period, sync_hash: None }
+    }
+
+    /// Starts the schedule over.
+    fn restart(&mut self) {
+        self.start = self.clock.now_utc();
+    }
+
+    /// Starts the schedule over if the sync target changed.
+    fn start_sync(&mut self, sync_hash: CryptoHash) {
+        if self.sync_hash.replace(sync_hash) != Some(sync_hash) {
+            self.restart();
+        }
+    }
+
+    /// Number of shards, out of `max`, the schedule allows at the current time.
+    fn limit(&self, max: usize) -> usize {
+        let elapsed = self.clock.now_utc() - self.start;
+        if elapsed >= self.period {
+            return max;
+        }
+        let fraction = elapsed.as_seconds_f64() / self.period.as_seconds_f64();
+        let limit = (max as f64 * fraction) as usize;
+        limit.clamp(max.min(1), max)
+    }
+}
+
 /// Returns parts that still need to be fetched.
 fn parts_to_fetch(
     new_shard_sync_download: &mut ShardSyncDownload,
@@ -1185,11 +1564,55 @@ fn parts_to_fetch(
         .map(|(part_id, download)| (part_id as u64, download))
 }
 
+/// Connects to the given external storage for downloading the state.
+fn create_external_connection(
+    location: &ExternalStorageLocation,
+    timeout: Duration,
+) -> ExternalConnection {
+    match location {
+        ExternalStorageLocation::S3 { bucket, region, .. } => {
+            let bucket = create_bucket_readonly(
+                &bucket,
+                &region,
+                timeout.max(Duration::ZERO).unsigned_abs(),
+            );
+            if let Err(err) = bucket {
+                panic!("Failed to create an S3 bucket: {}", err);
+            }
+            ExternalConnection::S3 { bucket: Arc::new(bucket.unwrap()) }
+        }
+        ExternalStorageLocation::Filesystem { root_dir } => {
+            ExternalConnection::Filesystem { root_dir: root_dir.clone() }
+        }
+        ExternalStorageLocation::GCS { bucket, .. } => ExternalConnection::GCS {
+            gcs_client: Arc::new(cloud_storage::Client::default()),
+            reqwest_client: Arc::new(reqwest::Client::default()),
+            bucket: bucket.clone(),
+        },
+        ExternalStorageLocation::Azure { account, container, endpoint } => {
+            // Like with GCS, the parts are downloaded anonymously.
+            create_azure_connection(account, container, endpoint.as_deref(), None)
+        }
+    }
+}
+
+/// Name of the external storage in the metrics.
+fn external_location_name(location: &ExternalStorageLocation) -> String {
+    match location {
+        ExternalStorageLocation::S3 { bucket, region } => format!("s3://{}/{}", region, bucket),
+        ExternalStorageLocation::Filesystem { root_dir } => root_dir.display().to_string(),
+        ExternalStorageLocation::GCS { bucket } => format!("gs://{}", bucket),
+        ExternalStorageLocation::Azure { account, container, .. } => {
+            format!("azure://{}/{}", account, container)
+        }
+    }
+}
+
 async fn download_header_from_external_storage(
     shard_id: ShardId,
     sync_hash: CryptoHash,
     location: String,
-    external: ExternalConnection,
+    external: FailoverExternalConnection,
 ) -> Result<StateSyncFileDownloadResult, std::string::String> {
     external
     .get_file(shard_id, &location, &StateFileType::StateHeader)
@@ -1215,7 +1638,7 @@ fn request_header_from_external_storage(
     epoch_id: &EpochId,
     epoch_height: EpochHeight,
     chain_id: &str,
-    external: ExternalConnection,
+    external: FailoverExternalConnection,
     state_parts_future_spawner: &dyn FutureSpawner,
     state_parts_mpsc_tx: Sender<StateSyncGetFileResult>,
 ) {
@@ -1259,11 +1682,13 @@ async fn download_and_store_part_from_external_storage(
     shard_id: ShardId,
     sync_hash: CryptoHash,
     state_root: StateRoot,
-    external: ExternalConnection,
+    external: FailoverExternalConnection,
+    compression: Compression,
+    verify_checksums: bool,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
 ) -> Result<StateSyncFileDownloadResult, String> {
     external
-    .get_file(shard_id, &location, file_type)
+    .get_state_part(shard_id, &location, file_type, compression, verify_checksums)
     .await
     .map_err(|err| err.to_string())
     .and_then(|data|  {
@@ -1295,8 +1720,11 @@ fn request_part_from_external_storage(
     chain_id: &str,
     state_root: StateRoot,
     semaphore: Arc<Semaphore>,
-    external: ExternalConnection,
+    external: FailoverExternalConnection,
+    compression: Compression,
+    verify_checksums: bool,
     runtime_adapter: Arc<dyn RuntimeAdapter>,
+    cancellation: CancellationToken,
     state_parts_future_spawner: &dyn FutureSpawner,
     state_parts_mpsc_tx: Sender<StateSyncGetFileResult>,
 ) {
@@ -1322,16 +1750,26 @@ fn request_part_from_external_storage(
                 async move {
                     let file_type = StateFileType::StatePart { part_id, num_parts };
                     let part_id = PartId{ idx: part_id, total: num_parts };
-                    let result = download_and_store_part_from_external_storage(
-                        part_id,
-                        &file_type,
-                        location,
-                        shard_id,
-                        sync_hash,
-                        state_root,
-                        external,
-                        runtime_adapter)
-                        .await;
+                    // Checked first, so that a download finishing after the
+                    // cancellation doesn't store the part.
+                    let result = tokio::select! {
+                        biased;
+                        _ = cancellation.cancelled() => {
+                            tracing::debug!(target: "sync", %shard_id, ?part_id, "Part download cancelled.");
+                            return;
+                        }
+                        result = download_and_store_part_from_external_storage(
+                            part_id,
+                            &file_type,
+                            location,
+                            shard_id,
+                            sync_hash,
+                            state_root,
+                            external,
+                            compression,
+                            verify_checksums,
+                            runtime_adapter) => result,
+                    };
 
                     match state_parts_mpsc_tx.send(StateSyncGetFileResult {
                         sync_hash,
@@ -1511,37 +1949,33 @@ mod test {
     use super::*;
     use actix::System;
     use actix_rt::Arbiter;
+    use futures::future::BoxFuture;
     use near_actix_test_utils::run_actix;
     use near_async::futures::ActixArbiterHandleFutureSpawner;
     use near_async::messaging::{noop, IntoMultiSender, IntoSender};
-    use near_async::time::Clock;
+    use near_async::time::{Clock, FakeClock};
     use near_chain::test_utils;
     use near_chain::{test_utils::process_block_sync, BlockProcessingArtifact, Provenance};
+    use near_client_primitives::types::StateSyncStatus;
     use near_crypto::SecretKey;
     use near_epoch_manager::EpochManagerAdapter;
     use near_network::test_utils::MockPeerManagerAdapter;
     use near_network::types::PeerInfo;
+    use near_primitives::challenge::PartialState;
     use near_primitives::state_sync::{
         CachedParts, ShardStateSyncResponseHeader, ShardStateSyncResponseV3,
     };
+    use near_primitives::validator_signer::ValidatorSigner;
+    use near_primitives::views::{ShardSyncDownloadView, ShardSyncProgressView};
     use near_primitives::{test_utils::TestBlockBuilder, types::EpochId};
+    use near_store::Trie;
 
-    #[test]
-    // Start a new state sync - and check that it asks for a header.
-    fn test_ask_for_header() {
-        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
-        let mut state_sync = StateSync::new(
-            Clock::real(),
-            mock_peer_manager.as_multi_sender(),
-            Duration::seconds(1),
-            "chain_id",
-            &SyncConfig::Peers,
-            false,
-        );
-        let mut new_shard_sync = HashMap::new();
-
-        let (mut chain, kv, runtime, signer) = test_utils::setup(Clock::real());
-
+    /// Produces blocks until the chain enters the next epoch.
+    fn produce_epoch(
+        chain: &mut Chain,
+        kv: &dyn EpochManagerAdapter,
+        signer: &Arc<ValidatorSigner>,
+    ) {
         // TODO: lower the epoch length
         for _ in 0..(chain.epoch_length + 1) {
             let prev = chain.get_block(&chain.head().unwrap().last_block_hash).unwrap();
@@ -1556,7 +1990,7 @@ mod test {
             };
 
             process_block_sync(
-                &mut chain,
+                chain,
                 &None,
                 block.into(),
                 Provenance::PRODUCED,
@@ -1564,6 +1998,28 @@ mod test {
             )
             .unwrap();
         }
+    }
+
+    #[test]
+    // Start a new state sync - and check that it asks for a header.
+    fn test_ask_for_header() {
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let mut state_sync = StateSync::new(
+            Clock::real(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            None,
+            None,
+            None,
+            vec![],
+            false,
+        );
+        let mut new_shard_sync = HashMap::new();
+
+        let (mut chain, kv, runtime, signer) = test_utils::setup(Clock::real());
+        produce_epoch(&mut chain, kv.as_ref(), &signer);
 
         let request_hash = &chain.head().unwrap().last_block_hash;
         let state_sync_header = chain.get_state_response_header(0, *request_hash).unwrap();
@@ -1656,4 +2112,572 @@ mod test {
             System::current().stop()
         });
     }
+
+    #[test]
+    // A paused shard makes no download attempts and picks up from where it was once resumed.
+    fn test_pause_and_resume_shard() {
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let mut state_sync = StateSync::new(
+            Clock::real(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::hours(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            None,
+            None,
+            None,
+            vec![],
+            false,
+        );
+        let mut new_shard_sync = HashMap::new();
+
+        let (mut chain, kv, runtime, signer) = test_utils::setup(Clock::real());
+        produce_epoch(&mut chain, kv.as_ref(), &signer);
+
+        let request_hash = chain.head().unwrap().last_block_hash;
+        let peer_id =
+            PeerId::new(SecretKey::from_random(near_crypto::KeyType::ED25519).public_key());
+        let highest_height_peer_info = HighestHeightPeerInfo {
+            peer_info: PeerInfo { id: peer_id.clone(), addr: None, account_id: None },
+            genesis_id: Default::default(),
+            highest_block_height: chain.epoch_length + 10,
+            highest_block_hash: Default::default(),
+            tracked_shards: vec![0],
+            archival: false,
+        };
+
+        run_actix(async {
+            let mut run = |state_sync: &mut StateSync, new_shard_sync: &mut HashMap<_, _>| {
+                state_sync
+                    .run(
+                        &None,
+                        request_hash,
+                        new_shard_sync,
+                        &mut chain,
+                        kv.as_ref(),
+                        &[highest_height_peer_info.clone()],
+                        vec![0],
+                        &noop().into_sender(),
+                        &noop().into_sender(),
+                        &noop().into_sender(),
+                        &ActixArbiterHandleFutureSpawner(Arbiter::new().handle()),
+                        false,
+                        runtime.clone(),
+                    )
+                    .unwrap()
+            };
+
+            state_sync.pause_shard(0);
+            assert!(state_sync.is_shard_paused(0));
+            assert!(matches!(
+                run(&mut state_sync, &mut new_shard_sync),
+                StateSyncResult::InProgress
+            ));
+            assert!(new_shard_sync.is_empty());
+            assert!(mock_peer_manager.pop().is_none());
+
+            state_sync.resume_shard(0);
+            run(&mut state_sync, &mut new_shard_sync);
+            mock_peer_manager.notify.notified().await;
+            assert_eq!(
+                NetworkRequests::StateRequestHeader {
+                    shard_id: 0,
+                    sync_hash: request_hash,
+                    peer_id: peer_id.clone(),
+                },
+                mock_peer_manager.pop().unwrap().as_network_requests()
+            );
+
+            // Mark the header request as failed, so that an unpaused shard would retry it.
+            state_sync.pause_shard(0);
+            new_shard_sync.get_mut(&0).unwrap().downloads[0].error = true;
+            run(&mut state_sync, &mut new_shard_sync);
+            let download = new_shard_sync.get(&0).unwrap();
+            assert_eq!(download.status, ShardSyncStatus::StateDownloadHeader);
+            assert_eq!(download.downloads[0].state_requests_count, 1);
+            assert!(mock_peer_manager.pop().is_none());
+
+            state_sync.resume_shard(0);
+            run(&mut state_sync, &mut new_shard_sync);
+            mock_peer_manager.notify.notified().await;
+            assert!(mock_peer_manager.pop().is_some());
+            assert_eq!(new_shard_sync.get(&0).unwrap().downloads[0].state_requests_count, 2);
+
+            System::current().stop()
+        });
+    }
+
+    #[test]
+    fn test_concurrency_ramp_up() {
+        let clock = FakeClock::default();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::ExternalStorage(ExternalStorageConfig {
+                location: ExternalStorageLocation::Filesystem {
+                    root_dir: root_dir.path().to_path_buf(),
+                },
+                additional_locations: vec![],
+                num_concurrent_requests: 10,
+                num_concurrent_requests_during_catchup: 10,
+                concurrency_ramp_up_period: Some(Duration::seconds(10)),
+                compression: Compression::None,
+                circuit_breaker_failure_threshold: None,
+                circuit_breaker_cooldown: None,
+                circuit_breaker_max_cooldown: None,
+                verify_checksums: false,
+                max_bytes_per_sec: None,
+            }),
+            None,
+            None,
+            None,
+            vec![],
+            false,
+        );
+        // The downloads are not limited by the ramp.
+        match &state_sync.inner {
+            StateSyncInner::External { semaphore, .. } => {
+                assert_eq!(semaphore.available_permits(), 10)
+            }
+            StateSyncInner::Peers { .. } => unreachable!(),
+        }
+        let ramp = |state_sync: &mut StateSync| state_sync.apply_concurrency_ramp.as_mut().unwrap();
+
+        let sync_hash = CryptoHash::hash_bytes(b"first");
+        ramp(&mut state_sync).start_sync(sync_hash);
+        assert_eq!(state_sync.apply_concurrency_limit(10), 1);
+        clock.advance(Duration::seconds(5));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 5);
+        clock.advance(Duration::seconds(3));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 8);
+        // Running the same sync again doesn't restart the schedule.
+        ramp(&mut state_sync).start_sync(sync_hash);
+        assert_eq!(state_sync.apply_concurrency_limit(10), 8);
+        clock.advance(Duration::seconds(10));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 10);
+
+        // A failure to apply the state starts the schedule over.
+        state_sync.set_apply_result(0, Err(near_chain::Error::Other("failed".to_string())));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 1);
+        clock.advance(Duration::seconds(20));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 10);
+
+        // So does a new sync.
+        ramp(&mut state_sync).start_sync(CryptoHash::hash_bytes(b"second"));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 1);
+        clock.advance(Duration::seconds(5));
+        assert_eq!(state_sync.apply_concurrency_limit(10), 5);
+    }
+
+    #[test]
+    // After enough consecutive failures the external storage is skipped until the cooldown elapses.
+    fn test_external_storage_circuit_breaker() {
+        let clock = FakeClock::default();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::ExternalStorage(ExternalStorageConfig {
+                location: ExternalStorageLocation::Filesystem {
+                    root_dir: root_dir.path().to_path_buf(),
+                },
+                additional_locations: vec![],
+                num_concurrent_requests: 10,
+                num_concurrent_requests_during_catchup: 10,
+                concurrency_ramp_up_period: None,
+                compression: Compression::None,
+                circuit_breaker_failure_threshold: Some(3),
+                circuit_breaker_cooldown: Some(Duration::seconds(10)),
+                circuit_breaker_max_cooldown: Some(Duration::seconds(25)),
+                verify_checksums: false,
+                max_bytes_per_sec: None,
+            }),
+            None,
+            None,
+            None,
+            vec![],
+            false,
+        );
+        let (mut chain, _, _, _) = test_utils::setup(clock.clock());
+        let sync_hash = CryptoHash::default();
+        let mut receive = |state_sync: &mut StateSync,
+                           msg_sync_hash: CryptoHash,
+                           result: Result<u64, String>| {
+            state_sync
+                .state_parts_mpsc_tx
+                .send(StateSyncGetFileResult {
+                    sync_hash: msg_sync_hash,
+                    shard_id: 0,
+                    part_id: None,
+                    result: result
+                        .map(|part_length| StateSyncFileDownloadResult::StatePart { part_length }),
+                })
+                .unwrap();
+            state_sync.process_downloaded_parts(&mut chain, sync_hash, &mut HashMap::new());
+        };
+
+        // Results of another sync don't count.
+        let other_sync_hash = CryptoHash::hash_bytes(b"other");
+        for _ in 0..3 {
+            receive(&mut state_sync, other_sync_hash, Err("failed".to_string()));
+        }
+        assert!(!state_sync.inner.use_peers());
+
+        for _ in 0..2 {
+            receive(&mut state_sync, sync_hash, Err("failed".to_string()));
+        }
+        assert!(!state_sync.inner.use_peers());
+        receive(&mut state_sync, sync_hash, Err("failed".to_string()));
+        assert!(state_sync.inner.use_peers());
+
+        clock.advance(Duration::seconds(5));
+        assert!(state_sync.inner.use_peers());
+        clock.advance(Duration::seconds(6));
+        assert!(!state_sync.inner.use_peers());
+
+        // A failure right after the cooldown trips the breaker again, for twice as long.
+        receive(&mut state_sync, sync_hash, Err("failed".to_string()));
+        assert!(state_sync.inner.use_peers());
+        clock.advance(Duration::seconds(11));
+        assert!(state_sync.inner.use_peers());
+        clock.advance(Duration::seconds(10));
+        assert!(!state_sync.inner.use_peers());
+
+        // The cooldown doesn't grow beyond the configured maximum.
+        receive(&mut state_sync, sync_hash, Err("failed".to_string()));
+        assert!(state_sync.inner.use_peers());
+        clock.advance(Duration::seconds(24));
+        assert!(state_sync.inner.use_peers());
+        clock.advance(Duration::seconds(2));
+        assert!(!state_sync.inner.use_peers());
+
+        // A success resets both the failure count and the cooldown.
+        receive(&mut state_sync, sync_hash, Ok(1));
+        receive(&mut state_sync, sync_hash, Err("failed".to_string()));
+        assert!(!state_sync.inner.use_peers());
+        for _ in 0..2 {
+            receive(&mut state_sync, sync_hash, Err("failed".to_string()));
+        }
+        assert!(state_sync.inner.use_peers());
+        clock.advance(Duration::seconds(11));
+        assert!(!state_sync.inner.use_peers());
+    }
+
+    #[test]
+    // A shard that never makes progress is abandoned once the timeout expires.
+    fn test_per_shard_total_timeout() {
+        let clock = FakeClock::default();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            Some(Duration::seconds(10)),
+            None,
+            None,
+            vec![],
+            false,
+        );
+        let mut sync_status = HashMap::new();
+        sync_status.insert(0, ShardSyncDownload::new_download_state_parts(clock.now_utc(), 3));
+        state_sync.shard_sync_started.insert(0, clock.now());
+        let (mut chain, _, _, _) = test_utils::setup(clock.clock());
+        let sync_hash = CryptoHash::default();
+
+        clock.advance(Duration::seconds(5));
+        assert_eq!(
+            state_sync
+                .check_per_shard_total_timeout(&mut chain, sync_hash, &mut sync_status)
+                .unwrap(),
+            None
+        );
+        assert!(sync_status.contains_key(&0));
+
+        clock.advance(Duration::seconds(6));
+        assert_eq!(
+            state_sync
+                .check_per_shard_total_timeout(&mut chain, sync_hash, &mut sync_status)
+                .unwrap(),
+            Some(0)
+        );
+        assert!(sync_status.is_empty());
+        assert!(state_sync.shard_sync_started.is_empty());
+    }
+
+    /// Keeps the spawned futures, so that a test decides when they run.
+    #[derive(Default)]
+    struct DeferredFutureSpawner(std::sync::Mutex<Vec<BoxFuture<'static, ()>>>);
+
+    impl FutureSpawner for DeferredFutureSpawner {
+        fn spawn_boxed(&self, _description: &'static str, f: BoxFuture<'static, ()>) {
+            self.0.lock().unwrap().push(f);
+        }
+    }
+
+    impl DeferredFutureSpawner {
+        fn run_all(&self, rt: &tokio::runtime::Runtime) {
+            let futures = std::mem::take(&mut *self.0.lock().unwrap());
+            for f in futures {
+                rt.block_on(f);
+            }
+        }
+    }
+
+    #[test]
+    // A part download that finishes after the shard timed out doesn't store the part.
+    fn test_per_shard_total_timeout_cancels_downloads() {
+        let clock = FakeClock::default();
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let root_dir = tempfile::tempdir().unwrap();
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            Some(Duration::seconds(10)),
+            None,
+            None,
+            vec![],
+            false,
+        );
+        let (mut chain, _, runtime, _) = test_utils::setup(clock.clock());
+        let sync_hash = CryptoHash::default();
+        let epoch_id = EpochId::default();
+
+        // A valid part of the empty state.
+        let file_type = StateFileType::StatePart { part_id: 0, num_parts: 1 };
+        let location = external_storage_location("chain_id", &epoch_id, 1, 0, &file_type);
+        let storage = ExternalConnection::Filesystem { root_dir: root_dir.path().to_path_buf() };
+        let part = borsh::to_vec(&PartialState::TrieValues(vec![])).unwrap();
+        rt.block_on(storage.put_file(file_type, &part, 0, &location)).unwrap();
+        let external = FailoverExternalConnection::new(vec![("test".to_string(), storage)]);
+
+        let part_key = borsh::to_vec(&StatePartKey(sync_hash, 0, 0)).unwrap();
+        let part_stored = || runtime.store().get(DBCol::StateParts, &part_key).unwrap().is_some();
+        let spawner = DeferredFutureSpawner::default();
+        let request_part =
+            |state_sync: &mut StateSync, sync_status: &mut HashMap<u64, ShardSyncDownload>| {
+                let shard_sync_download = sync_status.entry(0).or_insert_with(|| {
+                    ShardSyncDownload::new_download_state_parts(clock.now_utc(), 1)
+                });
+                state_sync.shard_sync_started.insert(0, clock.now());
+                request_part_from_external_storage(
+                    0,
+                    &mut shard_sync_download.downloads[0],
+                    0,
+                    sync_hash,
+                    &epoch_id,
+                    1,
+                    1,
+                    "chain_id",
+                    Trie::EMPTY_ROOT,
+                    Arc::new(Semaphore::new(1)),
+                    external.clone(),
+                    Compression::None,
+                    false,
+                    runtime.clone(),
+                    state_sync.shard_cancellation.entry(0).or_default().clone(),
+                    &spawner,
+                    state_sync.state_parts_mpsc_tx.clone(),
+                );
+            };
+
+        let mut sync_status = HashMap::new();
+        request_part(&mut state_sync, &mut sync_status);
+        clock.advance(Duration::seconds(11));
+        assert_eq!(
+            state_sync
+                .check_per_shard_total_timeout(&mut chain, sync_hash, &mut sync_status)
+                .unwrap(),
+            Some(0)
+        );
+        spawner.run_all(&rt);
+        assert!(!part_stored());
+        assert!(state_sync.state_parts_mpsc_rx.try_recv().is_err());
+
+        // The next attempt gets a fresh token, so its download stores the part.
+        request_part(&mut state_sync, &mut sync_status);
+        spawner.run_all(&rt);
+        assert!(part_stored());
+        assert!(state_sync.state_parts_mpsc_rx.try_recv().unwrap().result.is_ok());
+    }
+
+    #[test]
+    // The time a shard spends paused doesn't count towards the timeouts.
+    fn test_paused_shard_timers() {
+        let clock = FakeClock::default();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            Some(Duration::seconds(10)),
+            None,
+            None,
+            vec![],
+            false,
+        );
+        let mut sync_status = HashMap::new();
+        sync_status.insert(0, ShardSyncDownload::new_download_state_parts(clock.now_utc(), 3));
+        state_sync.shard_sync_started.insert(0, clock.now());
+        let (mut chain, _, _, _) = test_utils::setup(clock.clock());
+        let sync_hash = CryptoHash::default();
+
+        clock.advance(Duration::seconds(5));
+        state_sync.set_paused_shards(&[0]);
+        assert!(state_sync.is_shard_paused(0));
+        clock.advance(Duration::seconds(30));
+        assert_eq!(
+            state_sync
+                .check_per_shard_total_timeout(&mut chain, sync_hash, &mut sync_status)
+                .unwrap(),
+            None
+        );
+
+        state_sync.set_paused_shards(&[]);
+        assert!(!state_sync.is_shard_paused(0));
+        assert_eq!(state_sync.resumed_shards.get(&0), Some(&Duration::seconds(30)));
+        clock.advance(Duration::seconds(4));
+        assert_eq!(
+            state_sync
+                .check_per_shard_total_timeout(&mut chain, sync_hash, &mut sync_status)
+                .unwrap(),
+            None
+        );
+        clock.advance(Duration::seconds(2));
+        assert_eq!(
+            state_sync
+                .check_per_shard_total_timeout(&mut chain, sync_hash, &mut sync_status)
+                .unwrap(),
+            Some(0)
+        );
+    }
+
+    #[test]
+    // A hung header request is retried sooner than a hung part request.
+    fn test_header_and_part_timeouts() {
+        let clock = FakeClock::default();
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let mut state_sync = StateSync::new(
+            clock.clock(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(60),
+            "chain_id",
+            &SyncConfig::Peers,
+            None,
+            Some(Duration::seconds(2)),
+            Some(Duration::seconds(30)),
+            vec![],
+            false,
+        );
+        let (chain, _, _, _) = test_utils::setup(clock.clock());
+        let sync_hash = CryptoHash::default();
+        let mut header_download = ShardSyncDownload::new_download_state_header(clock.now_utc());
+        let mut parts_download = ShardSyncDownload::new_download_state_parts(clock.now_utc(), 3);
+
+        clock.advance(Duration::seconds(5));
+        let (header_timeout, _) = state_sync
+            .sync_shards_download_header_status(
+                0,
+                &mut header_download,
+                sync_hash,
+                &chain,
+                clock.now_utc(),
+            )
+            .unwrap();
+        assert!(header_timeout);
+        let (part_timeout, _) =
+            state_sync.sync_shards_download_parts_status(0, &mut parts_download, clock.now_utc());
+        assert!(!part_timeout);
+
+        clock.advance(Duration::seconds(30));
+        let (part_timeout, _) =
+            state_sync.sync_shards_download_parts_status(0, &mut parts_download, clock.now_utc());
+        assert!(part_timeout);
+    }
+
+    #[test]
+    // Prioritized shards are advanced first, the rest keep their natural order.
+    fn test_shard_priority_order() {
+        let mock_peer_manager = Arc::new(MockPeerManagerAdapter::default());
+        let state_sync = StateSync::new(
+            Clock::real(),
+            mock_peer_manager.as_multi_sender(),
+            Duration::seconds(1),
+            "chain_id",
+            &SyncConfig::Peers,
+            None,
+            None,
+            None,
+            vec![3, 1],
+            false,
+        );
+        assert_eq!(state_sync.order_by_priority(vec![0, 1, 2, 3]), vec![3, 1, 0, 2]);
+        assert_eq!(state_sync.order_by_priority(vec![2, 0]), vec![2, 0]);
+        assert_eq!(state_sync.order_by_priority(vec![1, 2, 0]), vec![1, 2, 0]);
+    }
+
+    #[test]
+    // The structured task snapshot reports each unfinished part download.
+    fn test_state_sync_task_statuses() {
+        let clock = FakeClock::default();
+        let mut download = ShardSyncDownload::new_download_state_parts(clock.now_utc(), 3);
+        download.downloads[0].done = true;
+        download.downloads[1].run_me.store(false, Ordering::SeqCst);
+        download.downloads[2].error = true;
+        let status = StateSyncStatus {
+            sync_hash: CryptoHash::default(),
+            sync_status: HashMap::from([(0, download)]),
+        };
+
+        clock.advance(Duration::milliseconds(1500));
+        let tasks = status.task_statuses(clock.now_utc());
+        let summary: Vec<_> = tasks
+            .iter()
+            .map(|t| (t.task_id.as_str(), t.shard_id, t.phase.as_str(), t.state.as_str()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![("part 1", 0, "parts", "requested"), ("part 2", 0, "parts", "failed")]
+        );
+        assert!(tasks.iter().all(|t| t.elapsed_ms == 1500));
+    }
+
+    #[test]
+    fn test_shard_sync_parts_progress() {
+        let clock = FakeClock::default();
+        // The number of parts is unknown until the header is downloaded.
+        let download = ShardSyncDownload::new_download_state_header(clock.now_utc());
+        assert_eq!(download.parts_progress(), None);
+        assert_eq!(ShardSyncDownloadView::from(download).progress, None);
+
+        let mut download = ShardSyncDownload::new_download_state_parts(clock.now_utc(), 4);
+        assert_eq!(download.parts_progress(), Some((0, 4)));
+        download.downloads[2].done = true;
+        assert_eq!(download.parts_progress(), Some((1, 4)));
+        download.downloads[0].done = true;
+        download.downloads[1].error = true;
+        assert_eq!(download.parts_progress(), Some((2, 4)));
+        for part in download.downloads.iter_mut() {
+            part.done = true;
+        }
+        download.status = ShardSyncStatus::StateApplyScheduling;
+        assert_eq!(
+            ShardSyncDownloadView::from(download).progress,
+            Some(ShardSyncProgressView { num_parts_done: 4, num_parts: 4 })
+        );
+    }
 }
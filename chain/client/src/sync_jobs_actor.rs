@@ -1,6 +1,9 @@
 use actix::Actor;
 use near_async::actix_wrapper::ActixWrapper;
-use near_async::futures::{DelayedActionRunner, DelayedActionRunnerExt};
+use near_async::futures::{
+    AsyncComputationSpawner, AsyncComputationSpawnerExt, DelayedActionRunner,
+    DelayedActionRunnerExt,
+};
 use near_async::messaging::{self, CanSend, Handler, HandlerWithContext, Sender};
 use near_async::time::Duration;
 use near_async::{MultiSend, MultiSenderFrom};
@@ -15,6 +18,7 @@ use near_primitives::state_part::PartId;
 use near_primitives::state_sync::StatePartKey;
 use near_primitives::types::ShardId;
 use near_store::DBCol;
+use std::sync::Arc;
 
 // Set the mailbox capacity for the SyncJobsActor from default 16 to 100.
 const MAILBOX_CAPACITY: usize = 100;
@@ -29,6 +33,9 @@ pub struct ClientSenderForSyncJobs {
 
 pub struct SyncJobsActor {
     client_sender: ClientSenderForSyncJobs,
+    /// If set, state parts are applied on this spawner rather than on the
+    /// arbiter of the actor, which is shared with the state sync downloads.
+    computation_spawner: Option<Arc<dyn AsyncComputationSpawner>>,
 }
 
 impl messaging::Actor for SyncJobsActor {}
@@ -63,7 +70,16 @@ impl HandlerWithContext<ReshardingRequest> for SyncJobsActor {
 
 impl SyncJobsActor {
     pub fn new(client_sender: ClientSenderForSyncJobs) -> Self {
-        Self { client_sender }
+        Self { client_sender, computation_spawner: None }
+    }
+
+    /// Applies the state parts on the given spawner instead of the arbiter of the actor.
+    pub fn with_computation_spawner(
+        mut self,
+        computation_spawner: Arc<dyn AsyncComputationSpawner>,
+    ) -> Self {
+        self.computation_spawner = Some(computation_spawner);
+        self
     }
 
     pub fn spawn_actix_actor(self) -> (actix::Addr<ActixWrapper<Self>>, actix::ArbiterHandle) {
@@ -77,7 +93,6 @@ impl SyncJobsActor {
     }
 
     fn apply_parts(
-        &mut self,
         msg: &ApplyStatePartsRequest,
     ) -> Result<(), near_chain_primitives::error::Error> {
         let _span: tracing::span::EnteredSpan =
@@ -104,7 +119,6 @@ impl SyncJobsActor {
     /// Clears flat storage before applying state parts.
     /// Returns whether the flat storage state was cleared.
     fn clear_flat_state(
-        &mut self,
         msg: &ApplyStatePartsRequest,
     ) -> Result<bool, near_chain_primitives::error::Error> {
         let _span = tracing::debug_span!(target: "sync", "clear_flat_state").entered();
@@ -132,13 +146,25 @@ impl SyncJobsActor {
     }
 
     pub fn handle_apply_state_parts_request(&mut self, msg: ApplyStatePartsRequest) {
+        match &self.computation_spawner {
+            Some(computation_spawner) => {
+                let client_sender = self.client_sender.clone();
+                computation_spawner.spawn("apply_state_parts", move || {
+                    Self::apply_state_parts(msg, &client_sender)
+                });
+            }
+            None => Self::apply_state_parts(msg, &self.client_sender),
+        }
+    }
+
+    fn apply_state_parts(msg: ApplyStatePartsRequest, client_sender: &ClientSenderForSyncJobs) {
         // Unload mem-trie (in case it is still loaded) before we apply state parts.
         msg.runtime_adapter.get_tries().unload_mem_trie(&msg.shard_uid);
 
         let shard_id = msg.shard_uid.shard_id as ShardId;
-        match self.clear_flat_state(&msg) {
+        match Self::clear_flat_state(&msg) {
             Err(err) => {
-                self.client_sender.send(ApplyStatePartsResponse {
+                client_sender.send(ApplyStatePartsResponse {
                     apply_result: Err(err),
                     shard_id,
                     sync_hash: msg.sync_hash,
@@ -154,8 +180,8 @@ impl SyncJobsActor {
             }
         }
 
-        let result = self.apply_parts(&msg);
-        self.client_sender.send(ApplyStatePartsResponse {
+        let result = Self::apply_parts(&msg);
+        client_sender.send(ApplyStatePartsResponse {
             apply_result: result,
             shard_id,
             sync_hash: msg.sync_hash,
@@ -208,3 +234,69 @@ impl SyncJobsActor {
         self.client_sender.send(response);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_async::messaging::{noop, IntoSender};
+    use near_chain::test_utils::{KeyValueRuntime, MockEpochManager};
+    use near_primitives::hash::CryptoHash;
+    use near_primitives::shard_layout::ShardUId;
+    use near_primitives::types::EpochId;
+    use near_store::test_utils::create_test_store;
+    use std::sync::Mutex;
+
+    /// Keeps the spawned computations, so that the test decides when they run.
+    #[derive(Default)]
+    struct DeferredComputationSpawner(Mutex<Vec<Box<dyn FnOnce() + Send>>>);
+
+    impl AsyncComputationSpawner for DeferredComputationSpawner {
+        fn spawn_boxed(&self, _name: &str, f: Box<dyn FnOnce() + Send>) {
+            self.0.lock().unwrap().push(f);
+        }
+    }
+
+    #[test]
+    fn test_apply_state_parts_on_computation_spawner() {
+        let store = create_test_store();
+        let epoch_manager = MockEpochManager::new(store.clone(), 5);
+        let runtime_adapter = KeyValueRuntime::new(store, &epoch_manager);
+        let make_request = || ApplyStatePartsRequest {
+            runtime_adapter: runtime_adapter.clone(),
+            shard_uid: ShardUId::single_shard(),
+            state_root: CryptoHash::default(),
+            num_parts: 0,
+            epoch_id: EpochId::default(),
+            sync_hash: CryptoHash::default(),
+        };
+        let responses = Arc::new(Mutex::new(vec![]));
+        let client_sender = ClientSenderForSyncJobs {
+            apply_state_parts_response: Sender::from_fn({
+                let responses = responses.clone();
+                move |response: ApplyStatePartsResponse| {
+                    responses.lock().unwrap().push(response.shard_id)
+                }
+            }),
+            block_catch_up_response: noop().into_sender(),
+            resharding_response: noop().into_sender(),
+            load_memtrie_response: noop().into_sender(),
+        };
+
+        // By default the state parts are applied by the actor itself.
+        let mut actor = SyncJobsActor::new(client_sender.clone());
+        actor.handle_apply_state_parts_request(make_request());
+        assert_eq!(*responses.lock().unwrap(), vec![0]);
+
+        // With a computation spawner, the actor only hands the work over to it.
+        let spawner = Arc::new(DeferredComputationSpawner::default());
+        let mut actor = SyncJobsActor::new(client_sender).with_computation_spawner(spawner.clone());
+        actor.handle_apply_state_parts_request(make_request());
+        assert_eq!(*responses.lock().unwrap(), vec![0]);
+        let computations = std::mem::take(&mut *spawner.0.lock().unwrap());
+        assert_eq!(computations.len(), 1);
+        for computation in computations {
+            computation();
+        }
+        assert_eq!(*responses.lock().unwrap(), vec![0, 0]);
+    }
+}
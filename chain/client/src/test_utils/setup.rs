@@ -196,7 +196,7 @@ pub fn setup(
         client_actor.clone().with_auto_span_context().into_sender(),
         MutableConfigValue::new(validator_signer, "validator_signer"),
         store,
-        config.chunk_request_retry_period,
+        &config,
     );
     let shards_manager_adapter = shards_manager_addr.with_auto_span_context();
     shards_manager_adapter_for_client.bind(shards_manager_adapter.clone());
@@ -263,6 +263,7 @@ pub fn setup_only_view(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            verify_memtrie_after_state_sync: false,
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
@@ -1059,6 +1060,7 @@ pub fn setup_synchronous_shards_manager(
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            verify_memtrie_after_state_sync: false,
         }, // irrelevant
         None,
         Arc::new(RayonAsyncComputationSpawner),
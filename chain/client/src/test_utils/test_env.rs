@@ -1,13 +1,14 @@
 use crate::{Client, DistributeStateWitnessRequest};
-use near_async::messaging::{CanSend, IntoMultiSender};
+use near_async::messaging::{CanSend, IntoMultiSender, Sender};
 use near_async::time::Clock;
 use near_async::time::{Duration, Instant};
+use near_chain::chain::ApplyStatePartsRequest;
 use near_chain::stateless_validation::processing_tracker::{
     ProcessingDoneTracker, ProcessingDoneWaiter,
 };
 use near_chain::test_utils::ValidatorSchedule;
 use near_chain::types::Tip;
-use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
+use near_chain::{ChainGenesis, ChainStoreAccess, Error, Provenance};
 use near_chain_configs::GenesisConfig;
 use near_chain_primitives::error::QueryError;
 use near_chunks::client::ShardsManagerResponse;
@@ -27,17 +28,21 @@ use near_primitives::epoch_manager::RngSeed;
 use near_primitives::errors::InvalidTxError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::sharding::{ChunkHash, PartialEncodedChunk};
+use near_primitives::state_part::PartId;
+use near_primitives::state_sync::StatePartKey;
 use near_primitives::stateless_validation::{ChunkEndorsement, ChunkStateWitness};
 use near_primitives::test_utils::create_test_signer;
-use near_primitives::transaction::{Action, FunctionCallAction, SignedTransaction};
-use near_primitives::types::{AccountId, Balance, BlockHeight, EpochId, NumSeats, ShardId};
+use near_primitives::transaction::{
+    Action, DeployContractAction, FunctionCallAction, SignedTransaction,
+};
+use near_primitives::types::{AccountId, Balance, BlockHeight, EpochId, Gas, NumSeats, ShardId};
 use near_primitives::utils::MaybeValidated;
 use near_primitives::views::{
-    AccountView, FinalExecutionOutcomeView, QueryRequest, QueryResponse, QueryResponseKind,
-    StateItem,
+    AccountView, FinalExecutionOutcomeView, FinalExecutionStatus, QueryRequest, QueryResponse,
+    QueryResponseKind, StateItem,
 };
 use near_store::metadata::DbKind;
-use near_store::ShardUId;
+use near_store::{DBCol, ShardUId};
 use near_vm_runner::logic::ProtocolVersion;
 use once_cell::sync::OnceCell;
 use std::collections::{HashMap, HashSet};
@@ -52,6 +57,40 @@ use super::TEST_SEED;
 /// Timeout used in tests that wait for a specific chunk endorsement to appear
 const CHUNK_ENDORSEMENTS_TIMEOUT: Duration = Duration::seconds(10);
 
+/// A source of state parts for `TestEnv::state_sync_shard_from_sources`.
+#[derive(Clone, Copy, Debug)]
+pub enum StateSyncTestSource {
+    /// Serves the state parts of the given client as they are.
+    Honest(usize),
+    /// Serves the state parts of the given client with their last byte
+    /// flipped, like a faulty or malicious peer would.
+    Corrupt(usize),
+}
+
+impl StateSyncTestSource {
+    fn client_idx(&self) -> usize {
+        match self {
+            Self::Honest(idx) | Self::Corrupt(idx) => *idx,
+        }
+    }
+
+    fn get_state_part(
+        &self,
+        env: &mut TestEnv,
+        shard_id: ShardId,
+        part_id: u64,
+        sync_hash: CryptoHash,
+    ) -> Result<Vec<u8>, Error> {
+        let mut part = env.clients[self.client_idx()]
+            .chain
+            .get_state_response_part(shard_id, part_id, sync_hash)?;
+        if let (Self::Corrupt(_), Some(byte)) = (self, part.last_mut()) {
+            *byte ^= 0xff;
+        }
+        Ok(part)
+    }
+}
+
 /// An environment for writing integration tests with multiple clients.
 /// This environment can simulate near nodes without network and it can be configured to use different runtimes.
 pub struct TestEnv {
@@ -292,6 +331,30 @@ impl TestEnv {
         );
     }
 
+    /// Asserts that client `id` has not emitted any network requests.
+    /// On failure, lists all pending requests in the panic message.
+    pub fn assert_no_network_requests(&self, id: usize) {
+        self.assert_only_requests_matching(id, |_| false);
+    }
+
+    /// Asserts that every network request emitted by client `id` satisfies `matcher`.
+    /// On failure, lists the requests that did not match in the panic message.
+    pub fn assert_only_requests_matching(
+        &self,
+        id: usize,
+        matcher: impl Fn(&PeerManagerMessageRequest) -> bool,
+    ) {
+        let requests = self.network_adapters[id].requests.read().unwrap();
+        let unexpected: Vec<_> = requests.iter().filter(|request| !matcher(request)).collect();
+        assert!(
+            unexpected.is_empty(),
+            "Client {} emitted {} unexpected network request(s):\n{:#?}",
+            id,
+            unexpected.len(),
+            unexpected
+        );
+    }
+
     pub fn process_shards_manager_responses(&mut self, id: usize) -> bool {
         let mut any_processed = false;
         while let Some(msg) = self.client_adapters[id].pop() {
@@ -310,6 +373,8 @@ impl TestEnv {
                     self.clients[id]
                         .mark_chunk_header_ready_for_inclusion(chunk_header, chunk_producer);
                 }
+                ShardsManagerResponse::ChunkCompleteStatus { .. } => {}
+                ShardsManagerResponse::PartRequestStats(_) => {}
             }
             any_processed = true;
         }
@@ -604,6 +669,88 @@ impl TestEnv {
         self.query_account(account_id).amount
     }
 
+    /// Syncs the state of `shard_id` as of `sync_hash` from client `from` to
+    /// client `to`: fetches the state header and all state parts from the
+    /// former, stores them on the latter, applies the parts and finalizes.
+    /// The flat storage of the shard on `to`, if any, is dropped before the
+    /// parts are applied.
+    pub fn state_sync_shard(
+        &mut self,
+        from: usize,
+        to: usize,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+    ) -> Result<(), Error> {
+        self.state_sync_shard_from_sources(
+            &[StateSyncTestSource::Honest(from)],
+            to,
+            shard_id,
+            sync_hash,
+        )
+    }
+
+    /// Like `state_sync_shard`, but every state part is taken from the first
+    /// of `sources` whose part is accepted by client `to`, the same way a node
+    /// falls back to another peer when a part fails validation. Fails with the
+    /// last validation error if no source serves a valid part.
+    pub fn state_sync_shard_from_sources(
+        &mut self,
+        sources: &[StateSyncTestSource],
+        to: usize,
+        shard_id: ShardId,
+        sync_hash: CryptoHash,
+    ) -> Result<(), Error> {
+        assert!(!sources.is_empty(), "no state sync sources");
+        let from = sources[0].client_idx();
+        let header = self.clients[from].chain.get_state_response_header(shard_id, sync_hash)?;
+        let num_parts = header.num_state_parts();
+        self.clients[to].chain.set_state_header(shard_id, sync_hash, header)?;
+        for part_id in 0..num_parts {
+            let mut result = Ok(());
+            for source in sources {
+                let part = source.get_state_part(self, shard_id, part_id, sync_hash)?;
+                result = self.clients[to].chain.set_state_part(
+                    shard_id,
+                    sync_hash,
+                    PartId::new(part_id, num_parts),
+                    &part,
+                );
+                if result.is_ok() {
+                    break;
+                }
+            }
+            result?;
+        }
+
+        let runtime = self.clients[to].runtime_adapter.clone();
+        let apply_parts = Sender::from_fn(move |msg: ApplyStatePartsRequest| {
+            let store = runtime.store();
+            let shard_id = msg.shard_uid.shard_id as ShardId;
+            let mut store_update = store.store_update();
+            runtime
+                .get_flat_storage_manager()
+                .remove_flat_storage_for_shard(msg.shard_uid, &mut store_update)
+                .expect("failed to remove flat storage");
+            store_update.commit().unwrap();
+            for part_id in 0..msg.num_parts {
+                let key = borsh::to_vec(&StatePartKey(msg.sync_hash, shard_id, part_id)).unwrap();
+                let part = store.get(DBCol::StateParts, &key).unwrap().expect("missing state part");
+                runtime
+                    .apply_state_part(
+                        shard_id,
+                        &msg.state_root,
+                        PartId::new(part_id, msg.num_parts),
+                        &part,
+                        &msg.epoch_id,
+                    )
+                    .expect("failed to apply state part");
+            }
+        });
+        let chain = &mut self.clients[to].chain;
+        chain.schedule_apply_state_parts(shard_id, sync_hash, num_parts, &apply_parts)?;
+        chain.set_state_finalize(shard_id, sync_hash)
+    }
+
     /// Restarts client at given index. Note that the new client reuses runtime
     /// adapter of old client.
     /// TODO (#8269): create new `KeyValueRuntime` for new client. Currently it
@@ -786,6 +933,39 @@ impl TestEnv {
         self.execute_tx(tx).unwrap()
     }
 
+    /// Deploy the contract to the account and execute a function call of the
+    /// given method on it, returning the outcome of the call.
+    ///
+    /// Like `call_main`, this function assumes that `InMemorySigner::from_seed`
+    /// produces a valid signer for the account. Panics if the deployment fails.
+    pub fn deploy_and_call(
+        &mut self,
+        account: &AccountId,
+        code: &[u8],
+        method: &str,
+        args: Vec<u8>,
+        gas: Gas,
+    ) -> FinalExecutionOutcomeView {
+        let signer = InMemorySigner::from_seed(account.clone(), KeyType::ED25519, account.as_str());
+        let actions = vec![Action::DeployContract(DeployContractAction { code: code.to_vec() })];
+        let tx = self.tx_from_actions(actions, &signer, account.clone());
+        let deploy_outcome = self.execute_tx(tx).unwrap();
+        assert!(
+            matches!(deploy_outcome.status, FinalExecutionStatus::SuccessValue(_)),
+            "failed to deploy the contract to {account}: {:?}",
+            deploy_outcome.status
+        );
+
+        let actions = vec![Action::FunctionCall(Box::new(FunctionCallAction {
+            method_name: method.to_string(),
+            args,
+            gas,
+            deposit: 0,
+        }))];
+        let tx = self.tx_from_actions(actions, &signer, account.clone());
+        self.execute_tx(tx).unwrap()
+    }
+
     /// Print a short summary of all the blocks from genesis to head.
     pub fn print_summary(&self) {
         let client = &self.clients[0];
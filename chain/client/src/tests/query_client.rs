@@ -1,6 +1,8 @@
 use crate::test_utils::{setup_no_network, setup_only_view};
 use crate::{
-    GetBlock, GetBlockWithMerkleTree, GetExecutionOutcomesForBlock, Query, Status, TxStatus,
+    GetBlock, GetBlockWithMerkleTree, GetChunkGasSummariesForBlock, GetExecutionOutcomesForBlock,
+    GetExecutionOutcomesWithProofsForBlock, GetStateChangesWithCauseInBlockForTrackedShards,
+    GetTrackedShardsForBlock, Query, Status, TxStatus,
 };
 use actix::System;
 use futures::{future, FutureExt};
@@ -17,12 +19,13 @@ use near_network::types::PeerInfo;
 use near_o11y::testonly::init_test_logger;
 use near_o11y::WithSpanContextExt;
 use near_primitives::block::{Block, BlockHeader};
-use near_primitives::merkle::PartialMerkleTree;
+use near_primitives::hash::CryptoHash;
+use near_primitives::merkle::{compute_root_from_path, PartialMerkleTree};
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::transaction::SignedTransaction;
-use near_primitives::types::{BlockId, BlockReference, EpochId};
+use near_primitives::types::{BlockId, BlockReference, EpochId, StateChangeValueKind};
 use near_primitives::version::PROTOCOL_VERSION;
-use near_primitives::views::{QueryRequest, QueryResponseKind};
+use near_primitives::views::{QueryRequest, QueryResponseKind, StateChangeValueView};
 use num_rational::Ratio;
 
 /// Query account from view client
@@ -217,6 +220,350 @@ fn test_execution_outcome_for_chunk() {
     });
 }
 
+/// Checks that the outcome root proofs returned together with the execution
+/// outcomes of a block verify against the outcome root of the block header.
+#[test]
+fn query_execution_outcomes_with_proofs() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles = setup_no_network(
+            Clock::real(),
+            vec!["test".parse().unwrap()],
+            "other".parse().unwrap(),
+            true,
+            true,
+        );
+        let signer = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+
+        actix::spawn(async move {
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(GetBlock::latest().with_span_context())
+                .await
+                .unwrap()
+                .unwrap()
+                .header
+                .hash;
+
+            let transaction = SignedTransaction::send_money(
+                1,
+                "test".parse().unwrap(),
+                "near".parse().unwrap(),
+                &signer.into(),
+                10,
+                block_hash,
+            );
+            let tx_hash = transaction.get_hash();
+            let res = actor_handles
+                .client_actor
+                .send(
+                    ProcessTxRequest { transaction, is_forwarded: false, check_only: false }
+                        .with_span_context(),
+                )
+                .await
+                .unwrap();
+            assert!(matches!(res, ProcessTxResponse::ValidTx));
+
+            actix::clock::sleep(std::time::Duration::from_millis(500)).await;
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(
+                    TxStatus {
+                        tx_hash,
+                        signer_account_id: "test".parse().unwrap(),
+                        fetch_receipt: false,
+                    }
+                    .with_span_context(),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+                .into_outcome()
+                .unwrap()
+                .transaction_outcome
+                .block_hash;
+
+            // The proof becomes available once the next block with a chunk is produced.
+            let shard_outcomes = loop {
+                let mut outcomes_in_block = actor_handles
+                    .view_client_actor
+                    .send(GetExecutionOutcomesWithProofsForBlock { block_hash }.with_span_context())
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let shard_outcomes = outcomes_in_block.remove(&0).unwrap();
+                if shard_outcomes.outcome_root_proof.is_some() {
+                    break shard_outcomes;
+                }
+                actix::clock::sleep(std::time::Duration::from_millis(100)).await;
+            };
+            assert_eq!(shard_outcomes.outcomes[0].id, tx_hash);
+
+            let outcome_root_proof = shard_outcomes.outcome_root_proof.unwrap();
+            let outcome_root = actor_handles
+                .view_client_actor
+                .send(
+                    GetBlock(BlockId::Hash(outcome_root_proof.block_hash).into())
+                        .with_span_context(),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+                .header
+                .outcome_root;
+            for outcome in &shard_outcomes.outcomes {
+                let outcome_hash = CryptoHash::hash_borsh(&outcome.to_hashes());
+                let shard_outcome_root = compute_root_from_path(&outcome.proof, outcome_hash);
+                assert_eq!(
+                    compute_root_from_path(
+                        &outcome_root_proof.proof,
+                        CryptoHash::hash_borsh(shard_outcome_root)
+                    ),
+                    outcome_root
+                );
+            }
+            System::current().stop();
+        });
+        near_network::test_utils::wait_or_panic(5000);
+    });
+}
+
+/// Checks that only the state changes of the requested kinds are returned.
+#[test]
+fn query_state_changes_filtered_by_kind() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles = setup_no_network(
+            Clock::real(),
+            vec!["test".parse().unwrap()],
+            "other".parse().unwrap(),
+            true,
+            true,
+        );
+        let signer = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+
+        actix::spawn(async move {
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(GetBlock::latest().with_span_context())
+                .await
+                .unwrap()
+                .unwrap()
+                .header
+                .hash;
+
+            let transaction = SignedTransaction::send_money(
+                1,
+                "test".parse().unwrap(),
+                "near".parse().unwrap(),
+                &signer.into(),
+                10,
+                block_hash,
+            );
+            let tx_hash = transaction.get_hash();
+            let res = actor_handles
+                .client_actor
+                .send(
+                    ProcessTxRequest { transaction, is_forwarded: false, check_only: false }
+                        .with_span_context(),
+                )
+                .await
+                .unwrap();
+            assert!(matches!(res, ProcessTxResponse::ValidTx));
+
+            actix::clock::sleep(std::time::Duration::from_millis(500)).await;
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(
+                    TxStatus {
+                        tx_hash,
+                        signer_account_id: "test".parse().unwrap(),
+                        fetch_receipt: false,
+                    }
+                    .with_span_context(),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+                .into_outcome()
+                .unwrap()
+                .transaction_outcome
+                .block_hash;
+            let epoch_id = EpochId(
+                actor_handles
+                    .view_client_actor
+                    .send(GetBlock(BlockId::Hash(block_hash).into()).with_span_context())
+                    .await
+                    .unwrap()
+                    .unwrap()
+                    .header
+                    .epoch_id,
+            );
+
+            // Converting the transaction into a receipt touches both the
+            // account and the access key of the signer.
+            for kind in
+                [StateChangeValueKind::AccountTouched, StateChangeValueKind::AccessKeyTouched]
+            {
+                let state_changes = actor_handles
+                    .view_client_actor
+                    .send(
+                        GetStateChangesWithCauseInBlockForTrackedShards {
+                            block_hash,
+                            epoch_id,
+                            kinds: Some([kind].into_iter().collect()),
+                        }
+                        .with_span_context(),
+                    )
+                    .await
+                    .unwrap()
+                    .unwrap();
+                let state_changes = state_changes.into_values().flatten().collect::<Vec<_>>();
+                assert!(!state_changes.is_empty());
+                for state_change in state_changes {
+                    let is_expected_kind = match state_change.value {
+                        StateChangeValueView::AccountUpdate { .. }
+                        | StateChangeValueView::AccountDeletion { .. } => {
+                            kind == StateChangeValueKind::AccountTouched
+                        }
+                        StateChangeValueView::AccessKeyUpdate { .. }
+                        | StateChangeValueView::AccessKeyDeletion { .. } => {
+                            kind == StateChangeValueKind::AccessKeyTouched
+                        }
+                        _ => false,
+                    };
+                    assert!(is_expected_kind, "{:?}", state_change.value);
+                }
+            }
+            System::current().stop();
+        });
+        near_network::test_utils::wait_or_panic(5000);
+    });
+}
+
+/// Checks that the gas used by applying a chunk matches the gas burnt by the
+/// execution outcomes of the shard in the same block.
+#[test]
+fn query_chunk_gas_summaries() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles = setup_no_network(
+            Clock::real(),
+            vec!["test".parse().unwrap()],
+            "other".parse().unwrap(),
+            true,
+            true,
+        );
+        let signer = InMemorySigner::from_seed("test".parse().unwrap(), KeyType::ED25519, "test");
+
+        actix::spawn(async move {
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(GetBlock::latest().with_span_context())
+                .await
+                .unwrap()
+                .unwrap()
+                .header
+                .hash;
+
+            let transaction = SignedTransaction::send_money(
+                1,
+                "test".parse().unwrap(),
+                "near".parse().unwrap(),
+                &signer.into(),
+                10,
+                block_hash,
+            );
+            let tx_hash = transaction.get_hash();
+            let res = actor_handles
+                .client_actor
+                .send(
+                    ProcessTxRequest { transaction, is_forwarded: false, check_only: false }
+                        .with_span_context(),
+                )
+                .await
+                .unwrap();
+            assert!(matches!(res, ProcessTxResponse::ValidTx));
+
+            actix::clock::sleep(std::time::Duration::from_millis(500)).await;
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(
+                    TxStatus {
+                        tx_hash,
+                        signer_account_id: "test".parse().unwrap(),
+                        fetch_receipt: false,
+                    }
+                    .with_span_context(),
+                )
+                .await
+                .unwrap()
+                .unwrap()
+                .into_outcome()
+                .unwrap()
+                .transaction_outcome
+                .block_hash;
+
+            let mut outcomes = actor_handles
+                .view_client_actor
+                .send(GetExecutionOutcomesForBlock { block_hash }.with_span_context())
+                .await
+                .unwrap()
+                .unwrap();
+            let gas_burnt: u64 =
+                outcomes.remove(&0).unwrap().iter().map(|outcome| outcome.outcome.gas_burnt).sum();
+            assert!(gas_burnt > 0);
+
+            let mut summaries = actor_handles
+                .view_client_actor
+                .send(GetChunkGasSummariesForBlock { block_hash }.with_span_context())
+                .await
+                .unwrap()
+                .unwrap();
+            let summary = summaries.remove(&0).unwrap();
+            assert_eq!(summary.gas_used, gas_burnt);
+            assert!(summary.gas_used <= summary.gas_limit);
+            assert!(summary.balance_burnt > 0);
+            System::current().stop();
+        });
+        near_network::test_utils::wait_or_panic(5000);
+    });
+}
+
+/// The node tracking all shards cares about the only shard.
+#[test]
+fn query_tracked_shards() {
+    init_test_logger();
+    run_actix(async {
+        let actor_handles = setup_no_network(
+            Clock::real(),
+            vec!["test".parse().unwrap()],
+            "other".parse().unwrap(),
+            true,
+            true,
+        );
+        actix::spawn(async move {
+            let block_hash = actor_handles
+                .view_client_actor
+                .send(GetBlock::latest().with_span_context())
+                .await
+                .unwrap()
+                .unwrap()
+                .header
+                .hash;
+            let tracked_shards = actor_handles
+                .view_client_actor
+                .send(GetTrackedShardsForBlock { block_hash }.with_span_context())
+                .await
+                .unwrap()
+                .unwrap();
+            assert_eq!(tracked_shards, vec![0]);
+            System::current().stop();
+        });
+        near_network::test_utils::wait_or_panic(5000);
+    });
+}
+
 #[test]
 fn test_state_request() {
     run_actix(async {
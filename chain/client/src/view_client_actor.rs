@@ -16,13 +16,15 @@ use near_chain::{
 use near_chain_configs::{ClientConfig, MutableValidatorSigner, ProtocolConfigView};
 use near_chain_primitives::error::EpochErrorResultToChainError;
 use near_client_primitives::types::{
-    Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError, GetBlockProofResponse,
-    GetBlockWithMerkleTree, GetChunkError, GetExecutionOutcome, GetExecutionOutcomeError,
-    GetExecutionOutcomesForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
+    ChunkGasSummary, Error, GetBlock, GetBlockError, GetBlockProof, GetBlockProofError,
+    GetBlockProofResponse, GetBlockWithMerkleTree, GetChunkError, GetChunkGasSummariesForBlock,
+    GetExecutionOutcome, GetExecutionOutcomeError, GetExecutionOutcomesForBlock,
+    GetExecutionOutcomesWithProofsForBlock, GetGasPrice, GetGasPriceError, GetMaintenanceWindows,
     GetMaintenanceWindowsError, GetNextLightClientBlockError, GetProtocolConfig,
     GetProtocolConfigError, GetReceipt, GetReceiptError, GetSplitStorageInfo,
     GetSplitStorageInfoError, GetStateChangesError, GetStateChangesWithCauseInBlock,
-    GetStateChangesWithCauseInBlockForTrackedShards, GetValidatorInfoError, Query, QueryError,
+    GetStateChangesWithCauseInBlockForTrackedShards, GetTrackedShardsForBlock,
+    GetValidatorInfoError, OutcomeRootProof, Query, QueryError, ShardExecutionOutcomesWithProof,
     TxStatus, TxStatusError,
 };
 use near_epoch_manager::shard_tracker::ShardTracker;
@@ -48,8 +50,8 @@ use near_primitives::state_sync::{
 };
 use near_primitives::transaction::SignedTransaction;
 use near_primitives::types::{
-    AccountId, BlockHeight, BlockId, BlockReference, EpochReference, Finality, MaybeBlockId,
-    ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
+    AccountId, BlockHeight, BlockId, BlockReference, EpochHeight, EpochReference, Finality,
+    MaybeBlockId, ShardId, SyncCheckpoint, TransactionOrReceiptId, ValidatorInfoIdentifier,
 };
 use near_primitives::validator_signer::ValidatorSigner;
 use near_primitives::views::validator_stake_view::ValidatorStakeView;
@@ -73,6 +75,9 @@ use tracing::{error, info, warn};
 const QUERY_REQUEST_LIMIT: usize = 500;
 /// Waiting time between requests, in ms
 const REQUEST_WAIT_TIME: i64 = 1000;
+/// Announcements of accounts for epochs older than this many epochs before the
+/// epoch of the head are dropped, so that old announcements can't be replayed.
+const ANNOUNCE_ACCOUNT_MAX_EPOCH_AGE: EpochHeight = 1;
 
 const POISONED_LOCK_ERR: &str = "The lock was poisoned.";
 
@@ -655,6 +660,21 @@ impl ViewClientActorInner {
         false
     }
 
+    /// Checks whether a state snapshot for the epoch of `sync_hash` was recorded as
+    /// available and hasn't been deleted since. Returns None if nothing is
+    /// recorded, e.g. for a snapshot made before the availability was recorded.
+    fn is_state_snapshot_available(
+        &self,
+        sync_hash: &CryptoHash,
+        shard_id: ShardId,
+    ) -> Result<Option<bool>, Error> {
+        let header = self.chain.get_block_header(sync_hash)?;
+        self.runtime
+            .get_tries()
+            .is_state_snapshot_available(header.epoch_id(), shard_id)
+            .map_err(|err| Error::Other(err.to_string()))
+    }
+
     fn has_state_snapshot(&self, sync_hash: &CryptoHash, shard_id: ShardId) -> Result<bool, Error> {
         let header = self.chain.get_block_header(sync_hash)?;
         let prev_header = self.chain.get_block_header(header.prev_hash())?;
@@ -930,6 +950,11 @@ impl Handler<GetStateChangesWithCauseInBlockForTrackedShards> for ViewClientActo
         let mut state_changes_with_cause_split_by_shard_id: HashMap<ShardId, StateChangesView> =
             HashMap::new();
         for state_change_with_cause in state_changes_with_cause_in_block {
+            if let Some(kinds) = &msg.kinds {
+                if !kinds.contains(&state_change_with_cause.value.kind()) {
+                    continue;
+                }
+            }
             let account_id = state_change_with_cause.value.affected_account_id();
             let shard_id = match self
                 .epoch_manager
@@ -1109,6 +1134,121 @@ impl Handler<GetExecutionOutcomesForBlock> for ViewClientActorInner {
     }
 }
 
+/// Extract the list of execution outcomes that were produced in a given block,
+/// together with the proofs of the shard outcome roots.
+/// Outcomes of a shard are committed to by the next block which contains a new
+/// chunk for that shard, so the proof is only available once such block exists.
+impl Handler<GetExecutionOutcomesWithProofsForBlock> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetExecutionOutcomesWithProofsForBlock,
+    ) -> Result<HashMap<ShardId, ShardExecutionOutcomesWithProof>, String> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetExecutionOutcomesWithProofsForBlock"])
+            .start_timer();
+        let outcomes = self
+            .chain
+            .chain_store()
+            .get_block_execution_outcomes(&msg.block_hash)
+            .map_err(|e| e.to_string())?;
+        let mut res = HashMap::new();
+        for (shard_id, outcomes) in outcomes {
+            let outcome_root_proof = match self
+                .chain
+                .get_next_block_hash_with_new_chunk(&msg.block_hash, shard_id)
+                .map_err(|e| e.to_string())?
+            {
+                Some((block_hash, target_shard_id)) => {
+                    let outcome_roots = self
+                        .chain
+                        .get_block(&block_hash)
+                        .map_err(|e| e.to_string())?
+                        .chunks()
+                        .iter()
+                        .map(|header| header.prev_outcome_root())
+                        .collect::<Vec<_>>();
+                    merklize(&outcome_roots)
+                        .1
+                        .get(target_shard_id as usize)
+                        .cloned()
+                        .map(|proof| OutcomeRootProof { block_hash, proof })
+                }
+                None => None,
+            };
+            res.insert(
+                shard_id,
+                ShardExecutionOutcomesWithProof {
+                    outcomes: outcomes.into_iter().map(Into::into).collect(),
+                    outcome_root_proof,
+                },
+            );
+        }
+        Ok(res)
+    }
+}
+
+/// Returns the gas usage of applying the chunks of the block. Shards which are
+/// not tracked by the node have no chunk extra and are left out.
+impl Handler<GetChunkGasSummariesForBlock> for ViewClientActorInner {
+    #[perf]
+    fn handle(
+        &mut self,
+        msg: GetChunkGasSummariesForBlock,
+    ) -> Result<HashMap<ShardId, ChunkGasSummary>, String> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetChunkGasSummariesForBlock"])
+            .start_timer();
+        let block = self.chain.get_block(&msg.block_hash).map_err(|e| e.to_string())?;
+        let epoch_id = block.header().epoch_id();
+        let mut res = HashMap::new();
+        for chunk_header in block.chunks().iter() {
+            let shard_id = chunk_header.shard_id();
+            let shard_uid = self
+                .epoch_manager
+                .shard_id_to_uid(shard_id, epoch_id)
+                .map_err(|e| e.to_string())?;
+            let chunk_extra = match self.chain.get_chunk_extra(&msg.block_hash, &shard_uid) {
+                Ok(chunk_extra) => chunk_extra,
+                Err(near_chain::Error::DBNotFoundErr(_)) => continue,
+                Err(e) => return Err(e.to_string()),
+            };
+            res.insert(
+                shard_id,
+                ChunkGasSummary {
+                    gas_used: chunk_extra.gas_used(),
+                    gas_limit: chunk_extra.gas_limit(),
+                    balance_burnt: chunk_extra.balance_burnt(),
+                },
+            );
+        }
+        Ok(res)
+    }
+}
+
+impl Handler<GetTrackedShardsForBlock> for ViewClientActorInner {
+    #[perf]
+    fn handle(&mut self, msg: GetTrackedShardsForBlock) -> Result<Vec<ShardId>, String> {
+        tracing::debug!(target: "client", ?msg);
+        let _timer = metrics::VIEW_CLIENT_MESSAGE_TIME
+            .with_label_values(&["GetTrackedShardsForBlock"])
+            .start_timer();
+        let header = self.chain.get_block_header(&msg.block_hash).map_err(|e| e.to_string())?;
+        let validator_signer = self.validator.get();
+        let me = validator_signer.as_ref().map(|validator| validator.validator_id());
+        let shard_ids =
+            self.epoch_manager.shard_ids(header.epoch_id()).map_err(|e| e.to_string())?;
+        Ok(shard_ids
+            .into_iter()
+            .filter(|shard_id| {
+                self.shard_tracker.care_about_shard(me, header.prev_hash(), *shard_id, true)
+            })
+            .collect())
+    }
+}
+
 impl Handler<GetReceipt> for ViewClientActorInner {
     #[perf]
     fn handle(&mut self, msg: GetReceipt) -> Result<Option<ReceiptView>, GetReceiptError> {
@@ -1353,6 +1493,18 @@ impl Handler<StateRequestPart> for ViewClientActorInner {
             tracing::debug!(target: "sync", ?sync_hash, "Throttle state sync requests");
             return None;
         }
+        // If the availability is unknown, rely on the check of the snapshot itself below.
+        match self.is_state_snapshot_available(&sync_hash, shard_id) {
+            Ok(Some(true) | None) => {}
+            Ok(Some(false)) => {
+                tracing::debug!(target: "sync", ?sync_hash, shard_id, "State snapshot for the requested epoch and shard is not available");
+                return None;
+            }
+            Err(err) => {
+                tracing::debug!(target: "sync", ?err, ?sync_hash, shard_id, "Failed to check state snapshot availability");
+                return None;
+            }
+        }
         if let Err(err) = self.has_state_snapshot(&sync_hash, shard_id) {
             tracing::debug!(target: "sync", ?err, ?sync_hash, "Node doesn't have a matching state snapshot");
             return None;
@@ -1439,6 +1591,22 @@ impl Handler<AnnounceAccountRequest> for ViewClientActorInner {
                 }
             }
 
+            // Drop the replays of announcements for epochs which are long gone.
+            match self
+                .chain
+                .is_recent_epoch(&announce_account.epoch_id, ANNOUNCE_ACCOUNT_MAX_EPOCH_AGE)
+            {
+                Ok(true) => {}
+                Ok(false) => {
+                    tracing::debug!(target: "view_client", account_id = ?announce_account.account_id, epoch_id = ?announce_account.epoch_id, "Dropping account announce for an outdated epoch");
+                    continue;
+                }
+                Err(err) => {
+                    tracing::debug!(target: "view_client", ?err, "Failed to check the epoch of account announce");
+                    continue;
+                }
+            }
+
             match self.check_signature_account_announce(&announce_account) {
                 Ok(true) => {
                     filtered_announce_accounts.push(announce_account);
@@ -62,7 +62,9 @@ impl ShardTracker {
         Self::new(TrackedConfig::new_empty(), epoch_manager)
     }
 
-    fn tracks_shard_at_epoch(
+    /// Whether the tracked-shards config of this node selects `shard_id` in the
+    /// given epoch. Doesn't account for shards tracked due to validator duties.
+    pub fn tracks_shard_at_epoch(
         &self,
         shard_id: ShardId,
         epoch_id: &EpochId,
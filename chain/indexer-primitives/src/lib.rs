@@ -8,12 +8,43 @@ pub struct StreamerMessage {
     pub shards: Vec<IndexerShard>,
 }
 
+impl StreamerMessage {
+    /// Serializes the message into a compact binary form, for passing it to
+    /// another process. It's zstd-compressed JSON, so that the message
+    /// round-trips exactly as it does through JSON.
+    pub fn to_compact_bytes(&self) -> std::io::Result<Vec<u8>> {
+        let mut encoder = zstd::stream::write::Encoder::new(Vec::new(), 0)?;
+        serde_json::to_writer(&mut encoder, self)?;
+        encoder.finish()
+    }
+
+    /// Deserializes a message serialized by [`Self::to_compact_bytes`].
+    pub fn from_compact_bytes(bytes: &[u8]) -> std::io::Result<Self> {
+        Ok(serde_json::from_reader(zstd::stream::read::Decoder::new(bytes)?)?)
+    }
+}
+
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
 pub struct IndexerChunkView {
     pub author: types::AccountId,
     pub header: views::ChunkHeaderView,
     pub transactions: Vec<IndexerTransactionWithOutcome>,
     pub receipts: Vec<views::ReceiptView>,
+    /// Only present if the indexer is configured to attach chunk gas summaries
+    /// and the node tracks the shard.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gas_summary: Option<IndexerChunkGasSummary>,
+}
+
+/// Gas usage of applying the chunk. Unlike `gas_used` and `balance_burnt` in
+/// the chunk header, which refer to the previous chunk of the shard, these
+/// values match the receipt execution outcomes of the shard in this block.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct IndexerChunkGasSummary {
+    pub gas_used: types::Gas,
+    pub gas_limit: types::Gas,
+    #[serde(with = "near_primitives::serialize::dec_format")]
+    pub balance_burnt: types::Balance,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -32,6 +63,19 @@ pub struct IndexerExecutionOutcomeWithOptionalReceipt {
 pub struct IndexerExecutionOutcomeWithReceipt {
     pub execution_outcome: views::ExecutionOutcomeWithIdView,
     pub receipt: views::ReceiptView,
+    /// Only present if the indexer is configured to attach outcome proofs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proof: Option<IndexerExecutionOutcomeProof>,
+}
+
+/// Proof that the outcome root of a shard is included in the `outcome_root` of
+/// the header of block `block_hash`. Together with `execution_outcome.proof`,
+/// which proves the outcome against the shard outcome root, it allows verifying
+/// the execution outcome against a block header.
+#[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
+pub struct IndexerExecutionOutcomeProof {
+    pub block_hash: CryptoHash,
+    pub outcome_root_proof: near_primitives::merkle::MerklePath,
 }
 
 #[derive(Clone, Debug, serde::Serialize, serde::Deserialize)]
@@ -40,4 +84,45 @@ pub struct IndexerShard {
     pub chunk: Option<IndexerChunkView>,
     pub receipt_execution_outcomes: Vec<IndexerExecutionOutcomeWithReceipt>,
     pub state_changes: views::StateChangesView,
+    /// Set when the node lacks some of the data of this shard, e.g. the
+    /// execution outcomes of a shard it doesn't track.
+    #[serde(default)]
+    pub incomplete: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use near_primitives::block::Block;
+    use near_primitives::version::PROTOCOL_VERSION;
+
+    #[test]
+    fn test_compact_bytes_round_trip() {
+        let block = Block::genesis(
+            PROTOCOL_VERSION,
+            vec![],
+            near_time::Utc::UNIX_EPOCH,
+            0,
+            100,
+            1_000_000,
+            CryptoHash::default(),
+        );
+        let message = StreamerMessage {
+            block: views::BlockView::from_author_block("test".parse().unwrap(), block),
+            shards: vec![IndexerShard {
+                shard_id: 0,
+                chunk: None,
+                receipt_execution_outcomes: vec![],
+                state_changes: vec![],
+                incomplete: true,
+            }],
+        };
+        let bytes = message.to_compact_bytes().unwrap();
+        let decoded = StreamerMessage::from_compact_bytes(&bytes).unwrap();
+        assert_eq!(
+            serde_json::to_value(&decoded).unwrap(),
+            serde_json::to_value(&message).unwrap()
+        );
+        assert!(StreamerMessage::from_compact_bytes(&bytes[..bytes.len() / 2]).is_err());
+    }
 }
@@ -1,20 +1,26 @@
 #![doc = include_str!("../README.md")]
 
+use std::collections::HashSet;
+
 use anyhow::Context;
 use tokio::sync::mpsc;
 
 use near_chain_configs::GenesisValidationMode;
 pub use near_primitives;
-use near_primitives::types::Gas;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{Gas, StateChangeValueKind};
 pub use nearcore::{get_default_home, init_configs, NearConfig};
 
 pub use near_indexer_primitives::{
-    IndexerChunkView, IndexerExecutionOutcomeWithOptionalReceipt,
+    IndexerChunkGasSummary, IndexerChunkView, IndexerExecutionOutcomeWithOptionalReceipt,
     IndexerExecutionOutcomeWithReceipt, IndexerShard, IndexerTransactionWithOutcome,
     StreamerMessage,
 };
 
-pub use streamer::build_streamer_message;
+pub use streamer::{
+    build_streamer_message, WriteAheadLog, DEFAULT_DELAYED_LOCAL_RECEIPTS_CACHE_SIZE,
+    DEFAULT_DELAYED_RECEIPT_LOOKBACK,
+};
 
 mod streamer;
 
@@ -61,6 +67,9 @@ pub enum SyncModeEnum {
     FromInterruption,
     /// Specific block height to start syncing from
     BlockHeight(u64),
+    /// Streams the blocks from `start` to `end` inclusive, then stops the
+    /// streamer and drops the sender side of the stream
+    BlockRange { start: u64, end: u64 },
 }
 
 /// Enum to define whether await for node to be fully synced or stream while syncing (useful for indexing from genesis)
@@ -72,6 +81,50 @@ pub enum AwaitForNodeSyncedEnum {
     StreamWhileSyncing,
 }
 
+/// Enum to define what to do with the execution outcomes of a shard whose id
+/// is invalid in the shard layout of the block, which happens across a resharding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReshardingOutcomesModeEnum {
+    /// Drop such outcomes
+    Skip,
+    /// Move every outcome into the child shard of its receipt's receiver,
+    /// according to the parent-child mapping of the shard layout
+    Remap,
+}
+
+/// Blocks into whose chunks the indexer includes the receipts which are
+/// executed in the block without being included into any of its chunks, keyed
+/// by chain id. Such receipts appear in the first blocks of protocol upgrades
+/// which restore receipts, see `build_streamer_message`. The default table has
+/// the two such blocks of mainnet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RestoredReceiptsBlocks(HashSet<(String, CryptoHash)>);
+
+impl RestoredReceiptsBlocks {
+    /// Table without any blocks.
+    pub fn empty() -> Self {
+        Self(HashSet::new())
+    }
+
+    pub fn insert(&mut self, chain_id: impl Into<String>, block_hash: CryptoHash) {
+        self.0.insert((chain_id.into(), block_hash));
+    }
+
+    pub fn contains(&self, chain_id: &str, block_hash: &CryptoHash) -> bool {
+        self.0.contains(&(chain_id.to_string(), *block_hash))
+    }
+}
+
+impl Default for RestoredReceiptsBlocks {
+    fn default() -> Self {
+        let mut blocks = Self::empty();
+        for block_hash in streamer::PROBLEMATIC_BLOCKS {
+            blocks.insert(near_primitives::chains::MAINNET, block_hash);
+        }
+        blocks
+    }
+}
+
 /// NEAR Indexer configuration to be provided to `Indexer::new(IndexerConfig)`
 #[derive(Debug, Clone)]
 pub struct IndexerConfig {
@@ -83,6 +136,107 @@ pub struct IndexerConfig {
     pub await_for_node_synced: AwaitForNodeSyncedEnum,
     /// Tells whether to validate the genesis file before starting
     pub validate_genesis: bool,
+    /// If set, at most this many blocks are streamed before the streamer checks
+    /// the node status and the latest block again. This paces catching up from
+    /// an old height instead of streaming all the way to the head at once.
+    /// `None` doesn't bound the number of blocks.
+    pub max_catchup_blocks_per_iteration: Option<u64>,
+    /// Number of recently streamed blocks kept in memory, so that looking up
+    /// delayed local receipts in previous blocks doesn't have to fetch them
+    /// from the node again. 0 disables the cache.
+    pub recent_blocks_cache_size: usize,
+    /// Whether to attach to every receipt execution outcome the proof of its
+    /// inclusion into the outcome root of a block. Costs extra work per block.
+    pub attach_outcome_proofs: bool,
+    /// Whether to attach to every chunk the gas usage of applying it. Costs an
+    /// extra request to the node per block.
+    pub attach_chunk_gas_summaries: bool,
+    /// If set, only the state changes of these kinds are streamed, e.g. an
+    /// indexer tracking balances only needs `AccountTouched`.
+    pub state_change_kinds: Option<HashSet<StateChangeValueKind>>,
+    /// Maximum number of blocks fetched concurrently while catching up. Blocks
+    /// are still streamed in order. Values below 1 are treated as 1.
+    pub max_concurrent_block_fetches: usize,
+    /// Maximum number of streamer messages built concurrently while catching
+    /// up. Messages are still sent in order. A delayed local receipt converted
+    /// in a block which is still being built isn't in the cache yet, so it is
+    /// looked up in previous blocks instead. Values below 1 are treated as 1.
+    pub max_concurrent_message_builds: usize,
+    /// Maximum number of self-call transactions of a chunk converted into
+    /// local receipts in parallel. Values below 1 are treated as 1.
+    pub local_receipt_conversion_concurrency: usize,
+    /// Whether to ask peers for the chunks of shards the node doesn't track,
+    /// so that blocks are streamed with all of their chunks. Execution outcomes
+    /// of such shards are not available, so they are marked as incomplete.
+    pub fetch_untracked_shards: bool,
+    /// Whether to include the restored receipts into the chunks of the blocks
+    /// listed in `restored_receipts_blocks`, see `build_streamer_message`.
+    /// Disable to stream these blocks exactly as they are stored on chain.
+    pub apply_problematic_block_fixups: bool,
+    /// Blocks with restored receipts of the chain, the mainnet ones by default.
+    pub restored_receipts_blocks: RestoredReceiptsBlocks,
+    /// Whether to write every streamer message to a write-ahead log in
+    /// `<home_dir>/indexer_wal` before sending it. The messages which the
+    /// listener doesn't acknowledge via `Indexer::write_ahead_log` are sent
+    /// again after a restart, so no message is lost if the listener crashes.
+    pub write_ahead_log: bool,
+    /// If set, a block is streamed only once it's at least this old according
+    /// to its header timestamp, which gives an extra margin against reorgs on
+    /// top of the finality of the streamed blocks.
+    pub min_block_age: Option<std::time::Duration>,
+    /// What to do with the execution outcomes of the shards whose ids are
+    /// invalid in the shard layout of the block. Such outcomes are counted by
+    /// the `near_indexer_num_resharded_outcomes_skipped` metric unless they
+    /// are remapped successfully.
+    pub resharding_outcomes_mode: ReshardingOutcomesModeEnum,
+    /// Number of previous blocks searched for the receipt of a delayed local
+    /// receipt before giving up on the block. A block whose receipt isn't found
+    /// is skipped instead of stopping the streamer. Defaults to
+    /// `DEFAULT_DELAYED_RECEIPT_LOOKBACK`.
+    pub delayed_receipt_lookback: Option<u32>,
+    /// Maximum number of local receipts kept in memory until their execution
+    /// outcomes are streamed. The oldest receipts are evicted from the full
+    /// cache and looked up in previous blocks once needed. 0 disables the
+    /// cache.
+    pub delayed_local_receipts_cache_size: usize,
+    /// Whether to stream only the shards the node cares about at each block
+    /// instead of all the shards of the block. The tracked shards are taken
+    /// per block, so around an epoch boundary where they change, blocks of the
+    /// old epoch carry the shards tracked in it and blocks of the new one the
+    /// shards tracked in the new epoch. Shards tracked because of validator
+    /// duties are included.
+    pub tracked_shards_only: bool,
+}
+
+impl Default for IndexerConfig {
+    /// Streams the latest finalized blocks of the node in the default home
+    /// directory once it's fully synced, building the messages the same way as
+    /// before any of the options were introduced.
+    fn default() -> Self {
+        Self {
+            home_dir: get_default_home(),
+            sync_mode: SyncModeEnum::LatestSynced,
+            await_for_node_synced: AwaitForNodeSyncedEnum::WaitForFullSync,
+            validate_genesis: true,
+            max_catchup_blocks_per_iteration: None,
+            recent_blocks_cache_size: 0,
+            attach_outcome_proofs: false,
+            attach_chunk_gas_summaries: false,
+            state_change_kinds: None,
+            max_concurrent_block_fetches: 1,
+            max_concurrent_message_builds: 1,
+            local_receipt_conversion_concurrency: 1,
+            fetch_untracked_shards: false,
+            apply_problematic_block_fixups: true,
+            restored_receipts_blocks: RestoredReceiptsBlocks::default(),
+            write_ahead_log: false,
+            min_block_age: None,
+            resharding_outcomes_mode: ReshardingOutcomesModeEnum::Skip,
+            delayed_receipt_lookback: None,
+            delayed_local_receipts_cache_size: DEFAULT_DELAYED_LOCAL_RECEIPTS_CACHE_SIZE,
+            tracked_shards_only: false,
+        }
+    }
 }
 
 /// This is the core component, which handles `nearcore` and internal `streamer`.
@@ -138,6 +292,39 @@ impl Indexer {
         receiver
     }
 
+    /// Write-ahead log of the streamed messages, if enabled by
+    /// `IndexerConfig::write_ahead_log`. The listener should acknowledge every
+    /// message once it has persisted it, so that it isn't replayed on restart.
+    pub fn write_ahead_log(&self) -> Option<WriteAheadLog> {
+        self.indexer_config.write_ahead_log.then(|| {
+            WriteAheadLog::open(streamer::write_ahead_log_dir(&self.indexer_config.home_dir))
+                .unwrap_or_else(|err| panic!("Unable to open indexer write-ahead log: {:?}", err))
+        })
+    }
+
+    /// Same as [`Self::streamer`], but the messages are serialized with
+    /// `StreamerMessage::to_compact_bytes`, which saves serializing them on
+    /// the user side when they are passed on to another process.
+    pub fn encoded_streamer(&self) -> mpsc::Receiver<Vec<u8>> {
+        let (sender, receiver) = mpsc::channel(100);
+        actix::spawn(streamer::start_encoded(
+            self.view_client.clone(),
+            self.client.clone(),
+            self.indexer_config.clone(),
+            self.near_config.config.store.clone(),
+            self.near_config.config.archive,
+            sender,
+        ));
+        receiver
+    }
+
+    /// Number of blocks the streamer is behind the latest block of the node.
+    /// It's 0 once the streamer has caught up, and while it waits for the
+    /// node to sync.
+    pub fn block_lag(&self) -> near_primitives::types::BlockHeightDelta {
+        streamer::block_lag()
+    }
+
     /// Expose neard config
     pub fn near_config(&self) -> &nearcore::NearConfig {
         &self.near_config
@@ -0,0 +1,87 @@
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::{BlockHeight, BlockHeightDelta};
+
+/// Bounded in-memory cache of recently streamed blocks. It lets the backward walk
+/// looking for delayed local receipts skip fetching recent blocks from the view client.
+///
+/// Blocks are evicted when there are more than `capacity` of them or when they are more
+/// than `max_age` blocks behind the latest inserted one. A capacity of 0 disables the cache.
+pub(crate) struct RecentBlocksCache<B> {
+    blocks: Option<LruCache<CryptoHash, (BlockHeight, B)>>,
+    max_age: BlockHeightDelta,
+}
+
+impl<B: Clone> RecentBlocksCache<B> {
+    pub fn new(capacity: usize, max_age: BlockHeightDelta) -> Self {
+        Self { blocks: NonZeroUsize::new(capacity).map(LruCache::new), max_age }
+    }
+
+    pub fn resize(&mut self, capacity: usize) {
+        match (NonZeroUsize::new(capacity), &mut self.blocks) {
+            (Some(capacity), Some(blocks)) => blocks.resize(capacity),
+            (capacity, blocks) => *blocks = capacity.map(LruCache::new),
+        }
+    }
+
+    pub fn insert(&mut self, hash: CryptoHash, height: BlockHeight, block: B) {
+        let Some(blocks) = &mut self.blocks else {
+            return;
+        };
+        blocks.put(hash, (height, block));
+        let min_height = height.saturating_sub(self.max_age);
+        while let Some((_, (oldest_height, _))) = blocks.peek_lru() {
+            if *oldest_height >= min_height {
+                break;
+            }
+            blocks.pop_lru();
+        }
+    }
+
+    /// Lookups don't promote the entry, so blocks are evicted in the order they
+    /// were streamed.
+    pub fn get(&self, hash: &CryptoHash) -> Option<B> {
+        self.blocks.as_ref()?.peek(hash).map(|(_, block)| block.clone())
+    }
+}
+
+#[cfg(test)]
+fn block_hash(height: BlockHeight) -> CryptoHash {
+    CryptoHash::hash_bytes(&height.to_le_bytes())
+}
+
+#[test]
+fn test_recent_blocks_cache_evicts_by_count() {
+    let mut cache = RecentBlocksCache::new(2, 1000);
+    cache.insert(block_hash(1), 1, 1);
+    cache.insert(block_hash(2), 2, 2);
+    assert_eq!(cache.get(&block_hash(1)), Some(1));
+    cache.insert(block_hash(3), 3, 3);
+    assert_eq!(cache.get(&block_hash(1)), None);
+    assert_eq!(cache.get(&block_hash(2)), Some(2));
+    assert_eq!(cache.get(&block_hash(3)), Some(3));
+}
+
+#[test]
+fn test_recent_blocks_cache_evicts_by_age() {
+    let mut cache = RecentBlocksCache::new(10, 5);
+    cache.insert(block_hash(1), 1, 1);
+    cache.insert(block_hash(2), 2, 2);
+    cache.insert(block_hash(7), 7, 7);
+    assert_eq!(cache.get(&block_hash(1)), None);
+    assert_eq!(cache.get(&block_hash(2)), Some(2));
+    assert_eq!(cache.get(&block_hash(7)), Some(7));
+}
+
+#[test]
+fn test_recent_blocks_cache_disabled() {
+    let mut cache = RecentBlocksCache::new(0, 1000);
+    cache.insert(block_hash(1), 1, 1);
+    assert_eq!(cache.get(&block_hash(1)), None);
+    cache.resize(1);
+    cache.insert(block_hash(1), 1, 1);
+    assert_eq!(cache.get(&block_hash(1)), Some(1));
+}
@@ -0,0 +1,86 @@
+use std::num::NonZeroUsize;
+
+use lru::LruCache;
+
+use near_primitives::hash::CryptoHash;
+
+use super::metrics;
+
+/// Bounded cache of the local receipts whose execution outcomes haven't been
+/// streamed yet. When full, the least recently inserted receipts are evicted;
+/// such receipts are looked up in the history of blocks once their outcomes
+/// show up. A capacity of 0 disables the cache.
+pub(crate) struct DelayedReceiptsCache<R> {
+    receipts: Option<LruCache<CryptoHash, R>>,
+}
+
+impl<R> DelayedReceiptsCache<R> {
+    pub fn new(capacity: usize) -> Self {
+        Self { receipts: NonZeroUsize::new(capacity).map(LruCache::new) }
+    }
+
+    pub fn resize(&mut self, capacity: usize) {
+        match (NonZeroUsize::new(capacity), &mut self.receipts) {
+            (Some(capacity), Some(receipts)) => {
+                let evicted = receipts.len().saturating_sub(capacity.get());
+                receipts.resize(capacity);
+                metrics::DELAYED_LOCAL_RECEIPTS_CACHE_EVICTIONS.inc_by(evicted as u64);
+            }
+            (capacity, receipts) => *receipts = capacity.map(LruCache::new),
+        }
+        self.update_size_metric();
+    }
+
+    pub fn insert(&mut self, receipt_id: CryptoHash, receipt: R) {
+        let Some(receipts) = &mut self.receipts else {
+            return;
+        };
+        if let Some((evicted_id, _)) = receipts.push(receipt_id, receipt) {
+            if evicted_id != receipt_id {
+                metrics::DELAYED_LOCAL_RECEIPTS_CACHE_EVICTIONS.inc();
+            }
+        }
+        self.update_size_metric();
+    }
+
+    /// Removes the receipt from the cache and returns it, if it's there.
+    pub fn take(&mut self, receipt_id: &CryptoHash) -> Option<R> {
+        let receipt = self.receipts.as_mut()?.pop(receipt_id);
+        self.update_size_metric();
+        receipt
+    }
+
+    fn update_size_metric(&self) {
+        let size = self.receipts.as_ref().map_or(0, LruCache::len);
+        metrics::DELAYED_LOCAL_RECEIPTS_CACHE_SIZE.set(size as i64);
+    }
+}
+
+#[cfg(test)]
+fn receipt_id(index: u64) -> CryptoHash {
+    CryptoHash::hash_bytes(&index.to_le_bytes())
+}
+
+#[test]
+fn test_delayed_receipts_cache_evicts_oldest() {
+    let evictions = metrics::DELAYED_LOCAL_RECEIPTS_CACHE_EVICTIONS.get();
+    let mut cache = DelayedReceiptsCache::new(2);
+    for index in 1..=3 {
+        cache.insert(receipt_id(index), index);
+    }
+    assert!(metrics::DELAYED_LOCAL_RECEIPTS_CACHE_EVICTIONS.get() >= evictions + 1);
+    assert_eq!(cache.take(&receipt_id(1)), None);
+    assert_eq!(cache.take(&receipt_id(2)), Some(2));
+    assert_eq!(cache.take(&receipt_id(2)), None);
+    assert_eq!(cache.take(&receipt_id(3)), Some(3));
+}
+
+#[test]
+fn test_delayed_receipts_cache_disabled() {
+    let mut cache = DelayedReceiptsCache::new(0);
+    cache.insert(receipt_id(1), 1);
+    assert_eq!(cache.take(&receipt_id(1)), None);
+    cache.resize(1);
+    cache.insert(receipt_id(1), 1);
+    assert_eq!(cache.take(&receipt_id(1)), Some(1));
+}
@@ -1,12 +1,15 @@
 //! Streamer watches the network and collects all the blocks and related chunks
 //! into one struct and pushes in to the given queue
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use actix::Addr;
 use futures::stream::StreamExt;
 use tracing::warn;
 
-use near_indexer_primitives::IndexerExecutionOutcomeWithOptionalReceipt;
+use near_indexer_primitives::{
+    IndexerChunkGasSummary, IndexerExecutionOutcomeProof,
+    IndexerExecutionOutcomeWithOptionalReceipt,
+};
 use near_o11y::WithSpanContextExt;
 use near_primitives::hash::CryptoHash;
 use near_primitives::{types, views};
@@ -71,16 +74,23 @@ pub(crate) async fn fetch_block(
         .map_err(|err| FailedToFetchData::String(err.to_string()))
 }
 
+/// Fetches the state changes of the block split by shard id
+/// If `kinds` is set, only the state changes of these kinds are fetched
 pub(crate) async fn fetch_state_changes(
     client: &Addr<near_client::ViewClientActor>,
     block_hash: CryptoHash,
     epoch_id: near_primitives::types::EpochId,
+    kinds: Option<HashSet<types::StateChangeValueKind>>,
 ) -> Result<HashMap<near_primitives::types::ShardId, views::StateChangesView>, FailedToFetchData> {
     tracing::debug!(target: INDEXER, "Fetching state changes for block: {}, epoch_id: {:?}", block_hash, epoch_id);
     client
         .send(
-            near_client::GetStateChangesWithCauseInBlockForTrackedShards { block_hash, epoch_id }
-                .with_span_context(),
+            near_client::GetStateChangesWithCauseInBlockForTrackedShards {
+                block_hash,
+                epoch_id,
+                kinds,
+            }
+            .with_span_context(),
         )
         .await?
         .map_err(|err| FailedToFetchData::String(err.to_string()))
@@ -106,32 +116,121 @@ pub(crate) async fn fetch_outcomes(
         Vec<IndexerExecutionOutcomeWithOptionalReceipt>,
     > = HashMap::new();
     for (shard_id, shard_outcomes) in outcomes {
-        tracing::debug!(target: INDEXER, "Fetching outcomes with receipts for shard: {}", shard_id);
-        let mut outcomes_with_receipts: Vec<IndexerExecutionOutcomeWithOptionalReceipt> = vec![];
-        for outcome in shard_outcomes {
-            let receipt = match fetch_receipt_by_id(&client, outcome.id).await {
-                Ok(res) => res,
-                Err(e) => {
-                    warn!(
-                        target: INDEXER,
-                        "Unable to fetch Receipt with id {}. Skipping it in ExecutionOutcome \n {:#?}",
-                        outcome.id,
-                        e,
-                    );
-                    None
-                }
-            };
-            outcomes_with_receipts.push(IndexerExecutionOutcomeWithOptionalReceipt {
-                execution_outcome: outcome,
-                receipt,
-            });
-        }
+        let outcomes_with_receipts =
+            fetch_receipts_for_outcomes(&client, shard_id, shard_outcomes).await;
         shard_execution_outcomes_with_receipts.insert(shard_id, outcomes_with_receipts);
     }
 
     Ok(shard_execution_outcomes_with_receipts)
 }
 
+/// Same as `fetch_outcomes`, but also returns the proofs of the shard outcome roots
+/// for the shards where they are already available
+pub(crate) async fn fetch_outcomes_with_proofs(
+    client: &Addr<near_client::ViewClientActor>,
+    block_hash: CryptoHash,
+) -> Result<
+    (
+        HashMap<near_primitives::types::ShardId, Vec<IndexerExecutionOutcomeWithOptionalReceipt>>,
+        HashMap<near_primitives::types::ShardId, IndexerExecutionOutcomeProof>,
+    ),
+    FailedToFetchData,
+> {
+    tracing::debug!(target: INDEXER, "Fetching outcomes with proofs for block: {}", block_hash);
+    let outcomes = client
+        .send(
+            near_client::GetExecutionOutcomesWithProofsForBlock { block_hash }.with_span_context(),
+        )
+        .await?
+        .map_err(FailedToFetchData::String)?;
+
+    let mut shard_execution_outcomes_with_receipts = HashMap::new();
+    let mut shard_outcome_proofs = HashMap::new();
+    for (shard_id, shard_outcomes) in outcomes {
+        if let Some(outcome_root_proof) = shard_outcomes.outcome_root_proof {
+            shard_outcome_proofs.insert(
+                shard_id,
+                IndexerExecutionOutcomeProof {
+                    block_hash: outcome_root_proof.block_hash,
+                    outcome_root_proof: outcome_root_proof.proof,
+                },
+            );
+        }
+        let outcomes_with_receipts =
+            fetch_receipts_for_outcomes(&client, shard_id, shard_outcomes.outcomes).await;
+        shard_execution_outcomes_with_receipts.insert(shard_id, outcomes_with_receipts);
+    }
+
+    Ok((shard_execution_outcomes_with_receipts, shard_outcome_proofs))
+}
+
+/// Fetches the gas usage of applying the chunks of the block
+/// Returns a HashMap where the key is shard id, shards untracked by the node are missing
+pub(crate) async fn fetch_chunk_gas_summaries(
+    client: &Addr<near_client::ViewClientActor>,
+    block_hash: CryptoHash,
+) -> Result<HashMap<types::ShardId, IndexerChunkGasSummary>, FailedToFetchData> {
+    tracing::debug!(target: INDEXER, "Fetching chunk gas summaries for block: {}", block_hash);
+    let summaries = client
+        .send(near_client::GetChunkGasSummariesForBlock { block_hash }.with_span_context())
+        .await?
+        .map_err(FailedToFetchData::String)?;
+    Ok(summaries
+        .into_iter()
+        .map(|(shard_id, summary)| {
+            (
+                shard_id,
+                IndexerChunkGasSummary {
+                    gas_used: summary.gas_used,
+                    gas_limit: summary.gas_limit,
+                    balance_burnt: summary.balance_burnt,
+                },
+            )
+        })
+        .collect())
+}
+
+/// Fetches the ids of the shards the node cares about at the block
+pub(crate) async fn fetch_tracked_shards(
+    client: &Addr<near_client::ViewClientActor>,
+    block_hash: CryptoHash,
+) -> Result<HashSet<types::ShardId>, FailedToFetchData> {
+    tracing::debug!(target: INDEXER, "Fetching tracked shards for block: {}", block_hash);
+    let shard_ids = client
+        .send(near_client::GetTrackedShardsForBlock { block_hash }.with_span_context())
+        .await?
+        .map_err(FailedToFetchData::String)?;
+    Ok(shard_ids.into_iter().collect())
+}
+
+async fn fetch_receipts_for_outcomes(
+    client: &Addr<near_client::ViewClientActor>,
+    shard_id: near_primitives::types::ShardId,
+    shard_outcomes: Vec<views::ExecutionOutcomeWithIdView>,
+) -> Vec<IndexerExecutionOutcomeWithOptionalReceipt> {
+    tracing::debug!(target: INDEXER, "Fetching outcomes with receipts for shard: {}", shard_id);
+    let mut outcomes_with_receipts: Vec<IndexerExecutionOutcomeWithOptionalReceipt> = vec![];
+    for outcome in shard_outcomes {
+        let receipt = match fetch_receipt_by_id(&client, outcome.id).await {
+            Ok(res) => res,
+            Err(e) => {
+                warn!(
+                    target: INDEXER,
+                    "Unable to fetch Receipt with id {}. Skipping it in ExecutionOutcome \n {:#?}",
+                    outcome.id,
+                    e,
+                );
+                None
+            }
+        };
+        outcomes_with_receipts.push(IndexerExecutionOutcomeWithOptionalReceipt {
+            execution_outcome: outcome,
+            receipt,
+        });
+    }
+    outcomes_with_receipts
+}
+
 async fn fetch_receipt_by_id(
     client: &Addr<near_client::ViewClientActor>,
     receipt_id: CryptoHash,
@@ -176,6 +275,79 @@ pub(crate) async fn fetch_block_chunks(
     Ok(chunks)
 }
 
+/// Number of times the chunks of untracked shards are requested from peers
+/// before the shard is given up on.
+const FETCH_UNTRACKED_CHUNKS_ATTEMPTS: usize = 5;
+/// Delay between the attempts to fetch the chunks of untracked shards.
+const FETCH_UNTRACKED_CHUNKS_DELAY: std::time::Duration = std::time::Duration::from_millis(500);
+
+/// Fetches all chunks belonging to given block, asking the node to request the
+/// chunks it doesn't have from peers. This is bounded, so the ids of the shards
+/// whose chunks still couldn't be fetched are returned along with the chunks.
+/// The ids of the shards whose chunks were fetched from peers are returned as
+/// well, as the node doesn't have their execution outcomes.
+pub(crate) async fn fetch_block_chunks_from_peers(
+    view_client: &Addr<near_client::ViewClientActor>,
+    client: &Addr<near_client::ClientActor>,
+    block: &views::BlockView,
+) -> Result<(Vec<views::ChunkView>, HashSet<types::ShardId>, Vec<types::ShardId>), FailedToFetchData>
+{
+    tracing::debug!(
+        target: INDEXER,
+        "Fetching chunks for block #{} including untracked shards",
+        block.header.height
+    );
+    let mut missing: Vec<&views::ChunkHeaderView> =
+        block.chunks.iter().filter(|chunk| chunk.height_included == block.header.height).collect();
+    let mut chunks = Vec::<views::ChunkView>::with_capacity(missing.len());
+    let mut fetched_from_peers = HashSet::new();
+    for attempt in 0..=FETCH_UNTRACKED_CHUNKS_ATTEMPTS {
+        if attempt > 0 {
+            client
+                .send(
+                    near_client::FetchFullChunks {
+                        block_hash: block.header.hash,
+                        shard_ids: missing.iter().map(|chunk| chunk.shard_id).collect(),
+                    }
+                    .with_span_context(),
+                )
+                .await?;
+            tokio::time::sleep(FETCH_UNTRACKED_CHUNKS_DELAY).await;
+        }
+        let results = futures::future::join_all(
+            missing.iter().map(|chunk| fetch_single_chunk(view_client, chunk.chunk_hash)),
+        )
+        .await;
+        let mut still_missing = vec![];
+        for (chunk_header, result) in missing.into_iter().zip(results) {
+            match result {
+                Ok(chunk) => {
+                    if attempt > 0 {
+                        fetched_from_peers.insert(chunk.header.shard_id);
+                    }
+                    chunks.push(chunk);
+                }
+                Err(_) => still_missing.push(chunk_header),
+            }
+        }
+        missing = still_missing;
+        if missing.is_empty() {
+            break;
+        }
+    }
+    let missing_shard_ids: Vec<types::ShardId> =
+        missing.iter().map(|chunk| chunk.shard_id).collect();
+    if !missing_shard_ids.is_empty() {
+        warn!(
+            target: INDEXER,
+            "Failed to fetch chunks of shards {:?} for block #{}",
+            missing_shard_ids,
+            block.header.height,
+        );
+    }
+    Ok((chunks, fetched_from_peers, missing_shard_ids))
+}
+
 pub(crate) async fn fetch_protocol_config(
     client: &Addr<near_client::ViewClientActor>,
     block_hash: near_primitives::hash::CryptoHash,
@@ -1,6 +1,6 @@
 use near_o11y::metrics::{
-    try_create_histogram, try_create_int_counter, try_create_int_gauge, Histogram, IntCounter,
-    IntGauge,
+    try_create_histogram, try_create_int_counter, try_create_int_counter_vec, try_create_int_gauge,
+    Histogram, IntCounter, IntCounterVec, IntGauge,
 };
 use once_cell::sync::Lazy;
 
@@ -28,6 +28,14 @@ pub(crate) static CURRENT_BLOCK_HEIGHT: Lazy<IntGauge> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static INDEXER_BLOCK_LAG: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_indexer_block_lag",
+        "Number of blocks between the latest block of the node and the block being indexed",
+    )
+    .unwrap()
+});
+
 pub(crate) static NUM_STREAMER_MESSAGES_SENT: Lazy<IntCounter> = Lazy::new(|| {
     try_create_int_counter(
         "near_indexer_num_streamer_messages_sent",
@@ -36,6 +44,39 @@ pub(crate) static NUM_STREAMER_MESSAGES_SENT: Lazy<IntCounter> = Lazy::new(|| {
     .unwrap()
 });
 
+pub(crate) static NUM_STREAMER_MESSAGES_SKIPPED: Lazy<IntCounterVec> = Lazy::new(|| {
+    try_create_int_counter_vec(
+        "near_indexer_num_streamer_messages_skipped",
+        "Number of blocks skipped by the streamer, by reason",
+        &["reason"],
+    )
+    .unwrap()
+});
+
+pub(crate) static NUM_RESHARDED_OUTCOMES_SKIPPED: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_indexer_num_resharded_outcomes_skipped",
+        "Number of execution outcomes dropped because their shard is invalid in the shard layout of the block",
+    )
+    .unwrap()
+});
+
+pub(crate) static DELAYED_LOCAL_RECEIPTS_CACHE_SIZE: Lazy<IntGauge> = Lazy::new(|| {
+    try_create_int_gauge(
+        "near_indexer_delayed_local_receipts_cache_size",
+        "Number of local receipts waiting in the cache for their execution outcomes",
+    )
+    .unwrap()
+});
+
+pub(crate) static DELAYED_LOCAL_RECEIPTS_CACHE_EVICTIONS: Lazy<IntCounter> = Lazy::new(|| {
+    try_create_int_counter(
+        "near_indexer_delayed_local_receipts_cache_evictions",
+        "Number of local receipts evicted from the full cache before their execution outcomes were streamed",
+    )
+    .unwrap()
+});
+
 pub(crate) static BUILD_STREAMER_MESSAGE_TIME: Lazy<Histogram> = Lazy::new(|| {
     try_create_histogram(
         "near_indexer_build_streamer_message_time",
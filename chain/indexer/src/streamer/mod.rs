@@ -1,8 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::ops::ControlFlow;
 use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 use actix::Addr;
+use futures::{FutureExt, Stream, StreamExt};
 use lazy_static::lazy_static;
 use rocksdb::DB;
 use tokio::sync::mpsc;
@@ -10,38 +12,72 @@ use tokio::time;
 use tracing::{debug, error, info};
 
 use near_indexer_primitives::{
-    IndexerChunkView, IndexerExecutionOutcomeWithOptionalReceipt,
+    IndexerChunkView, IndexerExecutionOutcomeProof, IndexerExecutionOutcomeWithOptionalReceipt,
     IndexerExecutionOutcomeWithReceipt, IndexerShard, IndexerTransactionWithOutcome,
     StreamerMessage,
 };
 use near_parameters::RuntimeConfig;
 use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::{account_id_to_shard_id, ShardLayout};
+use near_primitives::types::ShardId;
 use near_primitives::views;
 
+use self::block_cache::RecentBlocksCache;
+use self::delayed_receipts_cache::DelayedReceiptsCache;
 use self::errors::FailedToFetchData;
 use self::fetchers::{
-    fetch_block, fetch_block_by_height, fetch_block_chunks, fetch_latest_block, fetch_outcomes,
-    fetch_state_changes, fetch_status,
+    fetch_block, fetch_block_by_height, fetch_block_chunks, fetch_block_chunks_from_peers,
+    fetch_chunk_gas_summaries, fetch_latest_block, fetch_outcomes, fetch_outcomes_with_proofs,
+    fetch_state_changes, fetch_status, fetch_tracked_shards,
 };
 use self::utils::convert_transactions_sir_into_local_receipts;
+use self::wal::replay_write_ahead_log;
+pub(crate) use self::wal::write_ahead_log_dir;
+pub use self::wal::WriteAheadLog;
 use crate::streamer::fetchers::fetch_protocol_config;
 use crate::INDEXER;
-use crate::{AwaitForNodeSyncedEnum, IndexerConfig};
+use crate::{
+    AwaitForNodeSyncedEnum, IndexerConfig, ReshardingOutcomesModeEnum, RestoredReceiptsBlocks,
+};
 
+mod block_cache;
+mod delayed_receipts_cache;
 mod errors;
 mod fetchers;
 mod metrics;
 mod utils;
+mod wal;
 
 lazy_static! {
-    static ref DELAYED_LOCAL_RECEIPTS_CACHE: Arc<RwLock<HashMap<CryptoHash, views::ReceiptView>>> =
-        Arc::new(RwLock::new(HashMap::new()));
+    static ref DELAYED_LOCAL_RECEIPTS_CACHE: Arc<RwLock<DelayedReceiptsCache<views::ReceiptView>>> =
+        Arc::new(RwLock::new(DelayedReceiptsCache::new(DEFAULT_DELAYED_LOCAL_RECEIPTS_CACHE_SIZE)));
+    static ref RECENT_BLOCKS_CACHE: Arc<RwLock<RecentBlocksCache<views::BlockView>>> =
+        Arc::new(RwLock::new(RecentBlocksCache::new(0, RECENT_BLOCKS_CACHE_MAX_AGE)));
 }
 
+/// Default capacity of the cache of local receipts waiting for their execution outcomes.
+pub const DEFAULT_DELAYED_LOCAL_RECEIPTS_CACHE_SIZE: usize = 10_000;
+
+/// Number of previous blocks searched for a delayed local receipt unless
+/// `IndexerConfig::delayed_receipt_lookback` says otherwise.
+pub const DEFAULT_DELAYED_RECEIPT_LOOKBACK: u32 = 1000;
+
+/// Blocks further behind than this are never looked at by
+/// `lookup_delayed_local_receipt_in_previous_blocks` with the default lookback, so there
+/// is no point in caching them.
+const RECENT_BLOCKS_CACHE_MAX_AGE: near_primitives::types::BlockHeightDelta =
+    DEFAULT_DELAYED_RECEIPT_LOOKBACK as near_primitives::types::BlockHeightDelta;
+
 const INTERVAL: Duration = Duration::from_millis(500);
 
-/// Blocks #47317863 and #47317864 with restored receipts.
-const PROBLEMATIC_BLOCKS: [CryptoHash; 2] = [
+/// Key of the height of the last block processed by the streamer in the indexer db,
+/// stored as little-endian `u64`.
+const LAST_SYNCED_BLOCK_HEIGHT_KEY: &[u8] = b"last_synced_block_height_le";
+/// Key under which the height used to be stored as a decimal string.
+const LEGACY_LAST_SYNCED_BLOCK_HEIGHT_KEY: &[u8] = b"last_synced_block_height";
+
+/// Blocks #47317863 and #47317864 of mainnet with restored receipts.
+pub(crate) const PROBLEMATIC_BLOCKS: [CryptoHash; 2] = [
     CryptoHash(
         *b"\xcd\xde\x9a\x3f\x5d\xdf\xb4\x2c\xb9\x9b\xf4\x8c\x04\x95\x6f\x5b\
            \xa0\xb7\x29\xe2\xa5\x04\xf8\xbd\x9c\x86\x92\xd6\x16\x8c\xcf\x14",
@@ -52,6 +88,90 @@ const PROBLEMATIC_BLOCKS: [CryptoHash; 2] = [
     ),
 ];
 
+/// Whether the restored receipts should be included into the chunks of the block,
+/// see the comment in `build_streamer_message`.
+fn needs_problematic_block_fixup(
+    block_hash: &CryptoHash,
+    chain_id: &str,
+    restored_receipts_blocks: &RestoredReceiptsBlocks,
+    apply_problematic_block_fixups: bool,
+) -> bool {
+    apply_problematic_block_fixups && restored_receipts_blocks.contains(chain_id, block_hash)
+}
+
+#[test]
+fn test_needs_problematic_block_fixup() {
+    let mainnet = near_primitives::chains::MAINNET;
+    let testnet = near_primitives::chains::TESTNET;
+    let blocks = RestoredReceiptsBlocks::default();
+    for block_hash in &PROBLEMATIC_BLOCKS {
+        assert!(needs_problematic_block_fixup(block_hash, mainnet, &blocks, true));
+        assert!(!needs_problematic_block_fixup(block_hash, mainnet, &blocks, false));
+        assert!(!needs_problematic_block_fixup(block_hash, testnet, &blocks, true));
+    }
+    assert!(!needs_problematic_block_fixup(&CryptoHash::default(), mainnet, &blocks, true));
+}
+
+/// Receipts executed in the chunk which are not included into it, i.e. the
+/// restored receipts when the block is one of the `RestoredReceiptsBlocks`.
+fn restored_receipts(
+    chunk_non_local_receipts: &[views::ReceiptView],
+    receipt_execution_outcomes: &[IndexerExecutionOutcomeWithReceipt],
+) -> Vec<views::ReceiptView> {
+    let receipt_ids_included: HashSet<CryptoHash> =
+        chunk_non_local_receipts.iter().map(|receipt| receipt.receipt_id).collect();
+    receipt_execution_outcomes
+        .iter()
+        .filter(|outcome| !receipt_ids_included.contains(&outcome.receipt.receipt_id))
+        .map(|outcome| outcome.receipt.clone())
+        .collect()
+}
+
+#[test]
+fn test_restored_receipts_of_custom_chain() {
+    use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
+
+    let chain_id = "test-chain";
+    let block_hash = CryptoHash::hash_bytes(b"upgrade block");
+    let mut blocks = RestoredReceiptsBlocks::empty();
+    blocks.insert(chain_id, block_hash);
+    assert!(needs_problematic_block_fixup(&block_hash, chain_id, &blocks, true));
+    assert!(!needs_problematic_block_fixup(
+        &block_hash,
+        near_primitives::chains::MAINNET,
+        &blocks,
+        true
+    ));
+    assert!(!needs_problematic_block_fixup(
+        &PROBLEMATIC_BLOCKS[0],
+        near_primitives::chains::MAINNET,
+        &blocks,
+        true
+    ));
+
+    let make_receipt = |name: &str| views::ReceiptView {
+        predecessor_id: "test0".parse().unwrap(),
+        receiver_id: "test1".parse().unwrap(),
+        receipt_id: CryptoHash::hash_bytes(name.as_bytes()),
+        receipt: views::ReceiptEnumView::Data {
+            data_id: CryptoHash::default(),
+            data: None,
+            is_promise_resume: false,
+        },
+        priority: 0,
+    };
+    let make_outcome = |name: &str| IndexerExecutionOutcomeWithReceipt {
+        execution_outcome: ExecutionOutcomeWithIdAndProof::default().into(),
+        receipt: make_receipt(name),
+        proof: None,
+    };
+    // The outcome of the restored receipt is in the chunk, but the receipt isn't.
+    let chunk_receipts = vec![make_receipt("included")];
+    let outcomes = vec![make_outcome("included"), make_outcome("restored")];
+    assert_eq!(restored_receipts(&chunk_receipts, &outcomes), vec![make_receipt("restored")]);
+    assert_eq!(restored_receipts(&chunk_receipts, &outcomes[..1]), vec![]);
+}
+
 /// Tests whether raw hashes in [`PROBLEMATIC_BLOCKS`] match expected
 /// user-readable hashes.  Ideally we would compute the hashes at compile time
 /// but there’s no const function for base58→bytes conversion so instead we’re
@@ -72,13 +192,38 @@ fn test_problematic_blocks_hash() {
 
 /// This function supposed to return the entire `StreamerMessage`.
 /// It fetches the block and all related parts (chunks, outcomes, state changes etc.)
-/// and returns everything together in one struct
+/// and returns everything together in one struct.
+/// The message is built according to the options of `indexer_config`, see `IndexerConfig`.
+/// If `untracked_shards_client` is set, the chunks the node doesn't have are requested
+/// from peers through it. Shards whose chunks or execution outcomes are still missing
+/// are marked as incomplete instead of failing the whole block.
 pub async fn build_streamer_message(
     client: &Addr<near_client::ViewClientActor>,
     block: views::BlockView,
+    indexer_config: &IndexerConfig,
+    untracked_shards_client: Option<&Addr<near_client::ClientActor>>,
 ) -> Result<StreamerMessage, FailedToFetchData> {
     let _timer = metrics::BUILD_STREAMER_MESSAGE_TIME.start_timer();
-    let chunks = fetch_block_chunks(&client, &block).await?;
+    let IndexerConfig {
+        attach_outcome_proofs,
+        attach_chunk_gas_summaries,
+        ref state_change_kinds,
+        local_receipt_conversion_concurrency,
+        apply_problematic_block_fixups,
+        ref restored_receipts_blocks,
+        resharding_outcomes_mode,
+        delayed_receipt_lookback,
+        tracked_shards_only,
+        ..
+    } = *indexer_config;
+    let delayed_receipt_lookback =
+        delayed_receipt_lookback.unwrap_or(DEFAULT_DELAYED_RECEIPT_LOOKBACK);
+    let (chunks, fetched_from_peers, missing_shard_ids) =
+        if let Some(untracked_shards_client) = untracked_shards_client {
+            fetch_block_chunks_from_peers(&client, untracked_shards_client, &block).await?
+        } else {
+            (fetch_block_chunks(&client, &block).await?, HashSet::new(), vec![])
+        };
 
     let protocol_config_view = fetch_protocol_config(&client, block.header.hash).await?;
     let num_shards = protocol_config_view.num_block_producer_seats_per_shard.len()
@@ -87,11 +232,21 @@ pub async fn build_streamer_message(
     let runtime_config_store = near_parameters::RuntimeConfigStore::new(None);
     let runtime_config = runtime_config_store.get_config(protocol_config_view.protocol_version);
 
-    let mut shards_outcomes = fetch_outcomes(&client, block.header.hash).await?;
+    let (mut shards_outcomes, shards_outcome_proofs) = if attach_outcome_proofs {
+        fetch_outcomes_with_proofs(&client, block.header.hash).await?
+    } else {
+        (fetch_outcomes(&client, block.header.hash).await?, HashMap::new())
+    };
+    let mut chunk_gas_summaries = if attach_chunk_gas_summaries {
+        fetch_chunk_gas_summaries(&client, block.header.hash).await?
+    } else {
+        HashMap::new()
+    };
     let mut state_changes = fetch_state_changes(
         &client,
         block.header.hash,
         near_primitives::types::EpochId(block.header.epoch_id),
+        state_change_kinds.clone(),
     )
     .await?;
     let mut indexer_shards = (0..num_shards)
@@ -100,6 +255,7 @@ pub async fn build_streamer_message(
             chunk: None,
             receipt_execution_outcomes: vec![],
             state_changes: state_changes.remove(&shard_id).unwrap_or_default(),
+            incomplete: missing_shard_ids.contains(&shard_id),
         })
         .collect::<Vec<_>>();
 
@@ -108,6 +264,23 @@ pub async fn build_streamer_message(
             chunk;
 
         let shard_id = header.shard_id as usize;
+        let outcome_proof = shards_outcome_proofs.get(&header.shard_id);
+        let gas_summary = chunk_gas_summaries.remove(&header.shard_id);
+
+        // The node doesn't have the execution outcomes of shards it doesn't track,
+        // so only the receipts included into the chunks of such shards are known.
+        if fetched_from_peers.contains(&header.shard_id) {
+            shards_outcomes.remove(&header.shard_id);
+            indexer_shards[shard_id].incomplete = true;
+            indexer_shards[shard_id].chunk = Some(IndexerChunkView {
+                author,
+                header,
+                transactions: vec![],
+                receipts: chunk_non_local_receipts,
+                gas_summary,
+            });
+            continue;
+        }
 
         let mut outcomes = shards_outcomes
             .remove(&header.shard_id)
@@ -137,6 +310,7 @@ pub async fn build_streamer_message(
                 .collect::<Vec<&IndexerTransactionWithOutcome>>(),
             &block,
             protocol_config_view.protocol_version,
+            local_receipt_conversion_concurrency,
         )
         .await?;
 
@@ -173,15 +347,17 @@ pub async fn build_streamer_message(
                 let maybe_receipt = {
                     match DELAYED_LOCAL_RECEIPTS_CACHE.write() {
                         Ok(mut cache) => {
-                            // Lock acquired, attempt to remove the receipt
-                            cache.remove(&execution_outcome.id)
+                            // Lock acquired, attempt to remove the receipt. It might have
+                            // been evicted already, then it's looked up in the history.
+                            cache.take(&execution_outcome.id)
                         }
                         Err(_) => {
                             // Failed to acquire lock, log this event and decide to fetch the receipt
                             tracing::warn!(
                                 target: INDEXER,
-                                "Failed to acquire DELAYED_LOCAL_RECEIPTS_CACHE lock, starting to look for receipt {} in up to 1000 blocks back in time",
+                                "Failed to acquire DELAYED_LOCAL_RECEIPTS_CACHE lock, starting to look for receipt {} in up to {} blocks back in time",
                                 execution_outcome.id,
+                                delayed_receipt_lookback,
                             );
                             None // Indicate that receipt needs to be fetched
                         }
@@ -194,23 +370,28 @@ pub async fn build_streamer_message(
                     receipt
                 } else {
                     // Receipt not found in cache or failed to acquire lock, proceed to look it up
-                    // in the history of blocks (up to 1000 blocks back)
+                    // in the history of blocks (up to `delayed_receipt_lookback` blocks back)
                     tracing::warn!(
                         target: INDEXER,
-                        "Receipt {} is missing in block and in DELAYED_LOCAL_RECEIPTS_CACHE, looking for it in up to 1000 blocks back in time",
+                        "Receipt {} is missing in block and in DELAYED_LOCAL_RECEIPTS_CACHE, looking for it in up to {} blocks back in time",
                         execution_outcome.id,
+                        delayed_receipt_lookback,
                     );
                     lookup_delayed_local_receipt_in_previous_blocks(
                         &client,
                         &runtime_config,
                         block.clone(),
                         execution_outcome.id,
+                        delayed_receipt_lookback,
                     )
                     .await?
                 }
             };
-            receipt_execution_outcomes
-                .push(IndexerExecutionOutcomeWithReceipt { execution_outcome, receipt });
+            receipt_execution_outcomes.push(IndexerExecutionOutcomeWithReceipt {
+                execution_outcome,
+                receipt,
+                proof: outcome_proof.cloned(),
+            });
         }
 
         // Blocks #47317863 and #47317864
@@ -221,19 +402,15 @@ pub async fn build_streamer_message(
         // so it was decided to artificially include the Receipts into the Chunk of the Block where
         // ExecutionOutcomes appear.
         // ref: https://github.com/near/nearcore/pull/4248
-        if PROBLEMATIC_BLOCKS.contains(&block.header.hash)
-            && &protocol_config_view.chain_id == near_primitives::chains::MAINNET
-        {
-            let mut restored_receipts: Vec<views::ReceiptView> = vec![];
-            let receipt_ids_included: std::collections::HashSet<CryptoHash> =
-                chunk_non_local_receipts.iter().map(|receipt| receipt.receipt_id).collect();
-            for outcome in &receipt_execution_outcomes {
-                if !receipt_ids_included.contains(&outcome.receipt.receipt_id) {
-                    restored_receipts.push(outcome.receipt.clone());
-                }
-            }
-
-            chunk_receipts.extend(restored_receipts);
+        // Other chains can list their blocks of such upgrades in `restored_receipts_blocks`.
+        if needs_problematic_block_fixup(
+            &block.header.hash,
+            &protocol_config_view.chain_id,
+            restored_receipts_blocks,
+            apply_problematic_block_fixups,
+        ) {
+            chunk_receipts
+                .extend(restored_receipts(&chunk_non_local_receipts, &receipt_execution_outcomes));
         }
 
         chunk_receipts.extend(chunk_non_local_receipts);
@@ -245,40 +422,225 @@ pub async fn build_streamer_message(
             header,
             transactions: indexer_transactions,
             receipts: chunk_receipts,
+            gas_summary,
         });
     }
 
     // Ideally we expect `shards_outcomes` to be empty by this time, but if something went wrong with
     // chunks and we end up with non-empty `shards_outcomes` we want to be sure we put them into IndexerShard
     // That might happen before the fix https://github.com/near/nearcore/pull/4228
-    for (shard_id, outcomes) in shards_outcomes {
-        indexer_shards[shard_id as usize].receipt_execution_outcomes.extend(
-            outcomes.into_iter().map(|outcome| IndexerExecutionOutcomeWithReceipt {
-                execution_outcome: outcome.execution_outcome,
-                receipt: outcome.receipt.expect("`receipt` must be present at this moment"),
-            }),
-        )
+    add_remaining_outcomes(
+        &mut indexer_shards,
+        shards_outcomes,
+        &shards_outcome_proofs,
+        &protocol_config_view.shard_layout,
+        resharding_outcomes_mode,
+    );
+
+    if tracked_shards_only {
+        let tracked_shards = fetch_tracked_shards(&client, block.header.hash).await?;
+        retain_tracked_shards(&mut indexer_shards, &tracked_shards);
     }
 
     Ok(StreamerMessage { block, shards: indexer_shards })
 }
 
+/// Puts the execution outcomes which haven't been matched with the chunks of the
+/// block into their shards. The outcomes of a shard whose id is invalid in the
+/// shard layout of the block are dropped, or remapped to the children of that
+/// shard in `ReshardingOutcomesModeEnum::Remap` mode.
+fn add_remaining_outcomes(
+    indexer_shards: &mut [IndexerShard],
+    shards_outcomes: HashMap<ShardId, Vec<IndexerExecutionOutcomeWithOptionalReceipt>>,
+    shards_outcome_proofs: &HashMap<ShardId, IndexerExecutionOutcomeProof>,
+    shard_layout: &ShardLayout,
+    resharding_outcomes_mode: ReshardingOutcomesModeEnum,
+) {
+    let num_shards = indexer_shards.len() as ShardId;
+    for (shard_id, outcomes) in shards_outcomes {
+        for outcome in outcomes {
+            let target_shard_id = if shard_id < num_shards {
+                Some(shard_id)
+            } else {
+                match resharding_outcomes_mode {
+                    ReshardingOutcomesModeEnum::Skip => None,
+                    ReshardingOutcomesModeEnum::Remap => {
+                        resharded_outcome_shard_id(shard_layout, shard_id, &outcome)
+                            .filter(|&child_shard_id| child_shard_id < num_shards)
+                    }
+                }
+            };
+            let Some(target_shard_id) = target_shard_id else {
+                debug!(target: INDEXER, shard_id, outcome_id = ?outcome.execution_outcome.id, "Skipping execution outcome of a resharded shard");
+                metrics::NUM_RESHARDED_OUTCOMES_SKIPPED.inc();
+                continue;
+            };
+            indexer_shards[target_shard_id as usize].receipt_execution_outcomes.push(
+                IndexerExecutionOutcomeWithReceipt {
+                    execution_outcome: outcome.execution_outcome,
+                    receipt: outcome.receipt.expect("`receipt` must be present at this moment"),
+                    proof: shards_outcome_proofs.get(&shard_id).cloned(),
+                },
+            );
+        }
+    }
+}
+
+/// Returns the child of `parent_shard_id` in `shard_layout` which the given
+/// outcome belongs to, i.e. the shard of the receiver of its receipt.
+fn resharded_outcome_shard_id(
+    shard_layout: &ShardLayout,
+    parent_shard_id: ShardId,
+    outcome: &IndexerExecutionOutcomeWithOptionalReceipt,
+) -> Option<ShardId> {
+    let children = shard_layout.get_children_shards_ids(parent_shard_id)?;
+    let shard_id = match &outcome.receipt {
+        Some(receipt) => account_id_to_shard_id(&receipt.receiver_id, shard_layout),
+        None if children.len() == 1 => children[0],
+        None => return None,
+    };
+    children.contains(&shard_id).then_some(shard_id)
+}
+
+#[test]
+fn test_add_remaining_outcomes_across_resharding() {
+    use near_primitives::transaction::ExecutionOutcomeWithIdAndProof;
+
+    // Shard 2 of the previous layout has been split into shards 0 and 1.
+    let shard_layout =
+        ShardLayout::v1(vec!["test1".parse().unwrap()], Some(vec![vec![], vec![], vec![0, 1]]), 1);
+    let make_outcome = |receiver_id: &str| {
+        let receipt = views::ReceiptView {
+            predecessor_id: "test0".parse().unwrap(),
+            receiver_id: receiver_id.parse().unwrap(),
+            receipt_id: CryptoHash::hash_bytes(receiver_id.as_bytes()),
+            receipt: views::ReceiptEnumView::Data {
+                data_id: CryptoHash::default(),
+                data: None,
+                is_promise_resume: false,
+            },
+            priority: 0,
+        };
+        IndexerExecutionOutcomeWithOptionalReceipt {
+            execution_outcome: ExecutionOutcomeWithIdAndProof::default().into(),
+            receipt: Some(receipt),
+        }
+    };
+    let build = |mode| {
+        let mut indexer_shards = (0..2)
+            .map(|shard_id| IndexerShard {
+                shard_id,
+                chunk: None,
+                receipt_execution_outcomes: vec![],
+                state_changes: vec![],
+                incomplete: false,
+            })
+            .collect::<Vec<_>>();
+        let shards_outcomes = HashMap::from([
+            (0, vec![make_outcome("test0")]),
+            (2, vec![make_outcome("test0"), make_outcome("test2")]),
+        ]);
+        add_remaining_outcomes(
+            &mut indexer_shards,
+            shards_outcomes,
+            &HashMap::new(),
+            &shard_layout,
+            mode,
+        );
+        indexer_shards
+            .into_iter()
+            .map(|shard| {
+                shard
+                    .receipt_execution_outcomes
+                    .into_iter()
+                    .map(|outcome| outcome.receipt.receiver_id.to_string())
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    let skipped = || metrics::NUM_RESHARDED_OUTCOMES_SKIPPED.get();
+
+    let before = skipped();
+    assert_eq!(build(ReshardingOutcomesModeEnum::Skip), vec![vec!["test0"], vec![]]);
+    assert_eq!(skipped(), before + 2);
+
+    let before = skipped();
+    assert_eq!(
+        build(ReshardingOutcomesModeEnum::Remap),
+        vec![vec!["test0", "test0"], vec!["test2"]]
+    );
+    assert_eq!(skipped(), before);
+}
+
+/// Drops the shards which are not in `tracked_shards`, keeping the order of the rest.
+fn retain_tracked_shards(
+    indexer_shards: &mut Vec<IndexerShard>,
+    tracked_shards: &HashSet<ShardId>,
+) {
+    indexer_shards.retain(|shard| tracked_shards.contains(&shard.shard_id));
+}
+
+#[test]
+fn test_retain_tracked_shards() {
+    let mut indexer_shards = (0..4)
+        .map(|shard_id| IndexerShard {
+            shard_id,
+            chunk: None,
+            receipt_execution_outcomes: vec![],
+            state_changes: vec![],
+            incomplete: false,
+        })
+        .collect::<Vec<_>>();
+    retain_tracked_shards(&mut indexer_shards, &HashSet::from([3, 1]));
+    assert_eq!(indexer_shards.iter().map(|shard| shard.shard_id).collect::<Vec<_>>(), vec![1, 3]);
+}
+
 // Receipt might be missing only in case of delayed local receipt
 // that appeared in some of the previous blocks
 // we will be iterating over previous blocks until we found the receipt
-// or panic if we didn't find it in 1000 blocks
+// or fail if we didn't find it in `lookback` blocks
 async fn lookup_delayed_local_receipt_in_previous_blocks(
     client: &Addr<near_client::ViewClientActor>,
     runtime_config: &RuntimeConfig,
     block: views::BlockView,
     receipt_id: CryptoHash,
+    lookback: u32,
 ) -> Result<views::ReceiptView, FailedToFetchData> {
-    let mut prev_block_tried = 0u16;
-    let mut prev_block_hash = block.header.prev_hash;
-    'find_local_receipt: loop {
-        if prev_block_tried > 1000 {
-            panic!("Failed to find local receipt in 1000 prev blocks");
-        }
+    find_in_previous_blocks(
+        block.header.prev_hash,
+        receipt_id,
+        lookback,
+        |prev_block_hash| async move {
+            let cached_block =
+                RECENT_BLOCKS_CACHE.read().ok().and_then(|cache| cache.get(&prev_block_hash));
+            let prev_block = match cached_block {
+                Some(block) => block,
+                None => fetch_block(&client, prev_block_hash).await?,
+            };
+            let next_prev_block_hash = prev_block.header.prev_hash;
+            let receipt =
+                find_local_receipt_by_id_in_block(&client, &runtime_config, prev_block, receipt_id)
+                    .await?;
+            Ok((next_prev_block_hash, receipt))
+        },
+    )
+    .await
+}
+
+/// Walks back from the block `prev_block_hash` through at most `lookback` blocks
+/// until `find_in_block` finds the receipt in one of them. `find_in_block` returns
+/// the hash of the previous block along with the receipt if it's in the block.
+async fn find_in_previous_blocks<R, F, Fut>(
+    mut prev_block_hash: CryptoHash,
+    receipt_id: CryptoHash,
+    lookback: u32,
+    mut find_in_block: F,
+) -> Result<R, FailedToFetchData>
+where
+    F: FnMut(CryptoHash) -> Fut,
+    Fut: std::future::Future<Output = Result<(CryptoHash, Option<R>), FailedToFetchData>>,
+{
+    for prev_block_tried in 0..lookback {
         // Log a warning every 100 blocks
         if prev_block_tried % 100 == 0 {
             tracing::warn!(
@@ -288,17 +650,8 @@ async fn lookup_delayed_local_receipt_in_previous_blocks(
                 prev_block_tried,
             );
         }
-        let prev_block = match fetch_block(&client, prev_block_hash).await {
-            Ok(block) => block,
-            Err(err) => panic!("Unable to get previous block: {:?}", err),
-        };
-
-        prev_block_hash = prev_block.header.prev_hash;
-
-        if let Some(receipt) =
-            find_local_receipt_by_id_in_block(&client, &runtime_config, prev_block, receipt_id)
-                .await?
-        {
+        let (next_prev_block_hash, receipt) = find_in_block(prev_block_hash).await?;
+        if let Some(receipt) = receipt {
             tracing::debug!(
                 target: INDEXER,
                 "Found receipt {} in previous block {}",
@@ -306,11 +659,65 @@ async fn lookup_delayed_local_receipt_in_previous_blocks(
                 prev_block_tried,
             );
             metrics::LOCAL_RECEIPT_LOOKUP_IN_HISTORY_BLOCKS_BACK.set(prev_block_tried as i64);
-            break 'find_local_receipt Ok(receipt);
+            return Ok(receipt);
         }
+        prev_block_hash = next_prev_block_hash;
+    }
+    Err(FailedToFetchData::String(format!(
+        "Failed to find local receipt {} in {} prev blocks",
+        receipt_id, lookback
+    )))
+}
+
+#[test]
+fn test_find_in_previous_blocks() {
+    let block_hash = |height: u64| CryptoHash::hash_bytes(&height.to_le_bytes());
+    let receipt_id = CryptoHash::hash_bytes(b"receipt");
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let find = |receipt_height: u64, lookback: u32| {
+        let mut tried = vec![];
+        let result = rt.block_on(find_in_previous_blocks(
+            block_hash(100),
+            receipt_id,
+            lookback,
+            |hash| {
+                let height = (0..=100).find(|height| block_hash(*height) == hash).unwrap();
+                tried.push(height);
+                async move { Ok((block_hash(height - 1), (height == receipt_height).then_some(height))) }
+            },
+        ));
+        (result.ok(), tried)
+    };
 
-        prev_block_tried += 1;
+    assert_eq!(find(98, 5), (Some(98), vec![100, 99, 98]));
+    // The receipt which never shows up within the lookback is an error, not a panic.
+    assert_eq!(find(0, 3), (None, vec![100, 99, 98]));
+    assert_eq!(find(98, 0), (None, vec![]));
+}
+
+#[test]
+fn test_evicted_delayed_receipt_is_found_in_history() {
+    let block_hash = |height: u64| CryptoHash::hash_bytes(&height.to_le_bytes());
+    let receipt_id = |index: u64| CryptoHash::hash_bytes(&[index as u8; 4]);
+    // The receipts 1 to 3 were converted in the blocks 1 to 3.
+    let mut cache = DelayedReceiptsCache::new(2);
+    for index in 1..=3 {
+        cache.insert(receipt_id(index), index);
     }
+    assert_eq!(cache.take(&receipt_id(3)), Some(3));
+    assert_eq!(cache.take(&receipt_id(1)), None);
+
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let receipt = rt.block_on(find_in_previous_blocks(
+        block_hash(3),
+        receipt_id(1),
+        DEFAULT_DELAYED_RECEIPT_LOOKBACK,
+        |hash| async move {
+            let height = (1..=3).find(|height| block_hash(*height) == hash).unwrap();
+            Ok((block_hash(height - 1), (height == 1).then_some(1)))
+        },
+    ));
+    assert_eq!(receipt.ok(), Some(1));
 }
 
 /// Function that tries to find specific local receipt by it's ID and returns it
@@ -351,6 +758,7 @@ async fn find_local_receipt_by_id_in_block(
                 vec![&indexer_transaction],
                 &block,
                 protocol_config_view.protocol_version,
+                1,
             )
             .await?;
 
@@ -360,6 +768,479 @@ async fn find_local_receipt_by_id_in_block(
     Ok(None)
 }
 
+/// Reason for the streamer to skip a block height without sending a message.
+#[derive(Debug, Clone, Copy)]
+enum SkipReason {
+    /// There is no block at this height (or it couldn't be fetched).
+    BlockMissing,
+    /// The block was fetched but `build_streamer_message` failed.
+    BuildFailed,
+}
+
+impl SkipReason {
+    fn as_str(&self) -> &'static str {
+        match self {
+            SkipReason::BlockMissing => "block_missing",
+            SkipReason::BuildFailed => "build_failed",
+        }
+    }
+}
+
+fn record_skipped_block(block_height: near_primitives::types::BlockHeight, reason: SkipReason) {
+    debug!(target: INDEXER, reason = reason.as_str(), "Skipping block #{}", block_height);
+    metrics::NUM_STREAMER_MESSAGES_SKIPPED.with_label_values(&[reason.as_str()]).inc();
+}
+
+#[test]
+fn test_record_skipped_block() {
+    let counter = |reason: SkipReason| {
+        metrics::NUM_STREAMER_MESSAGES_SKIPPED.with_label_values(&[reason.as_str()]).get()
+    };
+    let block_missing = counter(SkipReason::BlockMissing);
+    let build_failed = counter(SkipReason::BuildFailed);
+    record_skipped_block(1, SkipReason::BuildFailed);
+    assert_eq!(counter(SkipReason::BlockMissing), block_missing);
+    assert_eq!(counter(SkipReason::BuildFailed), build_failed + 1);
+}
+
+/// Returns the last block height to stream in the current iteration, which is
+/// the latest available height capped by `stop_height` and
+/// by `max_blocks` blocks from `start_block_height`.
+fn last_block_height_to_stream(
+    start_block_height: near_primitives::types::BlockHeight,
+    latest_block_height: near_primitives::types::BlockHeight,
+    stop_height: Option<near_primitives::types::BlockHeight>,
+    max_blocks: Option<u64>,
+) -> near_primitives::types::BlockHeight {
+    let end_block_height =
+        stop_height.map_or(latest_block_height, |stop_height| stop_height.min(latest_block_height));
+    match max_blocks {
+        Some(max_blocks) => end_block_height
+            .min(start_block_height.saturating_add(max_blocks.max(1)).saturating_sub(1)),
+        None => end_block_height,
+    }
+}
+
+/// Sets the lag of the streamer behind the latest block of the node, which is 0
+/// once the streamer has caught up.
+fn update_block_lag(
+    latest_block_height: near_primitives::types::BlockHeight,
+    current_block_height: near_primitives::types::BlockHeight,
+) {
+    let lag = latest_block_height.saturating_sub(current_block_height);
+    metrics::INDEXER_BLOCK_LAG.set(lag as i64);
+}
+
+/// Number of blocks between the latest block of the node and the block being
+/// streamed, as of the last update by the streamer.
+pub(crate) fn block_lag() -> near_primitives::types::BlockHeightDelta {
+    metrics::INDEXER_BLOCK_LAG.get().max(0) as near_primitives::types::BlockHeightDelta
+}
+
+#[test]
+fn test_block_lag() {
+    update_block_lag(100, 90);
+    assert_eq!(block_lag(), 10);
+    // The node got ahead while the block was being streamed.
+    update_block_lag(105, 91);
+    assert_eq!(block_lag(), 14);
+    update_block_lag(105, 105);
+    assert_eq!(block_lag(), 0);
+    update_block_lag(100, 101);
+    assert_eq!(block_lag(), 0);
+}
+
+/// Returns the heights to stream in the current iteration, or `None` once the
+/// streamer is past `stop_height` and has to stop.
+fn heights_to_stream(
+    start_block_height: near_primitives::types::BlockHeight,
+    latest_block_height: near_primitives::types::BlockHeight,
+    stop_height: Option<near_primitives::types::BlockHeight>,
+    max_blocks: Option<u64>,
+) -> Option<std::ops::RangeInclusive<near_primitives::types::BlockHeight>> {
+    if stop_height.is_some_and(|stop_height| start_block_height > stop_height) {
+        return None;
+    }
+    let end_block_height = last_block_height_to_stream(
+        start_block_height,
+        latest_block_height,
+        stop_height,
+        max_blocks,
+    );
+    Some(start_block_height..=end_block_height)
+}
+
+/// Height after which the streamer stops, the end of `SyncModeEnum::BlockRange`.
+fn stop_height(sync_mode: &crate::SyncModeEnum) -> Option<near_primitives::types::BlockHeight> {
+    match sync_mode {
+        crate::SyncModeEnum::BlockRange { end, .. } => Some(*end),
+        _ => None,
+    }
+}
+
+/// Returns the height to continue streaming from: the one after the last streamed
+/// block, or the starting height of the sync mode on the first iteration.
+fn get_start_syncing_block_height(
+    db: &DB,
+    last_synced_block_height: Option<near_primitives::types::BlockHeight>,
+    sync_mode: &crate::SyncModeEnum,
+    latest_block_height: near_primitives::types::BlockHeight,
+) -> near_primitives::types::BlockHeight {
+    if let Some(last_synced_block_height) = last_synced_block_height {
+        return last_synced_block_height + 1;
+    }
+    match sync_mode {
+        crate::SyncModeEnum::FromInterruption => {
+            read_last_synced_block_height(db).unwrap_or(latest_block_height)
+        }
+        crate::SyncModeEnum::LatestSynced => latest_block_height,
+        crate::SyncModeEnum::BlockHeight(height) => *height,
+        crate::SyncModeEnum::BlockRange { start, .. } => *start,
+    }
+}
+
+/// The busy loop of the streamer. Every `INTERVAL` it asks `fetch_latest_block_height`
+/// for the height of the latest block of the node, which is `None` while the node
+/// isn't ready to be streamed from, and passes the heights which weren't streamed
+/// yet to `stream_blocks` along with that latest height. `stream_blocks` breaks if
+/// the streamer has to stop. The loop ends once the streamer is past the end of
+/// `SyncModeEnum::BlockRange`.
+async fn run_streamer_loop<L, LFut, S, SFut>(
+    db: &DB,
+    sync_mode: &crate::SyncModeEnum,
+    max_blocks: Option<u64>,
+    mut fetch_latest_block_height: L,
+    mut stream_blocks: S,
+) where
+    L: FnMut() -> LFut,
+    LFut: std::future::Future<Output = Option<near_primitives::types::BlockHeight>>,
+    S: FnMut(
+        std::ops::RangeInclusive<near_primitives::types::BlockHeight>,
+        near_primitives::types::BlockHeight,
+    ) -> SFut,
+    SFut: std::future::Future<Output = ControlFlow<()>>,
+{
+    let stop_height = stop_height(sync_mode);
+    let mut last_synced_block_height: Option<near_primitives::types::BlockHeight> = None;
+    loop {
+        time::sleep(INTERVAL).await;
+        let Some(latest_block_height) = fetch_latest_block_height().await else {
+            continue;
+        };
+        let start_syncing_block_height = get_start_syncing_block_height(
+            db,
+            last_synced_block_height,
+            sync_mode,
+            latest_block_height,
+        );
+        let Some(heights) = heights_to_stream(
+            start_syncing_block_height,
+            latest_block_height,
+            stop_height,
+            max_blocks,
+        ) else {
+            info!(
+                target: INDEXER,
+                "Reached the stop height #{}, stopping the streamer",
+                stop_height.unwrap_or_default()
+            );
+            return;
+        };
+        let last_height = (!heights.is_empty()).then(|| *heights.end());
+        if stream_blocks(heights, latest_block_height).await.is_break() {
+            return;
+        }
+        last_synced_block_height = last_height.or(last_synced_block_height);
+    }
+}
+
+#[test]
+fn test_block_range_stops_after_end() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = DB::open_default(dir.path()).unwrap();
+    let sync_mode = crate::SyncModeEnum::BlockRange { start: 5, end: 12 };
+    let (blocks_sink, mut blocks) = mpsc::channel(100);
+    // The node is syncing at first, then catches up 3 blocks per iteration
+    // starting below the range.
+    let mut latest_block_height = 0;
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    rt.block_on(run_streamer_loop(
+        &db,
+        &sync_mode,
+        None,
+        || {
+            latest_block_height += 3;
+            let latest = (latest_block_height > 3).then_some(latest_block_height);
+            async move { latest }
+        },
+        |heights, _| {
+            for height in heights {
+                blocks_sink.try_send(height).unwrap();
+            }
+            async { ControlFlow::Continue(()) }
+        },
+    ));
+    drop(blocks_sink);
+
+    let mut streamed = vec![];
+    while let Some(height) = blocks.blocking_recv() {
+        streamed.push(height);
+    }
+    assert_eq!(streamed, (5..=12).collect::<Vec<_>>());
+}
+
+#[test]
+fn test_stop_height() {
+    let block_range = crate::SyncModeEnum::BlockRange { start: 5, end: 12 };
+    assert_eq!(stop_height(&crate::SyncModeEnum::LatestSynced), None);
+    assert_eq!(stop_height(&crate::SyncModeEnum::BlockHeight(5)), None);
+    assert_eq!(stop_height(&block_range), Some(12));
+}
+
+#[test]
+fn test_last_block_height_to_stream() {
+    assert_eq!(last_block_height_to_stream(10, 100, None, None), 100);
+    assert_eq!(last_block_height_to_stream(10, 100, Some(150), None), 100);
+    assert_eq!(last_block_height_to_stream(10, 100, Some(50), None), 50);
+}
+
+#[test]
+fn test_last_block_height_to_stream_max_blocks() {
+    // Catching up is split into iterations of at most `max_blocks` blocks.
+    assert_eq!(last_block_height_to_stream(10, 100, None, Some(5)), 14);
+    assert_eq!(last_block_height_to_stream(15, 100, None, Some(5)), 19);
+    assert_eq!(last_block_height_to_stream(98, 100, None, Some(5)), 100);
+    assert_eq!(last_block_height_to_stream(10, 100, Some(12), Some(5)), 12);
+    assert_eq!(last_block_height_to_stream(10, 100, None, Some(1)), 10);
+    // At least one block is streamed per iteration.
+    assert_eq!(last_block_height_to_stream(10, 100, None, Some(0)), 10);
+}
+
+/// Fetches blocks at the given heights with up to `concurrency` requests in flight,
+/// while yielding the results strictly in the order of the heights.
+fn fetch_blocks_in_order<'a, T, Fut>(
+    heights: impl Iterator<Item = near_primitives::types::BlockHeight> + 'a,
+    concurrency: usize,
+    fetch: impl Fn(near_primitives::types::BlockHeight) -> Fut + 'a,
+) -> impl Stream<Item = (near_primitives::types::BlockHeight, T)> + 'a
+where
+    Fut: std::future::Future<Output = T> + 'a,
+{
+    futures::stream::iter(heights)
+        .map(move |block_height| fetch(block_height).map(move |result| (block_height, result)))
+        .buffered(concurrency.max(1))
+}
+
+/// Builds the messages of the fetched blocks with up to `concurrency` of them in
+/// progress, while yielding the results strictly in the order of the blocks.
+/// Blocks which couldn't be fetched yield `None`.
+fn build_messages_in_order<'a, B, E, M, Fut>(
+    blocks: impl Stream<Item = (near_primitives::types::BlockHeight, Result<B, E>)> + 'a,
+    concurrency: usize,
+    build: impl Fn(B) -> Fut + 'a,
+) -> impl Stream<Item = (near_primitives::types::BlockHeight, Option<M>)> + 'a
+where
+    Fut: std::future::Future<Output = M> + 'a,
+{
+    blocks
+        .map(move |(block_height, block)| {
+            let message = block.ok().map(&build);
+            async move {
+                match message {
+                    Some(message) => (block_height, Some(message.await)),
+                    None => (block_height, None),
+                }
+            }
+        })
+        .buffered(concurrency.max(1))
+}
+
+#[test]
+fn test_build_messages_in_order() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let in_flight = AtomicUsize::new(0);
+    let max_in_flight = AtomicUsize::new(0);
+    // The block at height 5 is missing.
+    let blocks = futures::stream::iter(
+        (1..=10).map(|height| (height, if height == 5 { Err(()) } else { Ok(height) })),
+    );
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let built = rt.block_on(
+        build_messages_in_order(blocks, 3, |block_height: u64| {
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                // Later blocks are built faster, so they would overtake earlier ones.
+                time::sleep(Duration::from_millis(30 - 2 * block_height)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                format!("message {block_height}")
+            }
+        })
+        .collect::<Vec<_>>(),
+    );
+    let expected = (1..=10)
+        .map(|height| (height, (height != 5).then(|| format!("message {height}"))))
+        .collect::<Vec<_>>();
+    assert_eq!(built, expected);
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+}
+
+#[test]
+fn test_fetch_blocks_in_order() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    let in_flight = AtomicUsize::new(0);
+    let max_in_flight = AtomicUsize::new(0);
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let fetched = rt.block_on(
+        fetch_blocks_in_order(1..=20, 4, |block_height| {
+            let in_flight = &in_flight;
+            let max_in_flight = &max_in_flight;
+            async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                max_in_flight.fetch_max(current, Ordering::SeqCst);
+                // Later blocks are fetched faster, so they would overtake earlier ones.
+                time::sleep(Duration::from_millis(25 - block_height)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                block_height
+            }
+        })
+        .collect::<Vec<_>>(),
+    );
+    assert_eq!(fetched, (1..=20).map(|height| (height, height)).collect::<Vec<_>>());
+    assert_eq!(max_in_flight.load(Ordering::SeqCst), 4);
+}
+
+/// Reads the height of the last block processed by the streamer from the indexer db.
+/// A height stored in the legacy string format is migrated to the binary one. Returns
+/// `None` if there is no height or it can't be decoded, in which case the streamer
+/// starts from the latest block as if it had never run.
+fn read_last_synced_block_height(db: &DB) -> Option<near_primitives::types::BlockHeight> {
+    let read = |key: &[u8]| {
+        db.get(key).unwrap_or_else(|err| {
+            tracing::warn!(target: INDEXER, ?err, "Unable to read the last synced block height");
+            None
+        })
+    };
+    if let Some(value) = read(LAST_SYNCED_BLOCK_HEIGHT_KEY) {
+        let height = <[u8; 8]>::try_from(value.as_slice()).ok().map(u64::from_le_bytes);
+        if height.is_none() {
+            tracing::warn!(target: INDEXER, ?value, "Invalid last synced block height");
+        }
+        return height;
+    }
+    let value = read(LEGACY_LAST_SYNCED_BLOCK_HEIGHT_KEY)?;
+    let Some(height) = std::str::from_utf8(&value).ok().and_then(|value| value.parse().ok()) else {
+        tracing::warn!(target: INDEXER, ?value, "Invalid legacy last synced block height");
+        return None;
+    };
+    let mut batch = rocksdb::WriteBatch::default();
+    batch.put(LAST_SYNCED_BLOCK_HEIGHT_KEY, u64::to_le_bytes(height));
+    batch.delete(LEGACY_LAST_SYNCED_BLOCK_HEIGHT_KEY);
+    if let Err(err) = db.write(batch) {
+        tracing::warn!(target: INDEXER, ?err, "Unable to migrate the last synced block height");
+    }
+    Some(height)
+}
+
+#[test]
+fn test_read_last_synced_block_height() {
+    let dir = tempfile::tempdir().unwrap();
+    let db = DB::open_default(dir.path()).unwrap();
+    assert_eq!(read_last_synced_block_height(&db), None);
+
+    // The legacy string is read and migrated to the binary format.
+    db.put(LEGACY_LAST_SYNCED_BLOCK_HEIGHT_KEY, "47317863").unwrap();
+    assert_eq!(read_last_synced_block_height(&db), Some(47317863));
+    assert_eq!(db.get(LEGACY_LAST_SYNCED_BLOCK_HEIGHT_KEY).unwrap(), None);
+    assert_eq!(
+        db.get(LAST_SYNCED_BLOCK_HEIGHT_KEY).unwrap(),
+        Some(47317863u64.to_le_bytes().to_vec())
+    );
+    assert_eq!(read_last_synced_block_height(&db), Some(47317863));
+
+    db.put(LAST_SYNCED_BLOCK_HEIGHT_KEY, 100u64.to_le_bytes()).unwrap();
+    assert_eq!(read_last_synced_block_height(&db), Some(100));
+
+    // Corrupted values are ignored instead of panicking.
+    db.put(LAST_SYNCED_BLOCK_HEIGHT_KEY, [1, 2, 3]).unwrap();
+    assert_eq!(read_last_synced_block_height(&db), None);
+    db.delete(LAST_SYNCED_BLOCK_HEIGHT_KEY).unwrap();
+    db.put(LEGACY_LAST_SYNCED_BLOCK_HEIGHT_KEY, "4731xyz").unwrap();
+    assert_eq!(read_last_synced_block_height(&db), None);
+}
+
+/// Same as [`start`], but sends the messages serialized with
+/// `StreamerMessage::to_compact_bytes`, for listeners which pass them on to
+/// another process.
+pub(crate) async fn start_encoded(
+    view_client: Addr<near_client::ViewClientActor>,
+    client: Addr<near_client::ClientActor>,
+    indexer_config: IndexerConfig,
+    store_config: near_store::StoreConfig,
+    archive: bool,
+    encoded_blocks_sink: mpsc::Sender<Vec<u8>>,
+) {
+    let (blocks_sink, mut blocks) = mpsc::channel(1);
+    let encode = async move {
+        while let Some(streamer_message) = blocks.recv().await {
+            let bytes = match streamer_message.to_compact_bytes() {
+                Ok(bytes) => bytes,
+                Err(err) => {
+                    error!(target: INDEXER, "Unable to serialize StreamerMessage: {:?}", err);
+                    break;
+                }
+            };
+            if encoded_blocks_sink.send(bytes).await.is_err() {
+                break;
+            }
+        }
+        // Dropping `blocks` stops the streamer on its next message.
+    };
+    futures::join!(
+        start(view_client, client, indexer_config, store_config, archive, blocks_sink),
+        encode
+    );
+}
+
+/// Waits until the block with the given timestamp is at least `min_block_age` old.
+async fn wait_for_min_block_age(
+    clock: &near_time::Clock,
+    block_timestamp_nanosec: u64,
+    min_block_age: Duration,
+) {
+    let block_time =
+        near_time::Utc::from_unix_timestamp_nanos(block_timestamp_nanosec.into()).unwrap();
+    let min_block_age = near_time::Duration::try_from(min_block_age).unwrap();
+    let remaining = min_block_age - (clock.now_utc() - block_time);
+    if remaining.is_positive() {
+        clock.sleep(remaining).await;
+    }
+}
+
+#[test]
+fn test_wait_for_min_block_age() {
+    let clock = near_time::FakeClock::default();
+    let block_timestamp = clock.now_utc().unix_timestamp_nanos() as u64;
+    let mut wait = std::pin::pin!(wait_for_min_block_age(
+        &clock.clock(),
+        block_timestamp,
+        Duration::from_secs(10)
+    ));
+    assert!((&mut wait).now_or_never().is_none());
+    clock.advance(near_time::Duration::seconds(5));
+    assert!((&mut wait).now_or_never().is_none());
+    clock.advance(near_time::Duration::seconds(5));
+    assert!((&mut wait).now_or_never().is_some());
+
+    // Blocks which are old enough are not held at all.
+    let wait = wait_for_min_block_age(&clock.clock(), block_timestamp, Duration::from_secs(10));
+    assert!(wait.now_or_never().is_some());
+}
+
 /// Function that starts Streamer's busy loop. Every half a seconds it fetches the status
 /// compares to already fetched block height and in case it differs fetches new block of given height.
 ///
@@ -383,83 +1264,167 @@ pub(crate) async fn start(
         Err(err) => panic!("Unable to open indexer db: {:?}", err),
     };
 
-    let mut last_synced_block_height: Option<near_primitives::types::BlockHeight> = None;
+    if let Ok(mut cache) = RECENT_BLOCKS_CACHE.write() {
+        cache.resize(indexer_config.recent_blocks_cache_size);
+    }
+    if let Ok(mut cache) = DELAYED_LOCAL_RECEIPTS_CACHE.write() {
+        cache.resize(indexer_config.delayed_local_receipts_cache_size);
+    }
 
-    'main: loop {
-        time::sleep(INTERVAL).await;
+    let wal = indexer_config.write_ahead_log.then(|| {
+        WriteAheadLog::open(write_ahead_log_dir(&indexer_config.home_dir))
+            .unwrap_or_else(|err| panic!("Unable to open indexer write-ahead log: {:?}", err))
+    });
+    if let Some(wal) = &wal {
+        info!(target: INDEXER, "Replaying the unacknowledged messages of the write-ahead log");
+        match replay_write_ahead_log(wal, &blocks_sink).await {
+            Ok(true) => {}
+            Ok(false) => {
+                error!(
+                    target: INDEXER,
+                    "Unable to send StreamerMessage to listener, listener doesn't listen. terminating..."
+                );
+                return;
+            }
+            Err(err) => {
+                error!(
+                    target: INDEXER,
+                    "Unable to read the indexer write-ahead log: {:?}. terminating...",
+                    err
+                );
+                return;
+            }
+        }
+    }
+
+    let clock = near_time::Clock::real();
+    let view_client = &view_client;
+    let client = &client;
+    let indexer_config = &indexer_config;
+    let db = &db;
+    let wal = &wal;
+    let blocks_sink = &blocks_sink;
+    let clock = &clock;
+
+    let fetch_latest_block_height = move || async move {
         match indexer_config.await_for_node_synced {
             AwaitForNodeSyncedEnum::WaitForFullSync => {
-                let status = fetch_status(&client).await;
+                let status = fetch_status(client).await;
                 if let Ok(status) = status {
                     if status.sync_info.syncing {
-                        continue;
+                        // Nothing is streamed until the node is synced, and the
+                        // latest block of a syncing node says little about the lag.
+                        update_block_lag(0, 0);
+                        return None;
                     }
                 }
             }
             AwaitForNodeSyncedEnum::StreamWhileSyncing => {}
         };
+        fetch_latest_block(view_client).await.ok().map(|block| block.header.height)
+    };
 
-        let block = if let Ok(block) = fetch_latest_block(&view_client).await {
-            block
-        } else {
-            continue;
-        };
-
-        let latest_block_height = block.header.height;
-        let start_syncing_block_height = if let Some(last_synced_block_height) =
-            last_synced_block_height
-        {
-            last_synced_block_height + 1
-        } else {
-            match indexer_config.sync_mode {
-                crate::SyncModeEnum::FromInterruption => {
-                    match db.get(b"last_synced_block_height").unwrap() {
-                        Some(value) => String::from_utf8(value).unwrap().parse::<u64>().unwrap(),
-                        None => latest_block_height,
-                    }
-                }
-                crate::SyncModeEnum::LatestSynced => latest_block_height,
-                crate::SyncModeEnum::BlockHeight(height) => height,
-            }
-        };
-
-        debug!(
-            target: INDEXER,
-            "Streaming is about to start from block #{} and the latest block is #{}",
-            start_syncing_block_height,
-            latest_block_height
-        );
-        metrics::START_BLOCK_HEIGHT.set(start_syncing_block_height as i64);
-        metrics::LATEST_BLOCK_HEIGHT.set(latest_block_height as i64);
-        for block_height in start_syncing_block_height..=latest_block_height {
-            metrics::CURRENT_BLOCK_HEIGHT.set(block_height as i64);
-            if let Ok(block) = fetch_block_by_height(&view_client, block_height).await {
-                let response = build_streamer_message(&view_client, block).await;
-
-                match response {
-                    Ok(streamer_message) => {
-                        debug!(target: INDEXER, "Sending streamer message for block #{} to the listener", streamer_message.block.header.height);
-                        if blocks_sink.send(streamer_message).await.is_err() {
-                            error!(
+    let stream_blocks =
+        move |heights: std::ops::RangeInclusive<near_primitives::types::BlockHeight>,
+              latest_block_height: near_primitives::types::BlockHeight| async move {
+            let start_syncing_block_height = *heights.start();
+            debug!(
+                target: INDEXER,
+                "Streaming is about to start from block #{} and the latest block is #{}",
+                start_syncing_block_height,
+                latest_block_height
+            );
+            metrics::START_BLOCK_HEIGHT.set(start_syncing_block_height as i64);
+            metrics::LATEST_BLOCK_HEIGHT.set(latest_block_height as i64);
+            update_block_lag(latest_block_height, start_syncing_block_height.saturating_sub(1));
+            // Blocks ahead of the one being processed are fetched and built concurrently,
+            // but are still processed and sent to the listener one by one in order.
+            let blocks = fetch_blocks_in_order(
+                heights,
+                indexer_config.max_concurrent_block_fetches,
+                |block_height| fetch_block_by_height(view_client, block_height),
+            );
+            let mut responses = std::pin::pin!(build_messages_in_order(
+                blocks,
+                indexer_config.max_concurrent_message_builds,
+                |block| {
+                    build_streamer_message(
+                        view_client,
+                        block,
+                        indexer_config,
+                        indexer_config.fetch_untracked_shards.then_some(client),
+                    )
+                },
+            ));
+            while let Some((block_height, response)) = responses.next().await {
+                metrics::CURRENT_BLOCK_HEIGHT.set(block_height as i64);
+                update_block_lag(latest_block_height, block_height);
+                if let Some(response) = response {
+                    match response {
+                        Ok(streamer_message) => {
+                            if let Ok(mut cache) = RECENT_BLOCKS_CACHE.write() {
+                                let header = &streamer_message.block.header;
+                                cache.insert(
+                                    header.hash,
+                                    header.height,
+                                    streamer_message.block.clone(),
+                                );
+                            }
+                            if let Some(min_block_age) = indexer_config.min_block_age {
+                                wait_for_min_block_age(
+                                    clock,
+                                    streamer_message.block.header.timestamp_nanosec,
+                                    min_block_age,
+                                )
+                                .await;
+                            }
+                            if let Some(wal) = wal {
+                                if let Err(err) = wal.append(block_height, &streamer_message).await
+                                {
+                                    error!(
+                                        target: INDEXER,
+                                        "Unable to write StreamerMessage for block #{} to the write-ahead log: {:?}. terminating...",
+                                        block_height,
+                                        err
+                                    );
+                                    return ControlFlow::Break(());
+                                }
+                            }
+                            debug!(target: INDEXER, "Sending streamer message for block #{} to the listener", streamer_message.block.header.height);
+                            if blocks_sink.send(streamer_message).await.is_err() {
+                                error!(
+                                    target: INDEXER,
+                                    "Unable to send StreamerMessage to listener, listener doesn't listen. terminating..."
+                                );
+                                return ControlFlow::Break(());
+                            } else {
+                                metrics::NUM_STREAMER_MESSAGES_SENT.inc();
+                            }
+                        }
+                        Err(err) => {
+                            tracing::warn!(
                                 target: INDEXER,
-                                "Unable to send StreamerMessage to listener, listener doesn't listen. terminating..."
+                                "Failed to build streamer message for block #{}: {:?}",
+                                block_height,
+                                err
                             );
-                            break 'main;
-                        } else {
-                            metrics::NUM_STREAMER_MESSAGES_SENT.inc();
+                            record_skipped_block(block_height, SkipReason::BuildFailed);
                         }
                     }
-                    Err(err) => {
-                        debug!(
-                            target: INDEXER,
-                            "Missing data, skipping block #{}...", block_height
-                        );
-                        debug!(target: INDEXER, "{:#?}", err);
-                    }
+                } else {
+                    record_skipped_block(block_height, SkipReason::BlockMissing);
                 }
+                db.put(LAST_SYNCED_BLOCK_HEIGHT_KEY, block_height.to_le_bytes()).unwrap();
             }
-            db.put(b"last_synced_block_height", &block_height.to_string()).unwrap();
-            last_synced_block_height = Some(block_height);
-        }
-    }
+            ControlFlow::Continue(())
+        };
+
+    run_streamer_loop(
+        db,
+        &indexer_config.sync_mode,
+        indexer_config.max_catchup_blocks_per_iteration,
+        fetch_latest_block_height,
+        stream_blocks,
+    )
+    .await;
 }
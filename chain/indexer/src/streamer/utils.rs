@@ -1,7 +1,12 @@
+use std::sync::Arc;
+
 use actix::Addr;
+use futures::StreamExt;
 
 use near_indexer_primitives::IndexerTransactionWithOutcome;
 use near_parameters::RuntimeConfig;
+use near_primitives::hash::CryptoHash;
+use near_primitives::types::Balance;
 use near_primitives::version::ProtocolVersion;
 use near_primitives::views;
 use node_runtime::config::tx_cost;
@@ -15,6 +20,7 @@ pub(crate) async fn convert_transactions_sir_into_local_receipts(
     txs: Vec<&IndexerTransactionWithOutcome>,
     block: &views::BlockView,
     protocol_version: ProtocolVersion,
+    concurrency: usize,
 ) -> Result<Vec<views::ReceiptView>, FailedToFetchData> {
     if txs.is_empty() {
         return Ok(vec![]);
@@ -22,53 +28,167 @@ pub(crate) async fn convert_transactions_sir_into_local_receipts(
     let prev_block = fetch_block(&client, block.header.prev_hash).await?;
     let prev_block_gas_price = prev_block.header.gas_price;
 
-    let local_receipts: Vec<views::ReceiptView> =
-        txs.into_iter()
+    Ok(convert_transactions_into_local_receipts(
+        runtime_config,
+        txs,
+        block.header.hash,
+        prev_block_gas_price,
+        protocol_version,
+        concurrency,
+    )
+    .await)
+}
+
+/// Converts the given self-call transactions into the local receipts they
+/// produce, with up to `concurrency` conversions running in parallel. The
+/// receipts are returned in the order of the transactions.
+async fn convert_transactions_into_local_receipts(
+    runtime_config: &RuntimeConfig,
+    txs: Vec<&IndexerTransactionWithOutcome>,
+    block_hash: CryptoHash,
+    prev_block_gas_price: Balance,
+    protocol_version: ProtocolVersion,
+    concurrency: usize,
+) -> Vec<views::ReceiptView> {
+    if concurrency <= 1 {
+        return txs
+            .into_iter()
             .map(|tx| {
-                let cost = tx_cost(
-                    &runtime_config,
-                    &near_primitives::transaction::Transaction::V0(
-                        near_primitives::transaction::TransactionV0 {
-                            signer_id: tx.transaction.signer_id.clone(),
-                            public_key: tx.transaction.public_key.clone(),
-                            nonce: tx.transaction.nonce,
-                            receiver_id: tx.transaction.receiver_id.clone(),
-                            block_hash: block.header.hash,
-                            actions: tx
-                                .transaction
-                                .actions
-                                .clone()
-                                .into_iter()
-                                .map(|action| {
-                                    near_primitives::transaction::Action::try_from(action).unwrap()
-                                })
-                                .collect(),
-                        },
-                    ),
+                convert_transaction_into_local_receipt(
+                    runtime_config,
+                    tx,
+                    block_hash,
                     prev_block_gas_price,
-                    true,
                     protocol_version,
                 )
-                .expect("TransactionCost returned IntegerOverflowError");
-                views::ReceiptView {
-                    predecessor_id: tx.transaction.signer_id.clone(),
-                    receiver_id: tx.transaction.receiver_id.clone(),
-                    receipt_id: *tx.outcome.execution_outcome.outcome.receipt_ids.first().expect(
-                        "The transaction ExecutionOutcome should have one receipt id in vec",
-                    ),
-                    receipt: views::ReceiptEnumView::Action {
-                        signer_id: tx.transaction.signer_id.clone(),
-                        signer_public_key: tx.transaction.public_key.clone(),
-                        gas_price: cost.receipt_gas_price,
-                        output_data_receivers: vec![],
-                        input_data_ids: vec![],
-                        actions: tx.transaction.actions.clone(),
-                        is_promise_yield: false,
-                    },
-                    priority: 0,
-                }
             })
             .collect();
+    }
+    let runtime_config = Arc::new(runtime_config.clone());
+    futures::stream::iter(txs.into_iter().cloned())
+        .map(|tx| {
+            let runtime_config = runtime_config.clone();
+            tokio::task::spawn_blocking(move || {
+                convert_transaction_into_local_receipt(
+                    &runtime_config,
+                    &tx,
+                    block_hash,
+                    prev_block_gas_price,
+                    protocol_version,
+                )
+            })
+        })
+        .buffered(concurrency)
+        .map(|receipt| receipt.expect("Local receipt conversion task panicked"))
+        .collect()
+        .await
+}
 
-    Ok(local_receipts)
+fn convert_transaction_into_local_receipt(
+    runtime_config: &RuntimeConfig,
+    tx: &IndexerTransactionWithOutcome,
+    block_hash: CryptoHash,
+    prev_block_gas_price: Balance,
+    protocol_version: ProtocolVersion,
+) -> views::ReceiptView {
+    let cost = tx_cost(
+        &runtime_config,
+        &near_primitives::transaction::Transaction::V0(
+            near_primitives::transaction::TransactionV0 {
+                signer_id: tx.transaction.signer_id.clone(),
+                public_key: tx.transaction.public_key.clone(),
+                nonce: tx.transaction.nonce,
+                receiver_id: tx.transaction.receiver_id.clone(),
+                block_hash,
+                actions: tx
+                    .transaction
+                    .actions
+                    .clone()
+                    .into_iter()
+                    .map(|action| near_primitives::transaction::Action::try_from(action).unwrap())
+                    .collect(),
+            },
+        ),
+        prev_block_gas_price,
+        true,
+        protocol_version,
+    )
+    .expect("TransactionCost returned IntegerOverflowError");
+    views::ReceiptView {
+        predecessor_id: tx.transaction.signer_id.clone(),
+        receiver_id: tx.transaction.receiver_id.clone(),
+        receipt_id: *tx
+            .outcome
+            .execution_outcome
+            .outcome
+            .receipt_ids
+            .first()
+            .expect("The transaction ExecutionOutcome should have one receipt id in vec"),
+        receipt: views::ReceiptEnumView::Action {
+            signer_id: tx.transaction.signer_id.clone(),
+            signer_public_key: tx.transaction.public_key.clone(),
+            gas_price: cost.receipt_gas_price,
+            output_data_receivers: vec![],
+            input_data_ids: vec![],
+            actions: tx.transaction.actions.clone(),
+            is_promise_yield: false,
+        },
+        priority: 0,
+    }
+}
+
+#[test]
+fn test_concurrent_local_receipt_conversion() {
+    use near_crypto::{InMemorySigner, KeyType};
+    use near_indexer_primitives::IndexerExecutionOutcomeWithOptionalReceipt;
+    use near_primitives::hash::hash;
+    use near_primitives::transaction::{ExecutionOutcomeWithIdAndProof, SignedTransaction};
+    use near_primitives::version::PROTOCOL_VERSION;
+
+    let account_id: near_primitives::types::AccountId = "test.near".parse().unwrap();
+    let signer = InMemorySigner::from_seed(account_id.clone(), KeyType::ED25519, "test.near");
+    let txs = (0..20u64)
+        .map(|nonce| {
+            let transaction = SignedTransaction::send_money(
+                nonce,
+                account_id.clone(),
+                account_id.clone(),
+                &signer.clone().into(),
+                nonce as Balance,
+                CryptoHash::default(),
+            );
+            let mut outcome = ExecutionOutcomeWithIdAndProof::default();
+            outcome.outcome_with_id.id = transaction.get_hash();
+            outcome.outcome_with_id.outcome.receipt_ids = vec![hash(&nonce.to_le_bytes())];
+            IndexerTransactionWithOutcome {
+                transaction: transaction.into(),
+                outcome: IndexerExecutionOutcomeWithOptionalReceipt {
+                    execution_outcome: outcome.into(),
+                    receipt: None,
+                },
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let runtime_config = RuntimeConfig::test();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    let convert = |concurrency| {
+        rt.block_on(convert_transactions_into_local_receipts(
+            &runtime_config,
+            txs.iter().collect(),
+            CryptoHash::default(),
+            100,
+            PROTOCOL_VERSION,
+            concurrency,
+        ))
+    };
+    let sequential = convert(1);
+    assert_eq!(sequential.len(), txs.len());
+    for (receipt, tx) in sequential.iter().zip(txs.iter()) {
+        assert_eq!(
+            &receipt.receipt_id,
+            tx.outcome.execution_outcome.outcome.receipt_ids.first().unwrap()
+        );
+    }
+    assert_eq!(convert(4), sequential);
 }
@@ -0,0 +1,138 @@
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::path::{Path, PathBuf};
+
+use near_indexer_primitives::StreamerMessage;
+use near_primitives::types::BlockHeight;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+const ENTRY_EXTENSION: &str = "json";
+
+/// Write-ahead log of the messages sent to the listener of the streamer.
+///
+/// Every message is stored in its own file named after the block height before
+/// it is sent, and stays there until the listener acknowledges that it has
+/// persisted the message. The messages left unacknowledged by a crash are
+/// replayed to the listener on restart, which gives at-least-once delivery.
+#[derive(Debug, Clone)]
+pub struct WriteAheadLog<T = StreamerMessage> {
+    dir: PathBuf,
+    _message: PhantomData<T>,
+}
+
+impl<T: Serialize + DeserializeOwned> WriteAheadLog<T> {
+    /// Opens the log stored in `dir`, creating the directory if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> io::Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir, _message: PhantomData })
+    }
+
+    /// Durably stores the message of the given height. The entry is written
+    /// to a temporary file first, so that a crash never leaves a partial one.
+    /// The writes and the fsync run on the blocking thread pool, so that they
+    /// don't stall the runtime of the streamer.
+    pub(crate) async fn append(&self, height: BlockHeight, message: &T) -> io::Result<()> {
+        let data = serde_json::to_vec(message)?;
+        let path = self.entry_path(height);
+        tokio::task::spawn_blocking(move || {
+            let tmp_path = path.with_extension("tmp");
+            let mut file = std::fs::File::create(&tmp_path)?;
+            file.write_all(&data)?;
+            file.sync_all()?;
+            std::fs::rename(tmp_path, path)
+        })
+        .await
+        .map_err(io::Error::other)?
+    }
+
+    /// Marks all the messages up to and including `height` as persisted by
+    /// the listener and prunes them from the log.
+    pub fn acknowledge(&self, height: BlockHeight) -> io::Result<()> {
+        for entry_height in self.heights()? {
+            if entry_height <= height {
+                std::fs::remove_file(self.entry_path(entry_height))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the messages which haven't been acknowledged yet, in the order
+    /// of their heights.
+    pub fn unacknowledged(&self) -> io::Result<Vec<(BlockHeight, T)>> {
+        self.heights()?
+            .into_iter()
+            .map(|height| {
+                let file = std::fs::File::open(self.entry_path(height))?;
+                Ok((height, serde_json::from_reader(io::BufReader::new(file))?))
+            })
+            .collect()
+    }
+
+    fn heights(&self) -> io::Result<Vec<BlockHeight>> {
+        let mut heights = vec![];
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|ext| ext.to_str()) != Some(ENTRY_EXTENSION) {
+                continue;
+            }
+            if let Some(height) = path.file_stem().and_then(|stem| stem.to_str()?.parse().ok()) {
+                heights.push(height);
+            }
+        }
+        heights.sort_unstable();
+        Ok(heights)
+    }
+
+    fn entry_path(&self, height: BlockHeight) -> PathBuf {
+        self.dir.join(format!("{height}.{ENTRY_EXTENSION}"))
+    }
+}
+
+/// Directory of the write-ahead log of the indexer with the given home dir.
+pub(crate) fn write_ahead_log_dir(home_dir: &Path) -> PathBuf {
+    home_dir.join("indexer_wal")
+}
+
+/// Sends all the unacknowledged messages of the log to the listener again.
+/// Returns false if the listener is gone.
+pub(crate) async fn replay_write_ahead_log<T: Serialize + DeserializeOwned>(
+    wal: &WriteAheadLog<T>,
+    blocks_sink: &mpsc::Sender<T>,
+) -> io::Result<bool> {
+    for (_, message) in wal.unacknowledged()? {
+        if blocks_sink.send(message).await.is_err() {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+#[test]
+fn test_write_ahead_log_replays_unacknowledged_messages() {
+    let dir = tempfile::tempdir().unwrap();
+    let wal = WriteAheadLog::<String>::open(dir.path()).unwrap();
+    let rt = tokio::runtime::Runtime::new().unwrap();
+    for height in 1..=3 {
+        rt.block_on(wal.append(height, &format!("message {height}"))).unwrap();
+    }
+    // The listener persisted the first message, then crashed before consuming the rest.
+    wal.acknowledge(1).unwrap();
+    drop(wal);
+
+    let wal = WriteAheadLog::<String>::open(dir.path()).unwrap();
+    assert_eq!(
+        wal.unacknowledged().unwrap(),
+        vec![(2, "message 2".to_string()), (3, "message 3".to_string())]
+    );
+    let (sender, mut receiver) = mpsc::channel(10);
+    assert!(rt.block_on(replay_write_ahead_log(&wal, &sender)).unwrap());
+    assert_eq!(receiver.try_recv().unwrap(), "message 2");
+    assert_eq!(receiver.try_recv().unwrap(), "message 3");
+    assert!(receiver.try_recv().is_err());
+
+    wal.acknowledge(3).unwrap();
+    assert!(wal.unacknowledged().unwrap().is_empty());
+}
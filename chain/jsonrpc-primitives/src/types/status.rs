@@ -4,9 +4,10 @@ use near_client_primitives::debug::{
 };
 #[cfg(feature = "debug_types")]
 use near_primitives::views::{
-    CatchupStatusView, ChainProcessingInfo, NetworkGraphView, NetworkRoutesView, PeerStoreView,
-    RecentOutboundConnectionsView, RequestedStatePartsView, SnapshotHostsView,
-    SplitStorageInfoView, SyncStatusView,
+    AnnounceAccountBroadcastsView, AnnounceAccountsView, CatchupStatusView, ChainProcessingInfo,
+    NetworkGraphView, NetworkRoutesView, PeerStoreView, RecentOutboundConnectionsView,
+    RequestedStatePartsView, SnapshotHostsView, SplitStorageInfoView, StateSyncTaskView,
+    SyncStatusView,
 };
 
 #[derive(Debug, serde::Serialize, serde::Deserialize)]
@@ -31,10 +32,14 @@ pub enum DebugStatusResponse {
     ChainProcessingStatus(ChainProcessingInfo),
     // The state parts already requested.
     RequestedStateParts(Vec<RequestedStatePartsView>),
+    // The unfinished state sync downloads.
+    StateSyncTasks(Vec<StateSyncTaskView>),
     NetworkGraph(NetworkGraphView),
     RecentOutboundConnections(RecentOutboundConnectionsView),
     Routes(NetworkRoutesView),
     SnapshotHosts(SnapshotHostsView),
+    AnnounceAccounts(AnnounceAccountsView),
+    AnnounceAccountBroadcasts(AnnounceAccountBroadcastsView),
     SplitStoreStatus(SplitStorageInfoView),
 }
 
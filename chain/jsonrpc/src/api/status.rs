@@ -33,6 +33,9 @@ impl RpcFrom<near_client_primitives::debug::DebugStatusResponse>
             near_client_primitives::debug::DebugStatusResponse::RequestedStateParts(x) => {
                 near_jsonrpc_primitives::types::status::DebugStatusResponse::RequestedStateParts(x)
             }
+            near_client_primitives::debug::DebugStatusResponse::StateSyncTasks(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::StateSyncTasks(x)
+            }
             near_client_primitives::debug::DebugStatusResponse::TrackedShards(x) => {
                 near_jsonrpc_primitives::types::status::DebugStatusResponse::TrackedShards(x)
             }
@@ -74,6 +77,12 @@ impl RpcFrom<near_network::debug::DebugStatus>
             near_network::debug::DebugStatus::SnapshotHosts(x) => {
                 near_jsonrpc_primitives::types::status::DebugStatusResponse::SnapshotHosts(x)
             }
+            near_network::debug::DebugStatus::AnnounceAccounts(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::AnnounceAccounts(x)
+            }
+            near_network::debug::DebugStatus::AnnounceAccountBroadcasts(x) => {
+                near_jsonrpc_primitives::types::status::DebugStatusResponse::AnnounceAccountBroadcasts(x)
+            }
         }
     }
 }
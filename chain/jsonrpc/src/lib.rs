@@ -801,6 +801,9 @@ impl JsonRpcHandler {
                     "/debug/api/requested_state_parts" => {
                         self.client_send(DebugStatus::RequestedStateParts).await?.rpc_into()
                     }
+                    "/debug/api/state_sync_tasks" => {
+                        self.client_send(DebugStatus::StateSyncTasks).await?.rpc_into()
+                    }
                     "/debug/api/peer_store" => self
                         .peer_manager_send(near_network::debug::GetDebugStatus::PeerStore)
                         .await?
@@ -823,6 +826,16 @@ impl JsonRpcHandler {
                         .peer_manager_send(near_network::debug::GetDebugStatus::SnapshotHosts)
                         .await?
                         .rpc_into(),
+                    "/debug/api/announce_accounts" => self
+                        .peer_manager_send(near_network::debug::GetDebugStatus::AnnounceAccounts)
+                        .await?
+                        .rpc_into(),
+                    "/debug/api/announce_account_broadcasts" => self
+                        .peer_manager_send(
+                            near_network::debug::GetDebugStatus::AnnounceAccountBroadcasts,
+                        )
+                        .await?
+                        .rpc_into(),
                     "/debug/api/split_store_info" => {
                         let split_storage_info: RpcSplitStorageInfoResponse = self
                             .split_storage_info(RpcSplitStorageInfoRequest {})
@@ -1,7 +1,8 @@
 use crate::store;
 use lru::LruCache;
 use near_primitives::network::{AnnounceAccount, PeerId};
-use near_primitives::types::AccountId;
+use near_primitives::types::{AccountId, EpochId};
+use near_primitives::views::{AnnounceAccountView, AnnounceAccountsView};
 use parking_lot::Mutex;
 use std::collections::HashMap;
 use std::num::NonZeroUsize;
@@ -89,6 +90,14 @@ impl AnnounceAccountCache {
         res
     }
 
+    /// Checks whether `add_accounts` would broadcast the given announcement,
+    /// i.e. whether we haven't broadcasted an announcement of the same account
+    /// for the same epoch yet. Doesn't modify the cache.
+    pub(crate) fn is_new_announcement(&self, announcement: &AnnounceAccount) -> bool {
+        self.0.lock().account_peers_broadcasted.peek(&announcement.account_id).map(|x| &x.epoch_id)
+            != Some(&announcement.epoch_id)
+    }
+
     /// Find peer that owns this AccountId.
     pub(crate) fn get_account_owner(&self, account_id: &AccountId) -> Option<PeerId> {
         self.0.lock().get_announce(account_id).map(|announce_account| announce_account.peer_id)
@@ -105,6 +114,38 @@ impl AnnounceAccountCache {
         self.0.lock().account_peers.iter().map(|(_, v)| v.clone()).collect()
     }
 
+    /// Returns all announcements currently in the cache, sorted by account id,
+    /// so that snapshots taken on different nodes can be compared directly.
+    /// The size is bounded by the cache size.
+    pub(crate) fn export_snapshot(&self) -> Vec<(AccountId, PeerId, EpochId)> {
+        let mut snapshot: Vec<_> = self
+            .0
+            .lock()
+            .account_peers
+            .iter()
+            .map(|(account_id, announce)| {
+                (account_id.clone(), announce.peer_id.clone(), announce.epoch_id)
+            })
+            .collect();
+        snapshot.sort_by(|a, b| a.0.cmp(&b.0));
+        snapshot
+    }
+
+    /// Presents the output of `export_snapshot` for the debug API.
+    pub(crate) fn snapshot_view(&self) -> AnnounceAccountsView {
+        AnnounceAccountsView {
+            announce_accounts: self
+                .export_snapshot()
+                .into_iter()
+                .map(|(account_id, peer_id, epoch_id)| AnnounceAccountView {
+                    account_id,
+                    peer_id,
+                    epoch_id,
+                })
+                .collect(),
+        }
+    }
+
     /// Get AnnounceAccount for the given AccountIds, that we already broadcasted.
     pub(crate) fn get_broadcasted_announcements<'a>(
         &'a self,
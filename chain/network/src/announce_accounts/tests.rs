@@ -106,3 +106,57 @@ fn load_from_disk() {
     // Second cache should contain account loaded from disk
     assert_eq!(announcements_cache1.get_announcements().len(), 1);
 }
+
+#[test]
+fn export_snapshot() {
+    let store = crate::store::Store::from(near_store::db::TestDB::new());
+    let announcements_cache = AnnounceAccountCache::new(store);
+
+    let peer_id0 = random_peer_id();
+    let peer_id1 = random_peer_id();
+    let epoch_id0 = random_epoch_id();
+    let epoch_id1 = random_epoch_id();
+
+    // Added in reverse order to check that the snapshot is sorted.
+    announcements_cache.add_accounts(vec![
+        AnnounceAccount {
+            account_id: "near1".parse().unwrap(),
+            peer_id: peer_id1.clone(),
+            epoch_id: epoch_id1,
+            signature: Signature::default(),
+        },
+        AnnounceAccount {
+            account_id: "near0".parse().unwrap(),
+            peer_id: peer_id0.clone(),
+            epoch_id: epoch_id0,
+            signature: Signature::default(),
+        },
+    ]);
+    assert_eq!(
+        announcements_cache.export_snapshot(),
+        vec![
+            ("near0".parse().unwrap(), peer_id0, epoch_id0),
+            ("near1".parse().unwrap(), peer_id1, epoch_id1),
+        ]
+    );
+}
+
+#[test]
+fn is_new_announcement() {
+    let store = crate::store::Store::from(near_store::db::TestDB::new());
+    let announcements_cache = AnnounceAccountCache::new(store);
+
+    let announce0 = AnnounceAccount {
+        account_id: "near0".parse().unwrap(),
+        peer_id: random_peer_id(),
+        epoch_id: random_epoch_id(),
+        signature: Signature::default(),
+    };
+    // Same account, next epoch.
+    let announce1 = AnnounceAccount { epoch_id: random_epoch_id(), ..announce0.clone() };
+
+    assert!(announcements_cache.is_new_announcement(&announce0));
+    announcements_cache.add_accounts(vec![announce0.clone()]);
+    assert!(!announcements_cache.is_new_announcement(&announce0));
+    assert!(announcements_cache.is_new_announcement(&announce1));
+}
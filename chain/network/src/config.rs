@@ -93,6 +93,9 @@ pub struct Tier1 {
     /// Interval between broacasts of the list of validator's proxies.
     /// Before the broadcast, validator tries to establish all the missing connections to proxies.
     pub advertise_proxies_interval: time::Duration,
+    /// How long the resolved addresses of the STUN servers are used before
+    /// they are resolved again.
+    pub stun_lookup_cache_ttl: time::Duration,
     /// Support for gradual TIER1 feature rollout:
     /// - establishing connection to node's own proxies is always enabled (it is a part of peer
     ///   discovery mechanism). Note that unless the proxy has enable_inbound set, establishing
@@ -369,6 +372,7 @@ impl NetworkConfig {
                 connect_interval: cfg.experimental.tier1_connect_interval.try_into()?,
                 new_connections_per_attempt: cfg.experimental.tier1_new_connections_per_attempt,
                 advertise_proxies_interval: time::Duration::minutes(15),
+                stun_lookup_cache_ttl: cfg.experimental.stun_lookup_cache_ttl,
                 enable_inbound: cfg.experimental.tier1_enable_inbound,
                 enable_outbound: cfg.experimental.tier1_enable_outbound,
             }),
@@ -453,6 +457,7 @@ impl NetworkConfig {
                 connect_interval: time::Duration::hours(1000),
                 new_connections_per_attempt: 10000,
                 advertise_proxies_interval: time::Duration::hours(1000),
+                stun_lookup_cache_ttl: time::Duration::hours(1),
                 enable_inbound: true,
                 enable_outbound: true,
             }),
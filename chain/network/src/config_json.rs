@@ -185,8 +185,9 @@ pub struct Config {
     /// Less recommended setup (requires exactly one public dynamic/ephemeral or static IP):
     /// If the list is empty, the validator node will query trusted_stun_servers to determine its own IP.
     /// Only if the answer from the STUN servers is unambiguous (at least 1 server responds and
-    /// all received responses provide the same IP), the IP (together with the port deduced from
-    /// the addr field in this config) will be signed and broadcasted.
+    /// a strict majority of the received responses provide the same IP), the IP (together with
+    /// the port deduced from the addr field in this config) will be signed and broadcasted.
+    /// Servers which don't respond are not counted.
     ///
     /// Discouraged setup (might be removed in the future)
     /// If the list is empty and STUN servers' response is ambiguous, the peers which connect to
@@ -203,10 +204,9 @@ pub struct Config {
     /// Used only if this node is a validator and public_addrs is empty (see
     /// description of public_addrs field).  Format `<domain/ip>:<port>`, for
     /// example `stun.l.google.com:19302`. The STUN servers are queried periodically in parallel.
-    /// We do not expect all the servers listed to be up all the time, but all the
-    /// responses are expected to be consistent - if different servers return differn IPs, then
-    /// the response set would be considered ambiguous and the node won't advertise any proxy in
-    /// such a case.
+    /// We do not expect all the servers listed to be up all the time, but a majority of the
+    /// servers is expected to return the same IP - otherwise the response set would be
+    /// considered ambiguous and the node won't advertise any proxy in such a case.
     #[serde(default = "default_trusted_stun_servers")]
     pub trusted_stun_servers: Vec<stun::ServerAddr>,
     // Experimental part of the JSON config. Regular users/validators should not have to set any values there.
@@ -231,6 +231,10 @@ fn default_tier1_new_connections_per_attempt() -> u64 {
     50
 }
 
+fn default_stun_lookup_cache_ttl() -> Duration {
+    Duration::hours(1)
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
 pub struct ExperimentalConfig {
     // If true - don't allow any inbound connections.
@@ -265,6 +269,11 @@ pub struct ExperimentalConfig {
     #[serde(default = "default_tier1_new_connections_per_attempt")]
     pub tier1_new_connections_per_attempt: u64,
 
+    /// See `near_network::config::Tier1::stun_lookup_cache_ttl`.
+    #[serde(default = "default_stun_lookup_cache_ttl")]
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub stun_lookup_cache_ttl: Duration,
+
     /// See `NetworkConfig`.
     /// Fields set here will override the NetworkConfig fields.
     #[serde(default)]
@@ -299,6 +308,7 @@ impl Default for ExperimentalConfig {
             tier1_enable_outbound: default_tier1_enable_outbound(),
             tier1_connect_interval: default_tier1_connect_interval(),
             tier1_new_connections_per_attempt: default_tier1_new_connections_per_attempt(),
+            stun_lookup_cache_ttl: default_stun_lookup_cache_ttl(),
             network_config_overrides: Default::default(),
         }
     }
@@ -1,7 +1,7 @@
 use ::actix::Message;
 use near_primitives::views::{
-    NetworkGraphView, NetworkRoutesView, PeerStoreView, RecentOutboundConnectionsView,
-    SnapshotHostsView,
+    AnnounceAccountBroadcastsView, AnnounceAccountsView, NetworkGraphView, NetworkRoutesView,
+    PeerStoreView, RecentOutboundConnectionsView, SnapshotHostsView,
 };
 
 // Different debug requests that can be sent by HTML pages, via GET.
@@ -11,6 +11,8 @@ pub enum GetDebugStatus {
     RecentOutboundConnections,
     Routes,
     SnapshotHosts,
+    AnnounceAccounts,
+    AnnounceAccountBroadcasts,
 }
 
 #[derive(actix::MessageResponse, Debug)]
@@ -20,6 +22,8 @@ pub enum DebugStatus {
     RecentOutboundConnections(RecentOutboundConnectionsView),
     Routes(NetworkRoutesView),
     SnapshotHosts(SnapshotHostsView),
+    AnnounceAccounts(AnnounceAccountsView),
+    AnnounceAccountBroadcasts(AnnounceAccountBroadcastsView),
 }
 
 impl Message for GetDebugStatus {
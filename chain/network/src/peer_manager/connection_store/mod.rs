@@ -27,12 +27,7 @@ struct Inner {
 impl Inner {
     /// Returns whether the store contains an outbound connection to the given peer
     fn contains_outbound(&self, peer_id: &PeerId) -> bool {
-        for stored_info in &self.outbound {
-            if stored_info.peer_info.id == *peer_id {
-                return true;
-            }
-        }
-        return false;
+        self.store.get_recent_outbound_connection(peer_id).is_some()
     }
 
     /// If there is an outbound connection to the given peer in storage, removes it
@@ -73,7 +68,8 @@ impl Inner {
 pub(crate) struct ConnectionStore(ArcMutex<Inner>);
 
 impl ConnectionStore {
-    pub fn new(store: store::Store) -> anyhow::Result<Self> {
+    pub fn new(mut store: store::Store) -> anyhow::Result<Self> {
+        store.index_recent_outbound_connections()?;
         let outbound = store.get_recent_outbound_connections();
         let inner = Inner { store, outbound };
         Ok(ConnectionStore(ArcMutex::new(inner)))
@@ -72,6 +72,37 @@ fn test_overwrite_stored_connection() {
     assert_eq!(connection_store.get_recent_outbound_connections(), vec![conn_info_b]);
 }
 
+#[test]
+fn test_lookup_by_peer() {
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let clock = time::FakeClock::default();
+    let store = store::Store::from(near_store::db::TestDB::new());
+    let connection_store = ConnectionStore::new(store.clone()).unwrap();
+
+    tracing::debug!(target:"test", "store connections to two peers");
+    let conn_info_a = make_connection_info(rng, clock.now_utc());
+    let conn_info_b = make_connection_info(rng, clock.now_utc());
+    connection_store.insert_outbound_connections(vec![conn_info_a.clone(), conn_info_b.clone()]);
+
+    tracing::debug!(target:"test", "store a newer connection to the first peer");
+    clock.advance(time::Duration::seconds(123));
+    let mut conn_info_a2 = make_connection_info(rng, clock.now_utc());
+    conn_info_a2.peer_info.id = conn_info_a.peer_info.id.clone();
+    connection_store.insert_outbound_connections(vec![conn_info_a2.clone()]);
+
+    tracing::debug!(target:"test", "check that each peer maps to its latest connection");
+    assert_eq!(store.get_recent_outbound_connection(&conn_info_a.peer_info.id), Some(conn_info_a2));
+    assert_eq!(
+        store.get_recent_outbound_connection(&conn_info_b.peer_info.id),
+        Some(conn_info_b.clone())
+    );
+
+    tracing::debug!(target:"test", "check that removed peers are dropped from the index");
+    connection_store.remove_from_connection_store(&conn_info_b.peer_info.id);
+    assert_eq!(store.get_recent_outbound_connection(&conn_info_b.peer_info.id), None);
+}
+
 #[test]
 fn test_evict_longest_disconnected() {
     let mut rng = make_rng(921853233);
@@ -21,6 +21,7 @@ use crate::snapshot_hosts::{SnapshotHostInfoError, SnapshotHostsCache};
 use crate::state_witness::PartialWitnessSenderForNetwork;
 use crate::stats::metrics;
 use crate::store;
+use crate::stun;
 use crate::tcp;
 use crate::types::{ChainInfo, PeerType, ReasonForBan};
 use anyhow::Context;
@@ -49,6 +50,9 @@ pub(crate) const LIMIT_PENDING_PEERS: usize = 60;
 /// production of 1 block should fit).
 const RECENT_ROUTED_MESSAGES_CACHE_SIZE: usize = 10000;
 
+/// Size of LRU cache of the authors of recent TIER2 messages that require a response.
+const TIER2_ROUTE_BACK_AUTHORS_CACHE_SIZE: usize = 10000;
+
 /// How long a peer has to be unreachable, until we prune it from the in-memory graph.
 const PRUNE_UNREACHABLE_PEERS_AFTER: time::Duration = time::Duration::hours(1);
 
@@ -130,6 +134,10 @@ pub(crate) struct NetworkState {
 
     /// Hash of messages that requires routing back to respective previous hop.
     pub tier2_route_back: Mutex<RouteBackCache>,
+    /// Authors of the messages in `tier2_route_back`. Unlike the route back, they
+    /// are not consumed by the first response, so that a response split into
+    /// several messages can reach the author through its own route.
+    pub tier2_route_back_authors: Mutex<lru::LruCache<CryptoHash, PeerId>>,
     /// Currently unused, as TIER1 messages do not require a response.
     /// Also TIER1 connections are direct by design (except for proxies),
     /// so routing shouldn't really be needed.
@@ -143,9 +151,13 @@ pub(crate) struct NetworkState {
     /// Whitelisted nodes, which are allowed to connect even if the connection limit has been
     /// reached.
     whitelist_nodes: Vec<WhitelistNode>,
+    /// Store of the network columns, kept to compact them periodically.
+    store: store::Store,
 
     /// Mutex which prevents overlapping calls to tier1_advertise_proxies.
     tier1_advertise_proxies_mutex: tokio::sync::Mutex<()>,
+    /// Addresses of the STUN servers used by tier1_advertise_proxies.
+    stun_lookup_cache: stun::LookupCache,
     /// Demultiplexer aggregating calls to add_edges(), for V1 routing protocol
     add_edges_demux: demux::Demux<Vec<Edge>, Result<(), ReasonForBan>>,
     /// Demultiplexer aggregating calls to update_routes(), for V2 routing protocol
@@ -193,17 +205,24 @@ impl NetworkState {
             connection_store: connection_store::ConnectionStore::new(store.clone()).unwrap(),
             pending_reconnect: Mutex::new(Vec::<PeerInfo>::new()),
             accounts_data: Arc::new(AccountDataCache::new()),
-            account_announcements: Arc::new(AnnounceAccountCache::new(store)),
+            account_announcements: Arc::new(AnnounceAccountCache::new(store.clone())),
             tier2_route_back: Mutex::new(RouteBackCache::default()),
+            tier2_route_back_authors: Mutex::new(lru::LruCache::new(
+                NonZeroUsize::new(TIER2_ROUTE_BACK_AUTHORS_CACHE_SIZE).unwrap(),
+            )),
             tier1_route_back: Mutex::new(RouteBackCache::default()),
             recent_routed_messages: Mutex::new(lru::LruCache::new(
                 NonZeroUsize::new(RECENT_ROUTED_MESSAGES_CACHE_SIZE).unwrap(),
             )),
             txns_since_last_block: AtomicUsize::new(0),
             whitelist_nodes,
+            store,
             add_edges_demux: demux::Demux::new(config.routing_table_update_rate_limit),
             update_routes_demux: demux::Demux::new(config.routing_table_update_rate_limit),
             set_chain_info_mutex: Mutex::new(()),
+            stun_lookup_cache: stun::LookupCache::new(
+                config.tier1.as_ref().map_or(time::Duration::ZERO, |t| t.stun_lookup_cache_ttl),
+            ),
             config,
             created_at: clock.now(),
             tier1_advertise_proxies_mutex: tokio::sync::Mutex::new(()),
@@ -782,6 +801,18 @@ impl NetworkState {
         self.connection_store.update(clock, &self.tier2.load());
     }
 
+    /// Compacts the network columns of the store. The compaction blocks until
+    /// it finishes, so it runs on a dedicated thread.
+    pub async fn compact_store(self: &Arc<Self>, clock: &time::Clock) {
+        let mut store = self.store.clone();
+        let clock = clock.clone();
+        let result =
+            self.runtime.handle.spawn_blocking(move || store.compact(&clock)).await.unwrap();
+        if let Err(err) = result {
+            tracing::error!(target: "network", ?err, "Failed to compact the network store");
+        }
+    }
+
     /// Clears pending_reconnect and returns the cleared values
     pub fn poll_pending_reconnect(&self) -> Vec<PeerInfo> {
         let mut pending_reconnect = self.pending_reconnect.lock();
@@ -13,6 +13,7 @@ use crate::types::ReasonForBan;
 use near_async::time;
 use near_primitives::hash::CryptoHash;
 use near_primitives::network::{AnnounceAccount, PeerId};
+use near_primitives::views::{AnnounceAccountBroadcastView, AnnounceAccountBroadcastsView};
 use std::collections::HashSet;
 use std::sync::Arc;
 
@@ -54,6 +55,39 @@ impl NetworkState {
         }).await.unwrap()
     }
 
+    /// Returns the connected peers which `add_accounts` would broadcast the
+    /// given AnnounceAccount to. It is empty if the announcement has been
+    /// broadcasted already.
+    pub(crate) fn would_broadcast_to(&self, aa: &AnnounceAccount) -> Vec<PeerId> {
+        if !self.account_announcements.is_new_announcement(aa) {
+            return vec![];
+        }
+        self.tier2.load().ready.keys().cloned().collect()
+    }
+
+    /// Reports, for every announcement in the cache, the peers it would be
+    /// broadcast to, sorted by account id. Announcements loaded from storage
+    /// have not been broadcast by this node yet.
+    pub(crate) fn announce_account_broadcasts_view(&self) -> AnnounceAccountBroadcastsView {
+        let mut broadcasts: Vec<_> = self
+            .account_announcements
+            .get_announcements()
+            .into_iter()
+            .map(|aa| {
+                let mut would_broadcast_to = self.would_broadcast_to(&aa);
+                would_broadcast_to.sort();
+                AnnounceAccountBroadcastView {
+                    account_id: aa.account_id,
+                    peer_id: aa.peer_id,
+                    epoch_id: aa.epoch_id,
+                    would_broadcast_to,
+                }
+            })
+            .collect();
+        broadcasts.sort_by(|a, b| a.account_id.cmp(&b.account_id));
+        AnnounceAccountBroadcastsView { broadcasts }
+    }
+
     /// Constructs a partial edge to the given peer with the nonce specified.
     /// If nonce is None, nonce is selected automatically.
     pub fn propose_edge(
@@ -212,12 +246,32 @@ impl NetworkState {
         let from = &conn.peer_info.id;
         match conn.tier {
             tcp::Tier::T1 => self.tier1_route_back.lock().insert(&clock, msg.hash(), from.clone()),
-            tcp::Tier::T2 => self.tier2_route_back.lock().insert(&clock, msg.hash(), from.clone()),
+            tcp::Tier::T2 => {
+                self.tier2_route_back.lock().insert(&clock, msg.hash(), from.clone());
+                self.tier2_route_back_authors.lock().put(msg.hash(), msg.author.clone());
+            }
+        }
+    }
+
+    /// Returns the target of a response to the message with the given hash. That is
+    /// the route back, until the first response consumes it, and the author of the
+    /// message afterwards.
+    pub(crate) fn tier2_response_target(&self, hash: &CryptoHash) -> Option<PeerIdOrHash> {
+        if self.peek_route_back(hash).is_some() {
+            return Some(PeerIdOrHash::Hash(*hash));
         }
+        self.tier2_route_back_authors.lock().peek(hash).cloned().map(PeerIdOrHash::PeerId)
     }
 
     pub(crate) fn compare_route_back(&self, hash: CryptoHash, peer_id: &PeerId) -> bool {
-        self.tier2_route_back.lock().get(&hash).is_some_and(|value| value == peer_id)
+        self.peek_route_back(&hash).as_ref() == Some(peer_id)
+    }
+
+    /// Returns the peer to which a response to the message with the given hash would be
+    /// routed back over TIER2. Unlike `tier2_find_route`, the entry is not consumed and
+    /// the time it was recorded at is left untouched.
+    pub(crate) fn peek_route_back(&self, hash: &CryptoHash) -> Option<PeerId> {
+        self.tier2_route_back.lock().get(hash).cloned()
     }
 
     /// Accepts NetworkTopologyChange events.
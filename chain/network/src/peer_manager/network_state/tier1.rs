@@ -108,39 +108,23 @@ impl super::NetworkState {
             // it means that this node is its own proxy.
             // Discover the public IP of this node using those STUN servers.
             // We do not require all stun servers to be available, but
-            // we require the received responses to be consistent.
+            // we require a majority of the ones which respond to agree on the IP.
             (Some(node_addr), config::ValidatorProxies::Dynamic(stun_servers)) => {
-                // Query all the STUN servers in parallel.
-                let queries = stun_servers.iter().map(|addr| {
-                    let clock = clock.clone();
-                    let want_ipv4 = node_addr.is_ipv4();
-                    let addr = addr.clone();
-                    self.spawn(async move {
-                        let addr = stun::lookup_host(&addr, want_ipv4).await?;
-                        match stun::query(&clock, &addr).await {
-                            Ok(ip) => Some(ip),
-                            Err(err) => {
-                                tracing::warn!(target:"network", "STUN lookup failed for {addr}: {err}");
-                                None
-                            }
-                        }
-                    })
-                });
-                let mut node_ips = vec![];
-                for q in queries {
-                    node_ips.extend(q.await.unwrap());
-                }
-                // Check that we have received non-zero responses and that they are consistent.
-                if node_ips.is_empty() {
-                    vec![]
-                } else if !node_ips.iter().all(|ip| ip == &node_ips[0]) {
-                    tracing::warn!(target:"network", "received inconsistent responses from the STUN servers");
-                    vec![]
-                } else {
-                    vec![PeerAddr {
+                let want_ipv4 = node_addr.is_ipv4();
+                let lookups = stun_servers
+                    .iter()
+                    .map(|addr| self.stun_lookup_cache.lookup_host(clock, addr, want_ipv4));
+                let addrs: Vec<_> =
+                    futures::future::join_all(lookups).await.into_iter().flatten().collect();
+                match stun::query_multi(clock, &addrs).await {
+                    Ok(ip) => vec![PeerAddr {
                         peer_id: self.config.node_id(),
-                        addr: std::net::SocketAddr::new(node_ips[0], node_addr.port()),
-                    }]
+                        addr: std::net::SocketAddr::new(ip, node_addr.port()),
+                    }],
+                    Err(err) => {
+                        tracing::warn!(target:"network", "failed to discover the public IP: {err}");
+                        vec![]
+                    }
                 }
             }
         };
@@ -57,6 +57,10 @@ const FIX_LOCAL_EDGES_TIMEOUT: time::Duration = time::Duration::seconds(6);
 /// Number of times to attempt reconnection when trying to re-establish a connection.
 const MAX_RECONNECT_ATTEMPTS: usize = 6;
 
+/// How often to compact the network columns of the store, so that the space
+/// of the data pruned from them is reclaimed.
+const COMPACT_STORE_INTERVAL: time::Duration = time::Duration::hours(6);
+
 /// How often to report bandwidth stats.
 const REPORT_BANDWIDTH_STATS_TRIGGER_INTERVAL: time::Duration =
     time::Duration::milliseconds(60_000);
@@ -181,6 +185,18 @@ impl actix::Actor for PeerManagerActor {
             }
         }));
 
+        // Periodically compact the network columns of the store.
+        let clock = self.clock.clone();
+        let state = self.state.clone();
+        ctx.spawn(wrap_future(async move {
+            let mut interval =
+                time::Interval::new(clock.now() + COMPACT_STORE_INTERVAL, COMPACT_STORE_INTERVAL);
+            loop {
+                interval.tick(&clock).await;
+                state.compact_store(&clock).await;
+            }
+        }));
+
         // Periodically prints bandwidth stats for each peer.
         self.report_bandwidth_stats_trigger(ctx, REPORT_BANDWIDTH_STATS_TRIGGER_INTERVAL);
 
@@ -906,17 +922,22 @@ impl PeerManagerActor {
                 }
             }
             NetworkRequests::PartialEncodedChunkResponse { route_back, response } => {
-                if self.state.send_message_to_peer(
-                    &self.clock,
-                    tcp::Tier::T2,
-                    self.state.sign_message(
+                // A response split into several messages reaches the requester through
+                // its own route once the first message consumes the route back.
+                let sent = self.state.tier2_response_target(&route_back).is_some_and(|target| {
+                    self.state.send_message_to_peer(
                         &self.clock,
-                        RawRoutedMessage {
-                            target: PeerIdOrHash::Hash(route_back),
-                            body: RoutedMessageBody::PartialEncodedChunkResponse(response),
-                        },
-                    ),
-                ) {
+                        tcp::Tier::T2,
+                        self.state.sign_message(
+                            &self.clock,
+                            RawRoutedMessage {
+                                target,
+                                body: RoutedMessageBody::PartialEncodedChunkResponse(response),
+                            },
+                        ),
+                    )
+                });
+                if sent {
                     NetworkResponses::NoResponse
                 } else {
                     NetworkResponses::RouteNotFound
@@ -1150,6 +1171,7 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
                     })
                     .collect(),
                 next_hops: (*self.state.graph.routing_table.info().next_hops).clone(),
+                reachability_ratio: self.state.graph.routing_table.reachability_ratio(),
             }),
             GetDebugStatus::RecentOutboundConnections => {
                 DebugStatus::RecentOutboundConnections(RecentOutboundConnectionsView {
@@ -1182,6 +1204,12 @@ impl actix::Handler<GetDebugStatus> for PeerManagerActor {
                     })
                     .collect::<Vec<_>>(),
             }),
+            GetDebugStatus::AnnounceAccounts => {
+                DebugStatus::AnnounceAccounts(self.state.account_announcements.snapshot_view())
+            }
+            GetDebugStatus::AnnounceAccountBroadcasts => DebugStatus::AnnounceAccountBroadcasts(
+                self.state.announce_account_broadcasts_view(),
+            ),
         }
     }
 }
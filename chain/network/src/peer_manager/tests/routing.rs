@@ -2,7 +2,10 @@ use crate::blacklist;
 use crate::broadcast;
 use crate::config::{NetworkConfig, SocketOptions};
 use crate::network_protocol::testonly as data;
-use crate::network_protocol::{Encoding, Ping, Pong, RoutedMessageBody, RoutingTableUpdate};
+use crate::network_protocol::{
+    Encoding, PartialEncodedChunkRequestMsg, PartialEncodedChunkResponseMsg, PeerIdOrHash, Ping,
+    Pong, RawRoutedMessage, RoutedMessageBody, RoutingTableUpdate,
+};
 use crate::peer;
 use crate::peer::peer_actor::{
     ClosingReason, ConnectionClosedEvent, DROP_DUPLICATED_MESSAGES_PERIOD,
@@ -12,11 +15,15 @@ use crate::peer_manager::peer_manager_actor::Event as PME;
 use crate::peer_manager::testonly::start as start_pm;
 use crate::peer_manager::testonly::Event;
 use crate::private_actix::RegisterPeerError;
+use crate::shards_manager::ShardsManagerRequestFromNetwork;
 use crate::tcp;
 use crate::testonly::{abort_on_panic, make_rng, Rng};
 use crate::types::{Edge, PeerMessage};
+use crate::types::{NetworkRequests, NetworkResponses, PeerManagerMessageRequest};
 use crate::types::{PeerInfo, ReasonForBan};
 use near_async::time;
+use near_o11y::WithSpanContextExt;
+use near_primitives::hash::CryptoHash;
 use near_primitives::network::PeerId;
 use near_store::db::TestDB;
 use pretty_assertions::assert_eq;
@@ -56,6 +63,173 @@ async fn simple() {
     pm1.wait_for_routing_table(&[(id0.clone(), vec![id0.clone()])]).await;
 }
 
+// test that peeking at a route back entry doesn't consume it
+#[tokio::test]
+async fn peek_route_back() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+    let pm = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+
+    let hash = CryptoHash::hash_bytes(b"routed message");
+    let target = data::make_peer_id(rng);
+    let clock = clock.clock();
+    pm.with_state(move |s| async move {
+        assert_eq!(s.peek_route_back(&hash), None);
+        s.tier2_route_back.lock().insert(&clock, hash, target.clone());
+        assert_eq!(s.peek_route_back(&hash), Some(target.clone()));
+        assert_eq!(s.peek_route_back(&hash), Some(target.clone()));
+        assert_eq!(s.tier2_find_route(&clock, &PeerIdOrHash::Hash(hash)).unwrap(), target);
+        assert_eq!(s.peek_route_back(&hash), None);
+    })
+    .await;
+}
+
+// test that a response split into several messages reaches the requester in full,
+// even though the first message consumes the route back
+#[tokio::test]
+async fn partial_encoded_chunk_response_split() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    tracing::info!(target:"test", "connect three nodes in a line");
+    let pm0 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let pm1 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let pm2 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    pm0.connect_to(&pm1.peer_info(), tcp::Tier::T2).await;
+    pm1.connect_to(&pm2.peer_info(), tcp::Tier::T2).await;
+    let id0 = pm0.cfg.node_id();
+    let id1 = pm1.cfg.node_id();
+    let id2 = pm2.cfg.node_id();
+    pm0.wait_for_routing_table(&[
+        (id1.clone(), vec![id1.clone()]),
+        (id2.clone(), vec![id1.clone()]),
+    ])
+    .await;
+    pm2.wait_for_routing_table(&[
+        (id0.clone(), vec![id1.clone()]),
+        (id1.clone(), vec![id1.clone()]),
+    ])
+    .await;
+
+    tracing::info!(target:"test", "send a chunk request from {id2} to {id0}");
+    let chunk_hash = chain.blocks[3].chunks()[0].chunk_hash();
+    let parts = data::make_chunk_parts(chain.chunks[&chunk_hash].clone());
+    let request = PartialEncodedChunkRequestMsg {
+        chunk_hash: chunk_hash.clone(),
+        part_ords: parts.iter().map(|part| part.part_ord).collect(),
+        tracking_shards: Default::default(),
+    };
+    let mut pm0_ev = pm0.events.from_now();
+    let mut pm2_ev = pm2.events.from_now();
+    {
+        let clock = clock.clock();
+        let id0 = id0.clone();
+        pm2.with_state(move |s| async move {
+            let msg = s.sign_message(
+                &clock,
+                RawRoutedMessage {
+                    target: PeerIdOrHash::PeerId(id0),
+                    body: RoutedMessageBody::PartialEncodedChunkRequest(request),
+                },
+            );
+            assert!(s.send_message_to_peer(&clock, tcp::Tier::T2, msg));
+        })
+        .await;
+    }
+    let route_back = pm0_ev
+        .recv_until(|ev| match ev {
+            Event::ShardsManager(
+                ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkRequest {
+                    route_back,
+                    ..
+                },
+            ) => Some(route_back),
+            _ => None,
+        })
+        .await;
+
+    tracing::info!(target:"test", "send the response from {id0} in several messages");
+    for parts in parts.chunks(2) {
+        let response = PartialEncodedChunkResponseMsg {
+            chunk_hash: chunk_hash.clone(),
+            parts: parts.to_vec(),
+            receipts: vec![],
+        };
+        let result = pm0
+            .actix
+            .addr
+            .send(
+                PeerManagerMessageRequest::NetworkRequests(
+                    NetworkRequests::PartialEncodedChunkResponse { route_back, response },
+                )
+                .with_span_context(),
+            )
+            .await
+            .unwrap()
+            .as_network_response();
+        assert_eq!(result, NetworkResponses::NoResponse);
+    }
+
+    tracing::info!(target:"test", "await all the parts at {id2}");
+    let mut received = vec![];
+    while received.len() < parts.len() {
+        let response = pm2_ev
+            .recv_until(|ev| match ev {
+                Event::ShardsManager(
+                    ShardsManagerRequestFromNetwork::ProcessPartialEncodedChunkResponse {
+                        partial_encoded_chunk_response,
+                        ..
+                    },
+                ) => Some(partial_encoded_chunk_response),
+                _ => None,
+            })
+            .await;
+        received.extend(response.parts);
+    }
+    received.sort_by_key(|part| part.part_ord);
+    assert_eq!(received, parts);
+}
+
+// test that a new announcement would be broadcasted to all the connected peers,
+// while a re-announcement wouldn't be broadcasted at all
+#[tokio::test]
+async fn would_broadcast_to() {
+    abort_on_panic();
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+    let mut clock = time::FakeClock::default();
+    let chain = Arc::new(data::Chain::make(&mut clock, rng, 10));
+
+    tracing::info!(target:"test", "connect two peers to a node");
+    let pm0 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let pm1 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    let pm2 = start_pm(clock.clock(), TestDB::new(), chain.make_config(rng), chain.clone()).await;
+    pm0.connect_to(&pm1.peer_info(), tcp::Tier::T2).await;
+    pm0.connect_to(&pm2.peer_info(), tcp::Tier::T2).await;
+
+    let mut want = vec![pm1.cfg.node_id(), pm2.cfg.node_id()];
+    want.sort();
+    let aa = data::make_announce_account(rng);
+    pm0.with_state(move |s| async move {
+        let mut got = s.would_broadcast_to(&aa);
+        got.sort();
+        assert_eq!(got, want);
+        s.add_accounts(vec![aa.clone()]).await;
+        assert_eq!(s.would_broadcast_to(&aa), vec![]);
+        let view = s.announce_account_broadcasts_view();
+        assert_eq!(view.broadcasts.len(), 1);
+        assert_eq!(view.broadcasts[0].account_id, aa.account_id);
+        assert_eq!(view.broadcasts[0].would_broadcast_to, vec![]);
+    })
+    .await;
+}
+
 // test routing for three nodes in a line
 #[tokio::test]
 async fn three_nodes_path() {
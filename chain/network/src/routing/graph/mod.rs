@@ -308,7 +308,12 @@ impl Graph {
                 }
                 let snapshot = inner.update(&clock, &this.unreliable_peers.load());
                 let snapshot = Arc::new(snapshot);
-                this.routing_table.update(snapshot.next_hops.clone(), snapshot.distances.clone());
+                // `peer_reachable_at` contains all the peers we know about, including ourselves.
+                this.routing_table.update(
+                    snapshot.next_hops.clone(),
+                    snapshot.distances.clone(),
+                    inner.peer_reachable_at.len().saturating_sub(1),
+                );
                 this.snapshot.store(snapshot);
                 (new_edges, oks)
             })
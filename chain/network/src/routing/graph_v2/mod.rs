@@ -723,7 +723,12 @@ impl GraphV2 {
                 let (next_hops, to_broadcast) =
                     inner.compute_routes(&clock, &this.unreliable_peers.load());
 
-                this.routing_table.update(next_hops.into(), Arc::new(inner.my_distances.clone()));
+                // The edge cache assigns ids to all the peers we know about, including ourselves.
+                this.routing_table.update(
+                    next_hops.into(),
+                    Arc::new(inner.my_distances.clone()),
+                    inner.edge_cache.iter_peers().len().saturating_sub(1),
+                );
 
                 inner.log_state();
 
@@ -1,4 +1,5 @@
 use crate::routing;
+use crate::stats::metrics;
 use lru::LruCache;
 use near_primitives::network::PeerId;
 use parking_lot::Mutex;
@@ -23,6 +24,10 @@ struct Inner {
     /// TODO(saketh): Remove this when we deprecate the V1 routing protocol.
     distance: Arc<routing::DistanceTable>,
 
+    /// Number of peers in the network known to the routing protocol, excluding
+    /// ourselves, as of the last update. Some of them may be unreachable.
+    known_peers: usize,
+
     /// Counter of number of calls to find_route_by_peer_id.
     find_route_calls: u64,
     /// Last time the given peer was selected by find_route_by_peer_id.
@@ -47,9 +52,19 @@ impl Inner {
         &mut self,
         next_hops: Arc<routing::NextHopTable>,
         distance: Arc<routing::DistanceTable>,
+        known_peers: usize,
     ) {
         self.next_hops = next_hops;
-        self.distance = distance
+        self.distance = distance;
+        self.known_peers = known_peers;
+    }
+
+    /// Fraction of the known peers which are reachable, 0 if no peers are known.
+    fn reachability_ratio(&self) -> f64 {
+        if self.known_peers == 0 {
+            return 0.;
+        }
+        (self.next_hops.len() as f64 / self.known_peers as f64).min(1.)
     }
 }
 
@@ -64,17 +79,23 @@ impl RoutingTableView {
         Self(Mutex::new(Inner {
             next_hops: Default::default(),
             distance: Default::default(),
+            known_peers: 0,
             find_route_calls: 0,
             last_routed: LruCache::new(NonZeroUsize::new(LAST_ROUTED_CACHE_SIZE).unwrap()),
         }))
     }
 
+    /// `known_peers` is the number of peers known to the routing protocol,
+    /// excluding ourselves, reachable or not.
     pub(crate) fn update(
         &self,
         next_hops: Arc<routing::NextHopTable>,
         distance: Arc<routing::DistanceTable>,
+        known_peers: usize,
     ) {
-        self.0.lock().update(next_hops, distance)
+        let mut inner = self.0.lock();
+        inner.update(next_hops, distance, known_peers);
+        metrics::PEER_REACHABILITY_RATIO.set(inner.reachability_ratio());
     }
 
     pub(crate) fn reachable_peers(&self) -> usize {
@@ -84,6 +105,12 @@ impl RoutingTableView {
         self.0.lock().next_hops.len()
     }
 
+    /// Fraction of the known peers which are reachable. A declining ratio means
+    /// that the node is losing visibility into the network.
+    pub(crate) fn reachability_ratio(&self) -> f64 {
+        self.0.lock().reachability_ratio()
+    }
+
     // Given a PeerId to which we wish to route a message, returns the first hop on a
     // route to the target. If no route is known, produces FindRouteError.
     pub(crate) fn find_next_hop_for_target(
@@ -20,10 +20,33 @@ fn find_route() {
 
     // Check that RoutingTableView always selects a valid next hop.
     let rtv = RoutingTableView::new();
-    rtv.update(next_hops.clone(), Default::default());
+    rtv.update(next_hops.clone(), Default::default(), peers.len());
     for _ in 0..1000 {
         let p = peers.choose(rng).unwrap();
         let got = rtv.find_next_hop_for_target(&p).unwrap();
         assert!(next_hops.get(p).unwrap().contains(&got));
     }
 }
+
+#[test]
+fn reachability_ratio() {
+    let mut rng = make_rng(921853233);
+    let rng = &mut rng;
+
+    let rtv = RoutingTableView::new();
+    assert_eq!(rtv.reachability_ratio(), 0.);
+
+    // 3 out of 4 known peers are reachable.
+    let peers: Vec<_> = (0..4).map(|_| data::make_peer_id(rng)).collect();
+    let mut next_hops = routing::NextHopTable::new();
+    for p in &peers[..3] {
+        next_hops.insert(p.clone(), vec![peers[0].clone()]);
+    }
+    rtv.update(Arc::new(next_hops), Default::default(), peers.len());
+    assert_eq!(rtv.reachable_peers(), 3);
+    assert_eq!(rtv.reachability_ratio(), 0.75);
+
+    // No peers are reachable anymore.
+    rtv.update(Default::default(), Default::default(), peers.len());
+    assert_eq!(rtv.reachability_ratio(), 0.);
+}
@@ -5,7 +5,7 @@ use crate::types::PeerType;
 use near_async::time;
 use near_o11y::metrics::prometheus;
 use near_o11y::metrics::{
-    exponential_buckets, try_create_histogram, try_create_histogram_vec,
+    exponential_buckets, try_create_gauge, try_create_histogram, try_create_histogram_vec,
     try_create_histogram_with_buckets, try_create_int_counter, try_create_int_counter_vec,
     try_create_int_gauge, try_create_int_gauge_vec, Histogram, HistogramVec, IntCounter,
     IntCounterVec, IntGauge, IntGaugeVec, MetricVec, MetricVecBuilder,
@@ -308,6 +308,14 @@ pub(crate) static PEER_REACHABLE: Lazy<IntGauge> = Lazy::new(|| {
     )
     .unwrap()
 });
+
+pub(crate) static PEER_REACHABILITY_RATIO: Lazy<prometheus::Gauge> = Lazy::new(|| {
+    try_create_gauge(
+        "near_peer_reachability_ratio",
+        "Fraction of the peers known to the routing protocol which are reachable",
+    )
+    .unwrap()
+});
 static DROPPED_MESSAGE_COUNT: Lazy<IntCounterVec> = Lazy::new(|| {
     try_create_int_counter_vec(
         "near_dropped_message_by_type_and_reason_count",
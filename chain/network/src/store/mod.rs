@@ -2,12 +2,20 @@
 /// All transactions should be implemented within this module,
 /// in particular schema::StoreUpdate is not exported.
 use crate::types::ConnectionInfo;
-use near_primitives::network::AnnounceAccount;
+use near_async::time;
+use near_primitives::network::{AnnounceAccount, PeerId};
 use near_primitives::types::AccountId;
+use parking_lot::Mutex;
 use std::sync::Arc;
 
 mod schema;
 
+#[cfg(test)]
+mod tests;
+
+/// Minimal time between two compactions of the network columns.
+const MIN_COMPACTION_INTERVAL: time::Duration = time::Duration::hours(1);
+
 /// Opaque error type representing storage errors.
 ///
 /// Invariant: any store error is a critical operational error
@@ -23,9 +31,44 @@ pub(crate) struct Error(schema::Error);
 /// In particular it doesn't implement Clone and requires &mut self for
 /// methods writing to the DB.
 #[derive(Clone)]
-pub(crate) struct Store(schema::Store);
+pub(crate) struct Store {
+    db: schema::Store,
+    /// Time of the last compaction, shared between the clones of the store.
+    last_compaction: Arc<Mutex<Option<time::Instant>>>,
+}
 
 impl Store {
+    fn new(db: schema::Store) -> Self {
+        Self { db, last_compaction: Default::default() }
+    }
+
+    /// Compacts the network columns, e.g. after a lot of data was pruned from
+    /// them, and logs the estimated size before and after. Does nothing if the
+    /// previous compaction happened less than `MIN_COMPACTION_INTERVAL` ago.
+    /// Returns whether the compaction ran.
+    #[tracing::instrument(target = "network::store", level = "debug", "Store::compact", skip_all)]
+    pub fn compact(&mut self, clock: &time::Clock) -> Result<bool, Error> {
+        let now = clock.now();
+        {
+            let mut last_compaction = self.last_compaction.lock();
+            if last_compaction.is_some_and(|last| now < last + MIN_COMPACTION_INTERVAL) {
+                tracing::debug!(target: "network::store", "Skipping compaction, the previous one was too recent");
+                return Ok(false);
+            }
+            *last_compaction = Some(now);
+        }
+        let size_before = self.db.estimate_live_data_size(schema::COLUMNS);
+        self.db.compact(schema::COLUMNS).map_err(Error)?;
+        let size_after = self.db.estimate_live_data_size(schema::COLUMNS);
+        tracing::info!(
+            target: "network::store",
+            ?size_before,
+            ?size_after,
+            reclaimed = ?size_before.zip(size_after).map(|(before, after)| before - after),
+            "Compacted the network columns");
+        Ok(true)
+    }
+
     /// Inserts (account_id,aa) to the AccountAnnouncements column.
     #[tracing::instrument(
         target = "network::store",
@@ -39,9 +82,9 @@ impl Store {
         account_id: &AccountId,
         aa: &AnnounceAccount,
     ) -> Result<(), Error> {
-        let mut update = self.0.new_update();
+        let mut update = self.db.new_update();
         update.set::<schema::AccountAnnouncements>(account_id, aa);
-        self.0.commit(update).map_err(Error)
+        self.db.commit(update).map_err(Error)
     }
 
     /// Fetches row with key account_id from the AccountAnnouncements column.
@@ -49,7 +92,7 @@ impl Store {
         &self,
         account_id: &AccountId,
     ) -> Result<Option<AnnounceAccount>, Error> {
-        self.0.get::<schema::AccountAnnouncements>(account_id).map_err(Error)
+        self.db.get::<schema::AccountAnnouncements>(account_id).map_err(Error)
     }
 }
 
@@ -65,9 +108,18 @@ impl Store {
         &mut self,
         recent_outbound_connections: &Vec<ConnectionInfo>,
     ) -> Result<(), Error> {
-        let mut update = self.0.new_update();
+        let mut update = self.db.new_update();
+        // Keep the per-peer index in sync with the list.
+        for stale in self.get_recent_outbound_connections() {
+            if !recent_outbound_connections.iter().any(|c| c.peer_info.id == stale.peer_info.id) {
+                update.delete::<schema::RecentOutboundConnectionsByPeer>(&stale.peer_info.id);
+            }
+        }
+        for conn in recent_outbound_connections {
+            update.set::<schema::RecentOutboundConnectionsByPeer>(&conn.peer_info.id, conn);
+        }
         update.set::<schema::RecentOutboundConnections>(&(), &recent_outbound_connections);
-        self.0.commit(update).map_err(Error)
+        self.db.commit(update).map_err(Error)
     }
 
     pub fn get_recent_outbound_connections(&self) -> Vec<ConnectionInfo> {
@@ -76,11 +128,35 @@ impl Store {
             .unwrap_or(Some(vec![]))
             .unwrap_or(vec![])
     }
+
+    /// Fetches the most recent outbound connection to the given peer, without
+    /// reading the whole RecentOutboundConnections list.
+    pub fn get_recent_outbound_connection(&self, peer_id: &PeerId) -> Option<ConnectionInfo> {
+        self.db.get::<schema::RecentOutboundConnectionsByPeer>(peer_id).unwrap_or(None)
+    }
+
+    /// Populates the per-peer index from RecentOutboundConnections for
+    /// databases written before the index was introduced.
+    #[tracing::instrument(
+        target = "network::store",
+        level = "trace",
+        "Store::index_recent_outbound_connections",
+        skip_all
+    )]
+    pub fn index_recent_outbound_connections(&mut self) -> Result<(), Error> {
+        let mut update = self.db.new_update();
+        for conn in self.get_recent_outbound_connections() {
+            if self.get_recent_outbound_connection(&conn.peer_info.id).is_none() {
+                update.set::<schema::RecentOutboundConnectionsByPeer>(&conn.peer_info.id, &conn);
+            }
+        }
+        self.db.commit(update).map_err(Error)
+    }
 }
 
 impl From<Arc<dyn near_store::db::Database>> for Store {
     fn from(store: Arc<dyn near_store::db::Database>) -> Self {
-        Self(schema::Store::from(store))
+        Self::new(schema::Store::from(store))
     }
 }
 
@@ -88,6 +164,6 @@ impl From<Arc<dyn near_store::db::Database>> for Store {
 impl From<Arc<near_store::db::TestDB>> for Store {
     fn from(store: Arc<near_store::db::TestDB>) -> Self {
         let database: Arc<dyn near_store::db::Database> = store;
-        Self(schema::Store::from(database))
+        Self::new(schema::Store::from(database))
     }
 }
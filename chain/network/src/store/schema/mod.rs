@@ -7,6 +7,7 @@ use near_async::time;
 use near_crypto::Signature;
 use near_primitives::account::id::AccountId;
 use near_primitives::network::{AnnounceAccount, PeerId};
+use near_store::db::StatsValue;
 use near_store::DBCol;
 use std::io;
 use std::sync::Arc;
@@ -101,6 +102,13 @@ impl Column for RecentOutboundConnections {
     type Value = Vec<ConnectionInfoRepr>;
 }
 
+pub(super) struct RecentOutboundConnectionsByPeer;
+impl Column for RecentOutboundConnectionsByPeer {
+    const COL: DBCol = DBCol::RecentOutboundConnectionsByPeer;
+    type Key = Borsh<PeerId>;
+    type Value = ConnectionInfoRepr;
+}
+
 pub(super) struct PeerComponent;
 impl Column for PeerComponent {
     const COL: DBCol = DBCol::PeerComponent;
@@ -122,6 +130,16 @@ impl Column for LastComponentNonce {
     type Value = Borsh<u64>;
 }
 
+/// All the columns owned by the network.
+pub(super) const COLUMNS: &[DBCol] = &[
+    AccountAnnouncements::COL,
+    RecentOutboundConnections::COL,
+    RecentOutboundConnectionsByPeer::COL,
+    PeerComponent::COL,
+    ComponentEdges::COL,
+    LastComponentNonce::COL,
+];
+
 ////////////////////////////////////////////////////
 // Storage
 
@@ -248,6 +266,28 @@ impl Store {
             None => None,
         })
     }
+
+    /// Compacts the given columns, blocking until the compaction finishes.
+    pub fn compact(&mut self, cols: &[DBCol]) -> Result<(), Error> {
+        self.0.compact_columns(cols)
+    }
+
+    /// Estimated size in bytes of the live data in the given columns, if the
+    /// DB reports it.
+    pub fn estimate_live_data_size(&self, cols: &[DBCol]) -> Option<i64> {
+        let stats = self.0.get_store_statistics()?;
+        let (_, values) =
+            stats.data.iter().find(|(name, _)| name == "rocksdb.estimate-live-data-size")?;
+        Some(
+            values
+                .iter()
+                .filter_map(|value| match value {
+                    StatsValue::ColumnValue(col, size) if cols.contains(col) => Some(*size),
+                    _ => None,
+                })
+                .sum(),
+        )
+    }
 }
 
 impl From<Arc<dyn near_store::db::Database>> for Store {
@@ -260,7 +300,7 @@ impl StoreUpdate {
     pub fn set<C: Column>(&mut self, k: &<C::Key as Format>::T, v: &<C::Value as Format>::T) {
         self.0.set(C::COL, to_vec::<C::Key>(k), to_vec::<C::Value>(v))
     }
-    pub fn _delete<C: Column>(&mut self, k: &<C::Key as Format>::T) {
+    pub fn delete<C: Column>(&mut self, k: &<C::Key as Format>::T) {
         self.0.delete(C::COL, to_vec::<C::Key>(k))
     }
 }
@@ -0,0 +1,28 @@
+use super::*;
+use crate::network_protocol::testonly as data;
+use crate::testonly::make_rng;
+
+#[test]
+fn compact_after_removing_connections() {
+    let mut rng = make_rng(8237412);
+    let rng = &mut rng;
+    let clock = time::FakeClock::default();
+    let mut store = Store::from(near_store::db::TestDB::new());
+
+    let conns: Vec<_> = (0..10)
+        .map(|_| ConnectionInfo {
+            peer_info: data::make_peer_info(rng),
+            time_established: clock.now_utc(),
+            time_connected_until: clock.now_utc(),
+        })
+        .collect();
+    store.set_recent_outbound_connections(&conns).unwrap();
+    store.set_recent_outbound_connections(&conns[..2].to_vec()).unwrap();
+
+    assert!(store.compact(&clock.clock()).unwrap());
+    // A compaction right after the previous one is skipped, also on a clone.
+    assert!(!store.clone().compact(&clock.clock()).unwrap());
+    clock.advance(MIN_COMPACTION_INTERVAL);
+    assert!(store.compact(&clock.clock()).unwrap());
+    assert_eq!(store.get_recent_outbound_connections(), conns[..2].to_vec());
+}
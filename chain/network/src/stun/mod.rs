@@ -1,5 +1,6 @@
 use near_async::time;
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use stun::message::Getter as _;
 
@@ -14,6 +15,13 @@ pub type ServerAddr = String;
 
 pub(crate) type Error = stun::Error;
 
+#[derive(thiserror::Error, Debug)]
+#[error("no IP was reported by a majority of the STUN servers which responded out of {num_servers}, got {ips:?}")]
+pub(crate) struct NoQuorumError {
+    num_servers: usize,
+    ips: Vec<IpAddr>,
+}
+
 /// Convert from ServerAddr to SocketAddr via DNS resolution.
 /// Looks for IPv4 or IPv6 according to `want_ipv4`.
 pub(crate) async fn lookup_host(addr: &ServerAddr, want_ipv4: bool) -> Option<SocketAddr> {
@@ -25,15 +33,77 @@ pub(crate) async fn lookup_host(addr: &ServerAddr, want_ipv4: bool) -> Option<So
     None
 }
 
+/// Caches the addresses resolved by `lookup_host`, so that the STUN servers
+/// aren't resolved again on every query, and a transient DNS outage doesn't
+/// prevent querying them. Entries older than the TTL are resolved again, but
+/// are still used if the new lookup fails.
+pub(crate) struct LookupCache {
+    ttl: time::Duration,
+    /// Keyed by the address family as well, as both IPv4 and IPv6 addresses
+    /// may be looked up for the same server.
+    entries: parking_lot::Mutex<HashMap<(ServerAddr, bool), (SocketAddr, time::Instant)>>,
+}
+
+impl LookupCache {
+    pub fn new(ttl: time::Duration) -> Self {
+        Self { ttl, entries: Default::default() }
+    }
+
+    /// Same as `lookup_host`, but served from the cache if possible.
+    pub async fn lookup_host(
+        &self,
+        clock: &time::Clock,
+        addr: &ServerAddr,
+        want_ipv4: bool,
+    ) -> Option<SocketAddr> {
+        self.lookup_with(clock, addr, want_ipv4, lookup_host(addr, want_ipv4)).await
+    }
+
+    async fn lookup_with(
+        &self,
+        clock: &time::Clock,
+        addr: &ServerAddr,
+        want_ipv4: bool,
+        lookup: impl std::future::Future<Output = Option<SocketAddr>>,
+    ) -> Option<SocketAddr> {
+        let key = (addr.clone(), want_ipv4);
+        let cached = self.entries.lock().get(&key).copied();
+        if let Some((socket_addr, resolved_at)) = cached {
+            if clock.now() - resolved_at < self.ttl {
+                return Some(socket_addr);
+            }
+        }
+        match lookup.await {
+            Some(socket_addr) => {
+                self.entries.lock().insert(key, (socket_addr, clock.now()));
+                Some(socket_addr)
+            }
+            None => {
+                let (socket_addr, _) = cached?;
+                tracing::warn!(target: "network", "DNS lookup of {addr} failed, using the previously resolved {socket_addr}");
+                Some(socket_addr)
+            }
+        }
+    }
+}
+
 const QUERY_TIMEOUT: time::Duration = time::Duration::seconds(5);
 
+/// Result of a STUN query.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct StunResult {
+    /// IP of this machine as perceived by the STUN server.
+    pub ip: IpAddr,
+    /// STUN server which answered the query.
+    pub server: SocketAddr,
+    /// Time between sending the request and receiving the response.
+    pub rtt: time::Duration,
+}
+
 /// Sends a STUN BINDING request to `addr`.
-/// Returns the result of the query: the IP of this machine as perceived by the STUN server.
+/// Returns the result of the query, including the IP of this machine as perceived by the STUN server.
 /// It should be used to determine the public IP of this machine.
-pub(crate) async fn query(
-    clock: &time::Clock,
-    addr: &SocketAddr,
-) -> Result<std::net::IpAddr, Error> {
+pub(crate) async fn query(clock: &time::Clock, addr: &SocketAddr) -> Result<StunResult, Error> {
     let socket = tokio::net::UdpSocket::bind("[::]:0").await?;
     socket.connect(addr).await?;
     let mut client = stun::client::ClientBuilder::new().with_conn(Arc::new(socket)).build()?;
@@ -42,6 +112,7 @@ pub(crate) async fn query(
     msg.set_type(stun::message::BINDING_REQUEST);
     msg.build(&[])?;
     let (send, mut recv) = tokio::sync::mpsc::unbounded_channel();
+    let sent_at = clock.now();
     client.send(&msg, Some(Arc::new(send))).await?;
     // Note that both clock.sleep() and recv.recv() are cancellable,
     // so it is safe to use them in tokio::select!.
@@ -64,6 +135,36 @@ pub(crate) async fn query(
             }
         }
     };
+    let rtt = clock.now() - sent_at;
     client.close().await?;
-    Ok(ip)
+    Ok(StunResult { ip, server: *addr, rtt })
+}
+
+/// Sends STUN BINDING requests to all the given servers in parallel.
+/// Returns the IP of this machine reported by a majority of the servers which
+/// responded, so that a single misbehaving server cannot make us use a wrong IP
+/// while servers which are down don't prevent a quorum.
+pub(crate) async fn query_multi(
+    clock: &time::Clock,
+    addrs: &[SocketAddr],
+) -> Result<IpAddr, NoQuorumError> {
+    let results = futures::future::join_all(addrs.iter().map(|addr| query(clock, addr))).await;
+    let mut ips = vec![];
+    for (addr, result) in addrs.iter().zip(results) {
+        match result {
+            Ok(result) => {
+                tracing::debug!(target: "network", server = %result.server, ip = %result.ip, rtt = ?result.rtt, "STUN query succeeded");
+                ips.push(result.ip);
+            }
+            Err(err) => tracing::warn!(target: "network", "STUN query failed for {addr}: {err}"),
+        }
+    }
+    let mut votes = HashMap::<IpAddr, usize>::new();
+    for ip in &ips {
+        *votes.entry(*ip).or_default() += 1;
+    }
+    match votes.into_iter().max_by_key(|(_, count)| *count) {
+        Some((ip, count)) if count > ips.len() / 2 => Ok(ip),
+        _ => Err(NoQuorumError { num_servers: addrs.len(), ips }),
+    }
 }
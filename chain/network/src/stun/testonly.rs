@@ -59,3 +59,57 @@ impl Server {
         self.inner.close().await.unwrap();
     }
 }
+
+/// STUN server which reports the given IP to every client, regardless of the
+/// actual address of the client. Emulates a misbehaving server.
+pub(crate) struct FixedIpServer {
+    addr: std::net::SocketAddr,
+    handle: tokio::task::JoinHandle<()>,
+}
+
+impl FixedIpServer {
+    pub async fn new(ip: std::net::IpAddr) -> Self {
+        Self::spawn(Some(ip)).await
+    }
+
+    /// Server which answers every request with an error instead of an IP, so
+    /// that queries to it fail.
+    pub async fn failing() -> Self {
+        Self::spawn(None).await
+    }
+
+    async fn spawn(ip: Option<std::net::IpAddr>) -> Self {
+        let socket = tokio::net::UdpSocket::bind("[::1]:0").await.unwrap();
+        let addr = socket.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let mut buf = vec![0; 1500];
+            while let Ok((len, src)) = socket.recv_from(&mut buf).await {
+                let mut request = stun::message::Message::new();
+                if request.unmarshal_binary(&buf[..len]).is_err() {
+                    continue;
+                }
+                let setters: Vec<Box<dyn stun::message::Setter>> = match ip {
+                    Some(ip) => vec![
+                        // Copies the transaction id of the request.
+                        Box::new(request),
+                        Box::new(stun::message::BINDING_SUCCESS),
+                        Box::new(stun::xoraddr::XorMappedAddress { ip, port: src.port() }),
+                    ],
+                    None => vec![Box::new(request), Box::new(stun::message::BINDING_ERROR)],
+                };
+                let mut response = stun::message::Message::new();
+                response.build(&setters).unwrap();
+                let _ = socket.send_to(&response.raw, src).await;
+            }
+        });
+        Self { addr, handle }
+    }
+
+    pub fn addr(&self) -> super::SocketAddr {
+        self.addr
+    }
+
+    pub fn close(self) {
+        self.handle.abort();
+    }
+}
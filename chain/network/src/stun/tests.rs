@@ -8,11 +8,121 @@ async fn test_query() {
     init_test_logger();
     let clock = time::FakeClock::default();
     let server = stun::testonly::Server::new().await;
-    let ip = stun::query(&clock.clock(), &server.addr()).await.unwrap();
-    assert_eq!(std::net::Ipv6Addr::LOCALHOST, ip);
+    let result = stun::query(&clock.clock(), &server.addr()).await.unwrap();
+    assert_eq!(std::net::Ipv6Addr::LOCALHOST, result.ip);
+    assert_eq!(server.addr(), result.server);
+    server.close().await;
+}
+
+#[tokio::test]
+async fn test_query_rtt() {
+    init_test_logger();
+    let server = stun::testonly::Server::new().await;
+    let result = stun::query(&time::Clock::real(), &server.addr()).await.unwrap();
+    assert_eq!(server.addr(), result.server);
+    assert!(result.rtt > time::Duration::ZERO && result.rtt < stun::QUERY_TIMEOUT, "{result:?}");
     server.close().await;
 }
 
+#[tokio::test]
+async fn test_query_multi() {
+    init_test_logger();
+    let clock = time::FakeClock::default();
+    let honest = stun::testonly::Server::new().await;
+    let wrong_ip = std::net::IpAddr::from([1, 2, 3, 4]);
+    let other_wrong_ip = std::net::IpAddr::from([5, 6, 7, 8]);
+    let liars = [
+        stun::testonly::FixedIpServer::new(wrong_ip).await,
+        stun::testonly::FixedIpServer::new(wrong_ip).await,
+        stun::testonly::FixedIpServer::new(other_wrong_ip).await,
+    ];
+    let clock = clock.clock();
+
+    // The honest server outvotes a single liar.
+    let ip =
+        stun::query_multi(&clock, &[honest.addr(), liars[2].addr(), honest.addr()]).await.unwrap();
+    assert_eq!(std::net::Ipv6Addr::LOCALHOST, ip);
+
+    // The majority wins even if it is wrong.
+    let ip = stun::query_multi(&clock, &[honest.addr(), liars[0].addr(), liars[1].addr()])
+        .await
+        .unwrap();
+    assert_eq!(wrong_ip, ip);
+
+    // No IP is reported by a majority of the servers.
+    let addrs = [honest.addr(), liars[0].addr(), liars[2].addr()];
+    assert!(stun::query_multi(&clock, &addrs).await.is_err());
+    let addrs = [honest.addr(), liars[0].addr()];
+    assert!(stun::query_multi(&clock, &addrs).await.is_err());
+
+    // Only the servers which responded are counted towards the majority.
+    let failing = [
+        stun::testonly::FixedIpServer::failing().await,
+        stun::testonly::FixedIpServer::failing().await,
+    ];
+    let addrs =
+        [honest.addr(), honest.addr(), liars[0].addr(), failing[0].addr(), failing[1].addr()];
+    let ip = stun::query_multi(&clock, &addrs).await.unwrap();
+    assert_eq!(std::net::Ipv6Addr::LOCALHOST, ip);
+    let addrs = [honest.addr(), failing[0].addr()];
+    let ip = stun::query_multi(&clock, &addrs).await.unwrap();
+    assert_eq!(std::net::Ipv6Addr::LOCALHOST, ip);
+    let addrs = [failing[0].addr(), failing[1].addr()];
+    assert!(stun::query_multi(&clock, &addrs).await.is_err());
+
+    for server in liars.into_iter().chain(failing) {
+        server.close();
+    }
+    honest.close().await;
+}
+
+#[tokio::test]
+async fn test_lookup_cache() {
+    init_test_logger();
+    let clock = time::FakeClock::default();
+    let cache = stun::LookupCache::new(time::Duration::minutes(10));
+    let server = "stun.example.com:3478".to_string();
+    let ipv4: std::net::SocketAddr = "1.2.3.4:3478".parse().unwrap();
+    let ipv6: std::net::SocketAddr = "[2001:db8::1]:3478".parse().unwrap();
+    let new_ipv4: std::net::SocketAddr = "5.6.7.8:3478".parse().unwrap();
+
+    // The addresses of both families are cached separately.
+    assert_eq!(
+        Some(ipv4),
+        cache.lookup_with(&clock.clock(), &server, true, async { Some(ipv4) }).await
+    );
+    assert_eq!(
+        Some(ipv6),
+        cache.lookup_with(&clock.clock(), &server, false, async { Some(ipv6) }).await
+    );
+    // Within the TTL, the host isn't resolved again.
+    assert_eq!(
+        Some(ipv4),
+        cache.lookup_with(&clock.clock(), &server, true, async { Some(new_ipv4) }).await
+    );
+
+    // After the TTL, a failed lookup falls back to the cached address.
+    clock.advance(time::Duration::minutes(11));
+    assert_eq!(Some(ipv4), cache.lookup_with(&clock.clock(), &server, true, async { None }).await);
+    assert_eq!(Some(ipv6), cache.lookup_with(&clock.clock(), &server, false, async { None }).await);
+    // And a successful one replaces it.
+    assert_eq!(
+        Some(new_ipv4),
+        cache.lookup_with(&clock.clock(), &server, true, async { Some(new_ipv4) }).await
+    );
+    assert_eq!(
+        Some(new_ipv4),
+        cache.lookup_with(&clock.clock(), &server, true, async { None }).await
+    );
+
+    // Nothing to fall back to for a server which was never resolved.
+    let unknown_server = "stun.unknown.com:3478".to_string();
+    assert_eq!(
+        None,
+        cache.lookup_with(&clock.clock(), &unknown_server, true, async { None }).await
+    );
+}
+
 #[tokio::test]
 async fn test_lookup_host() {
     init_test_logger();
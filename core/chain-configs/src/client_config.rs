@@ -28,6 +28,10 @@ pub const MIN_GC_NUM_EPOCHS_TO_KEEP: u64 = 3;
 /// Default number of epochs for which we keep store data
 pub const DEFAULT_GC_NUM_EPOCHS_TO_KEEP: u64 = 5;
 
+/// Maximum number of extra epochs for which execution outcomes can be retained
+/// after the blocks they belong to are garbage collected.
+pub const MAX_EXTRA_OUTCOME_RETENTION_EPOCHS: u64 = 100;
+
 /// Default number of concurrent requests to external storage to fetch state parts.
 pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL: u32 = 25;
 pub const DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL: u32 = 5;
@@ -50,6 +54,12 @@ pub struct GCConfig {
     /// How often gc should be run
     #[serde(with = "near_time::serde_duration_as_std")]
     pub gc_step_period: Duration,
+
+    /// Number of epochs for which execution outcomes are kept after the block
+    /// they belong to is garbage collected. Allows RPC nodes to answer
+    /// transaction status queries for longer without running as archival
+    /// nodes. Capped at `MAX_EXTRA_OUTCOME_RETENTION_EPOCHS`; 0 disables it.
+    pub extra_outcome_retention_epochs: u64,
 }
 
 impl Default for GCConfig {
@@ -59,6 +69,7 @@ impl Default for GCConfig {
             gc_fork_clean_step: 100,
             gc_num_epochs_to_keep: DEFAULT_GC_NUM_EPOCHS_TO_KEEP,
             gc_step_period: Duration::seconds(1),
+            extra_outcome_retention_epochs: 0,
         }
     }
 }
@@ -67,6 +78,10 @@ impl GCConfig {
     pub fn gc_num_epochs_to_keep(&self) -> u64 {
         max(MIN_GC_NUM_EPOCHS_TO_KEEP, self.gc_num_epochs_to_keep)
     }
+
+    pub fn extra_outcome_retention_epochs(&self) -> u64 {
+        min(MAX_EXTRA_OUTCOME_RETENTION_EPOCHS, self.extra_outcome_retention_epochs)
+    }
 }
 
 fn default_num_concurrent_requests() -> u32 {
@@ -81,6 +96,11 @@ fn default_num_concurrent_requests_during_catchup() -> u32 {
 pub struct ExternalStorageConfig {
     /// Location of state parts.
     pub location: ExternalStorageLocation,
+    /// Mirrors of `location` to fail over to when downloads from it fail,
+    /// in the order of preference.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub additional_locations: Vec<ExternalStorageLocation>,
     /// When fetching state parts from external storage, throttle fetch requests
     /// to this many concurrent requests.
     #[serde(default = "default_num_concurrent_requests")]
@@ -89,6 +109,61 @@ pub struct ExternalStorageConfig {
     /// to reduce the performance impact of state sync.
     #[serde(default = "default_num_concurrent_requests_during_catchup")]
     pub num_concurrent_requests_during_catchup: u32,
+    /// If set, state sync starts by applying the state of a single shard at a
+    /// time and linearly increases the limit to all the tracked shards over
+    /// this period. The schedule starts over for every new sync and whenever
+    /// applying the state fails. Smooths resource usage at the start of state
+    /// sync on nodes that also serve traffic. If unset, the state of all the
+    /// shards is applied at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    pub concurrency_ramp_up_period: Option<Duration>,
+    /// Compression format of the state parts to try first when downloading.
+    /// Parts stored in a different format are still found and decompressed.
+    #[serde(default)]
+    pub compression: Compression,
+    /// If set, the external storage is not used for `circuit_breaker_cooldown`
+    /// after this many consecutive failed downloads, and the state is requested
+    /// from peers instead. Protects against retrying forever against a dead
+    /// bucket.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub circuit_breaker_failure_threshold: Option<u32>,
+    /// How long to avoid the external storage once the circuit breaker trips.
+    /// Defaults to 10 minutes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    pub circuit_breaker_cooldown: Option<Duration>,
+    /// Upper bound of the cooldown of the circuit breaker. The cooldown doubles
+    /// every time the breaker trips again without a successful download since
+    /// the previous trip, up to this value. Defaults to 1 hour.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    pub circuit_breaker_max_cooldown: Option<Duration>,
+    /// If set, every downloaded state part is checked against the checksum
+    /// stored next to it, and downloaded again on a mismatch. Requires the
+    /// state to be dumped by a node which writes the checksums.
+    #[serde(default)]
+    pub verify_checksums: bool,
+    /// If set, limits the rate of downloading the state from the external
+    /// storage, shared by all the concurrent requests. Useful to leave enough
+    /// bandwidth for block processing on a shared machine.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub max_bytes_per_sec: Option<u64>,
+}
+
+/// Compression applied to the state parts stored in external storage.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Compression {
+    /// State parts are stored as is.
+    #[default]
+    None,
+    /// State parts are compressed with zstd and stored with a `.zst` suffix.
+    Zstd,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
@@ -105,6 +180,17 @@ pub enum ExternalStorageLocation {
     GCS {
         bucket: String,
     },
+    Azure {
+        /// Storage account holding the container.
+        account: String,
+        /// Container with the state dumps.
+        container: String,
+        /// Overrides the default `https://{account}.blob.core.windows.net`
+        /// endpoint, e.g. to point at a local emulator.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        endpoint: Option<String>,
+    },
 }
 
 /// Configures how to dump state to external storage.
@@ -123,8 +209,12 @@ pub struct DumpConfig {
     #[serde(with = "near_time::serde_opt_duration_as_std")]
     pub iteration_delay: Option<Duration>,
     /// Location of a json file with credentials allowing write access to the bucket.
+    /// For Azure, the file contains a SAS token of the container instead.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub credentials_file: Option<PathBuf>,
+    /// Compression to apply to the state parts before uploading them.
+    #[serde(default)]
+    pub compression: Compression,
 }
 
 /// Configures how to fetch state parts during state sync.
@@ -150,6 +240,41 @@ pub struct StateSyncConfig {
     pub dump: Option<DumpConfig>,
     #[serde(skip_serializing_if = "SyncConfig::is_default", default = "SyncConfig::default")]
     pub sync: SyncConfig,
+    /// If set, state sync of a single shard is abandoned once it takes longer
+    /// than this, and the node retries state sync from a fresh sync hash.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    pub per_shard_total_timeout: Option<Duration>,
+    /// How long to wait for a state header from the configured source before
+    /// requesting it again. Defaults to `state_sync_timeout`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    pub header_timeout: Option<Duration>,
+    /// How long to wait for a state part from the configured source before
+    /// requesting it again. Defaults to `state_sync_timeout`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    #[serde(with = "near_time::serde_opt_duration_as_std")]
+    pub part_timeout: Option<Duration>,
+    /// Shards whose state should be synced first, most urgent first. These
+    /// shards are advanced before all others on every state sync step and so
+    /// get the first share of the download concurrency. Shards not listed
+    /// here follow in their natural order.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub shard_priority: Vec<ShardId>,
+    /// After the state parts of a shard are applied, build a memtrie from the
+    /// resulting flat state and check its root against the expected state
+    /// root before finalizing the sync. This reads the whole shard state.
+    #[serde(default)]
+    pub verify_memtrie: bool,
+    /// Apply the downloaded state parts on a dedicated thread pool instead of
+    /// the arbiter which also drives the state part downloads, so that the CPU
+    /// heavy work doesn't stall the network IO of state sync.
+    #[serde(default)]
+    pub dedicated_computation_pool: bool,
 }
 
 impl SyncConfig {
@@ -270,10 +395,24 @@ pub fn default_state_sync() -> Option<StateSyncConfig> {
         dump: None,
         sync: SyncConfig::ExternalStorage(ExternalStorageConfig {
             location: GCS { bucket: "state-parts".to_string() },
+            additional_locations: vec![],
             num_concurrent_requests: DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_EXTERNAL,
             num_concurrent_requests_during_catchup:
                 DEFAULT_STATE_SYNC_NUM_CONCURRENT_REQUESTS_ON_CATCHUP_EXTERNAL,
+            concurrency_ramp_up_period: None,
+            compression: Compression::None,
+            circuit_breaker_failure_threshold: None,
+            circuit_breaker_cooldown: None,
+            circuit_breaker_max_cooldown: None,
+            verify_checksums: false,
+            max_bytes_per_sec: None,
         }),
+        per_shard_total_timeout: None,
+        header_timeout: None,
+        part_timeout: None,
+        shard_priority: vec![],
+        verify_memtrie: false,
+        dedicated_computation_pool: false,
     })
 }
 
@@ -297,6 +436,10 @@ pub fn default_trie_viewer_state_size_limit() -> Option<u64> {
     Some(50_000)
 }
 
+pub fn default_max_concurrent_view_calls() -> Option<usize> {
+    Some(256)
+}
+
 pub fn default_transaction_pool_size_limit() -> Option<u64> {
     Some(100_000_000) // 100 MB.
 }
@@ -313,6 +456,24 @@ pub fn default_produce_chunk_add_transactions_time_limit() -> Option<Duration> {
     Some(Duration::milliseconds(200))
 }
 
+/// Fraction of the chunk request retry period by which the re-requests are
+/// jittered, so that the nodes don't re-request chunks in lockstep.
+pub fn default_chunk_request_retry_jitter() -> f64 {
+    0.1
+}
+
+pub fn default_max_chunk_response_parts() -> usize {
+    1024
+}
+
+pub fn default_max_chunk_response_bytes() -> usize {
+    64 * 1024 * 1024
+}
+
+pub fn default_max_chain_head_staleness() -> Duration {
+    Duration::minutes(5)
+}
+
 /// Returns the default size of the OrphanStateWitnessPool, ie. the maximum number of
 /// state-witnesses that can be accommodated in OrphanStateWitnessPool.
 pub fn default_orphan_state_witness_pool_size() -> usize {
@@ -402,6 +563,22 @@ pub struct ClientConfig {
     pub catchup_step_period: Duration,
     /// Time between checking to re-request chunks.
     pub chunk_request_retry_period: Duration,
+    /// Fraction of `chunk_request_retry_period` by which every re-request of
+    /// the chunks is randomly delayed or advanced.
+    pub chunk_request_retry_jitter: f64,
+    /// Whether to persist the pending chunk requests across restarts.
+    pub persist_chunk_request_pool: bool,
+    /// Maximum number of parts and receipt proofs in a single chunk part
+    /// response served to a peer.
+    pub max_chunk_response_parts: usize,
+    /// Maximum size in bytes of a single chunk part response served to a peer.
+    pub max_chunk_response_bytes: usize,
+    /// How long the chain heads cached by the shards manager may go without an
+    /// update from the client before a warning is logged.
+    pub max_chain_head_staleness: Duration,
+    /// Maximum number of parts forwarded ahead of the chunk header that are
+    /// cached per chunk. The number of parts of a chunk is the limit if unset.
+    pub max_forwarded_parts_per_chunk: Option<usize>,
     /// Time between running doomslug timer.
     pub doosmslug_step_period: Duration,
     /// Behind this horizon header fetch kicks in.
@@ -435,6 +612,19 @@ pub struct ClientConfig {
     /// genesis file.  The value only affects the RPCs without influencing the
     /// protocol thus changing it per-node doesn’t affect the blockchain.
     pub max_gas_burnt_view: Option<Gas>,
+    /// Upper bound on the gas limit a single view function call may ask for
+    /// instead of `max_gas_burnt_view`.  None only allows asking for up to
+    /// `max_gas_burnt_view`.
+    pub max_gas_burnt_view_cap: Option<Gas>,
+    /// Upper bound on the number of view function calls executed at the same
+    /// time.  Calls above the limit are rejected rather than queued.  None is
+    /// no limit.
+    pub max_concurrent_view_calls: Option<usize>,
+    /// Number of accounts cached by `view_account` queries.  Accounts are
+    /// cached by the state root they were read at, so repeated queries at the
+    /// same block skip reading and decoding the account.  None disables the
+    /// cache.
+    pub view_account_cache_size: Option<usize>,
     /// Re-export storage layer statistics as prometheus metrics.
     pub enable_statistics_export: bool,
     /// Number of threads to execute background migration work in client.
@@ -463,6 +653,9 @@ pub struct ClientConfig {
     /// some limit is reached. This time limit ensures that adding transactions won't take
     /// longer than the specified duration, which helps to produce the chunk quickly.
     pub produce_chunk_add_transactions_time_limit: MutableConfigValue<Option<Duration>>,
+    /// Shards whose state sync is paused, e.g. to throttle the sync of a heavy
+    /// shard. The parts downloaded so far are kept.
+    pub state_sync_paused_shards: MutableConfigValue<Vec<ShardId>>,
     /// Optional config for the Chunk Distribution Network feature.
     /// If set to `None` then this node does not participate in the Chunk Distribution Network.
     /// Nodes not participating will still function fine, but possibly with higher
@@ -535,6 +728,12 @@ impl ClientConfig {
                 Duration::milliseconds(100),
                 Duration::milliseconds(min_block_prod_time as i64 / 5),
             ),
+            chunk_request_retry_jitter: default_chunk_request_retry_jitter(),
+            persist_chunk_request_pool: false,
+            max_chunk_response_parts: default_max_chunk_response_parts(),
+            max_chunk_response_bytes: default_max_chunk_response_bytes(),
+            max_chain_head_staleness: default_max_chain_head_staleness(),
+            max_forwarded_parts_per_chunk: None,
             doosmslug_step_period: Duration::milliseconds(100),
             block_header_fetch_horizon: 50,
             gc: GCConfig { gc_blocks_limit: 100, ..GCConfig::default() },
@@ -549,6 +748,9 @@ impl ClientConfig {
             view_client_throttle_period: Duration::seconds(1),
             trie_viewer_state_size_limit: None,
             max_gas_burnt_view: None,
+            max_gas_burnt_view_cap: None,
+            max_concurrent_view_calls: None,
+            view_account_cache_size: None,
             enable_statistics_export: true,
             client_background_migration_threads: 1,
             flat_storage_creation_enabled: true,
@@ -566,6 +768,7 @@ impl ClientConfig {
                 default_produce_chunk_add_transactions_time_limit(),
                 "produce_chunk_add_transactions_time_limit",
             ),
+            state_sync_paused_shards: MutableConfigValue::new(vec![], "state_sync_paused_shards"),
             chunk_distribution_network: None,
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
@@ -1,4 +1,4 @@
-use near_primitives::types::BlockHeight;
+use near_primitives::types::{BlockHeight, ShardId};
 use near_primitives::validator_signer::ValidatorSigner;
 #[cfg(feature = "metrics")]
 use near_time::Clock;
@@ -108,6 +108,10 @@ pub struct UpdateableClientConfig {
     #[serde(default)]
     #[serde(with = "near_time::serde_opt_duration_as_std")]
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+
+    /// Shards whose state sync is paused.
+    #[serde(default)]
+    pub state_sync_paused_shards: Vec<ShardId>,
 }
 
 pub type MutableValidatorSigner = MutableConfigValue<Option<Arc<ValidatorSigner>>>;
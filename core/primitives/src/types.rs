@@ -278,6 +278,33 @@ impl StateChangeValue {
             | StateChangeValue::ContractCodeDeletion { account_id } => account_id,
         }
     }
+
+    pub fn kind(&self) -> StateChangeValueKind {
+        match &self {
+            StateChangeValue::AccountUpdate { .. } | StateChangeValue::AccountDeletion { .. } => {
+                StateChangeValueKind::AccountTouched
+            }
+            StateChangeValue::AccessKeyUpdate { .. }
+            | StateChangeValue::AccessKeyDeletion { .. } => StateChangeValueKind::AccessKeyTouched,
+            StateChangeValue::DataUpdate { .. } | StateChangeValue::DataDeletion { .. } => {
+                StateChangeValueKind::DataTouched
+            }
+            StateChangeValue::ContractCodeUpdate { .. }
+            | StateChangeValue::ContractCodeDeletion { .. } => {
+                StateChangeValueKind::ContractCodeTouched
+            }
+        }
+    }
+}
+
+/// The kind of a state change, regardless of the affected account.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum StateChangeValueKind {
+    AccountTouched,
+    AccessKeyTouched,
+    DataTouched,
+    ContractCodeTouched,
 }
 
 #[derive(Debug)]
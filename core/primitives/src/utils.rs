@@ -9,7 +9,7 @@ use serde;
 
 use crate::hash::{hash, CryptoHash};
 use crate::transaction::SignedTransaction;
-use crate::types::{NumSeats, NumShards, ShardId};
+use crate::types::{BlockHeight, NumSeats, NumShards, ShardId};
 use crate::version::{
     ProtocolVersion, CORRECT_RANDOM_VALUE_PROTOCOL_VERSION, CREATE_HASH_PROTOCOL_VERSION,
     CREATE_RECEIPT_ID_SWITCH_TO_CURRENT_BLOCK_VERSION,
@@ -220,6 +220,24 @@ pub fn get_outcome_id_block_hash_rev(key: &[u8]) -> std::io::Result<(CryptoHash,
     Ok((outcome_id, block_hash))
 }
 
+/// Key of a (height, block hash) pair. The height is big-endian so that
+/// iterating over the keys visits them in height order.
+pub fn get_height_block_hash(height: BlockHeight, block_hash: &CryptoHash) -> Vec<u8> {
+    let mut res = Vec::with_capacity(40);
+    res.extend_from_slice(&height.to_be_bytes());
+    res.extend_from_slice(block_hash.as_ref());
+    res
+}
+
+pub fn get_height_block_hash_rev(key: &[u8]) -> std::io::Result<(BlockHeight, CryptoHash)> {
+    if key.len() != 40 {
+        return Err(std::io::Error::new(std::io::ErrorKind::InvalidData, "Invalid key length"));
+    }
+    let height = BlockHeight::from_be_bytes(key[..8].try_into().unwrap());
+    let block_hash = CryptoHash::try_from(&key[8..]).unwrap();
+    Ok((height, block_hash))
+}
+
 /// Creates a new Receipt ID from a given signed transaction and a block hash.
 /// This method is backward compatible, so it takes the current protocol version.
 pub fn create_receipt_id_from_transaction(
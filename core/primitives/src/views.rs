@@ -449,6 +449,33 @@ pub struct SnapshotHostsView {
     pub hosts: Vec<SnapshotHostInfoView>,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct AnnounceAccountView {
+    pub account_id: AccountId,
+    pub peer_id: PeerId,
+    pub epoch_id: EpochId,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct AnnounceAccountsView {
+    pub announce_accounts: Vec<AnnounceAccountView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct AnnounceAccountBroadcastView {
+    pub account_id: AccountId,
+    pub peer_id: PeerId,
+    pub epoch_id: EpochId,
+    /// Connected peers the announcement would be broadcast to. Empty if it
+    /// has been broadcast already.
+    pub would_broadcast_to: Vec<PeerId>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct AnnounceAccountBroadcastsView {
+    pub broadcasts: Vec<AnnounceAccountBroadcastView>,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
 pub struct EdgeView {
     pub peer0: PeerId,
@@ -456,10 +483,12 @@ pub struct EdgeView {
     pub nonce: u64,
 }
 
-#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
 pub struct NetworkGraphView {
     pub edges: Vec<EdgeView>,
     pub next_hops: HashMap<PeerId, Vec<PeerId>>,
+    /// Fraction of the peers known to the routing protocol which are reachable.
+    pub reachability_ratio: f64,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
@@ -493,6 +522,16 @@ pub struct NetworkRoutesView {
 pub struct ShardSyncDownloadView {
     pub downloads: Vec<DownloadStatusView>,
     pub status: String,
+    /// Unset while the state header is being downloaded, as the number of
+    /// parts isn't known yet.
+    #[serde(default)]
+    pub progress: Option<ShardSyncProgressView>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
+pub struct ShardSyncProgressView {
+    pub num_parts_done: u64,
+    pub num_parts: u64,
 }
 
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq)]
@@ -538,6 +577,20 @@ impl From<Tip> for BlockStatusView {
     }
 }
 
+/// Machine readable snapshot of a single in-flight state sync download.
+#[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+pub struct StateSyncTaskView {
+    // Identifies the download within the shard, e.g. "header" or "part 3".
+    pub task_id: String,
+    pub shard_id: ShardId,
+    // Phase of the shard sync the download belongs to.
+    pub phase: String,
+    // Time since the download was started.
+    pub elapsed_ms: u128,
+    // One of "pending", "requested" or "failed".
+    pub state: String,
+}
+
 #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq, Eq, Clone)]
 pub struct PartElapsedTimeView {
     pub part_id: u64,
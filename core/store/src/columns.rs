@@ -69,6 +69,11 @@ pub enum DBCol {
     /// - *Rows*: single row (empty row name)
     /// - *Content type*: Vec of [network_primitives::types::ConnectionInfo]
     RecentOutboundConnections,
+    /// Per-peer index of RecentOutboundConnections, holding the most recent
+    /// outbound TIER2 connection to each of the peers listed there.
+    /// - *Rows*: PeerId
+    /// - *Content type*: [network_primitives::types::ConnectionInfo]
+    RecentOutboundConnectionsByPeer,
     /// Mapping from EpochId to EpochInfo
     /// - *Rows*: EpochId (CryptoHash)
     /// - *Content type*: [near_primitives::epoch_manager::epoch_info::EpochInfo]
@@ -294,6 +299,23 @@ pub enum DBCol {
     /// Witnesses with the lowest index are garbage collected first.
     /// u64 -> LatestWitnessesKey
     LatestWitnessesByIndex,
+    /// Records for which epochs and shards this node currently has a state snapshot,
+    /// i.e. which state parts it can serve to peers. Entries are added when a state
+    /// snapshot is created and removed when the snapshot is deleted.
+    /// - *Rows*: EpochShardId (EpochId || ShardId) - 40 bytes
+    /// - *Column type*: empty
+    StateSnapshotAvailability,
+    /// Execution outcomes kept past the regular GC window when
+    /// `extra_outcome_retention_epochs` is configured. Mirrors
+    /// DBCol::TransactionResultForBlock for blocks that were already GC-ed.
+    /// - *Rows*: OutcomeId (CryptoHash) || BlockHash (CryptoHash)
+    /// - *Column type*: [near_primitives::transaction::ExecutionOutcomeWithProof]
+    RetainedOutcomes,
+    /// Index of DBCol::RetainedOutcomes by the height of the block the
+    /// outcomes belong to, used to expire them.
+    /// - *Rows*: BlockHeight (big-endian u64) || BlockHash (CryptoHash)
+    /// - *Column type*: Vec<OutcomeId (CryptoHash)>
+    RetainedOutcomeIdsByHeight,
     /// Column to store data for Epoch Sync.
     /// Does not contain data for genesis epoch.
     /// - *Rows*: `epoch_id`
@@ -473,6 +495,11 @@ impl DBCol {
             // LatestChunkStateWitnesses stores the last N observed witnesses, used only for debugging.
             DBCol::LatestChunkStateWitnesses => false,
             DBCol::LatestWitnessesByIndex => false,
+            // StateSnapshotAvailability describes local state snapshots only.
+            DBCol::StateSnapshotAvailability => false,
+            // RetainedOutcomes only extend the hot storage GC window; cold
+            // storage already has the outcomes in TransactionResultForBlock.
+            DBCol::RetainedOutcomes | DBCol::RetainedOutcomeIdsByHeight => false,
 
             // Columns that are not GC-ed need not be copied to the cold storage.
             DBCol::BlockHeader
@@ -480,6 +507,7 @@ impl DBCol {
             | DBCol::BlockHeight
             | DBCol::_Peers
             | DBCol::RecentOutboundConnections
+            | DBCol::RecentOutboundConnectionsByPeer
             | DBCol::BlockMerkleTree
             | DBCol::AccountAnnouncements
             | DBCol::EpochLightClientBlocks
@@ -527,6 +555,7 @@ impl DBCol {
             DBCol::IncomingReceipts => &[DBKeyType::BlockHash, DBKeyType::ShardId],
             DBCol::_Peers => &[DBKeyType::PeerId],
             DBCol::RecentOutboundConnections => &[DBKeyType::Empty],
+            DBCol::RecentOutboundConnectionsByPeer => &[DBKeyType::PeerId],
             DBCol::EpochInfo => &[DBKeyType::EpochId],
             DBCol::BlockInfo => &[DBKeyType::BlockHash],
             DBCol::Chunks => &[DBKeyType::ChunkHash],
@@ -574,6 +603,9 @@ impl DBCol {
             DBCol::StateTransitionData => &[DBKeyType::BlockHash, DBKeyType::ShardId],
             DBCol::LatestChunkStateWitnesses => &[DBKeyType::LatestWitnessesKey],
             DBCol::LatestWitnessesByIndex => &[DBKeyType::LatestWitnessIndex],
+            DBCol::StateSnapshotAvailability => &[DBKeyType::EpochId, DBKeyType::ShardId],
+            DBCol::RetainedOutcomes => &[DBKeyType::OutcomeId, DBKeyType::BlockHash],
+            DBCol::RetainedOutcomeIdsByHeight => &[DBKeyType::BlockHeight, DBKeyType::BlockHash],
             #[cfg(feature = "new_epoch_sync")]
             DBCol::EpochSyncInfo => &[DBKeyType::EpochId],
         }
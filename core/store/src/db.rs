@@ -241,6 +241,14 @@ pub trait Database: Sync + Send {
     /// is blocking until compaction finishes. Otherwise, this is a no-op.
     fn compact(&self) -> io::Result<()>;
 
+    /// Compact representation of the given columns.
+    ///
+    /// Databases which can't compact individual columns compact everything.
+    fn compact_columns(&self, cols: &[DBCol]) -> io::Result<()> {
+        let _ = cols;
+        self.compact()
+    }
+
     /// Returns statistics about the database if available.
     fn get_store_statistics(&self) -> Option<StoreStatistics>;
 
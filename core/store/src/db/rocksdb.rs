@@ -415,6 +415,13 @@ impl Database for RocksDB {
         Ok(())
     }
 
+    fn compact_columns(&self, cols: &[DBCol]) -> io::Result<()> {
+        for col in cols {
+            self.compact_column(*col)?;
+        }
+        Ok(())
+    }
+
     #[tracing::instrument(
         target = "store::db::rocksdb",
         level = "debug",
@@ -107,6 +107,13 @@ impl<'a> DiskTrieIterator<'a> {
         self.seek_nibble_slice(NibbleSlice::new(key.as_ref()), true).map(drop)
     }
 
+    /// Position the iterator on the first element with key >= `key`.  Unlike
+    /// [`Self::seek_prefix`], the iteration isn't limited to the keys starting
+    /// with `key` and continues until the end of the trie.
+    pub fn seek<K: AsRef<[u8]>>(&mut self, key: K) -> Result<(), StorageError> {
+        self.seek_nibble_slice(NibbleSlice::new(key.as_ref()), false).map(drop)
+    }
+
     /// Configures whether the iterator should remember all the nodes its
     /// visiting.
     ///
@@ -4,7 +4,7 @@ use crate::flat::store_helper::{
     decode_flat_state_db_key, get_all_deltas_metadata, get_delta_changes, get_flat_storage_status,
 };
 use crate::flat::{FlatStorageError, FlatStorageStatus};
-use crate::trie::mem::arena::Arena;
+use crate::trie::mem::arena::{Arena, STArena};
 use crate::trie::mem::construction::TrieConstructor;
 use crate::trie::mem::parallel_loader::load_memtrie_in_parallel;
 use crate::trie::mem::updating::apply_memtrie_changes;
@@ -58,44 +58,13 @@ pub fn load_trie_from_flat_state(
     tries.construct_root(block_height, |arena| -> Result<Option<MemTrieNodeId>, StorageError> {
         info!(target: "memtrie", shard_uid=%shard_uid, "Loading trie from flat state...");
         let load_start = Instant::now();
-        let mut recon = TrieConstructor::new(arena);
-        let mut num_keys_loaded = 0;
-        for item in store
-            .iter_prefix_ser::<FlatStateValue>(DBCol::FlatState, &borsh::to_vec(&shard_uid).unwrap())
-        {
-            let (key, value) = item.map_err(|err| {
-                FlatStorageError::StorageInternalError(format!("Error iterating over FlatState: {err}"))
-            })?;
-            let (_, key) = decode_flat_state_db_key(&key).map_err(|err| {
-                FlatStorageError::StorageInternalError(format!(
-                    "invalid FlatState key format: {err}"
-                ))})?;
-            recon.add_leaf(NibbleSlice::new(&key), value);
-            num_keys_loaded += 1;
-            if num_keys_loaded % 1000000 == 0 {
-                debug!(
-                    target: "memtrie",
-                    %shard_uid,
-                    "Loaded {} keys, current key: {}",
-                    num_keys_loaded,
-                    hex::encode(&key)
-                );
-            }
-        }
-        let root_id = match recon.finalize() {
+        let root_id = match construct_trie_from_flat_state(arena, store, shard_uid)? {
             Some(root_id) => root_id,
             None => {
                 info!(target: "memtrie", shard_uid=%shard_uid, "No keys loaded, trie is empty");
                 return Ok(None);
             }
         };
-
-        debug!(
-            target: "memtrie",
-            %shard_uid,
-            "Loaded {} keys in total",
-            num_keys_loaded
-        );
         info!(target: "memtrie", shard_uid=%shard_uid, "Done loading trie from flat state, took {:?}", load_start.elapsed());
 
         let root = root_id.as_ptr(arena.memory());
@@ -107,6 +76,58 @@ pub fn load_trie_from_flat_state(
     Ok(tries)
 }
 
+/// Builds a trie from the FlatState column of the shard in the given arena.
+/// Returns `None` if the shard has no keys.
+fn construct_trie_from_flat_state(
+    arena: &mut STArena,
+    store: &Store,
+    shard_uid: ShardUId,
+) -> Result<Option<MemTrieNodeId>, StorageError> {
+    let mut recon = TrieConstructor::new(arena);
+    let mut num_keys_loaded = 0;
+    for item in store
+        .iter_prefix_ser::<FlatStateValue>(DBCol::FlatState, &borsh::to_vec(&shard_uid).unwrap())
+    {
+        let (key, value) = item.map_err(|err| {
+            FlatStorageError::StorageInternalError(format!("Error iterating over FlatState: {err}"))
+        })?;
+        let (_, key) = decode_flat_state_db_key(&key).map_err(|err| {
+            FlatStorageError::StorageInternalError(format!("invalid FlatState key format: {err}"))
+        })?;
+        recon.add_leaf(NibbleSlice::new(&key), value);
+        num_keys_loaded += 1;
+        if num_keys_loaded % 1000000 == 0 {
+            debug!(
+                target: "memtrie",
+                %shard_uid,
+                "Loaded {} keys, current key: {}",
+                num_keys_loaded,
+                hex::encode(&key)
+            );
+        }
+    }
+    let root_id = recon.finalize();
+    debug!(
+        target: "memtrie",
+        %shard_uid,
+        "Loaded {} keys in total",
+        num_keys_loaded
+    );
+    Ok(root_id)
+}
+
+/// Builds an in-memory trie from the FlatState column of the shard and
+/// returns its root, so that it can be compared against the expected state
+/// root, e.g. after the state of the shard was restored from state parts.
+/// Unlike `load_trie_from_flat_state`, a mismatching root is not a panic.
+pub fn compute_trie_root_from_flat_state(
+    store: &Store,
+    shard_uid: ShardUId,
+) -> Result<StateRoot, StorageError> {
+    let mut tries = MemTries::new(shard_uid);
+    tries.construct_root(0, |arena| construct_trie_from_flat_state(arena, store, shard_uid))
+}
+
 fn get_state_root(
     store: &Store,
     block_hash: CryptoHash,
@@ -207,7 +228,7 @@ mod tests {
         create_test_store, simplify_changes, test_populate_flat_storage, test_populate_trie,
         TestTriesBuilder,
     };
-    use crate::trie::mem::loading::load_trie_from_flat_state;
+    use crate::trie::mem::loading::{compute_trie_root_from_flat_state, load_trie_from_flat_state};
     use crate::trie::mem::lookup::memtrie_lookup;
     use crate::{DBCol, KeyLookupMode, NibbleSlice, ShardTries, Store, Trie, TrieUpdate};
     use near_primitives::congestion_info::CongestionInfo;
@@ -245,6 +266,10 @@ mod tests {
         )
         .unwrap();
         eprintln!("In memory trie loaded");
+        assert_eq!(
+            compute_trie_root_from_flat_state(&shard_tries.get_store(), shard_uid).unwrap(),
+            state_root
+        );
 
         if keys.is_empty() {
             assert_eq!(in_memory_trie.num_roots(), 0);
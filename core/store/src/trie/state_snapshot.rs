@@ -10,6 +10,8 @@ use near_primitives::errors::EpochError;
 use near_primitives::errors::StorageError;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{EpochId, ShardId};
+use near_primitives::utils::get_block_shard_id;
 use std::error::Error;
 use std::io;
 use std::path::{Path, PathBuf};
@@ -236,6 +238,11 @@ impl ShardTries {
             }
         }
 
+        let epoch_id = block.header().epoch_id();
+        if let Err(err) = self.set_state_snapshot_availability(Some((epoch_id, shard_uids))) {
+            tracing::error!(target: "state_snapshot", ?err, ?epoch_id, "Failed to record the state snapshot availability in rocksdb");
+        }
+
         metrics::HAS_STATE_SNAPSHOT.set(1);
         tracing::info!(target: "state_snapshot", ?prev_block_hash, "Made a checkpoint");
         Ok(Some(state_snapshot_lock.as_ref().unwrap().get_shard_uids()))
@@ -276,6 +283,16 @@ impl ShardTries {
             }
         }
 
+        // this will clear the availability records of the deleted snapshot. Will retry 3 times
+        for _ in 0..3 {
+            match self.set_state_snapshot_availability(None) {
+                Ok(_) => break,
+                Err(err) => {
+                    tracing::error!(target: "state_snapshot", ?err, "Failed to clear the state snapshot availability in rocksdb")
+                }
+            }
+        }
+
         metrics::HAS_STATE_SNAPSHOT.set(0);
     }
 
@@ -326,6 +343,47 @@ impl ShardTries {
         store_update.commit().into()
     }
 
+    /// Returns whether this node has a state snapshot from which it can serve
+    /// state parts of `shard_id` for the epoch `epoch_id`.
+    ///
+    /// Returns None if the availability is unknown because nothing is recorded,
+    /// e.g. when there is no snapshot or the snapshot was made by a binary
+    /// which didn't record the availability yet.
+    pub fn is_state_snapshot_available(
+        &self,
+        epoch_id: &EpochId,
+        shard_id: ShardId,
+    ) -> Result<Option<bool>, io::Error> {
+        let store = self.get_store();
+        if store.iter(DBCol::StateSnapshotAvailability).next().transpose()?.is_none() {
+            return Ok(None);
+        }
+        store
+            .exists(DBCol::StateSnapshotAvailability, &get_block_shard_id(&epoch_id.0, shard_id))
+            .map(Some)
+    }
+
+    /// Replaces the records in DBCol::StateSnapshotAvailability with the given
+    /// epoch and shards, or removes all of them if `value` is None.
+    fn set_state_snapshot_availability(
+        &self,
+        value: Option<(&EpochId, &[ShardUId])>,
+    ) -> Result<(), io::Error> {
+        let store = self.get_store();
+        let mut store_update = self.store_update();
+        for item in store.iter(DBCol::StateSnapshotAvailability) {
+            let (key, _) = item?;
+            store_update.delete(DBCol::StateSnapshotAvailability, &key);
+        }
+        if let Some((epoch_id, shard_uids)) = value {
+            for shard_uid in shard_uids {
+                let key = get_block_shard_id(&epoch_id.0, shard_uid.shard_id());
+                store_update.set(DBCol::StateSnapshotAvailability, &key, &[]);
+            }
+        }
+        store_update.commit()
+    }
+
     /// Read RocksDB for the latest available snapshot hash, if available, open base_path+snapshot_hash for the state snapshot
     /// we don't deal with multiple snapshots here because we will deal with it whenever a new snapshot is created and saved to file system
     pub fn maybe_open_state_snapshot(
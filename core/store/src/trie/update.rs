@@ -202,6 +202,12 @@ impl TrieUpdate {
         self.prospective.clear();
     }
 
+    /// Whether any changes, committed or not, were made on top of the trie,
+    /// i.e. whether the state differs from the one at `get_root()`.
+    pub fn has_changes(&self) -> bool {
+        !self.committed.is_empty() || !self.prospective.is_empty()
+    }
+
     /// Prepare the accumulated state changes to be applied to the underlying storage.
     ///
     /// This Function returns the [`Trie`] with which the [`TrieUpdate`] has been initially
@@ -5,14 +5,18 @@ use std::collections::HashSet;
 
 use near_chain_configs::Genesis;
 use near_parameters::RuntimeConfig;
+use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::state_record::{state_record_to_account_id, StateRecord};
 use near_primitives::types::AccountId;
+use near_primitives::types::EpochId;
 use near_primitives::types::StateRoot;
+use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives_core::types::NumShards;
 use near_store::genesis::GenesisStateApplier;
 use near_store::test_utils::TestTriesBuilder;
 use near_store::{ShardTries, TrieUpdate};
+use node_runtime::state_viewer::ViewApplyState;
 use node_runtime::{state_viewer::TrieViewer, Runtime};
 use testlib::runtime_utils::{add_test_contract, alice_account, bob_account};
 
@@ -35,6 +39,26 @@ pub fn get_test_trie_viewer() -> (TrieViewer, TrieUpdate) {
     (trie_viewer, state_update)
 }
 
+/// State of a view call against the trie of `get_runtime_and_trie`. Tests
+/// override the fields they need with struct update syntax.
+pub fn test_view_apply_state() -> ViewApplyState {
+    ViewApplyState {
+        block_height: 1,
+        prev_block_hash: CryptoHash::default(),
+        block_hash: CryptoHash::default(),
+        shard_id: TEST_SHARD_UID.shard_id(),
+        epoch_id: EpochId::default(),
+        epoch_height: 0,
+        block_timestamp: 1,
+        current_protocol_version: PROTOCOL_VERSION,
+        cache: None,
+        storage_get_mode: None,
+        originator_id: None,
+        public_key: None,
+        max_gas_burnt: None,
+    }
+}
+
 pub fn get_runtime_and_trie_from_genesis(genesis: &Genesis) -> (Runtime, ShardTries, StateRoot) {
     let shard_layout = &genesis.config.shard_layout;
     let tries = TestTriesBuilder::new()
@@ -13,7 +13,7 @@ use near_chain::state_snapshot_actor::{
 use near_chain::types::RuntimeAdapter;
 use near_chain::ChainGenesis;
 use near_chain_configs::{
-    ClientConfig, DumpConfig, ExternalStorageConfig, ExternalStorageLocation, Genesis,
+    ClientConfig, Compression, DumpConfig, ExternalStorageConfig, ExternalStorageLocation, Genesis,
     MutableConfigValue, StateSyncConfig, SyncConfig,
 };
 use near_chunks::shards_manager_actor::ShardsManagerActor;
@@ -151,12 +151,27 @@ impl TestLoopBuilder {
                 location: external_storage_location.clone(),
                 credentials_file: None,
                 restart_dump_for_shards: None,
+                compression: Compression::None,
             }),
             sync: SyncConfig::ExternalStorage(ExternalStorageConfig {
                 location: external_storage_location,
+                additional_locations: vec![],
                 num_concurrent_requests: 1,
                 num_concurrent_requests_during_catchup: 1,
+                concurrency_ramp_up_period: None,
+                compression: Compression::None,
+                circuit_breaker_failure_threshold: None,
+                circuit_breaker_cooldown: None,
+                circuit_breaker_max_cooldown: None,
+                verify_checksums: false,
+                max_bytes_per_sec: None,
             }),
+            per_shard_total_timeout: None,
+            header_timeout: None,
+            part_timeout: None,
+            shard_priority: vec![],
+            verify_memtrie: false,
+            dedicated_computation_pool: false,
         };
         client_config.tracked_shards = Vec::new();
 
@@ -1,4 +1,4 @@
-use std::collections::{HashSet, VecDeque};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::str::FromStr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, RwLock};
@@ -8,24 +8,27 @@ use assert_matches::assert_matches;
 use futures::{future, FutureExt};
 use itertools::Itertools;
 use near_actix_test_utils::run_actix;
-use near_async::messaging::Sender;
 use near_async::time::{Clock, Duration};
-use near_chain::chain::ApplyStatePartsRequest;
 use near_chain::test_utils::ValidatorSchedule;
 use near_chain::types::{LatestKnown, RuntimeAdapter};
 use near_chain::validate::validate_chunk_with_chunk_extra;
 use near_chain::ChainStore;
-use near_chain::{Block, BlockProcessingArtifact, ChainStoreAccess, Error, Provenance};
+use near_chain::{
+    Block, BlockProcessingArtifact, ChainStoreAccess, DoomslugThresholdMode, Error, Provenance,
+    TxRejectionReason,
+};
 use near_chain_configs::test_utils::{TESTING_INIT_BALANCE, TESTING_INIT_STAKE};
 use near_chain_configs::{Genesis, GenesisConfig, DEFAULT_GC_NUM_EPOCHS_TO_KEEP, NEAR_BASE};
 use near_client::test_utils::{
-    create_chunk_on_height, setup_mock, setup_mock_all_validators, TestEnv,
+    create_chunk_on_height, setup_mock, setup_mock_all_validators, StateSyncTestSource, TestEnv,
 };
 use near_client::{
     BlockApproval, BlockResponse, GetBlockWithMerkleTree, ProcessTxResponse, ProduceChunkResult,
     SetNetworkInfo,
 };
-use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature};
+use near_crypto::{InMemorySigner, KeyType, PublicKey, Signature, Signer};
+use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
+use near_epoch_manager::EpochManager;
 use near_network::test_utils::{wait_or_panic, MockPeerManagerAdapter};
 use near_network::types::{
     BlockInfo, ConnectedPeerInfo, HighestHeightPeerInfo, NetworkInfo, PeerChainInfo,
@@ -38,7 +41,7 @@ use near_o11y::WithSpanContextExt;
 use near_parameters::{ActionCosts, ExtCosts};
 use near_parameters::{RuntimeConfig, RuntimeConfigStore};
 use near_primitives::block::Approval;
-use near_primitives::block_header::BlockHeader;
+use near_primitives::block_header::{ApprovalInner, BlockHeader};
 use near_primitives::errors::TxExecutionError;
 use near_primitives::errors::{ActionError, ActionErrorKind, InvalidTxError};
 use near_primitives::hash::{hash, CryptoHash};
@@ -47,7 +50,6 @@ use near_primitives::receipt::DelayedReceiptIndices;
 use near_primitives::shard_layout::{get_block_shard_uid, ShardUId};
 use near_primitives::sharding::{ShardChunkHeader, ShardChunkHeaderInner, ShardChunkHeaderV3};
 use near_primitives::state_part::PartId;
-use near_primitives::state_sync::StatePartKey;
 use near_primitives::stateless_validation::ChunkEndorsement;
 use near_primitives::test_utils::create_test_signer;
 use near_primitives::test_utils::TestBlockBuilder;
@@ -67,7 +69,7 @@ use near_primitives_core::types::ShardId;
 use near_store::cold_storage::{update_cold_db, update_cold_head};
 use near_store::metadata::DbKind;
 use near_store::metadata::DB_VERSION;
-use near_store::test_utils::create_test_node_storage_with_cold;
+use near_store::test_utils::{create_test_node_storage_with_cold, create_test_store};
 use near_store::NodeStorage;
 use near_store::{get, DBCol, TrieChanges};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
@@ -1064,6 +1066,48 @@ fn test_process_invalid_tx() {
     );
 }
 
+#[test]
+fn test_describe_tx_rejection() {
+    init_test_logger();
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.transaction_validity_period = 10;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer: Signer =
+        InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0").into();
+    let send_money = |nonce, block_hash| {
+        SignedTransaction::send_money(
+            nonce,
+            "test0".parse().unwrap(),
+            "test1".parse().unwrap(),
+            &signer,
+            100,
+            block_hash,
+        )
+    };
+    for i in 1..4 {
+        env.produce_block(0, i);
+    }
+    let chain = &env.clients[0].chain;
+    assert_eq!(chain.describe_tx_rejection(&send_money(1, genesis_hash)).unwrap(), None);
+    assert_eq!(
+        chain.describe_tx_rejection(&send_money(0, genesis_hash)).unwrap(),
+        Some(TxRejectionReason::InvalidNonce { tx_nonce: 0, ak_nonce: 0 })
+    );
+    assert_eq!(
+        chain.describe_tx_rejection(&send_money(1, hash(&[1]))).unwrap(),
+        Some(TxRejectionReason::UnknownBlockHash)
+    );
+
+    for i in 4..13 {
+        env.produce_block(0, i);
+    }
+    assert_eq!(
+        env.clients[0].chain.describe_tx_rejection(&send_money(1, genesis_hash)).unwrap(),
+        Some(TxRejectionReason::Expired { base_block_height: 0, expired_by: 2 })
+    );
+}
+
 /// If someone produce a block with Utc::now() + 1 min, we should produce a block with valid timestamp
 #[test]
 fn test_time_attack() {
@@ -1135,6 +1179,72 @@ fn test_invalid_height_too_old() {
     assert_matches!(res.unwrap_err(), Error::InvalidBlockHeight(_));
 }
 
+#[test]
+fn test_verify_block_body_integrity() {
+    let mut env = TestEnv::default_builder().mock_epoch_managers().build();
+    for i in 1..4 {
+        env.produce_block(0, i);
+    }
+    let block = env.clients[0].produce_block(4).unwrap().unwrap();
+    env.clients[0].chain.verify_block_body_integrity(&block).unwrap();
+    {
+        // Change the chunk in any way, chunk_headers_root won't match
+        let mut block = block.clone();
+        let chunk = block.mut_chunks()[0].get_mut();
+        match &mut chunk.inner {
+            ShardChunkHeaderInner::V1(inner) => inner.prev_outcome_root = CryptoHash([1; 32]),
+            ShardChunkHeaderInner::V2(inner) => inner.prev_outcome_root = CryptoHash([1; 32]),
+            ShardChunkHeaderInner::V3(inner) => inner.prev_outcome_root = CryptoHash([1; 32]),
+        }
+        chunk.hash = ShardChunkHeaderV3::compute_hash(&chunk.inner);
+        let res = env.clients[0].chain.verify_block_body_integrity(&block);
+        assert_matches!(res.unwrap_err(), Error::InvalidChunkHeadersRoot);
+    }
+    {
+        let mut block = block;
+        block.mut_header().get_mut().inner_rest.block_body_hash = CryptoHash([1; 32]);
+        let res = env.clients[0].chain.verify_block_body_integrity(&block);
+        assert_matches!(res.unwrap_err(), Error::InvalidBlockBodyHash);
+    }
+}
+
+#[test]
+fn test_tracked_shards_at() {
+    init_test_logger();
+    let epoch_length = 5;
+    let mut genesis =
+        Genesis::test_sharded_new_version(vec!["test0".parse().unwrap()], 1, vec![1, 1]);
+    genesis.config.epoch_length = epoch_length;
+    let store = create_test_store();
+    let epoch_manager = EpochManager::new_arc_handle(store.clone(), &genesis.config);
+    // Alternate between tracking shard 0 and shard 1 every epoch.
+    let shard_tracker =
+        ShardTracker::new(TrackedConfig::Schedule(vec![vec![0], vec![1]]), epoch_manager.clone());
+    let mut env = TestEnv::builder(&genesis.config)
+        .stores(vec![store])
+        .epoch_managers(vec![epoch_manager])
+        .shard_trackers(vec![shard_tracker])
+        .nightshade_runtimes(&genesis)
+        .build();
+    for height in 1..=3 * epoch_length {
+        env.produce_block(0, height);
+    }
+
+    let client = &env.clients[0];
+    let mut seen_tracked_shards = HashSet::new();
+    for height in 1..=3 * epoch_length {
+        let block_hash = client.chain.get_block_hash_by_height(height).unwrap();
+        let epoch_id = *client.chain.get_block_header(&block_hash).unwrap().epoch_id();
+        let epoch_height = client.epoch_manager.get_epoch_info(&epoch_id).unwrap().epoch_height();
+        let expected: Vec<ShardId> = if epoch_height % 2 == 0 { vec![0] } else { vec![1] };
+        assert_eq!(client.chain.tracked_shards_at(&block_hash).unwrap(), expected);
+        seen_tracked_shards.insert(expected);
+    }
+    assert_eq!(seen_tracked_shards.len(), 2);
+
+    assert!(client.chain.tracked_shards_at(&hash(b"unknown block")).is_err());
+}
+
 #[test]
 fn test_bad_orphan() {
     let mut env = TestEnv::default_builder().mock_epoch_managers().build();
@@ -1222,6 +1332,132 @@ fn test_bad_orphan() {
     env.clients[0].process_block_test(block.into(), Provenance::NONE).unwrap();
 }
 
+#[test]
+fn test_verify_block_approvals() {
+    let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+    let genesis = Genesis::test(accounts, 2);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    env.clients[0].chain.doomslug_threshold_mode = DoomslugThresholdMode::TwoThirds;
+    let genesis_block = env.clients[0].chain.get_block_by_height(0).unwrap();
+    let mut block =
+        TestBlockBuilder::new(Clock::real(), &genesis_block, Arc::new(create_test_signer("test0")))
+            .height(1)
+            .build();
+    let approvers = env.clients[0]
+        .epoch_manager
+        .get_epoch_block_approvers_ordered(genesis_block.hash())
+        .unwrap();
+    assert_eq!(approvers.len(), 2);
+    let approval_inner = ApprovalInner::new(genesis_block.hash(), 0, 1);
+    let approvals = approvers
+        .iter()
+        .map(|(approver, _)| {
+            let signer = create_test_signer(approver.account_id.as_str());
+            Some(Box::new(signer.sign_approval(&approval_inner, 1)))
+        })
+        .collect::<Vec<_>>();
+    let mut verify = |approvals: Vec<Option<Box<Signature>>>| {
+        block.mut_header().get_mut().inner_rest.approvals = approvals;
+        env.clients[0].chain.verify_block_approvals(&block)
+    };
+
+    assert_matches!(verify(approvals.clone()), Ok(()));
+    // Half of the stake isn't enough to reach the threshold.
+    assert_matches!(verify(vec![approvals[0].clone(), None]), Err(Error::NotEnoughApprovals));
+    assert_matches!(
+        verify(vec![approvals[0].clone(), approvals[0].clone()]),
+        Err(Error::InvalidApprovalSignature(account_id)) if account_id == approvers[1].0.account_id
+    );
+    assert_matches!(
+        verify(vec![approvals[0].clone(), approvals[1].clone(), approvals[1].clone()]),
+        Err(Error::InvalidApprover(2))
+    );
+}
+
+#[test]
+fn test_block_chunk_endorsements() {
+    let accounts: Vec<AccountId> = vec!["test0".parse().unwrap(), "test1".parse().unwrap()];
+    let genesis = Genesis::test(accounts, 2);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_block = env.clients[0].chain.get_block_by_height(0).unwrap();
+    let mut block =
+        TestBlockBuilder::new(Clock::real(), &genesis_block, Arc::new(create_test_signer("test0")))
+            .height(1)
+            .build();
+    let mut chunk_header = block.chunks()[0].clone();
+    *chunk_header.height_included_mut() = block.header().height();
+    block.set_chunks(vec![chunk_header.clone()]);
+    let chunk_validators = env.clients[0]
+        .epoch_manager
+        .get_chunk_validator_assignments(
+            &EpochId::default(),
+            chunk_header.shard_id(),
+            chunk_header.height_created(),
+        )
+        .unwrap()
+        .ordered_chunk_validators();
+    // Only the first chunk validator endorsed the chunk.
+    let endorsement = ChunkEndorsement::new(
+        chunk_header.chunk_hash(),
+        &create_test_signer(chunk_validators[0].as_str()),
+    );
+    let mut signatures = vec![None; chunk_validators.len()];
+    signatures[0] = Some(Box::new(endorsement.signature));
+    block.set_chunk_endorsements(vec![signatures]);
+    env.clients[0].chain.save_block(block.clone().into()).unwrap();
+
+    assert_eq!(
+        env.clients[0].chain.block_chunk_endorsements(block.hash()).unwrap(),
+        vec![(chunk_header.shard_id(), vec![chunk_validators[0].clone()])]
+    );
+
+    // Without the chunk included at the height of the block, there are no endorsements.
+    let mut chunk_header = chunk_header;
+    *chunk_header.height_included_mut() = 0;
+    block.set_chunks(vec![chunk_header.clone()]);
+    block.set_chunk_endorsements(vec![vec![]]);
+    block.mut_header().get_mut().inner_lite.height = 2;
+    block.mut_header().resign(&create_test_signer("test0"));
+    env.clients[0].chain.save_block(block.clone().into()).unwrap();
+    assert_eq!(
+        env.clients[0].chain.block_chunk_endorsements(block.hash()).unwrap(),
+        vec![(chunk_header.shard_id(), vec![])]
+    );
+}
+
+#[test]
+fn test_is_recent_epoch() {
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    for height in 1..=3 * epoch_length {
+        env.produce_block(0, height);
+    }
+
+    let chain = &env.clients[0].chain;
+    let head = chain.head().unwrap();
+    let epoch_id_at = |height| {
+        *chain
+            .get_block_header(&chain.get_block_hash_by_height(height).unwrap())
+            .unwrap()
+            .epoch_id()
+    };
+    let prev_epoch_id = epoch_id_at(2 * epoch_length);
+    let old_epoch_id = epoch_id_at(epoch_length);
+    assert_ne!(prev_epoch_id, head.epoch_id);
+    assert_ne!(old_epoch_id, prev_epoch_id);
+
+    // Announcements for the current and the previous epoch are accepted, a
+    // replay of an older one is not.
+    assert!(chain.is_recent_epoch(&head.epoch_id, 1).unwrap());
+    assert!(chain.is_recent_epoch(&head.next_epoch_id, 1).unwrap());
+    assert!(chain.is_recent_epoch(&prev_epoch_id, 1).unwrap());
+    assert!(!chain.is_recent_epoch(&old_epoch_id, 1).unwrap());
+    assert!(chain.is_recent_epoch(&old_epoch_id, 2).unwrap());
+    assert!(chain.is_recent_epoch(&EpochId(hash(b"unknown epoch")), 1).is_err());
+}
+
 #[test]
 fn test_bad_chunk_mask() {
     init_test_logger();
@@ -1288,6 +1524,13 @@ fn test_bad_chunk_mask() {
                 .mut_header()
                 .resign(&*env.client(&block_producer).validator_signer.get().unwrap().clone());
 
+            let mask_validation = env.clients[0].chain.validate_chunk_mask(&block);
+            if !mess_with_chunk_mask {
+                mask_validation.unwrap();
+            } else {
+                assert_matches!(mask_validation, Err(Error::InvalidChunkMask));
+            }
+
             for client in env.clients.iter_mut() {
                 let res = client
                     .process_block_test_no_produce_chunk(block.clone().into(), Provenance::NONE);
@@ -1627,6 +1870,30 @@ fn test_gc_execution_outcome() {
     assert!(env.clients[0].chain.get_final_transaction_result(&tx_hash).is_err());
 }
 
+#[test]
+fn test_get_receipt() {
+    let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer =
+        InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0").into();
+    let tx = SignedTransaction::send_money(
+        1,
+        "test0".parse().unwrap(),
+        "test1".parse().unwrap(),
+        &signer,
+        100,
+        genesis_hash,
+    );
+    let outcome = env.execute_tx(tx).unwrap();
+    let receipt_id = outcome.transaction_outcome.outcome.receipt_ids[0];
+
+    let receipt = env.clients[0].chain.get_receipt(&receipt_id).unwrap().unwrap();
+    assert_eq!(receipt.receipt_id(), &receipt_id);
+    assert_eq!(receipt.receiver_id().as_str(), "test1");
+    assert!(env.clients[0].chain.get_receipt(&hash(b"random")).unwrap().is_none());
+}
+
 #[test]
 #[cfg_attr(not(feature = "expensive_tests"), ignore)]
 fn test_gc_after_state_sync() {
@@ -1706,6 +1973,42 @@ fn test_process_block_after_state_sync() {
     env.clients[0].process_block_test(block.into(), Provenance::PRODUCED).unwrap();
 }
 
+#[test]
+fn test_state_sync_target_info() {
+    let epoch_length = 5;
+    let mut genesis = Genesis::test_sharded_new_version(
+        vec!["test0".parse().unwrap(), "test1".parse().unwrap()],
+        1,
+        vec![1, 1],
+    );
+    genesis.config.epoch_length = epoch_length;
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+
+    let sync_height = epoch_length * 2 + 1;
+    for i in 1..=sync_height + 1 {
+        env.produce_block(0, i);
+    }
+    let chain = &env.clients[0].chain;
+    let sync_block = chain.get_block_by_height(sync_height).unwrap();
+    let sync_hash = *sync_block.hash();
+    let info = chain.state_sync_target_info(sync_hash).unwrap();
+
+    let sync_prev_header = chain.get_previous_header(sync_block.header()).unwrap();
+    assert_eq!(info.sync_hash, sync_hash);
+    assert_eq!(info.sync_prev_hash, *sync_prev_header.hash());
+    assert_eq!(info.sync_prev_prev_hash, *sync_prev_header.prev_hash());
+    assert_eq!(info.epoch_id, *sync_block.header().epoch_id());
+    assert_eq!(info.prev_state_roots.len(), 2);
+    for (shard_id, state_root) in info.prev_state_roots {
+        let header = chain.compute_state_response_header(shard_id, sync_hash).unwrap();
+        assert_eq!(state_root, header.chunk_prev_state_root());
+    }
+
+    // Only the first block of an epoch can be a sync hash.
+    let not_sync_hash = *chain.get_block_by_height(sync_height + 1).unwrap().hash();
+    assert!(chain.state_sync_target_info(not_sync_hash).is_err());
+}
+
 #[test]
 fn test_gc_fork_tail() {
     let epoch_length = 101;
@@ -1740,6 +2043,15 @@ fn test_gc_fork_tail() {
     let tail = env.clients[1].chain.chain_store().tail().unwrap();
     let fork_tail = env.clients[1].chain.chain_store().fork_tail().unwrap();
     assert!(tail <= fork_tail && fork_tail < second_epoch_start.unwrap());
+
+    let gc_bounds = env.clients[1].chain.gc_bounds().unwrap();
+    assert_eq!(gc_bounds.tail, tail);
+    assert_eq!(gc_bounds.fork_tail, fork_tail);
+    assert_eq!(gc_bounds.chunk_tail, env.clients[1].chain.chain_store().chunk_tail().unwrap());
+    assert_eq!(
+        gc_bounds.gc_stop_height,
+        env.clients[1].runtime_adapter.get_gc_stop_height(&head.last_block_hash)
+    );
 }
 
 #[test]
@@ -1777,7 +2089,7 @@ fn test_tx_forwarding_no_double_forwarding() {
         env.clients[0].process_tx(SignedTransaction::empty(genesis_hash), true, false),
         ProcessTxResponse::NoResponse
     );
-    assert!(env.network_adapters[0].requests.read().unwrap().is_empty());
+    env.assert_no_network_requests(0);
 }
 
 #[test]
@@ -1952,6 +2264,51 @@ fn test_gas_price_change() {
     }
 }
 
+#[test]
+fn test_block_transactions_with_outcomes() {
+    let genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
+    let genesis_hash = *env.clients[0].chain.genesis().hash();
+    let signer =
+        InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0").into();
+    let success_tx = SignedTransaction::send_money(
+        1,
+        "test0".parse().unwrap(),
+        "test1".parse().unwrap(),
+        &signer,
+        1,
+        genesis_hash,
+    );
+    // Transfers to a missing account fail when the receipt is executed.
+    let failed_tx = SignedTransaction::send_money(
+        2,
+        "test0".parse().unwrap(),
+        "missing".parse().unwrap(),
+        &signer,
+        1,
+        genesis_hash,
+    );
+    for tx in [&success_tx, &failed_tx] {
+        assert_eq!(env.clients[0].process_tx(tx.clone(), false, false), ProcessTxResponse::ValidTx);
+    }
+    for i in 1..=5 {
+        env.produce_block(0, i);
+    }
+
+    let chain = &env.clients[0].chain;
+    let mut statuses = HashMap::new();
+    for height in 1..=5 {
+        let block_hash = chain.get_block_hash_by_height(height).unwrap();
+        for (tx, status) in chain.block_transactions_with_outcomes(&block_hash).unwrap() {
+            assert_eq!(chain.get_final_transaction_result(&tx.get_hash()).unwrap().status, status);
+            statuses.insert(tx.get_hash(), status);
+        }
+    }
+    assert_eq!(statuses.len(), 2);
+    assert_matches!(statuses[&success_tx.get_hash()], FinalExecutionStatus::SuccessValue(_));
+    assert_matches!(statuses[&failed_tx.get_hash()], FinalExecutionStatus::Failure(_));
+}
+
 #[test]
 fn test_gas_price_overflow() {
     let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
@@ -2009,6 +2366,12 @@ fn test_incorrect_validator_key_produce_block() {
 
     let res = env.clients[0].produce_block(1);
     assert_matches!(res, Ok(None));
+
+    let diagnosis = env.clients[0].explain_block_production(1).unwrap();
+    assert!(!diagnosis.is_block_producer);
+    assert!(!diagnosis.has_validator_key);
+    assert_eq!(diagnosis.validator_id, Some("test0".parse().unwrap()));
+    assert_eq!(diagnosis.skip_reason, Some("not the assigned producer"));
 }
 
 fn test_block_merkle_proof_with_len(n: NumBlocks, rng: &mut StdRng) {
@@ -2398,46 +2761,15 @@ fn test_catchup_gas_price_change() {
     let sync_hash = *blocks[5].hash();
     assert_ne!(blocks[4].header().epoch_id(), blocks[5].header().epoch_id());
     assert!(env.clients[0].chain.check_sync_hash_validity(&sync_hash).unwrap());
-    let state_sync_header = env.clients[0].chain.get_state_response_header(0, sync_hash).unwrap();
-    let num_parts = state_sync_header.num_state_parts();
-    let state_sync_parts = (0..num_parts)
-        .map(|i| env.clients[0].chain.get_state_response_part(0, i, sync_hash).unwrap())
-        .collect::<Vec<_>>();
-
-    env.clients[1].chain.set_state_header(0, sync_hash, state_sync_header).unwrap();
-    for i in 0..num_parts {
-        env.clients[1]
-            .chain
-            .set_state_part(0, sync_hash, PartId::new(i, num_parts), &state_sync_parts[i as usize])
-            .unwrap();
-    }
+    // Client 1 has the flat storage of the shard, which state sync has to replace.
     let rt = Arc::clone(&env.clients[1].runtime_adapter);
-    let f = Sender::from_fn(move |msg: ApplyStatePartsRequest| {
-        let store = rt.store();
-
-        let shard_id = msg.shard_uid.shard_id as ShardId;
-        let mut store_update = store.store_update();
-        assert!(rt
-            .get_flat_storage_manager()
-            .remove_flat_storage_for_shard(msg.shard_uid, &mut store_update)
-            .unwrap());
-        store_update.commit().unwrap();
-        for part_id in 0..msg.num_parts {
-            let key = borsh::to_vec(&StatePartKey(msg.sync_hash, shard_id, part_id)).unwrap();
-            let part = store.get(DBCol::StateParts, &key).unwrap().unwrap();
-
-            rt.apply_state_part(
-                shard_id,
-                &msg.state_root,
-                PartId::new(part_id, msg.num_parts),
-                &part,
-                &msg.epoch_id,
-            )
-            .unwrap();
-        }
-    });
-    env.clients[1].chain.schedule_apply_state_parts(0, sync_hash, num_parts, &f).unwrap();
-    env.clients[1].chain.set_state_finalize(0, sync_hash).unwrap();
+    let mut store_update = rt.store().store_update();
+    assert!(rt
+        .get_flat_storage_manager()
+        .remove_flat_storage_for_shard(ShardUId::single_shard(), &mut store_update)
+        .unwrap());
+    store_update.commit().unwrap();
+    env.state_sync_shard(0, 1, 0, sync_hash).unwrap();
     let chunk_extra_after_sync =
         env.clients[1].chain.get_chunk_extra(blocks[4].hash(), &ShardUId::single_shard()).unwrap();
     let expected_chunk_extra =
@@ -2446,6 +2778,49 @@ fn test_catchup_gas_price_change() {
     assert_eq!(chunk_extra_after_sync, expected_chunk_extra);
 }
 
+#[test]
+fn test_state_sync_falls_back_on_corrupt_parts() {
+    init_test_logger();
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
+    genesis.config.epoch_length = epoch_length;
+
+    let mut env = TestEnv::builder(&genesis.config)
+        .clients_count(2)
+        .use_state_snapshots()
+        .real_stores()
+        .nightshade_runtimes(&genesis)
+        .build();
+
+    let mut blocks = vec![];
+    for i in 1..=6 {
+        let block = env.clients[0].produce_block(i).unwrap().unwrap();
+        blocks.push(block.clone());
+        env.process_block(0, block.clone(), Provenance::PRODUCED);
+        env.process_block(1, block, Provenance::NONE);
+    }
+    let sync_hash = *blocks[5].hash();
+    assert!(env.clients[0].chain.check_sync_hash_validity(&sync_hash).unwrap());
+
+    // Corrupt parts are rejected.
+    assert!(env
+        .state_sync_shard_from_sources(&[StateSyncTestSource::Corrupt(0)], 1, 0, sync_hash)
+        .is_err());
+    // With an honest source to fall back to, state sync completes.
+    env.state_sync_shard_from_sources(
+        &[StateSyncTestSource::Corrupt(0), StateSyncTestSource::Honest(0)],
+        1,
+        0,
+        sync_hash,
+    )
+    .unwrap();
+    let chunk_extra_after_sync =
+        env.clients[1].chain.get_chunk_extra(blocks[4].hash(), &ShardUId::single_shard()).unwrap();
+    let expected_chunk_extra =
+        env.clients[0].chain.get_chunk_extra(blocks[4].hash(), &ShardUId::single_shard()).unwrap();
+    assert_eq!(chunk_extra_after_sync, expected_chunk_extra);
+}
+
 #[test]
 fn test_block_execution_outcomes() {
     init_test_logger();
@@ -2798,6 +3173,47 @@ fn test_epoch_protocol_version_change() {
     assert_eq!(protocol_version, PROTOCOL_VERSION);
 }
 
+#[test]
+fn test_epoch_protocol_history() {
+    init_test_logger();
+    let epoch_length = 5;
+    let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 2);
+    genesis.config.epoch_length = epoch_length;
+    genesis.config.protocol_version = PROTOCOL_VERSION - 1;
+    let mut env = TestEnv::builder(&genesis.config)
+        .clients_count(2)
+        .validator_seats(2)
+        .nightshade_runtimes(&genesis)
+        .build();
+    for i in 1..=16 {
+        let head = env.clients[0].chain.head().unwrap();
+        let epoch_id = env.clients[0]
+            .epoch_manager
+            .get_epoch_id_from_prev_block(&head.last_block_hash)
+            .unwrap();
+
+        produce_chunks(&mut env, &epoch_id, i);
+
+        let block_producer = env.clients[0].epoch_manager.get_block_producer(&epoch_id, i).unwrap();
+        let index = if block_producer == "test0" { 0 } else { 1 };
+        let mut block = env.clients[index].produce_block(i).unwrap().unwrap();
+        set_block_protocol_version(&mut block, block_producer.clone(), PROTOCOL_VERSION);
+        for j in 0..2 {
+            env.clients[j].process_block_test(block.clone().into(), Provenance::NONE).unwrap();
+        }
+    }
+
+    let history = env.clients[0].chain.epoch_protocol_history().unwrap();
+    assert!(history.windows(2).all(|pair| pair[0].1 < pair[1].1));
+    let last_block = env.clients[0].chain.get_block_by_height(16).unwrap();
+    assert_eq!(history.last().unwrap().0, *last_block.header().epoch_id());
+    assert_eq!(history.first().unwrap().2, PROTOCOL_VERSION - 1);
+    assert_eq!(history.last().unwrap().2, PROTOCOL_VERSION);
+    assert!(history
+        .windows(2)
+        .any(|pair| { pair[0].2 == PROTOCOL_VERSION - 1 && pair[1].2 == PROTOCOL_VERSION }));
+}
+
 #[test]
 fn test_epoch_multi_protocol_version_change() {
     init_test_logger();
@@ -3246,6 +3662,14 @@ fn test_fork_execution_outcome() {
     assert_eq!(receipt_execution_outcomes.len(), 2);
     let canonical_chain_outcome = env.clients[0].chain.get_execution_outcome(&receipt_id).unwrap();
     assert_eq!(canonical_chain_outcome.block_hash, *block2.hash());
+    let all_outcomes = env.clients[0].chain.all_outcomes_for_receipt(&receipt_id).unwrap();
+    assert_eq!(all_outcomes.len(), 2);
+    let canonical_outcomes: Vec<_> =
+        all_outcomes.iter().filter(|(_, _, is_canonical)| *is_canonical).collect();
+    assert_eq!(canonical_outcomes.len(), 1);
+    assert_eq!(canonical_outcomes[0].0, *block2.hash());
+    assert_eq!(canonical_outcomes[0].1, canonical_chain_outcome.outcome_with_id.outcome);
+    assert!(all_outcomes.iter().any(|(block_hash, _, _)| block_hash == block1.hash()));
 
     // Make sure that GC cleanups execution outcomes.
     let epoch_length = env.clients[0].config.epoch_length;
@@ -3260,6 +3684,42 @@ fn test_fork_execution_outcome() {
     assert!(receipt_execution_outcomes.is_empty());
 }
 
+// Check that with `extra_outcome_retention_epochs` set, execution outcomes
+// outlive the GC of their block by the configured number of epochs.
+#[test]
+fn test_extra_outcome_retention() {
+    init_test_logger();
+
+    let (mut env, tx_hash) = prepare_env_with_transaction();
+    let extra_outcome_retention_epochs = 3;
+    env.clients[0].config.gc.extra_outcome_retention_epochs = extra_outcome_retention_epochs;
+    let epoch_length = env.clients[0].config.epoch_length;
+
+    // Past the regular GC window the outcome is only kept in the retained column.
+    let mut height = 1;
+    while height < epoch_length * 7 {
+        env.produce_block(0, height);
+        height += 1;
+    }
+    let tail = env.clients[0].chain.tail().unwrap();
+    let outcomes = env.clients[0].chain.chain_store().get_outcomes_by_id(&tx_hash).unwrap();
+    assert_eq!(outcomes.len(), 1);
+    let outcome_height =
+        env.clients[0].chain.get_block_header(&outcomes[0].block_hash).unwrap().height();
+    assert!(outcome_height < tail);
+    assert!(env.clients[0].chain.get_block(&outcomes[0].block_hash).is_err());
+    let receipt_id = outcomes[0].outcome_with_id.outcome.receipt_ids[0];
+    assert_eq!(env.clients[0].chain.get_execution_outcome(&receipt_id).unwrap().id(), &receipt_id);
+
+    // Once the retention window passes, the outcomes are removed.
+    while height < epoch_length * (7 + extra_outcome_retention_epochs + 1) {
+        env.produce_block(0, height);
+        height += 1;
+    }
+    assert!(env.clients[0].chain.chain_store().get_outcomes_by_id(&tx_hash).unwrap().is_empty());
+    assert!(env.clients[0].chain.chain_store().get_outcomes_by_id(&receipt_id).unwrap().is_empty());
+}
+
 fn prepare_env_with_transaction() -> (TestEnv, CryptoHash) {
     let epoch_length = 5;
     let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
@@ -3488,36 +3948,14 @@ fn test_validator_stake_host_function() {
     let mut genesis = Genesis::test(vec!["test0".parse().unwrap(), "test1".parse().unwrap()], 1);
     genesis.config.epoch_length = epoch_length;
     let mut env = TestEnv::builder(&genesis.config).nightshade_runtimes(&genesis).build();
-    let genesis_block = env.clients[0].chain.get_block_by_height(0).unwrap();
-    let block_height = deploy_test_contract(
-        &mut env,
-        "test0".parse().unwrap(),
+    let outcome = env.deploy_and_call(
+        &"test0".parse().unwrap(),
         near_test_contracts::rs_contract(),
-        epoch_length,
-        1,
+        "ext_validator_stake",
+        b"test0".to_vec(),
+        100_000_000_000_000,
     );
-    let signer = InMemorySigner::from_seed("test0".parse().unwrap(), KeyType::ED25519, "test0");
-    let signed_transaction = SignedTransaction::from_actions(
-        10,
-        "test0".parse().unwrap(),
-        "test0".parse().unwrap(),
-        &signer.into(),
-        vec![Action::FunctionCall(Box::new(FunctionCallAction {
-            method_name: "ext_validator_stake".to_string(),
-            args: b"test0".to_vec(),
-            gas: 100_000_000_000_000,
-            deposit: 0,
-        }))],
-        *genesis_block.hash(),
-        0,
-    );
-    assert_eq!(
-        env.clients[0].process_tx(signed_transaction, false, false),
-        ProcessTxResponse::ValidTx
-    );
-    for i in 0..3 {
-        env.produce_block(0, block_height + i);
-    }
+    assert_matches!(outcome.status, FinalExecutionStatus::SuccessValue(_));
 }
 
 #[test]
@@ -3700,6 +4138,10 @@ mod contract_precompilation_tests {
             block_timestamp: block.header().raw_timestamp(),
             current_protocol_version: PROTOCOL_VERSION,
             cache: Some(Box::new(caches.swap_remove(1))),
+            storage_get_mode: None,
+            originator_id: None,
+            public_key: None,
+            max_gas_burnt: None,
         };
         viewer
             .call_function(
@@ -4,7 +4,7 @@ use near_async::time::{Clock, Duration};
 use near_chain::near_chain_primitives::error::QueryError;
 use near_chain::{ChainGenesis, ChainStoreAccess, Provenance};
 use near_chain_configs::ExternalStorageLocation::Filesystem;
-use near_chain_configs::{DumpConfig, Genesis, MutableConfigValue, NEAR_BASE};
+use near_chain_configs::{Compression, DumpConfig, Genesis, MutableConfigValue, NEAR_BASE};
 use near_client::sync::external::{external_storage_location, StateFileType};
 use near_client::test_utils::TestEnv;
 use near_client::ProcessTxResponse;
@@ -56,6 +56,7 @@ fn test_state_dump() {
             restart_dump_for_shards: None,
             iteration_delay: Some(Duration::ZERO),
             credentials_file: None,
+            compression: Compression::None,
         });
 
         let validator = MutableConfigValue::new(
@@ -169,6 +170,7 @@ fn run_state_sync_with_dumped_parts(
             restart_dump_for_shards: None,
             iteration_delay: Some(Duration::ZERO),
             credentials_file: None,
+            compression: Compression::None,
         });
         let mut state_sync_dumper = StateSyncDumper {
             clock: Clock::real(),
@@ -8,13 +8,14 @@ use near_primitives::block::Block;
 use near_primitives::hash::CryptoHash;
 use near_primitives::shard_layout::ShardUId;
 use near_primitives::transaction::SignedTransaction;
+use near_primitives::types::EpochId;
 use near_store::config::StateSnapshotType;
 use near_store::flat::FlatStorageManager;
 use near_store::{
     config::TrieCacheConfig, test_utils::create_test_store, Mode, ShardTries, StateSnapshotConfig,
     StoreConfig, TrieConfig,
 };
-use near_store::{NodeStorage, Store};
+use near_store::{DBCol, NodeStorage, Store};
 use nearcore::test_utils::TestEnvNightshadeSetupExt;
 use std::path::PathBuf;
 
@@ -257,3 +258,73 @@ fn test_make_state_snapshot() {
         )
     );
 }
+
+#[test]
+// Checks that the state snapshot availability is recorded when a snapshot is
+// created and cleared when it is deleted.
+fn test_state_snapshot_availability() {
+    init_test_logger();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config)
+        .clients_count(1)
+        .use_state_snapshots()
+        .real_stores()
+        .nightshade_runtimes(&genesis)
+        .build();
+    let block = env.clients[0].produce_block(1).unwrap().unwrap();
+    env.process_block(0, block.clone(), Provenance::PRODUCED);
+
+    let store = env.clients[0].chain.chain_store().store();
+    let state_snapshot_test_env = set_up_test_env_for_state_snapshots(store);
+    let shard_tries = &state_snapshot_test_env.shard_tries;
+    let epoch_id = block.header().epoch_id();
+    let shard_id = ShardUId::single_shard().shard_id();
+
+    shard_tries.delete_state_snapshot();
+    assert_eq!(shard_tries.is_state_snapshot_available(epoch_id, shard_id).unwrap(), None);
+
+    shard_tries.create_state_snapshot(*block.hash(), &[ShardUId::single_shard()], &block).unwrap();
+    assert_eq!(shard_tries.is_state_snapshot_available(epoch_id, shard_id).unwrap(), Some(true));
+    assert_eq!(
+        shard_tries.is_state_snapshot_available(epoch_id, shard_id + 1).unwrap(),
+        Some(false)
+    );
+    let other_epoch_id = EpochId(CryptoHash::hash_bytes(b"other_epoch"));
+    assert_eq!(
+        shard_tries.is_state_snapshot_available(&other_epoch_id, shard_id).unwrap(),
+        Some(false)
+    );
+
+    shard_tries.delete_state_snapshot();
+    assert_eq!(shard_tries.is_state_snapshot_available(epoch_id, shard_id).unwrap(), None);
+}
+
+#[test]
+// A snapshot made by a binary which didn't record the availability yet must
+// not be reported as unavailable.
+fn test_state_snapshot_availability_after_upgrade() {
+    init_test_logger();
+    let genesis = Genesis::test(vec!["test0".parse().unwrap()], 1);
+    let mut env = TestEnv::builder(&genesis.config)
+        .clients_count(1)
+        .use_state_snapshots()
+        .real_stores()
+        .nightshade_runtimes(&genesis)
+        .build();
+    let block = env.clients[0].produce_block(1).unwrap().unwrap();
+    env.process_block(0, block.clone(), Provenance::PRODUCED);
+
+    let store = env.clients[0].chain.chain_store().store();
+    let state_snapshot_test_env = set_up_test_env_for_state_snapshots(store);
+    let shard_tries = &state_snapshot_test_env.shard_tries;
+    let epoch_id = block.header().epoch_id();
+    let shard_id = ShardUId::single_shard().shard_id();
+
+    shard_tries.create_state_snapshot(*block.hash(), &[ShardUId::single_shard()], &block).unwrap();
+    // Drop the records, as if the snapshot was made before the upgrade.
+    let mut store_update = shard_tries.get_store().store_update();
+    store_update.delete_all(DBCol::StateSnapshotAvailability);
+    store_update.commit().unwrap();
+
+    assert_eq!(shard_tries.is_state_snapshot_available(epoch_id, shard_id).unwrap(), None);
+}
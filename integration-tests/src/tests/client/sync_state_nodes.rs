@@ -6,7 +6,7 @@ use near_async::time::Duration;
 use near_chain::chain::ApplyStatePartsRequest;
 use near_chain::Provenance;
 use near_chain_configs::ExternalStorageLocation::Filesystem;
-use near_chain_configs::{DumpConfig, ExternalStorageConfig, Genesis, SyncConfig};
+use near_chain_configs::{Compression, DumpConfig, ExternalStorageConfig, Genesis, SyncConfig};
 use near_client::test_utils::TestEnv;
 use near_client::{GetBlock, ProcessTxResponse};
 use near_client_primitives::types::GetValidatorInfo;
@@ -454,6 +454,7 @@ fn sync_state_dump() {
                 restart_dump_for_shards: None,
                 iteration_delay: Some(Duration::milliseconds(500)),
                 credentials_file: None,
+                compression: Compression::None,
             });
             near1.config.store.state_snapshot_enabled = true;
 
@@ -498,8 +499,16 @@ fn sync_state_dump() {
                                         location: Filesystem {
                                             root_dir: dump_dir.path().to_path_buf(),
                                         },
+                                        additional_locations: vec![],
                                         num_concurrent_requests: 1,
                                         num_concurrent_requests_during_catchup: 1,
+                                        concurrency_ramp_up_period: None,
+                                        compression: Compression::None,
+                                        circuit_breaker_failure_threshold: None,
+                                        circuit_breaker_cooldown: None,
+                                        circuit_breaker_max_cooldown: None,
+                                        verify_checksums: false,
+                                        max_bytes_per_sec: None,
                                     });
 
                                 let nearcore::NearNode {
@@ -707,6 +716,7 @@ fn test_dump_epoch_missing_chunk_in_last_block() {
             env.clients[1].chain.schedule_apply_state_parts(0, sync_hash, num_parts, &f).unwrap();
 
             tracing::info!(target: "test", "state sync - set state finalize");
+            env.clients[1].chain.verify_memtrie_after_state_sync = true;
             env.clients[1].chain.set_state_finalize(0, sync_hash).unwrap();
 
             let last_chunk_height = epoch_length - num_last_chunks_missing;
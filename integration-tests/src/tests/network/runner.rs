@@ -132,7 +132,7 @@ fn setup_network_node(
         client_actor.clone().with_auto_span_context().into_sender(),
         validator_signer.clone(),
         runtime.store().clone(),
-        client_config.chunk_request_retry_period,
+        &client_config,
     );
     let (partial_witness_actor, _) = spawn_actix_actor(PartialWitnessActor::new(
         Clock::real(),
@@ -1,121 +1,34 @@
-use std::{collections::HashMap, io, sync::Arc};
+use std::sync::Arc;
 
-use borsh::BorshDeserialize;
-
-use crate::runtime_utils::{get_runtime_and_trie, get_test_trie_viewer, TEST_SHARD_UID};
+use crate::runtime_utils::{
+    get_runtime_and_trie, get_test_trie_viewer, test_view_apply_state, TEST_SHARD_UID,
+};
+use near_crypto::{KeyType, PublicKey};
+use near_parameters::ExtCosts;
 use near_primitives::{
     account::Account,
     hash::{hash as sha256, CryptoHash},
     serialize::to_base64,
-    trie_key::trie_key_parsers,
-    types::{AccountId, StateRoot},
+    types::AccountId,
     views::StateItem,
 };
 use near_primitives::{
-    test_utils::MockEpochInfoProvider,
-    trie_key::TrieKey,
-    types::{EpochId, StateChangeCause},
+    test_utils::MockEpochInfoProvider, trie_key::TrieKey, types::StateChangeCause,
     version::PROTOCOL_VERSION,
 };
-use near_store::{set_account, NibbleSlice, RawTrieNode, RawTrieNodeWithSize, ShardUId};
+use near_store::test_utils::test_populate_trie;
+use near_store::{set_account, TrieUpdate};
+use near_vm_runner::logic::StorageGetMode;
 use node_runtime::state_viewer::errors;
 use node_runtime::state_viewer::*;
 use testlib::runtime_utils::alice_account;
 
-struct ProofVerifier {
-    nodes: HashMap<CryptoHash, RawTrieNodeWithSize>,
-}
-
-impl ProofVerifier {
-    fn new(proof: Vec<Arc<[u8]>>) -> Result<Self, io::Error> {
-        let nodes = proof
-            .into_iter()
-            .map(|bytes| {
-                let hash = CryptoHash::hash_bytes(&bytes);
-                let node = RawTrieNodeWithSize::try_from_slice(&bytes)?;
-                Ok((hash, node))
-            })
-            .collect::<Result<HashMap<_, _>, io::Error>>()?;
-        Ok(Self { nodes })
-    }
-
-    fn verify(
-        &self,
-        state_root: &StateRoot,
-        account_id: &AccountId,
-        key: &[u8],
-        expected: Option<&[u8]>,
-    ) -> bool {
-        let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, key);
-        let mut key = NibbleSlice::new(&query);
-
-        let mut expected_hash = state_root;
-        while let Some(node) = self.nodes.get(expected_hash) {
-            match &node.node {
-                RawTrieNode::Leaf(node_key, value) => {
-                    let nib = &NibbleSlice::from_encoded(&node_key).0;
-                    return if &key != nib {
-                        expected.is_none()
-                    } else {
-                        expected.is_some_and(|expected| value == expected)
-                    };
-                }
-                RawTrieNode::Extension(node_key, child_hash) => {
-                    expected_hash = child_hash;
-
-                    // To avoid unnecessary copy
-                    let nib = NibbleSlice::from_encoded(&node_key).0;
-                    if !key.starts_with(&nib) {
-                        return expected.is_none();
-                    }
-                    key = key.mid(nib.len());
-                }
-                RawTrieNode::BranchNoValue(children) => {
-                    if key.is_empty() {
-                        return expected.is_none();
-                    }
-                    match children[key.at(0)] {
-                        Some(ref child_hash) => {
-                            key = key.mid(1);
-                            expected_hash = child_hash;
-                        }
-                        None => return expected.is_none(),
-                    }
-                }
-                RawTrieNode::BranchWithValue(value, children) => {
-                    if key.is_empty() {
-                        return expected.is_some_and(|exp| value == exp);
-                    }
-                    match children[key.at(0)] {
-                        Some(ref child_hash) => {
-                            key = key.mid(1);
-                            expected_hash = child_hash;
-                        }
-                        None => return expected.is_none(),
-                    }
-                }
-            }
-        }
-        false
-    }
-}
-
 #[test]
 fn test_view_call() {
     let (viewer, root) = get_test_trie_viewer();
 
     let mut logs = vec![];
-    let view_state = ViewApplyState {
-        block_height: 1,
-        prev_block_hash: CryptoHash::default(),
-        block_hash: CryptoHash::default(),
-        shard_id: ShardUId::single_shard().shard_id(),
-        epoch_id: EpochId::default(),
-        epoch_height: 0,
-        block_timestamp: 1,
-        current_protocol_version: PROTOCOL_VERSION,
-        cache: None,
-    };
+    let view_state = test_view_apply_state();
     let result = viewer.call_function(
         root,
         view_state,
@@ -129,22 +42,180 @@ fn test_view_call() {
     assert_eq!(result.unwrap(), (10i32).to_le_bytes());
 }
 
+#[test]
+fn test_view_call_at_root() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let viewer = TrieViewer::default();
+    let contract_id: AccountId = "test.contract".parse().unwrap();
+    let args = [1u64.to_le_bytes(), 2u64.to_le_bytes()].concat();
+
+    let mut logs = vec![];
+    let manual = viewer.call_function(
+        tries.new_trie_update_view(TEST_SHARD_UID, root),
+        test_view_apply_state(),
+        &contract_id,
+        "sum_with_input",
+        &args,
+        &mut logs,
+        &MockEpochInfoProvider::default(),
+    );
+    let mut logs_at_root = vec![];
+    let at_root = viewer.call_function_at_root(
+        &tries,
+        TEST_SHARD_UID,
+        root,
+        test_view_apply_state(),
+        &contract_id,
+        "sum_with_input",
+        &args,
+        &mut logs_at_root,
+        &MockEpochInfoProvider::default(),
+    );
+
+    assert_eq!(manual.unwrap(), 3u64.to_le_bytes().to_vec());
+    assert_eq!(at_root.unwrap(), 3u64.to_le_bytes().to_vec());
+    assert_eq!(logs_at_root, logs);
+}
+
+#[test]
+fn test_view_call_concurrency_limit() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let contract_id: AccountId = "test.contract".parse().unwrap();
+    let call = |viewer: &TrieViewer| {
+        viewer.call_function_at_root(
+            &tries,
+            TEST_SHARD_UID,
+            root,
+            test_view_apply_state(),
+            &contract_id,
+            "run_test",
+            &[],
+            &mut vec![],
+            &MockEpochInfoProvider::default(),
+        )
+    };
+
+    // With no free slots the call is rejected without being executed.
+    let viewer = TrieViewer::new(None, None, None, Some(0), None);
+    let result = call(&viewer);
+    assert!(matches!(result, Err(errors::CallFunctionError::TooManyRequests { limit: 0 })));
+
+    // The slot is released once a call finishes, so sequential calls keep working.
+    let viewer = TrieViewer::new(None, None, None, Some(1), None);
+    for _ in 0..3 {
+        assert_eq!(call(&viewer).unwrap(), (10i32).to_le_bytes());
+    }
+}
+
+#[test]
+fn test_estimate_view_gas() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let max_gas_burnt_view = 300_000_000_000_000;
+    let viewer = TrieViewer::new(None, Some(max_gas_burnt_view), None, None, None);
+    let contract_id: AccountId = "test.contract".parse().unwrap();
+    let estimate = |viewer: &TrieViewer| {
+        viewer.estimate_view_gas(
+            tries.new_trie_update_view(TEST_SHARD_UID, root),
+            test_view_apply_state(),
+            &contract_id,
+            "run_test",
+            &[],
+            &MockEpochInfoProvider::default(),
+        )
+    };
+
+    let gas = estimate(&viewer).unwrap();
+    assert!(gas > 0);
+    assert!(gas <= max_gas_burnt_view);
+    assert_eq!(estimate(&viewer).unwrap(), gas);
+
+    // A call which doesn't fit into the limit fails instead of being estimated at the limit.
+    let viewer = TrieViewer::new(None, Some(gas - 1), None, None, None);
+    assert!(matches!(estimate(&viewer), Err(errors::CallFunctionError::VMError { .. })));
+}
+
+#[test]
+fn test_call_function_detailed() {
+    let (viewer, root) = get_test_trie_viewer();
+    let view_state = test_view_apply_state();
+    let result = viewer
+        .call_function_detailed(
+            root,
+            view_state,
+            &"test.contract".parse().unwrap(),
+            "ext_account_id",
+            &[],
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+    assert_eq!(result.result, b"test.contract");
+    assert!(result.gas_burnt > 0);
+    assert!(result.logs.is_empty());
+    // The host function call shows up in the profile.
+    assert!(result.profile.get_ext_cost(ExtCosts::base) > 0);
+    assert!(result.profile.get_ext_cost(ExtCosts::base) < result.gas_burnt);
+}
+
+#[test]
+fn test_view_call_max_gas_burnt_override() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let contract_id: AccountId = "test.contract".parse().unwrap();
+    let make_view_state =
+        |max_gas_burnt| ViewApplyState { max_gas_burnt, ..test_view_apply_state() };
+    let gas = TrieViewer::default()
+        .estimate_view_gas(
+            tries.new_trie_update_view(TEST_SHARD_UID, root),
+            make_view_state(None),
+            &contract_id,
+            "run_test",
+            &[],
+            &MockEpochInfoProvider::default(),
+        )
+        .unwrap();
+
+    // The default limit of the viewer is too low for the call.
+    let viewer = TrieViewer::new(None, Some(gas - 1), Some(2 * gas), None, None);
+    let call = |max_gas_burnt| {
+        viewer.call_function(
+            tries.new_trie_update_view(TEST_SHARD_UID, root),
+            make_view_state(max_gas_burnt),
+            &contract_id,
+            "run_test",
+            &[],
+            &mut vec![],
+            &MockEpochInfoProvider::default(),
+        )
+    };
+    assert!(matches!(call(None), Err(errors::CallFunctionError::VMError { .. })));
+    assert_eq!(call(Some(gas)).unwrap(), 10i32.to_le_bytes());
+    assert_eq!(call(Some(2 * gas)).unwrap(), 10i32.to_le_bytes());
+    assert!(matches!(call(Some(gas / 2)), Err(errors::CallFunctionError::VMError { .. })));
+    assert!(matches!(
+        call(Some(2 * gas + 1)),
+        Err(errors::CallFunctionError::GasLimitTooHigh { requested, limit })
+            if requested == 2 * gas + 1 && limit == 2 * gas
+    ));
+
+    // Without a cap, the calls may only lower the limit.
+    let viewer = TrieViewer::new(None, Some(gas), None, None, None);
+    let result = viewer.call_function(
+        tries.new_trie_update_view(TEST_SHARD_UID, root),
+        make_view_state(Some(gas + 1)),
+        &contract_id,
+        "run_test",
+        &[],
+        &mut vec![],
+        &MockEpochInfoProvider::default(),
+    );
+    assert!(matches!(result, Err(errors::CallFunctionError::GasLimitTooHigh { .. })));
+}
+
 #[test]
 fn test_view_call_try_changing_storage() {
     let (viewer, root) = get_test_trie_viewer();
 
     let mut logs = vec![];
-    let view_state = ViewApplyState {
-        block_height: 1,
-        prev_block_hash: CryptoHash::default(),
-        block_hash: CryptoHash::default(),
-        shard_id: ShardUId::single_shard().shard_id(),
-        epoch_id: EpochId::default(),
-        epoch_height: 0,
-        block_timestamp: 1,
-        current_protocol_version: PROTOCOL_VERSION,
-        cache: None,
-    };
+    let view_state = test_view_apply_state();
     let result = viewer.call_function(
         root,
         view_state,
@@ -167,17 +238,7 @@ fn test_view_call_with_args() {
     let (viewer, root) = get_test_trie_viewer();
     let args: Vec<_> = [1u64, 2u64].iter().flat_map(|x| (*x).to_le_bytes().to_vec()).collect();
     let mut logs = vec![];
-    let view_state = ViewApplyState {
-        block_height: 1,
-        prev_block_hash: CryptoHash::default(),
-        block_hash: CryptoHash::default(),
-        shard_id: ShardUId::single_shard().shard_id(),
-        epoch_id: EpochId::default(),
-        epoch_height: 0,
-        block_timestamp: 1,
-        current_protocol_version: PROTOCOL_VERSION,
-        cache: None,
-    };
+    let view_state = test_view_apply_state();
     let view_call_result = viewer.call_function(
         root,
         view_state,
@@ -190,13 +251,96 @@ fn test_view_call_with_args() {
     assert_eq!(view_call_result.unwrap(), 3u64.to_le_bytes().to_vec());
 }
 
+#[test]
+fn test_view_call_storage_get_mode() {
+    let (_, tries, genesis_root) = get_runtime_and_trie();
+    let viewer = TrieViewer::default();
+    let contract_id: AccountId = "test.contract".parse().unwrap();
+    // Commit the value to the trie only. Flat storage stays at the genesis
+    // head, so the value is visible to `Trie` reads but not to `FlatStorage`
+    // reads.
+    let key =
+        TrieKey::ContractData { account_id: contract_id.clone(), key: 7u64.to_le_bytes().to_vec() };
+    let root = test_populate_trie(
+        &tries,
+        &genesis_root,
+        TEST_SHARD_UID,
+        vec![(key.to_vec(), Some(42u64.to_le_bytes().to_vec()))],
+    );
+
+    let read_value = |storage_get_mode| {
+        let trie = tries.get_trie_with_block_hash_for_shard(
+            TEST_SHARD_UID,
+            root,
+            &CryptoHash::default(),
+            true,
+        );
+        let view_state = ViewApplyState { storage_get_mode, ..test_view_apply_state() };
+        viewer
+            .call_function(
+                TrieUpdate::new(trie),
+                view_state,
+                &contract_id,
+                "read_value",
+                &7u64.to_le_bytes(),
+                &mut vec![],
+                &MockEpochInfoProvider::default(),
+            )
+            .unwrap()
+    };
+
+    assert_eq!(read_value(Some(StorageGetMode::Trie)), 42u64.to_le_bytes().to_vec());
+    assert!(read_value(Some(StorageGetMode::FlatStorage)).is_empty());
+}
+
+#[test]
+fn test_view_call_originator_and_public_key() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let viewer = TrieViewer::default();
+    let contract_id: AccountId = "test.contract".parse().unwrap();
+    let call = |originator_id: Option<AccountId>, public_key: Option<PublicKey>, method| {
+        let view_state = ViewApplyState { originator_id, public_key, ..test_view_apply_state() };
+        viewer
+            .call_function(
+                tries.new_trie_update(TEST_SHARD_UID, root),
+                view_state,
+                &contract_id,
+                method,
+                &[],
+                &mut vec![],
+                &MockEpochInfoProvider::default(),
+            )
+            .unwrap()
+    };
+
+    // By default the contract itself is the originator of the call.
+    assert_eq!(call(None, None, "ext_predecessor_account_id"), contract_id.as_bytes());
+    assert_eq!(call(None, None, "ext_signer_id"), contract_id.as_bytes());
+    assert_eq!(
+        call(None, None, "ext_signer_pk"),
+        borsh::to_vec(&PublicKey::empty(KeyType::ED25519)).unwrap()
+    );
+
+    let originator_id = alice_account();
+    let public_key = PublicKey::from_seed(KeyType::ED25519, "alice");
+    assert_eq!(
+        call(Some(originator_id.clone()), None, "ext_predecessor_account_id"),
+        originator_id.as_bytes()
+    );
+    assert_eq!(call(Some(originator_id.clone()), None, "ext_signer_id"), originator_id.as_bytes());
+    assert_eq!(
+        call(None, Some(public_key.clone()), "ext_signer_pk"),
+        borsh::to_vec(&public_key).unwrap()
+    );
+}
+
 fn assert_view_state(
     trie_viewer: &TrieViewer,
     state_update: &near_store::TrieUpdate,
     prefix: &[u8],
     want_values: &[(&[u8], &[u8])],
     want_proof: &[&'static str],
-) -> ProofVerifier {
+) -> Vec<Arc<[u8]>> {
     let alice = alice_account();
     let alina = "alina".parse().unwrap();
 
@@ -228,40 +372,40 @@ fn assert_view_state(
     assert_eq!(&want_proof[..], &got[2..]);
 
     // Verify proofs for all the expected values.
-    let proof_verifier = ProofVerifier::new(result.proof).unwrap();
+    let proof = result.proof;
     let root = state_update.get_root();
     for (key, value) in want_values {
         // Proof for known (key, value) should succeed.
         assert!(
-            proof_verifier.verify(root, &alice, key, Some(value)),
+            TrieViewer::verify_state_proof(root, &alice, key, Some(value), &proof),
             "key: alice / {key:x?}; value: {value:x?}"
         );
         // The key exist, so proof for non-existence should fail.
         assert!(
-            !proof_verifier.verify(root, &alice, key, None),
+            !TrieViewer::verify_state_proof(root, &alice, key, None, &proof),
             "key: alice / {key:x?}; value: None"
         );
         // Proof for different value should fail.
         assert!(
-            !proof_verifier.verify(root, &alice, key, Some(b"bogus")),
+            !TrieViewer::verify_state_proof(root, &alice, key, Some(b"bogus"), &proof),
             "key: alice / {key:x?}; value: None"
         );
         // Proofs for different account should fail.
         assert!(
-            !proof_verifier.verify(root, &alina, key, Some(value)),
+            !TrieViewer::verify_state_proof(root, &alina, key, Some(value), &proof),
             "key: alice / {key:x?}; value: {value:x?}"
         );
         assert!(
-            !proof_verifier.verify(root, &alina, key, None),
+            !TrieViewer::verify_state_proof(root, &alina, key, None, &proof),
             "key: alice / {key:x?}; value: {value:x?}"
         );
     }
 
-    proof_verifier
+    proof
 }
 
 #[test]
-fn test_view_state() {
+fn test_view_apply_state() {
     // in order to ensure determinism under all conditions (compiler, build output, etc)
     // avoid deploying a test contract. See issue #7238
     let (_, tries, root) = get_runtime_and_trie();
@@ -314,7 +458,7 @@ fn test_view_state() {
         "AwwAAAAWUubmVhcix0ZXN0PKtrEndk0LxM+qpzp0PVtjf+xlrzz4TT0qA+hTtm6BLlYBAAAAAAAA",
     ][..]);
 
-    let proof_verifier = assert_view_state(
+    let proof = assert_view_state(
         &trie_viewer,
         &state_update,
         b"test123",
@@ -344,7 +488,7 @@ fn test_view_state() {
         (false, b"test1234", Some(b"123")),
         (true, b"test1234", None),
     ] {
-        let got = proof_verifier.verify(&root, &account, key, value);
+        let got = TrieViewer::verify_state_proof(&root, &account, key, value, &proof);
         assert_eq!(
             want,
             got,
@@ -353,6 +497,241 @@ fn test_view_state() {
             value.map(|value| std::str::from_utf8(value).unwrap())
         );
     }
+
+    // Tampering with any node or dropping it from the proof breaks the chain of hashes.
+    let key = b"test123";
+    let value = Some(b"123".as_ref());
+    for idx in 0..proof.len() {
+        let mut tampered = proof.clone();
+        let mut node = tampered[idx].to_vec();
+        *node.last_mut().unwrap() ^= 1;
+        tampered[idx] = node.into();
+        assert!(!TrieViewer::verify_state_proof(&root, &account, key, value, &tampered));
+
+        let mut truncated = proof.clone();
+        truncated.remove(idx);
+        assert!(!TrieViewer::verify_state_proof(&root, &account, key, value, &truncated));
+    }
+}
+
+#[test]
+fn test_view_state_raw_without_account() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let shard_uid = TEST_SHARD_UID;
+    let orphan: AccountId = "orphan".parse().unwrap();
+    let mut state_update = tries.new_trie_update(shard_uid, root);
+    state_update.set(
+        TrieKey::ContractData { account_id: orphan.clone(), key: b"test123".to_vec() },
+        b"123".to_vec(),
+    );
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().1;
+    let mut db_changes = tries.store_update();
+    let new_root = tries.apply_all(&trie_changes, shard_uid, &mut db_changes);
+    db_changes.commit().unwrap();
+
+    let state_update = tries.new_trie_update(shard_uid, new_root);
+    let trie_viewer = TrieViewer::new(Some(0), None, None, None, None);
+    let result = trie_viewer.view_state(&state_update, &orphan, b"test", false);
+    assert!(matches!(result, Err(errors::ViewStateError::AccountDoesNotExist { .. })));
+
+    let result = trie_viewer.view_state_raw(&state_update, &orphan, b"test", false).unwrap();
+    assert_eq!(
+        result.values,
+        [StateItem { key: b"test123".to_vec().into(), value: b"123".to_vec().into() }]
+    );
+}
+
+#[test]
+fn test_view_state_paged() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let shard_uid = TEST_SHARD_UID;
+    let mut state_update = tries.new_trie_update(shard_uid, root);
+    for i in 0..10u8 {
+        state_update.set(
+            TrieKey::ContractData { account_id: alice_account(), key: vec![b'k', i] },
+            vec![i],
+        );
+    }
+    state_update.set(
+        TrieKey::ContractData { account_id: alice_account(), key: b"other".to_vec() },
+        b"other".to_vec(),
+    );
+    state_update.set(
+        TrieKey::ContractData { account_id: "alina".parse().unwrap(), key: b"k".to_vec() },
+        b"alina".to_vec(),
+    );
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().1;
+    let mut db_changes = tries.store_update();
+    let new_root = tries.apply_all(&trie_changes, shard_uid, &mut db_changes);
+    db_changes.commit().unwrap();
+
+    let state_update = tries.new_trie_update(shard_uid, new_root);
+    let trie_viewer = TrieViewer::default();
+    let account = alice_account();
+
+    for prefix in [&b""[..], b"k"] {
+        let everything =
+            trie_viewer.view_state(&state_update, &account, prefix, false).unwrap().values;
+        let mut all = vec![];
+        let mut from_key: Option<Vec<u8>> = None;
+        loop {
+            let (result, next_key) = trie_viewer
+                .view_state_paged(&state_update, &account, prefix, from_key.as_deref(), 3, true)
+                .unwrap();
+            assert!(result.values.len() <= 3);
+            // The proof of every page is enough to verify the items on it.
+            for item in &result.values {
+                assert!(TrieViewer::verify_state_proof(
+                    state_update.get_root(),
+                    &account,
+                    &item.key,
+                    Some(&item.value[..]),
+                    &result.proof,
+                ));
+            }
+            all.extend(result.values);
+            match next_key {
+                Some(next_key) => from_key = Some(next_key),
+                None => break,
+            }
+        }
+        assert_eq!(all, everything);
+    }
+
+    // Paging starts at the given key, even if it isn't present in the trie.
+    let (result, next_key) = trie_viewer
+        .view_state_paged(&state_update, &account, b"k", Some(&[b'k', 7, 0]), 10, false)
+        .unwrap();
+    let keys: Vec<Vec<u8>> = result.values.iter().map(|item| item.key.to_vec()).collect();
+    assert_eq!(keys, vec![vec![b'k', 8], vec![b'k', 9]]);
+    assert_eq!(next_key, None);
+    assert!(result.proof.is_empty());
+
+    let (result, next_key) =
+        trie_viewer.view_state_paged(&state_update, &account, b"xyz", None, 10, false).unwrap();
+    assert!(result.values.is_empty());
+    assert_eq!(next_key, None);
+}
+
+#[test]
+fn test_list_accounts_with_prefix() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let shard_uid = TEST_SHARD_UID;
+    let mut state_update = tries.new_trie_update(shard_uid, root);
+    let account = Account::new(0, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION);
+    for account_id in
+        ["a.foo.near", "b.foo.near", "c.foo.near", "d.foo.near", "foo.near", "other.near"]
+    {
+        set_account(&mut state_update, account_id.parse().unwrap(), &account);
+    }
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().1;
+    let mut db_changes = tries.store_update();
+    let new_root = tries.apply_all(&trie_changes, shard_uid, &mut db_changes);
+    db_changes.commit().unwrap();
+
+    let state_update = tries.new_trie_update(shard_uid, new_root);
+    let trie_viewer = TrieViewer::default();
+    let ids = |ids: &[&str]| ids.iter().map(|id| id.parse().unwrap()).collect::<Vec<AccountId>>();
+
+    let (accounts, next) =
+        trie_viewer.list_accounts_with_prefix(&state_update, "", 100, None).unwrap();
+    assert!(accounts.len() >= 6);
+    assert_eq!(next, None);
+
+    let (accounts, next) =
+        trie_viewer.list_accounts_with_prefix(&state_update, "foo.near", 10, None).unwrap();
+    assert_eq!(accounts, ids(&["foo.near"]));
+    assert_eq!(next, None);
+
+    let (accounts, next) =
+        trie_viewer.list_accounts_with_prefix(&state_update, "a.foo", 10, None).unwrap();
+    assert_eq!(accounts, ids(&["a.foo.near"]));
+    assert_eq!(next, None);
+
+    // Paging through all the accounts yields the same accounts as listing them at once.
+    let mut all = vec![];
+    let mut start_after: Option<AccountId> = None;
+    loop {
+        let (accounts, next) = trie_viewer
+            .list_accounts_with_prefix(&state_update, "", 2, start_after.as_ref())
+            .unwrap();
+        assert!(accounts.len() <= 2);
+        all.extend(accounts);
+        match next {
+            Some(next) => start_after = Some(next),
+            None => break,
+        }
+    }
+    let (everything, _) =
+        trie_viewer.list_accounts_with_prefix(&state_update, "", 100, None).unwrap();
+    assert_eq!(all, everything);
+    for id in ids(&["a.foo.near", "b.foo.near", "c.foo.near", "d.foo.near"]) {
+        assert!(all.contains(&id));
+    }
+
+    // The page starts right after `start_after`, whether or not such an account exists.
+    let start_after = "b.foo.near".parse().unwrap();
+    let (accounts, next) =
+        trie_viewer.list_accounts_with_prefix(&state_update, "", 2, Some(&start_after)).unwrap();
+    assert_eq!(accounts, ids(&["c.foo.near", "d.foo.near"]));
+    assert_eq!(next, Some("d.foo.near".parse().unwrap()));
+    let start_after = "a.foo.nea".parse().unwrap();
+    let (accounts, next) = trie_viewer
+        .list_accounts_with_prefix(&state_update, "a.foo", 10, Some(&start_after))
+        .unwrap();
+    assert_eq!(accounts, ids(&["a.foo.near"]));
+    assert_eq!(next, None);
+
+    let (accounts, next) =
+        trie_viewer.list_accounts_with_prefix(&state_update, "nonexistent", 10, None).unwrap();
+    assert!(accounts.is_empty());
+    assert_eq!(next, None);
+}
+
+#[test]
+fn test_view_account_cache() {
+    let (_, tries, root) = get_runtime_and_trie();
+    let shard_uid = TEST_SHARD_UID;
+    let set_balance = |root, amount| {
+        let mut state_update = tries.new_trie_update(shard_uid, root);
+        set_account(
+            &mut state_update,
+            alice_account(),
+            &Account::new(amount, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION),
+        );
+        state_update.commit(StateChangeCause::InitialState);
+        let trie_changes = state_update.finalize().unwrap().1;
+        let mut db_changes = tries.store_update();
+        let new_root = tries.apply_all(&trie_changes, shard_uid, &mut db_changes);
+        db_changes.commit().unwrap();
+        new_root
+    };
+    let first_root = set_balance(root, 1);
+    let second_root = set_balance(first_root, 2);
+
+    let trie_viewer = TrieViewer::new(None, None, None, None, Some(10));
+    let view_balance = |root| {
+        let state_update = tries.new_trie_update_view(shard_uid, root);
+        trie_viewer.view_account(&state_update, &alice_account()).unwrap().amount()
+    };
+    assert_eq!(view_balance(first_root), 1);
+    // A different state root never returns the account cached for another one.
+    assert_eq!(view_balance(second_root), 2);
+    assert_eq!(view_balance(first_root), 1);
+    assert_eq!(view_balance(second_root), 2);
+
+    // Changes made on top of the state root bypass the cache.
+    let mut state_update = tries.new_trie_update(shard_uid, first_root);
+    set_account(
+        &mut state_update,
+        alice_account(),
+        &Account::new(3, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION),
+    );
+    assert_eq!(trie_viewer.view_account(&state_update, &alice_account()).unwrap().amount(), 3);
+    assert_eq!(view_balance(first_root), 1);
 }
 
 #[test]
@@ -364,7 +743,7 @@ fn test_view_state_too_large() {
         alice_account(),
         &Account::new(0, 0, 0, CryptoHash::default(), 50_001, PROTOCOL_VERSION),
     );
-    let trie_viewer = TrieViewer::new(Some(50_000), None);
+    let trie_viewer = TrieViewer::new(Some(50_000), None, None, None, None);
     let result = trie_viewer.view_state(&state_update, &alice_account(), b"", false);
     assert!(matches!(result, Err(errors::ViewStateError::AccountStateTooLarge { .. })));
 }
@@ -380,7 +759,7 @@ fn test_view_state_with_large_contract() {
         &Account::new(0, 0, 0, sha256(&contract_code), 50_001, PROTOCOL_VERSION),
     );
     state_update.set(TrieKey::ContractCode { account_id: alice_account() }, contract_code);
-    let trie_viewer = TrieViewer::new(Some(50_000), None);
+    let trie_viewer = TrieViewer::new(Some(50_000), None, None, None, None);
     let result = trie_viewer.view_state(&state_update, &alice_account(), b"", false);
     assert!(result.is_ok());
 }
@@ -388,17 +767,7 @@ fn test_view_state_with_large_contract() {
 #[test]
 fn test_log_when_panic() {
     let (viewer, root) = get_test_trie_viewer();
-    let view_state = ViewApplyState {
-        block_height: 1,
-        prev_block_hash: CryptoHash::default(),
-        block_hash: CryptoHash::default(),
-        shard_id: ShardUId::single_shard().shard_id(),
-        epoch_id: EpochId::default(),
-        epoch_height: 0,
-        block_timestamp: 1,
-        current_protocol_version: PROTOCOL_VERSION,
-        cache: None,
-    };
+    let view_state = test_view_apply_state();
     let mut logs = vec![];
     viewer
         .call_function(
@@ -308,6 +308,10 @@ impl User for RuntimeUser {
             block_timestamp: apply_state.block_timestamp,
             current_protocol_version: PROTOCOL_VERSION,
             cache: apply_state.cache,
+            storage_get_mode: None,
+            originator_id: None,
+            public_key: None,
+            max_gas_burnt: None,
         };
         result.result = self
             .trie_viewer
@@ -9,24 +9,26 @@ use near_chain_configs::test_utils::{
     TESTING_INIT_BALANCE, TESTING_INIT_STAKE,
 };
 use near_chain_configs::{
-    default_enable_multiline_logging, default_epoch_sync_enabled,
-    default_header_sync_expected_height_per_second, default_header_sync_initial_timeout,
-    default_header_sync_progress_timeout, default_header_sync_stall_ban_timeout,
-    default_log_summary_period, default_orphan_state_witness_max_size,
-    default_orphan_state_witness_pool_size, default_produce_chunk_add_transactions_time_limit,
-    default_state_sync, default_state_sync_enabled, default_state_sync_timeout,
-    default_sync_check_period, default_sync_height_threshold, default_sync_step_period,
-    default_transaction_pool_size_limit, default_trie_viewer_state_size_limit,
-    default_tx_routing_height_horizon, default_view_client_threads,
-    default_view_client_throttle_period, get_initial_supply, ChunkDistributionNetworkConfig,
-    ClientConfig, GCConfig, Genesis, GenesisConfig, GenesisValidationMode, LogSummaryStyle,
-    MutableConfigValue, MutableValidatorSigner, ReshardingConfig, StateSyncConfig,
-    BLOCK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_PRODUCER_KICKOUT_THRESHOLD,
-    CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD, EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD,
-    GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME, INITIAL_GAS_LIMIT, MAX_INFLATION_RATE,
-    MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE, NUM_BLOCKS_PER_YEAR,
-    NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE, PROTOCOL_UPGRADE_STAKE_THRESHOLD,
-    TRANSACTION_VALIDITY_PERIOD,
+    default_chunk_request_retry_jitter, default_enable_multiline_logging,
+    default_epoch_sync_enabled, default_header_sync_expected_height_per_second,
+    default_header_sync_initial_timeout, default_header_sync_progress_timeout,
+    default_header_sync_stall_ban_timeout, default_log_summary_period,
+    default_max_chain_head_staleness, default_max_chunk_response_bytes,
+    default_max_chunk_response_parts, default_max_concurrent_view_calls,
+    default_orphan_state_witness_max_size, default_orphan_state_witness_pool_size,
+    default_produce_chunk_add_transactions_time_limit, default_state_sync,
+    default_state_sync_enabled, default_state_sync_timeout, default_sync_check_period,
+    default_sync_height_threshold, default_sync_step_period, default_transaction_pool_size_limit,
+    default_trie_viewer_state_size_limit, default_tx_routing_height_horizon,
+    default_view_client_threads, default_view_client_throttle_period, get_initial_supply,
+    ChunkDistributionNetworkConfig, ClientConfig, GCConfig, Genesis, GenesisConfig,
+    GenesisValidationMode, LogSummaryStyle, MutableConfigValue, MutableValidatorSigner,
+    ReshardingConfig, StateSyncConfig, BLOCK_PRODUCER_KICKOUT_THRESHOLD,
+    CHUNK_PRODUCER_KICKOUT_THRESHOLD, CHUNK_VALIDATOR_ONLY_KICKOUT_THRESHOLD,
+    EXPECTED_EPOCH_LENGTH, FISHERMEN_THRESHOLD, GAS_PRICE_ADJUSTMENT_RATE, GENESIS_CONFIG_FILENAME,
+    INITIAL_GAS_LIMIT, MAX_INFLATION_RATE, MIN_BLOCK_PRODUCTION_DELAY, MIN_GAS_PRICE, NEAR_BASE,
+    NUM_BLOCKS_PER_YEAR, NUM_BLOCK_PRODUCER_SEATS, PROTOCOL_REWARD_RATE,
+    PROTOCOL_UPGRADE_STAKE_THRESHOLD, TRANSACTION_VALIDITY_PERIOD,
 };
 use near_config_utils::{ValidationError, ValidationErrors};
 use near_crypto::{InMemorySigner, KeyFile, KeyType, PublicKey};
@@ -141,6 +143,32 @@ pub struct Consensus {
     /// Time between checking to re-request chunks.
     #[serde(with = "near_async::time::serde_duration_as_std")]
     pub chunk_request_retry_period: Duration,
+    /// Fraction of `chunk_request_retry_period` by which every re-request of
+    /// the chunks is randomly delayed or advanced, so that the nodes don't
+    /// re-request chunks in lockstep. 0 disables the jitter.
+    #[serde(default = "default_chunk_request_retry_jitter")]
+    pub chunk_request_retry_jitter: f64,
+    /// Whether to persist the pending chunk requests, so that fetching of
+    /// missing chunks resumes right away after a restart.
+    #[serde(default)]
+    pub persist_chunk_request_pool: bool,
+    /// Maximum number of parts and receipt proofs in a single chunk part
+    /// response served to a peer. Larger responses are split into several.
+    #[serde(default = "default_max_chunk_response_parts")]
+    pub max_chunk_response_parts: usize,
+    /// Maximum size in bytes of a single chunk part response served to a peer.
+    #[serde(default = "default_max_chunk_response_bytes")]
+    pub max_chunk_response_bytes: usize,
+    /// How long the chain heads cached by the shards manager may go without an
+    /// update from the client before a warning is logged.
+    #[serde(default = "default_max_chain_head_staleness")]
+    #[serde(with = "near_async::time::serde_duration_as_std")]
+    pub max_chain_head_staleness: Duration,
+    /// Maximum number of parts forwarded ahead of the chunk header that are
+    /// cached per chunk. The number of parts of a chunk is the limit if unset.
+    #[serde(default)]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_forwarded_parts_per_chunk: Option<usize>,
     /// How much time to wait after initial header sync
     #[serde(default = "default_header_sync_initial_timeout")]
     #[serde(with = "near_async::time::serde_duration_as_std")]
@@ -191,6 +219,12 @@ impl Default for Consensus {
             block_header_fetch_horizon: BLOCK_HEADER_FETCH_HORIZON,
             catchup_step_period: Duration::milliseconds(CATCHUP_STEP_PERIOD),
             chunk_request_retry_period: Duration::milliseconds(CHUNK_REQUEST_RETRY_PERIOD),
+            chunk_request_retry_jitter: default_chunk_request_retry_jitter(),
+            persist_chunk_request_pool: false,
+            max_chunk_response_parts: default_max_chunk_response_parts(),
+            max_chunk_response_bytes: default_max_chunk_response_bytes(),
+            max_chain_head_staleness: default_max_chain_head_staleness(),
+            max_forwarded_parts_per_chunk: None,
             header_sync_initial_timeout: default_header_sync_initial_timeout(),
             header_sync_progress_timeout: default_header_sync_progress_timeout(),
             header_sync_stall_ban_timeout: default_header_sync_stall_ban_timeout(),
@@ -251,6 +285,17 @@ pub struct Config {
     /// If set, overrides value in genesis configuration.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_gas_burnt_view: Option<Gas>,
+    /// Upper bound on the gas limit a single view function call may ask for.
+    /// None only allows asking for up to `max_gas_burnt_view`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_gas_burnt_view_cap: Option<Gas>,
+    /// Upper bound on the number of view function calls executed at the same
+    /// time.  None is no limit.
+    pub max_concurrent_view_calls: Option<usize>,
+    /// Number of accounts cached by `view_account` queries, keyed by the state
+    /// root they were read at.  None disables the cache.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub view_account_cache_size: Option<usize>,
     /// Different parameters to configure underlying storage.
     pub store: near_store::StoreConfig,
     /// Different parameters to configure underlying cold storage.
@@ -290,6 +335,11 @@ pub struct Config {
     #[serde(default)]
     #[serde(with = "near_async::time::serde_opt_duration_as_std")]
     pub produce_chunk_add_transactions_time_limit: Option<Duration>,
+    /// Shards whose state sync is paused, e.g. to throttle the sync of a heavy
+    /// shard. The parts downloaded so far are kept, and the sync continues from
+    /// there once the shard is removed from the list.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub state_sync_paused_shards: Vec<ShardId>,
     /// Optional config for the Chunk Distribution Network feature.
     ///
     /// If set to `None` then this node does not participate in the Chunk Distribution Network.
@@ -349,6 +399,9 @@ impl Default for Config {
             view_client_throttle_period: default_view_client_throttle_period(),
             trie_viewer_state_size_limit: default_trie_viewer_state_size_limit(),
             max_gas_burnt_view: None,
+            max_gas_burnt_view_cap: None,
+            max_concurrent_view_calls: default_max_concurrent_view_calls(),
+            view_account_cache_size: None,
             store: near_store::StoreConfig::default(),
             cold_store: None,
             split_storage: None,
@@ -361,6 +414,7 @@ impl Default for Config {
             tx_routing_height_horizon: default_tx_routing_height_horizon(),
             produce_chunk_add_transactions_time_limit:
                 default_produce_chunk_add_transactions_time_limit(),
+            state_sync_paused_shards: vec![],
             chunk_distribution_network: None,
             orphan_state_witness_pool_size: default_orphan_state_witness_pool_size(),
             orphan_state_witness_max_size: default_orphan_state_witness_max_size(),
@@ -554,6 +608,12 @@ impl NearConfig {
                 block_header_fetch_horizon: config.consensus.block_header_fetch_horizon,
                 catchup_step_period: config.consensus.catchup_step_period,
                 chunk_request_retry_period: config.consensus.chunk_request_retry_period,
+                chunk_request_retry_jitter: config.consensus.chunk_request_retry_jitter,
+                persist_chunk_request_pool: config.consensus.persist_chunk_request_pool,
+                max_chunk_response_parts: config.consensus.max_chunk_response_parts,
+                max_chunk_response_bytes: config.consensus.max_chunk_response_bytes,
+                max_chain_head_staleness: config.consensus.max_chain_head_staleness,
+                max_forwarded_parts_per_chunk: config.consensus.max_forwarded_parts_per_chunk,
                 doosmslug_step_period: config.consensus.doomslug_step_period,
                 tracked_accounts: config.tracked_accounts,
                 tracked_shards: config.tracked_shards,
@@ -567,6 +627,9 @@ impl NearConfig {
                 view_client_throttle_period: config.view_client_throttle_period,
                 trie_viewer_state_size_limit: config.trie_viewer_state_size_limit,
                 max_gas_burnt_view: config.max_gas_burnt_view,
+                max_gas_burnt_view_cap: config.max_gas_burnt_view_cap,
+                max_concurrent_view_calls: config.max_concurrent_view_calls,
+                view_account_cache_size: config.view_account_cache_size,
                 enable_statistics_export: config.store.enable_statistics_export,
                 client_background_migration_threads: 8,
                 flat_storage_creation_enabled: false,
@@ -584,6 +647,10 @@ impl NearConfig {
                     config.produce_chunk_add_transactions_time_limit,
                     "produce_chunk_add_transactions_time_limit",
                 ),
+                state_sync_paused_shards: MutableConfigValue::new(
+                    config.state_sync_paused_shards,
+                    "state_sync_paused_shards",
+                ),
                 chunk_distribution_network: config.chunk_distribution_network,
                 orphan_state_witness_pool_size: config.orphan_state_witness_pool_size,
                 orphan_state_witness_max_size: config.orphan_state_witness_max_size,
@@ -680,6 +747,9 @@ impl NightshadeRuntime {
             epoch_manager,
             config.client_config.trie_viewer_state_size_limit,
             config.client_config.max_gas_burnt_view,
+            config.client_config.max_gas_burnt_view_cap,
+            config.client_config.max_concurrent_view_calls,
+            config.client_config.view_account_cache_size,
             None,
             config.config.gc.gc_num_epochs_to_keep(),
             TrieConfig::from_store_config(&config.config.store),
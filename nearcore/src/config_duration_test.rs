@@ -31,6 +31,9 @@ fn test_config_duration_all_std() {
         expected_shutdown: Some(Default::default()),
         genesis_records_file: Some(Default::default()),
         max_gas_burnt_view: Some(Default::default()),
+        max_gas_burnt_view_cap: Some(Default::default()),
+        max_concurrent_view_calls: Some(Default::default()),
+        view_account_cache_size: Some(Default::default()),
         produce_chunk_add_transactions_time_limit: Some(Default::default()),
         rpc: Some(RpcConfig {
             experimental_debug_pages_src_path: Some(Default::default()),
@@ -108,6 +108,12 @@ impl<'a> ConfigValidator<'a> {
                             self.validation_errors.push_config_semantics_error(error_message);
                         }
                     }
+                    ExternalStorageLocation::Azure { account, container, .. } => {
+                        if account.is_empty() || container.is_empty() {
+                            let error_message = format!("'config.state_sync.dump.location.Azure.account' and 'config.state_sync.dump.location.Azure.container' need to be specified when 'config.state_sync.dump.location.Azure' is present.");
+                            self.validation_errors.push_config_semantics_error(error_message);
+                        }
+                    }
                 }
 
                 if let Some(credentials_file) = &dump_config.credentials_file {
@@ -120,23 +126,41 @@ impl<'a> ConfigValidator<'a> {
             match &state_sync.sync {
                 SyncConfig::Peers => {}
                 SyncConfig::ExternalStorage(config) => {
-                    match &config.location {
-                        ExternalStorageLocation::S3 { bucket, region } => {
-                            if bucket.is_empty() || region.is_empty() {
-                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.S3.bucket' and 'config.state_sync.sync.ExternalStorage.location.S3.region' need to be specified when 'config.state_sync.sync.ExternalStorage.location.S3' is present.");
-                                self.validation_errors.push_config_semantics_error(error_message);
+                    let locations = std::iter::once(("location".to_string(), &config.location))
+                        .chain(
+                            config.additional_locations.iter().enumerate().map(|(i, location)| {
+                                (format!("additional_locations[{i}]"), location)
+                            }),
+                        );
+                    for (field, location) in locations {
+                        match location {
+                            ExternalStorageLocation::S3 { bucket, region } => {
+                                if bucket.is_empty() || region.is_empty() {
+                                    let error_message = format!("'config.state_sync.sync.ExternalStorage.{field}.S3.bucket' and 'config.state_sync.sync.ExternalStorage.{field}.S3.region' need to be specified when 'config.state_sync.sync.ExternalStorage.{field}.S3' is present.");
+                                    self.validation_errors
+                                        .push_config_semantics_error(error_message);
+                                }
                             }
-                        }
-                        ExternalStorageLocation::Filesystem { root_dir } => {
-                            if root_dir.as_path() == Path::new("") {
-                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.Filesystem.root_dir' needs to be specified when 'config.state_sync.sync.ExternalStorage.location.Filesystem' is present.");
-                                self.validation_errors.push_config_semantics_error(error_message);
+                            ExternalStorageLocation::Filesystem { root_dir } => {
+                                if root_dir.as_path() == Path::new("") {
+                                    let error_message = format!("'config.state_sync.sync.ExternalStorage.{field}.Filesystem.root_dir' needs to be specified when 'config.state_sync.sync.ExternalStorage.{field}.Filesystem' is present.");
+                                    self.validation_errors
+                                        .push_config_semantics_error(error_message);
+                                }
                             }
-                        }
-                        ExternalStorageLocation::GCS { bucket } => {
-                            if bucket.is_empty() {
-                                let error_message = format!("'config.state_sync.sync.ExternalStorage.location.GCS.bucket' needs to be specified when 'config.state_sync.sync.ExternalStorage.location.GCS' is present.");
-                                self.validation_errors.push_config_semantics_error(error_message);
+                            ExternalStorageLocation::GCS { bucket } => {
+                                if bucket.is_empty() {
+                                    let error_message = format!("'config.state_sync.sync.ExternalStorage.{field}.GCS.bucket' needs to be specified when 'config.state_sync.sync.ExternalStorage.{field}.GCS' is present.");
+                                    self.validation_errors
+                                        .push_config_semantics_error(error_message);
+                                }
+                            }
+                            ExternalStorageLocation::Azure { account, container, .. } => {
+                                if account.is_empty() || container.is_empty() {
+                                    let error_message = format!("'config.state_sync.sync.ExternalStorage.{field}.Azure.account' and 'config.state_sync.sync.ExternalStorage.{field}.Azure.container' need to be specified when 'config.state_sync.sync.ExternalStorage.{field}.Azure' is present.");
+                                    self.validation_errors
+                                        .push_config_semantics_error(error_message);
+                                }
                             }
                         }
                     }
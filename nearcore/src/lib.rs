@@ -412,7 +412,7 @@ pub fn start_with_config_and_synchronization(
         client_adapter_for_shards_manager.as_sender(),
         config.validator_signer.clone(),
         split_store.unwrap_or_else(|| storage.get_hot_store()),
-        config.client_config.chunk_request_retry_period,
+        &config.client_config,
     );
     shards_manager_adapter.bind(shards_manager_actor.with_auto_span_context());
 
@@ -7,9 +7,12 @@ use futures::FutureExt;
 use near_async::time::{Clock, Duration, Instant};
 use near_chain::types::RuntimeAdapter;
 use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode, Error};
-use near_chain_configs::{ClientConfig, ExternalStorageLocation, MutableValidatorSigner};
+use near_chain_configs::{
+    ClientConfig, Compression, ExternalStorageLocation, MutableValidatorSigner,
+};
 use near_client::sync::external::{
-    create_bucket_readwrite, external_storage_location, StateFileType,
+    create_azure_connection, create_bucket_readwrite, external_storage_location,
+    read_azure_sas_token, StateFileType,
 };
 use near_client::sync::external::{
     external_storage_location_directory, get_part_id_from_filename, is_part_filename,
@@ -79,6 +82,14 @@ impl StateSyncDumper {
                     bucket,
                 }
             },
+            ExternalStorageLocation::Azure { account, container, endpoint } => {
+                let sas_token = read_azure_sas_token(dump_config.credentials_file).expect(
+                    "Failed to read the Azure SAS token. Please either provide AZURE_STORAGE_SAS_TOKEN in the environment, or write it to a file and link it in config.json as 'credentials_file'.");
+                if sas_token.is_none() {
+                    tracing::warn!(target: "state_sync_dump", "No SAS token is provided for the Azure container '{container}', uploading the state parts will likely fail");
+                }
+                create_azure_connection(&account, &container, endpoint.as_deref(), sas_token)
+            }
         };
 
         // Determine how many threads to start.
@@ -127,6 +138,7 @@ impl StateSyncDumper {
                         chain_id.clone(),
                         dump_config.restart_dump_for_shards.clone().unwrap_or_default(),
                         external.clone(),
+                        dump_config.compression,
                         dump_config.iteration_delay.unwrap_or(Duration::seconds(10)),
                         self.validator.clone(),
                         keep_running.clone(),
@@ -209,7 +221,13 @@ async fn get_missing_part_ids_for_epoch(
         shard_id,
         &StateFileType::StatePart { part_id: 0, num_parts: 0 },
     );
-    let file_names = external.list_objects(shard_id, &directory_path).await?;
+    // Skip the checksums stored next to the parts.
+    let file_names: Vec<String> = external
+        .list_objects(shard_id, &directory_path)
+        .await?
+        .into_iter()
+        .filter(|file_name| is_part_filename(file_name))
+        .collect();
     if !file_names.is_empty() {
         let existing_nums: HashSet<_> = file_names
             .iter()
@@ -336,6 +354,7 @@ async fn state_sync_dump(
     chain_id: String,
     restart_dump_for_shards: Vec<ShardId>,
     external: ExternalConnection,
+    compression: Compression,
     iteration_delay: Duration,
     validator: MutableValidatorSigner,
     keep_running: Arc<AtomicBool>,
@@ -486,7 +505,13 @@ async fn state_sync_dump(
                                         &file_type,
                                     );
                                     if let Err(err) = external
-                                        .put_file(file_type, &state_part, shard_id, &location)
+                                        .put_state_part(
+                                            file_type,
+                                            &state_part,
+                                            shard_id,
+                                            &location,
+                                            compression,
+                                        )
                                         .await
                                     {
                                         // no need to break if there's an error, we should keep dumping other parts.
@@ -574,10 +599,8 @@ fn get_in_progress_data(
     let state_root = state_header.chunk_prev_state_root();
     let num_parts = state_header.num_state_parts();
 
-    let sync_block_header = chain.get_block_header(&sync_hash)?;
-    let sync_prev_block_header = chain.get_previous_header(&sync_block_header)?;
-    let sync_prev_prev_hash = sync_prev_block_header.prev_hash();
-    Ok((state_root, num_parts, *sync_prev_prev_hash))
+    let sync_prev_prev_hash = chain.state_sync_target_info(sync_hash)?.sync_prev_prev_hash;
+    Ok((state_root, num_parts, sync_prev_prev_hash))
 }
 
 fn update_dumped_size_and_cnt_metrics(
@@ -0,0 +1,53 @@
+#[macro_use]
+extern crate bencher;
+
+use bencher::Bencher;
+
+use near_primitives::account::Account;
+use near_primitives::hash::CryptoHash;
+use near_primitives::shard_layout::ShardUId;
+use near_primitives::types::{AccountId, StateChangeCause, StateRoot};
+use near_primitives::version::PROTOCOL_VERSION;
+use near_store::test_utils::TestTriesBuilder;
+use near_store::{set_account, ShardTries, Trie};
+use node_runtime::state_viewer::TrieViewer;
+
+fn setup() -> (ShardTries, StateRoot, AccountId) {
+    let tries = TestTriesBuilder::new().build();
+    let shard_uid = ShardUId::single_shard();
+    let account_id: AccountId = "alice.near".parse().unwrap();
+    let mut state_update = tries.new_trie_update(shard_uid, Trie::EMPTY_ROOT);
+    set_account(
+        &mut state_update,
+        account_id.clone(),
+        &Account::new(1, 0, 0, CryptoHash::default(), 100, PROTOCOL_VERSION),
+    );
+    state_update.commit(StateChangeCause::InitialState);
+    let trie_changes = state_update.finalize().unwrap().1;
+    let mut store_update = tries.store_update();
+    let root = tries.apply_all(&trie_changes, shard_uid, &mut store_update);
+    store_update.commit().unwrap();
+    (tries, root, account_id)
+}
+
+/// Repeatedly views the same account at the same state root.
+fn view_account(bench: &mut Bencher, trie_viewer: TrieViewer) {
+    let (tries, root, account_id) = setup();
+    bench.iter(|| {
+        for _ in 0..100 {
+            let state_update = tries.new_trie_update_view(ShardUId::single_shard(), root);
+            trie_viewer.view_account(&state_update, &account_id).unwrap();
+        }
+    });
+}
+
+fn view_account_without_cache(bench: &mut Bencher) {
+    view_account(bench, TrieViewer::new(None, None, None, None, None));
+}
+
+fn view_account_with_cache(bench: &mut Bencher) {
+    view_account(bench, TrieViewer::new(None, None, None, None, Some(100)));
+}
+
+benchmark_group!(benches, view_account_without_cache, view_account_with_cache);
+benchmark_main!(benches);
@@ -52,6 +52,10 @@ pub enum CallFunctionError {
     InternalError { error_message: String },
     #[error("VM error occurred: #{error_message}")]
     VMError { error_message: String },
+    #[error("Too many concurrent view calls, the limit is {limit}")]
+    TooManyRequests { limit: usize },
+    #[error("Requested gas limit {requested} exceeds the maximum of {limit}")]
+    GasLimitTooHigh { requested: near_primitives::types::Gas, limit: near_primitives::types::Gas },
 }
 
 impl From<ViewAccountError> for ViewContractCodeError {
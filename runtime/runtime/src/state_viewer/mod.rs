@@ -9,18 +9,27 @@ use near_primitives::borsh::BorshDeserialize;
 use near_primitives::hash::CryptoHash;
 use near_primitives::receipt::ActionReceipt;
 use near_primitives::runtime::migration_data::{MigrationData, MigrationFlags};
+use near_primitives::shard_layout::ShardUId;
 use near_primitives::transaction::FunctionCallAction;
-use near_primitives::trie_key::trie_key_parsers;
+use near_primitives::trie_key::{col, trie_key_parsers};
 use near_primitives::types::{
-    AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, ShardId,
+    AccountId, BlockHeight, EpochHeight, EpochId, EpochInfoProvider, Gas, ShardId, StateRoot,
 };
 use near_primitives::version::PROTOCOL_VERSION;
 use near_primitives::views::{StateItem, ViewStateResult};
 use near_primitives_core::config::ViewConfig;
-use near_store::{get_access_key, get_account, get_code, TrieUpdate};
-use near_vm_runner::logic::{ProtocolVersion, ReturnData};
-use near_vm_runner::{ContractCode, ContractRuntimeCache};
-use std::{str, sync::Arc, time::Instant};
+use near_store::{
+    get_access_key, get_account, get_code, NibbleSlice, RawTrieNode, RawTrieNodeWithSize,
+    ShardTries, TrieUpdate,
+};
+use near_vm_runner::logic::{ProtocolVersion, ReturnData, StorageGetMode, VMOutcome};
+use near_vm_runner::{ContractCode, ContractRuntimeCache, ProfileDataV3};
+use std::collections::HashMap;
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use std::{str, sync::Arc};
 use tracing::debug;
 
 pub mod errors;
@@ -46,6 +55,34 @@ pub struct ViewApplyState {
     pub current_protocol_version: ProtocolVersion,
     /// Cache for compiled contracts.
     pub cache: Option<Box<dyn ContractRuntimeCache>>,
+    /// If set, storage reads of the call go through flat storage or the trie
+    /// as specified, regardless of the runtime config. Useful for comparing
+    /// the results of the two when debugging flat storage.
+    pub storage_get_mode: Option<StorageGetMode>,
+    /// Account seen by the contract as the signer and the predecessor of the
+    /// call. Defaults to the contract account itself.
+    pub originator_id: Option<AccountId>,
+    /// Public key seen by the contract as the signer's key. Defaults to an
+    /// empty ED25519 key.
+    pub public_key: Option<PublicKey>,
+    /// If set, overrides the gas limit of the viewer for this call.  It can't
+    /// exceed the `max_gas_burnt_view_cap` of the viewer.
+    pub max_gas_burnt: Option<Gas>,
+}
+
+/// Result of a view call along with the cost of its execution.
+#[derive(Debug)]
+pub struct CallFunctionResult {
+    /// Value returned by the called method.
+    pub result: Vec<u8>,
+    /// Gas burnt by the execution.
+    pub gas_burnt: Gas,
+    /// Logs emitted by the execution.
+    pub logs: Vec<String>,
+    /// Wall-clock time of the execution.
+    pub elapsed: Duration,
+    /// Breakdown of the burnt gas by host function and wasm instructions.
+    pub profile: ProfileDataV3,
 }
 
 pub struct TrieViewer {
@@ -53,6 +90,27 @@ pub struct TrieViewer {
     state_size_limit: Option<u64>,
     /// Gas limit used when handling call_function queries.
     max_gas_burnt_view: Gas,
+    /// Upper bound on the gas limit a single call_function query may ask for.
+    /// Never lower than `max_gas_burnt_view`.
+    max_gas_burnt_view_cap: Gas,
+    /// Upper bound on the number of call_function queries executed at the same time. None is no limit
+    max_concurrent_view_calls: Option<usize>,
+    /// Number of call_function queries currently being executed.
+    active_view_calls: Arc<AtomicUsize>,
+    /// Recently viewed accounts by the state root they were read at. Entries
+    /// never go stale, as any change of an account changes the state root.
+    view_account_cache: Option<Mutex<lru::LruCache<(StateRoot, AccountId), Account>>>,
+}
+
+/// Slot taken by a running call_function query, released on drop.
+struct ViewCallPermit {
+    active_view_calls: Arc<AtomicUsize>,
+}
+
+impl Drop for ViewCallPermit {
+    fn drop(&mut self) {
+        self.active_view_calls.fetch_sub(1, Ordering::AcqRel);
+    }
 }
 
 impl Default for TrieViewer {
@@ -60,15 +118,70 @@ impl Default for TrieViewer {
         let config_store = RuntimeConfigStore::new(None);
         let latest_runtime_config = config_store.get_config(PROTOCOL_VERSION);
         let max_gas_burnt = latest_runtime_config.wasm_config.limit_config.max_gas_burnt;
-        Self { state_size_limit: None, max_gas_burnt_view: max_gas_burnt }
+        Self {
+            state_size_limit: None,
+            max_gas_burnt_view: max_gas_burnt,
+            max_gas_burnt_view_cap: max_gas_burnt,
+            max_concurrent_view_calls: None,
+            active_view_calls: Arc::new(AtomicUsize::new(0)),
+            view_account_cache: None,
+        }
     }
 }
 
 impl TrieViewer {
-    pub fn new(state_size_limit: Option<u64>, max_gas_burnt_view: Option<Gas>) -> Self {
+    pub fn new(
+        state_size_limit: Option<u64>,
+        max_gas_burnt_view: Option<Gas>,
+        max_gas_burnt_view_cap: Option<Gas>,
+        max_concurrent_view_calls: Option<usize>,
+        view_account_cache_size: Option<usize>,
+    ) -> Self {
         let max_gas_burnt_view =
             max_gas_burnt_view.unwrap_or_else(|| TrieViewer::default().max_gas_burnt_view);
-        Self { state_size_limit, max_gas_burnt_view }
+        let max_gas_burnt_view_cap =
+            max_gas_burnt_view_cap.map_or(max_gas_burnt_view, |cap| cap.max(max_gas_burnt_view));
+        let view_account_cache = view_account_cache_size
+            .and_then(NonZeroUsize::new)
+            .map(|size| Mutex::new(lru::LruCache::new(size)));
+        Self {
+            state_size_limit,
+            max_gas_burnt_view,
+            max_gas_burnt_view_cap,
+            max_concurrent_view_calls,
+            active_view_calls: Arc::new(AtomicUsize::new(0)),
+            view_account_cache,
+        }
+    }
+
+    /// Returns the gas limit of a call_function query which asked for
+    /// `max_gas_burnt`, or the default limit if it didn't ask for any.
+    fn view_call_gas_limit(
+        &self,
+        max_gas_burnt: Option<Gas>,
+    ) -> Result<Gas, errors::CallFunctionError> {
+        match max_gas_burnt {
+            None => Ok(self.max_gas_burnt_view),
+            Some(requested) if requested > self.max_gas_burnt_view_cap => {
+                Err(errors::CallFunctionError::GasLimitTooHigh {
+                    requested,
+                    limit: self.max_gas_burnt_view_cap,
+                })
+            }
+            Some(requested) => Ok(requested),
+        }
+    }
+
+    /// Reserves a slot for a call_function query without waiting.  Fails
+    /// right away if `max_concurrent_view_calls` queries are already running.
+    fn try_acquire_view_call(&self) -> Result<ViewCallPermit, errors::CallFunctionError> {
+        let limit = self.max_concurrent_view_calls.unwrap_or(usize::MAX);
+        self.active_view_calls
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |active| {
+                (active < limit).then_some(active + 1)
+            })
+            .map_err(|_| errors::CallFunctionError::TooManyRequests { limit })?;
+        Ok(ViewCallPermit { active_view_calls: self.active_view_calls.clone() })
     }
 
     pub fn view_account(
@@ -76,11 +189,21 @@ impl TrieViewer {
         state_update: &TrieUpdate,
         account_id: &AccountId,
     ) -> Result<Account, errors::ViewAccountError> {
-        get_account(state_update, account_id)?.ok_or_else(|| {
+        // The state root identifies the state only if nothing was changed on top of it.
+        let cache = self.view_account_cache.as_ref().filter(|_| !state_update.has_changes());
+        let key = (*state_update.get_root(), account_id.clone());
+        if let Some(account) = cache.and_then(|cache| cache.lock().unwrap().get(&key).cloned()) {
+            return Ok(account);
+        }
+        let account = get_account(state_update, account_id)?.ok_or_else(|| {
             errors::ViewAccountError::AccountDoesNotExist {
                 requested_account_id: account_id.clone(),
             }
-        })
+        })?;
+        if let Some(cache) = cache {
+            cache.lock().unwrap().put(key, account.clone());
+        }
+        Ok(account)
     }
 
     pub fn view_contract_code(
@@ -144,6 +267,60 @@ impl TrieViewer {
         prefix: &[u8],
         include_proof: bool,
     ) -> Result<ViewStateResult, errors::ViewStateError> {
+        self.check_state_size_limit(state_update, account_id)?;
+        self.iter_contract_data(state_update, account_id, prefix, include_proof)
+    }
+
+    /// Paged version of [`Self::view_state`] for contracts with too much data
+    /// to return at once.  Returns at most `limit` items whose key starts with
+    /// `prefix`, beginning at `from_key` if it is given.  The second element of
+    /// the result is the key to pass as `from_key` to fetch the next page, or
+    /// `None` if there are no more items.  The proof, if requested, covers
+    /// only the returned items.
+    pub fn view_state_paged(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+        prefix: &[u8],
+        from_key: Option<&[u8]>,
+        limit: usize,
+        include_proof: bool,
+    ) -> Result<(ViewStateResult, Option<Vec<u8>>), errors::ViewStateError> {
+        self.check_state_size_limit(state_update, account_id)?;
+        let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
+        let acc_sep_len = query.len() - prefix.len();
+        let start = match from_key {
+            Some(from_key) => std::cmp::max(
+                trie_key_parsers::get_raw_prefix_for_contract_data(account_id, from_key),
+                query.clone(),
+            ),
+            None => query.clone(),
+        };
+        let mut values = vec![];
+        let mut next_key = None;
+        let mut iter = state_update.trie().disk_iter()?;
+        iter.remember_visited_nodes(include_proof);
+        iter.seek(&start)?;
+        for item in &mut iter {
+            let (key, value) = item?;
+            if !key.starts_with(&query) {
+                break;
+            }
+            if values.len() == limit {
+                next_key = Some(key[acc_sep_len..].to_vec());
+                break;
+            }
+            values.push(StateItem { key: key[acc_sep_len..].to_vec().into(), value: value.into() });
+        }
+        let proof = iter.into_visited_nodes();
+        Ok((ViewStateResult { values, proof }, next_key))
+    }
+
+    fn check_state_size_limit(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+    ) -> Result<(), errors::ViewStateError> {
         match get_account(state_update, account_id)? {
             Some(account) => {
                 let code_len = get_code(state_update, account_id, Some(account.code_hash()))?
@@ -163,7 +340,150 @@ impl TrieViewer {
                 })
             }
         };
+        Ok(())
+    }
+
+    /// Returns a page of at most `limit` ids of the accounts whose id starts
+    /// with `prefix`, in lexicographic order, beginning right after
+    /// `start_after` if it is given.  The second element of the result is the
+    /// token to pass as `start_after` to fetch the next page, or `None` if
+    /// there are no more such accounts.
+    pub fn list_accounts_with_prefix(
+        &self,
+        state_update: &TrieUpdate,
+        prefix: &str,
+        limit: usize,
+        start_after: Option<&AccountId>,
+    ) -> Result<(Vec<AccountId>, Option<AccountId>), errors::ViewStateError> {
+        let mut query = vec![col::ACCOUNT];
+        query.extend_from_slice(prefix.as_bytes());
+        // The smallest key after the one of `start_after`, as account ids never
+        // contain zero bytes.
+        let start = match start_after {
+            Some(start_after) => {
+                let mut start = vec![col::ACCOUNT];
+                start.extend_from_slice(start_after.as_bytes());
+                start.push(0);
+                std::cmp::max(start, query.clone())
+            }
+            None => query.clone(),
+        };
+        let mut account_ids = vec![];
+        let mut iter = state_update.trie().disk_iter()?;
+        iter.seek(&start)?;
+        for item in iter {
+            let (key, _) = item?;
+            if !key.starts_with(&query) {
+                break;
+            }
+            let account_id =
+                trie_key_parsers::parse_account_id_from_account_key(&key).map_err(|err| {
+                    errors::ViewStateError::InternalError {
+                        error_message: format!("Unexpected invalid account key in the trie: {err}"),
+                    }
+                })?;
+            if account_ids.len() == limit {
+                let next = account_ids.last().cloned();
+                return Ok((account_ids, next));
+            }
+            account_ids.push(account_id);
+        }
+        Ok((account_ids, None))
+    }
+
+    /// Same as [`Self::view_state`], but doesn't require the account to exist
+    /// and doesn't apply the state size limit.
+    ///
+    /// This is a debugging tool meant for inspecting contract data that isn't
+    /// tied to a live account record, e.g. data orphaned in the trie. It must
+    /// not be exposed to untrusted callers.
+    pub fn view_state_raw(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+        prefix: &[u8],
+        include_proof: bool,
+    ) -> Result<ViewStateResult, errors::ViewStateError> {
+        self.iter_contract_data(state_update, account_id, prefix, include_proof)
+    }
+
+    /// Checks a proof returned by [`Self::view_state`] with `include_proof`
+    /// set.  Returns whether `proof` shows that under `state_root` the contract
+    /// data `key` of `account_id` holds `value`, or that the key is absent if
+    /// `value` is `None`.  Malformed or incomplete proofs never verify.
+    pub fn verify_state_proof(
+        state_root: &StateRoot,
+        account_id: &AccountId,
+        key: &[u8],
+        value: Option<&[u8]>,
+        proof: &[Arc<[u8]>],
+    ) -> bool {
+        let mut nodes = HashMap::with_capacity(proof.len());
+        for bytes in proof {
+            let Ok(node) = RawTrieNodeWithSize::try_from_slice(bytes) else {
+                return false;
+            };
+            nodes.insert(CryptoHash::hash_bytes(bytes), node);
+        }
+
+        let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, key);
+        let mut key = NibbleSlice::new(&query);
+        let mut expected_hash = state_root;
+        while let Some(node) = nodes.get(expected_hash) {
+            match &node.node {
+                RawTrieNode::Leaf(node_key, node_value) => {
+                    let nib = &NibbleSlice::from_encoded(node_key).0;
+                    return if &key != nib {
+                        value.is_none()
+                    } else {
+                        value.is_some_and(|value| node_value == value)
+                    };
+                }
+                RawTrieNode::Extension(node_key, child_hash) => {
+                    let nib = NibbleSlice::from_encoded(node_key).0;
+                    if !key.starts_with(&nib) {
+                        return value.is_none();
+                    }
+                    key = key.mid(nib.len());
+                    expected_hash = child_hash;
+                }
+                RawTrieNode::BranchNoValue(children) => {
+                    if key.is_empty() {
+                        return value.is_none();
+                    }
+                    match &children[key.at(0)] {
+                        Some(child_hash) => {
+                            key = key.mid(1);
+                            expected_hash = child_hash;
+                        }
+                        None => return value.is_none(),
+                    }
+                }
+                RawTrieNode::BranchWithValue(node_value, children) => {
+                    if key.is_empty() {
+                        return value.is_some_and(|value| node_value == value);
+                    }
+                    match &children[key.at(0)] {
+                        Some(child_hash) => {
+                            key = key.mid(1);
+                            expected_hash = child_hash;
+                        }
+                        None => return value.is_none(),
+                    }
+                }
+            }
+        }
+        // A node on the path is missing from the proof.
+        false
+    }
 
+    fn iter_contract_data(
+        &self,
+        state_update: &TrieUpdate,
+        account_id: &AccountId,
+        prefix: &[u8],
+        include_proof: bool,
+    ) -> Result<ViewStateResult, errors::ViewStateError> {
         let mut values = vec![];
         let query = trie_key_parsers::get_raw_prefix_for_contract_data(account_id, prefix);
         let acc_sep_len = query.len() - prefix.len();
@@ -178,9 +498,38 @@ impl TrieViewer {
         Ok(ViewStateResult { values, proof })
     }
 
+    /// Runs a view call against the given state root of the shard.
+    ///
+    /// This is a convenience wrapper around [`Self::call_function`] which
+    /// builds the view `TrieUpdate` from `tries` instead of requiring the
+    /// caller to construct it.
+    pub fn call_function_at_root(
+        &self,
+        tries: &ShardTries,
+        shard_uid: ShardUId,
+        state_root: StateRoot,
+        view_state: ViewApplyState,
+        contract_id: &AccountId,
+        method_name: &str,
+        args: &[u8],
+        logs: &mut Vec<String>,
+        epoch_info_provider: &(dyn EpochInfoProvider),
+    ) -> Result<Vec<u8>, errors::CallFunctionError> {
+        let state_update = tries.new_trie_update_view(shard_uid, state_root);
+        self.call_function(
+            state_update,
+            view_state,
+            contract_id,
+            method_name,
+            args,
+            logs,
+            epoch_info_provider,
+        )
+    }
+
     pub fn call_function(
         &self,
-        mut state_update: TrieUpdate,
+        state_update: TrieUpdate,
         view_state: ViewApplyState,
         contract_id: &AccountId,
         method_name: &str,
@@ -188,6 +537,86 @@ impl TrieViewer {
         logs: &mut Vec<String>,
         epoch_info_provider: &(dyn EpochInfoProvider),
     ) -> Result<Vec<u8>, errors::CallFunctionError> {
+        let (outcome, _) = self.execute_view_call(
+            state_update,
+            view_state,
+            contract_id,
+            method_name,
+            args,
+            logs,
+            epoch_info_provider,
+        )?;
+        Ok(return_value(outcome.return_data))
+    }
+
+    /// Same as [`Self::call_function`], but also returns the gas burnt by the
+    /// call, its gas profile and how long it took to execute.  The logs are
+    /// only returned for successful calls.
+    pub fn call_function_detailed(
+        &self,
+        state_update: TrieUpdate,
+        view_state: ViewApplyState,
+        contract_id: &AccountId,
+        method_name: &str,
+        args: &[u8],
+        epoch_info_provider: &(dyn EpochInfoProvider),
+    ) -> Result<CallFunctionResult, errors::CallFunctionError> {
+        let mut logs = vec![];
+        let (outcome, elapsed) = self.execute_view_call(
+            state_update,
+            view_state,
+            contract_id,
+            method_name,
+            args,
+            &mut logs,
+            epoch_info_provider,
+        )?;
+        Ok(CallFunctionResult {
+            result: return_value(outcome.return_data),
+            gas_burnt: outcome.burnt_gas,
+            logs,
+            elapsed,
+            profile: outcome.profile,
+        })
+    }
+
+    /// Estimates the gas cost of the view call by executing it the same way as
+    /// `call_function` does and returning the gas burnt by the execution. A call
+    /// which runs out of the gas limit of view calls fails the same way as with
+    /// `call_function`, so the returned estimate is always within the limit.
+    pub fn estimate_view_gas(
+        &self,
+        state_update: TrieUpdate,
+        view_state: ViewApplyState,
+        contract_id: &AccountId,
+        method_name: &str,
+        args: &[u8],
+        epoch_info_provider: &(dyn EpochInfoProvider),
+    ) -> Result<Gas, errors::CallFunctionError> {
+        let (outcome, _) = self.execute_view_call(
+            state_update,
+            view_state,
+            contract_id,
+            method_name,
+            args,
+            &mut vec![],
+            epoch_info_provider,
+        )?;
+        Ok(outcome.burnt_gas)
+    }
+
+    fn execute_view_call(
+        &self,
+        mut state_update: TrieUpdate,
+        view_state: ViewApplyState,
+        contract_id: &AccountId,
+        method_name: &str,
+        args: &[u8],
+        logs: &mut Vec<String>,
+        epoch_info_provider: &(dyn EpochInfoProvider),
+    ) -> Result<(VMOutcome, Duration), errors::CallFunctionError> {
+        let max_gas_burnt = self.view_call_gas_limit(view_state.max_gas_burnt)?;
+        let _permit = self.try_acquire_view_call()?;
         let now = Instant::now();
         let root = *state_update.get_root();
         let account = get_account(&state_update, contract_id)?.ok_or_else(|| {
@@ -195,9 +624,9 @@ impl TrieViewer {
                 requested_account_id: contract_id.clone(),
             }
         })?;
-        // TODO(#1015): Add ability to pass public key and originator_id
-        let originator_id = contract_id;
-        let public_key = PublicKey::empty(KeyType::ED25519);
+        let originator_id = view_state.originator_id.unwrap_or_else(|| contract_id.clone());
+        let public_key =
+            view_state.public_key.unwrap_or_else(|| PublicKey::empty(KeyType::ED25519));
         let empty_hash = CryptoHash::default();
         let mut receipt_manager = ReceiptManager::default();
         let mut runtime_ext = RuntimeExt::new(
@@ -213,7 +642,11 @@ impl TrieViewer {
             view_state.current_protocol_version,
         );
         let config_store = RuntimeConfigStore::new(None);
-        let config = config_store.get_config(PROTOCOL_VERSION);
+        let mut config = config_store.get_config(PROTOCOL_VERSION).clone();
+        if let Some(storage_get_mode) = view_state.storage_get_mode {
+            Arc::make_mut(&mut Arc::make_mut(&mut config).wasm_config).storage_get_mode =
+                storage_get_mode;
+        }
         let apply_state = ApplyState {
             apply_reason: None,
             block_height: view_state.block_height,
@@ -246,20 +679,20 @@ impl TrieViewer {
         let function_call = FunctionCallAction {
             method_name: method_name.to_string(),
             args: args.to_vec(),
-            gas: self.max_gas_burnt_view,
+            gas: max_gas_burnt,
             deposit: 0,
         };
-        let outcome = execute_function_call(
+        let mut outcome = execute_function_call(
             &apply_state,
             &mut runtime_ext,
-            originator_id,
+            &originator_id,
             &action_receipt,
             [].into(),
             &function_call,
             &empty_hash,
-            config,
+            &config,
             true,
-            Some(ViewConfig { max_gas_burnt: self.max_gas_burnt_view }),
+            Some(ViewConfig { max_gas_burnt }),
         )
         .map_err(|e| errors::CallFunctionError::InternalError { error_message: e.to_string() })?;
         let elapsed = now.elapsed();
@@ -274,12 +707,15 @@ impl TrieViewer {
             Err(errors::CallFunctionError::VMError { error_message: message })
         } else {
             debug!(target: "runtime", "(exec time {}) result of execution: {:?}", time_str, outcome);
-            logs.extend(outcome.logs);
-            let result = match outcome.return_data {
-                ReturnData::Value(buf) => buf,
-                ReturnData::ReceiptIndex(_) | ReturnData::None => vec![],
-            };
-            Ok(result)
+            logs.extend(std::mem::take(&mut outcome.logs));
+            Ok((outcome, elapsed))
         }
     }
 }
+
+fn return_value(return_data: ReturnData) -> Vec<u8> {
+    match return_data {
+        ReturnData::Value(buf) => buf,
+        ReturnData::ReceiptIndex(_) | ReturnData::None => vec![],
+    }
+}
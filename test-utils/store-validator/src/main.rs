@@ -23,6 +23,20 @@ fn main() {
                 .help("Directory for config and data (default \"~/.near\")")
                 .action(clap::ArgAction::Set),
         )
+        .arg(
+            Arg::new("from-height")
+                .long("from-height")
+                .value_parser(clap::value_parser!(u64))
+                .help("Only validate the blocks starting from this height")
+                .action(clap::ArgAction::Set),
+        )
+        .arg(
+            Arg::new("to-height")
+                .long("to-height")
+                .value_parser(clap::value_parser!(u64))
+                .help("Only validate the blocks up to this height")
+                .action(clap::ArgAction::Set),
+        )
         .subcommand(Command::new("validate"))
         .get_matches();
 
@@ -60,6 +74,11 @@ fn main() {
         store,
         false,
     );
+    let from_height = matches.get_one::<u64>("from-height").copied();
+    let to_height = matches.get_one::<u64>("to-height").copied();
+    if from_height.is_some() || to_height.is_some() {
+        store_validator.set_height_range(from_height.unwrap_or(0), to_height.unwrap_or(u64::MAX));
+    }
     store_validator.validate();
 
     if store_validator.tests_done() == 0 {
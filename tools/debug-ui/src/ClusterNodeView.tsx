@@ -114,7 +114,14 @@ function syncStatusToText(syncStatus: SyncStatusResponse): string {
         return 'Header sync';
     }
     if ('StateSync' in status) {
-        return 'State sync';
+        const shards = Object.entries(status.StateSync[1]).map(([shardId, shard]) => {
+            if (shard.progress == null || shard.progress.num_parts === 0) {
+                return `${shardId}: ${shard.status}`;
+            }
+            const { num_parts_done, num_parts } = shard.progress;
+            return `${shardId}: ${Math.floor((100 * num_parts_done) / num_parts)}%`;
+        });
+        return `State sync ${shards.join(', ')}`;
     }
     return `Block sync ${status.BlockSync.start_height} -> ${status.BlockSync.highest_height}`;
 }
@@ -136,6 +136,7 @@ export type SyncStatusView =
 export interface ShardSyncDownloadView {
     downloads: { error: boolean; done: boolean }[];
     status: string;
+    progress: { num_parts_done: number; num_parts: number } | null;
 }
 
 export interface DebugBlockStatusData {
@@ -276,6 +276,9 @@ fn main() -> Result<()> {
                 sync_mode: near_indexer::SyncModeEnum::FromInterruption,
                 await_for_node_synced: near_indexer::AwaitForNodeSyncedEnum::WaitForFullSync,
                 validate_genesis: true,
+                recent_blocks_cache_size: 100,
+                max_concurrent_block_fetches: 10,
+                ..Default::default()
             };
             let system = actix::System::new();
             system.block_on(async move {
@@ -252,6 +252,7 @@ fn load_snapshot(load_cmd: LoadCmd) {
                 ReshardingConfig::default(),
                 "resharding_config",
             ),
+            verify_memtrie_after_state_sync: false,
         },
         None,
         Arc::new(RayonAsyncComputationSpawner),
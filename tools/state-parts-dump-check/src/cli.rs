@@ -1,9 +1,10 @@
 use actix_web::{web, App, HttpServer};
 use anyhow::anyhow;
 use borsh::BorshDeserialize;
+use near_chain_configs::Compression;
 use near_client::sync::external::{
     create_bucket_readonly, external_storage_location, external_storage_location_directory,
-    get_num_parts_from_filename, ExternalConnection, StateFileType,
+    get_num_parts_from_filename, is_part_filename, ExternalConnection, StateFileType,
 };
 use near_jsonrpc::client::{new_client, JsonRpcClient};
 use near_primitives::hash::CryptoHash;
@@ -493,7 +494,12 @@ async fn check_parts(
         &StateFileType::StatePart { part_id: 0, num_parts: 0 },
     );
     tracing::info!(directory_path, "the storage location for the state parts being checked:");
-    let part_file_names = external.list_objects(shard_id, &directory_path).await?;
+    let part_file_names: Vec<String> = external
+        .list_objects(shard_id, &directory_path)
+        .await?
+        .into_iter()
+        .filter(|file_name| is_part_filename(file_name))
+        .collect();
     if part_file_names.is_empty() {
         return Ok(false);
     }
@@ -804,7 +810,7 @@ async fn process_part(
     let file_type = StateFileType::StatePart { part_id, num_parts };
     let location =
         external_storage_location(&chain_id, &epoch_id, epoch_height, shard_id, &file_type);
-    let part = external.get_file(shard_id, &location, &file_type).await?;
+    let part = external.get_state_part(shard_id, &location, &file_type, Compression::None).await?;
     let is_part_valid = validate_state_part(&state_root, PartId::new(part_id, num_parts), &part);
     if is_part_valid {
         crate::metrics::STATE_SYNC_DUMP_CHECK_NUM_PARTS_VALID
@@ -1,10 +1,11 @@
 use crate::epoch_info::iterate_and_filter;
 use borsh::{BorshDeserialize, BorshSerialize};
 use near_chain::{Chain, ChainGenesis, ChainStoreAccess, DoomslugThresholdMode};
+use near_chain_configs::Compression;
 use near_client::sync::external::{
     create_bucket_readonly, create_bucket_readwrite, external_storage_location,
-    external_storage_location_directory, get_num_parts_from_filename, ExternalConnection,
-    StateFileType,
+    external_storage_location_directory, get_num_parts_from_filename, is_part_filename,
+    ExternalConnection, StateFileType,
 };
 use near_client::sync::state::StateSync;
 use near_epoch_manager::shard_tracker::{ShardTracker, TrackedConfig};
@@ -354,7 +355,13 @@ async fn load_state_parts(
         shard_id,
         &StateFileType::StatePart { part_id: 0, num_parts: 0 },
     );
-    let part_file_names = external.list_objects(shard_id, &directory_path).await.unwrap();
+    let part_file_names: Vec<String> = external
+        .list_objects(shard_id, &directory_path)
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|file_name| is_part_filename(file_name))
+        .collect();
     assert!(!part_file_names.is_empty());
     let num_parts = part_file_names.len() as u64;
     assert_eq!(Some(num_parts), get_num_parts_from_filename(&part_file_names[0]));
@@ -376,7 +383,10 @@ async fn load_state_parts(
         let file_type = StateFileType::StatePart { part_id, num_parts };
         let location =
             external_storage_location(chain_id, &epoch_id, epoch_height, shard_id, &file_type);
-        let part = external.get_file(shard_id, &location, &file_type).await.unwrap();
+        let part = external
+            .get_state_part(shard_id, &location, &file_type, Compression::None)
+            .await
+            .unwrap();
 
         match action {
             LoadAction::Apply => {